@@ -1,6 +1,8 @@
-use std::path::Path;
+use std::{io::Write, path::Path};
 
-use image::ImageFormat;
+use anyhow::Context;
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use ironworks::Ironworks;
 
 use super::{
 	error::{Error, Result},
@@ -8,40 +10,222 @@ use super::{
 	texture,
 };
 
+/// Options controlling the output of a conversion.
+#[derive(Debug, Default, Clone)]
+pub struct ConvertOptions {
+	/// Mip level of textures to convert, where `0` is the full resolution image.
+	pub mip: u32,
+
+	/// Maximum width of the output image. The aspect ratio is preserved.
+	pub width: Option<u32>,
+
+	/// Maximum height of the output image. The aspect ratio is preserved.
+	pub height: Option<u32>,
+
+	/// Convert animated textures to an animation of all frames, rather than a
+	/// single image. Only supported by PNG output.
+	pub animated: bool,
+
+	/// Quality (1-100) to use for lossy output formats. Values outside this
+	/// range are clamped. If unset, encoder defaults are used.
+	pub quality: Option<u8>,
+}
+
+/// A prepared conversion. Source data has been read and validated, such that
+/// only encoding the output remains - this allows output to be written
+/// incrementally, with any errors in the source surfaced beforehand.
+pub struct Conversion(Box<dyn FnOnce(&mut dyn Write) -> Result<()> + Send>);
+
+impl Conversion {
+	pub(super) fn new(write: impl FnOnce(&mut dyn Write) -> Result<()> + Send + 'static) -> Self {
+		Self(Box::new(write))
+	}
+
+	/// Encode the converted output to the provided writer.
+	pub fn write(self, writer: &mut dyn Write) -> Result<()> {
+		(self.0)(writer)
+	}
+}
+
 pub trait Converter {
-	// TODO: Consider using a stream for this - the only converter I actually have right now doesn't operate with streams, but it may be relevant for other converters - or possibly would tie in with caching. Ref. https://github.com/tokio-rs/axum/discussions/608 re: responding to requests with streams.
-	fn convert(&self, data: &bm_data::Version, path: &str, format: Format) -> Result<Vec<u8>>;
+	/// Check if the source file at the given path can be converted by this
+	/// converter. This is determined from the path alone, and does not guarantee
+	/// that the file exists or is well formed.
+	fn supports(&self, path: &str) -> bool;
+
+	fn convert(
+		&self,
+		data: &bm_data::Version,
+		path: &str,
+		format: Format,
+		options: &ConvertOptions,
+	) -> Result<Conversion>;
+}
+
+/// Formats that the source file at the given path can be converted into.
+pub fn formats(path: &str) -> Vec<Format> {
+	Format::iter()
+		.filter(|format| format.converter().supports(path))
+		.collect()
+}
+
+fn extension(path: &str) -> Option<&str> {
+	Path::new(path)
+		.extension()
+		.and_then(|extension| extension.to_str())
 }
 
 pub struct Image;
 
 impl Converter for Image {
-	fn convert(&self, data: &bm_data::Version, path: &str, format: Format) -> Result<Vec<u8>> {
-		let extension = Path::new(path)
-			.extension()
-			.and_then(|extension| extension.to_str());
+	fn supports(&self, path: &str) -> bool {
+		matches!(extension(path), Some("tex" | "atex"))
+	}
+
+	fn convert(
+		&self,
+		data: &bm_data::Version,
+		path: &str,
+		format: Format,
+		options: &ConvertOptions,
+	) -> Result<Conversion> {
+		if !self.supports(path) {
+			return Err(Error::InvalidConversion(
+				extension(path).unwrap_or("(none)").into(),
+				format,
+			));
+		}
 
 		// TODO: add error handling case on this once a failure case actually exists.
 		let output_format = match format {
+			Format::Avif => ImageFormat::Avif,
 			Format::Jpeg => ImageFormat::Jpeg,
 			Format::Png => ImageFormat::Png,
 			Format::Webp => ImageFormat::WebP,
+			Format::Raw => return Err(Error::InvalidConversion(path.into(), format)),
 		};
 
 		// TODO: should i just pass IW to convert? is there any realistic expectation that a converter will need excel?
 		let ironworks = data.ironworks();
 
-		let buffer = match extension {
-			Some("tex") | Some("atex") => texture::read(&ironworks, path),
+		if options.animated {
+			return convert_animated(&ironworks, path, format, options);
+		}
+
+		let buffer = texture::read(&ironworks, path, options.mip)?;
+
+		let buffer = fit(buffer, options.width, options.height);
+
+		let quality = options.quality;
+		Ok(Conversion::new(move |writer| {
+			texture::write(buffer, output_format, quality, writer)
+		}))
+	}
+}
+
+fn convert_animated(
+	ironworks: &Ironworks,
+	path: &str,
+	format: Format,
+	options: &ConvertOptions,
+) -> Result<Conversion> {
+	if !matches!(format, Format::Png) {
+		return Err(Error::InvalidOptions(format!(
+			"animated output is not supported for {format:?}"
+		)));
+	}
+
+	let frames = texture::read_frames(ironworks, path)?
+		.into_iter()
+		.map(|frame| fit(frame, options.width, options.height))
+		.collect();
+
+	Ok(Conversion::new(move |writer| {
+		texture::write_apng(frames, writer)
+	}))
+}
+
+pub struct Raw;
+
+impl Converter for Raw {
+	fn supports(&self, _path: &str) -> bool {
+		// Any file can be returned as-is.
+		true
+	}
+
+	fn convert(
+		&self,
+		data: &bm_data::Version,
+		path: &str,
+		_format: Format,
+		_options: &ConvertOptions,
+	) -> Result<Conversion> {
+		let bytes = match data.ironworks().file::<Vec<u8>>(path) {
+			Ok(value) => value,
+			Err(ironworks::Error::NotFound(_)) => return Err(Error::NotFound(path.into())),
+			other => other.context("read file")?,
+		};
+
+		Ok(Conversion::new(move |writer| {
+			writer.write_all(&bytes).context("failed to write output")?;
+			Ok(())
+		}))
+	}
+}
+
+/// Scale an image down to fit within the given bounds, if any, preserving its
+/// aspect ratio. Images are never scaled up.
+fn fit(image: DynamicImage, width: Option<u32>, height: Option<u32>) -> DynamicImage {
+	let width = width.unwrap_or(u32::MAX);
+	let height = height.unwrap_or(u32::MAX);
+
+	if image.width() <= width && image.height() <= height {
+		return image;
+	}
+
+	image.resize(width, height, FilterType::Lanczos3)
+}
+
+#[cfg(test)]
+mod test {
+	use image::RgbaImage;
+
+	use super::*;
+
+	fn image(width: u32, height: u32) -> DynamicImage {
+		RgbaImage::new(width, height).into()
+	}
+
+	#[test]
+	fn fit_preserves_aspect_ratio() {
+		let got = fit(image(100, 50), Some(20), None);
+		assert_eq!((got.width(), got.height()), (20, 10));
+
+		let got = fit(image(100, 50), Some(80), Some(20));
+		assert_eq!((got.width(), got.height()), (40, 20));
+	}
+
+	#[test]
+	fn formats_by_source_kind() {
+		let got = formats("ui/icon/051000/051474_hr1.tex");
+		assert_eq!(
+			got.iter().map(Format::extension).collect::<Vec<_>>(),
+			["avif", "jpg", "png", "webp", "raw"]
+		);
+
+		let got = formats("chara/equipment/e0001/model/c0101e0001_top.mdl");
+		assert_eq!(
+			got.iter().map(Format::extension).collect::<Vec<_>>(),
+			["raw"]
+		);
+	}
 
-			other => {
-				return Err(Error::InvalidConversion(
-					other.unwrap_or("(none)").into(),
-					format,
-				));
-			}
-		}?;
+	#[test]
+	fn fit_does_not_upscale() {
+		let got = fit(image(100, 50), Some(200), Some(200));
+		assert_eq!((got.width(), got.height()), (100, 50));
 
-		texture::write(buffer, output_format)
+		let got = fit(image(100, 50), None, None);
+		assert_eq!((got.width(), got.height()), (100, 50));
 	}
 }
@@ -14,6 +14,9 @@ pub enum Error {
 	#[error("{0} cannot be converted to {1:?}")]
 	InvalidConversion(String, Format),
 
+	#[error("invalid conversion options: {0}")]
+	InvalidOptions(String),
+
 	#[error(transparent)]
 	Failure(#[from] anyhow::Error),
 }
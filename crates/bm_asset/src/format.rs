@@ -7,9 +7,12 @@ use super::{convert, error::Error};
 
 #[derive(Debug, Clone, Copy, EnumIter)]
 pub enum Format {
+	Avif,
 	Jpeg,
 	Png,
 	Webp,
+	/// The source file, without conversion.
+	Raw,
 }
 
 impl Format {
@@ -19,17 +22,21 @@ impl Format {
 
 	pub fn extension(&self) -> &str {
 		match self {
+			Self::Avif => "avif",
 			Self::Jpeg => "jpg",
 			Self::Png => "png",
 			Self::Webp => "webp",
+			Self::Raw => "raw",
 		}
 	}
 
 	pub(super) fn converter(&self) -> &dyn convert::Converter {
 		match self {
+			Self::Avif => &convert::Image,
 			Self::Jpeg => &convert::Image,
 			Self::Png => &convert::Image,
 			Self::Webp => &convert::Image,
+			Self::Raw => &convert::Raw,
 		}
 	}
 }
@@ -49,9 +56,11 @@ impl FromStr for Format {
 
 	fn from_str(input: &str) -> Result<Self, Self::Err> {
 		Ok(match input {
+			"avif" => Self::Avif,
 			"jpg" => Self::Jpeg,
 			"png" => Self::Png,
 			"webp" => Self::Webp,
+			"raw" => Self::Raw,
 			other => return Err(Error::UnknownFormat(other.into())),
 		})
 	}
@@ -1,7 +1,15 @@
 mod convert;
 mod error;
 mod format;
+mod map;
 mod service;
 mod texture;
+pub mod uld;
 
-pub use {error::Error, format::Format, service::Service};
+pub use {
+	convert::{Conversion, ConvertOptions},
+	error::Error,
+	format::Format,
+	map::MapLayer,
+	service::{AssetInfo, Config, Service},
+};
@@ -0,0 +1,43 @@
+use std::str::FromStr;
+
+use super::error::Error;
+
+/// Layers that may be composed into a map image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapLayer {
+	/// The map texture itself.
+	Base,
+	/// Background texture, multiplied into the base layer when both are present.
+	Mask,
+}
+
+impl FromStr for MapLayer {
+	type Err = Error;
+
+	fn from_str(input: &str) -> Result<Self, Self::Err> {
+		Ok(match input {
+			"base" => Self::Base,
+			"mask" => Self::Mask,
+			other => {
+				return Err(Error::InvalidOptions(format!(
+					"unknown map layer \"{other}\""
+				)))
+			}
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn parse_layers() {
+		assert_eq!("base".parse::<MapLayer>().unwrap(), MapLayer::Base);
+		assert_eq!("mask".parse::<MapLayer>().unwrap(), MapLayer::Mask);
+		assert!(matches!(
+			"overlay".parse::<MapLayer>(),
+			Err(Error::InvalidOptions(_))
+		));
+	}
+}
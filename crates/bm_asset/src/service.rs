@@ -4,20 +4,43 @@ use anyhow::{anyhow, Context};
 use bm_version::VersionKey;
 use image::{ImageBuffer, Pixel, Rgb};
 use ironworks::Ironworks;
+use serde::Deserialize;
 
 use super::{
+	convert::{self, Conversion, ConvertOptions},
 	error::{Error, Result},
 	format::Format,
-	texture,
+	map::MapLayer,
+	texture, uld,
 };
 
+#[derive(Debug, Deserialize)]
+pub struct Config {
+	/// Maximum width or height that converted images may be resized to.
+	max_dimension: u32,
+}
+
+/// Metadata about a source asset.
+#[derive(Debug)]
+pub struct AssetInfo {
+	/// Kind of the source file, i.e. its extension.
+	pub kind: String,
+	pub width: u32,
+	pub height: u32,
+	pub mip_count: u32,
+}
+
 pub struct Service {
 	data: Arc<bm_data::Data>,
+	max_dimension: u32,
 }
 
 impl Service {
-	pub fn new(data: Arc<bm_data::Data>) -> Self {
-		Self { data }
+	pub fn new(config: Config, data: Arc<bm_data::Data>) -> Self {
+		Self {
+			data,
+			max_dimension: config.max_dimension,
+		}
 	}
 
 	pub fn ready(&self) -> bool {
@@ -25,19 +48,92 @@ impl Service {
 		true
 	}
 
-	pub fn convert(&self, version: VersionKey, path: &str, format: Format) -> Result<Vec<u8>> {
+	/// Convert the asset at the given path. If a language code is provided, a
+	/// localized variant of the asset will be used if one exists. Output is not
+	/// encoded until the returned conversion is written.
+	pub fn convert(
+		&self,
+		version: VersionKey,
+		path: &str,
+		format: Format,
+		language: Option<&str>,
+		options: &ConvertOptions,
+	) -> Result<Conversion> {
 		// TODO: presumably this is where caching would be resolved
 
+		self.validate_options(options)?;
+
 		let data_version = self
 			.data
 			.version(version)
 			.with_context(|| format!("data for {version} not ready"))?;
 
 		let converter = format.converter();
-		converter.convert(&data_version, path, format)
+		resolve_localized(path, language, |path| {
+			converter.convert(&data_version, path, format, options)
+		})
+	}
+
+	/// Formats that the asset at the given path can be converted into. As the
+	/// raw format returns files unchanged, it is available for any path.
+	pub fn formats(&self, path: &str) -> Vec<Format> {
+		convert::formats(path)
+	}
+
+	/// Read metadata about the asset at the given path, without converting it.
+	pub fn info(&self, version: VersionKey, path: &str) -> Result<AssetInfo> {
+		let version = self
+			.data
+			.version(version)
+			.with_context(|| format!("data for {version} not ready"))?;
+
+		read_info(&version.ironworks(), path)
+	}
+
+	fn validate_options(&self, options: &ConvertOptions) -> Result<()> {
+		let dimensions = [("width", options.width), ("height", options.height)];
+		for (name, value) in dimensions {
+			match value {
+				Some(0) => {
+					return Err(Error::InvalidOptions(format!("{name} must be non-zero")));
+				}
+				Some(value) if value > self.max_dimension => {
+					return Err(Error::InvalidOptions(format!(
+						"{name} {value} exceeds maximum of {}",
+						self.max_dimension
+					)));
+				}
+				_ => {}
+			}
+		}
+
+		Ok(())
 	}
 
-	pub fn map(&self, version: VersionKey, territory: &str, index: &str) -> Result<Vec<u8>> {
+	/// Compose the specified map. If no layers are specified, all available
+	/// layers will be composed.
+	pub fn map(
+		&self,
+		version: VersionKey,
+		territory: &str,
+		index: &str,
+		layers: Option<&[MapLayer]>,
+	) -> Result<Conversion> {
+		let version = self
+			.data
+			.version(version)
+			.with_context(|| format!("data for {version} not ready"))?;
+
+		let ironworks = version.ironworks();
+
+		let image = self.compose_map(&ironworks, territory, index, layers)?;
+
+		Ok(Conversion::new(move |writer| {
+			texture::write(image, image::ImageFormat::Jpeg, None, writer)
+		}))
+	}
+
+	pub fn uld(&self, version: VersionKey, path: &str) -> Result<uld::Layout> {
 		let version = self
 			.data
 			.version(version)
@@ -45,9 +141,14 @@ impl Service {
 
 		let ironworks = version.ironworks();
 
-		let image = self.compose_map(&ironworks, territory, index)?;
+		let bytes = match ironworks.file::<Vec<u8>>(path) {
+			Ok(value) => value,
+			Err(ironworks::Error::NotFound(_)) => return Err(Error::NotFound(path.into())),
+			other => other.context("read file")?,
+		};
 
-		texture::write(image, image::ImageFormat::Jpeg)
+		uld::parse(&bytes)
+			.map_err(|error| Error::UnsupportedSource(path.into(), format!("{error:#}")))
 	}
 
 	fn compose_map(
@@ -55,11 +156,28 @@ impl Service {
 		ironworks: &Ironworks,
 		territory: &str,
 		index: &str,
+		layers: Option<&[MapLayer]>,
 	) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>> {
+		let include = |layer| layers.is_none_or(|layers| layers.contains(&layer));
+
 		let path = format!("ui/map/{territory}/{index}/{territory}{index}");
-		let mut buffer_map = texture::read(&ironworks, &format!("{path}_m.tex"))?.into_rgb8();
+		let read_background = || texture::read(&ironworks, &format!("{path}m_m.tex"), 0);
 
-		let buffer_background = match texture::read(&ironworks, &format!("{path}m_m.tex")) {
+		if !include(MapLayer::Base) {
+			return match include(MapLayer::Mask) {
+				true => Ok(read_background()?.into_rgb8()),
+				false => Err(Error::InvalidOptions(
+					"at least one map layer must be selected".into(),
+				)),
+			};
+		}
+
+		let mut buffer_map = texture::read(&ironworks, &format!("{path}_m.tex"), 0)?.into_rgb8();
+		if !include(MapLayer::Mask) {
+			return Ok(buffer_map);
+		}
+
+		let buffer_background = match read_background() {
 			// If the background texture wasn't found, we can assume the map texture is pre-composed.
 			Err(Error::NotFound(_)) => return Ok(buffer_map),
 			Ok(image) => image.into_rgb8(),
@@ -79,3 +197,156 @@ impl Service {
 		Ok(buffer_map)
 	}
 }
+
+fn read_info(ironworks: &Ironworks, path: &str) -> Result<AssetInfo> {
+	let kind = match path.rsplit_once('.') {
+		Some((_, extension @ ("tex" | "atex"))) => extension,
+		_ => {
+			return Err(Error::UnsupportedSource(
+				path.into(),
+				"metadata is only available for textures".into(),
+			))
+		}
+	};
+
+	let info = texture::info(ironworks, path)?;
+
+	Ok(AssetInfo {
+		kind: kind.into(),
+		width: info.width,
+		height: info.height,
+		mip_count: info.mip_count,
+	})
+}
+
+/// Read a localized variant of a path for the given language, falling back to
+/// the base path if no such variant exists.
+fn resolve_localized<T>(
+	path: &str,
+	language: Option<&str>,
+	read: impl Fn(&str) -> Result<T>,
+) -> Result<T> {
+	if let Some(localized) = language.and_then(|language| localized_path(path, language)) {
+		match read(&localized) {
+			Err(Error::NotFound(_)) => {}
+			other => return other,
+		}
+	}
+
+	read(path)
+}
+
+/// Build the localized variant of a path, i.e. `foo.tex` -> `foo_en.tex`.
+fn localized_path(path: &str, language: &str) -> Option<String> {
+	let (stem, extension) = path.rsplit_once('.')?;
+	// Avoid treating a dot in a directory name as the extension separator.
+	if extension.contains('/') {
+		return None;
+	}
+
+	Some(format!("{stem}_{language}.{extension}"))
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+
+	use super::*;
+
+	/// Resource serving a single uncompressed 4x2 texture at `ui/test.tex`.
+	struct TextureResource;
+
+	impl ironworks::Resource for TextureResource {
+		fn version(&self, _path: &str) -> ironworks::Result<String> {
+			Ok("test".into())
+		}
+
+		fn file(&self, path: &str) -> ironworks::Result<Box<dyn ironworks::FileStream>> {
+			if path != "ui/test.tex" {
+				return Err(ironworks::Error::NotFound(ironworks::ErrorValue::Path(
+					path.into(),
+				)));
+			}
+
+			let mut bytes = vec![];
+			// Attributes (2D texture), format (B8G8R8A8).
+			bytes.extend(0x0080_0000u32.to_le_bytes());
+			bytes.extend(0x1450u32.to_le_bytes());
+			// Width, height, depth, mip levels, array size.
+			bytes.extend(4u16.to_le_bytes());
+			bytes.extend(2u16.to_le_bytes());
+			bytes.extend(1u16.to_le_bytes());
+			bytes.extend([1, 1]);
+			// LoD offsets, and surface offsets with the single mip following the header.
+			bytes.extend([0; 12]);
+			bytes.extend(80u32.to_le_bytes());
+			bytes.extend([0; 48]);
+			bytes.extend([0xFF; 4 * 2 * 4]);
+
+			Ok(Box::new(Cursor::new(bytes)))
+		}
+	}
+
+	fn texture_ironworks() -> Ironworks {
+		Ironworks::new().with_resource(TextureResource)
+	}
+
+	fn read_existing<'a>(existing: &'a [&'a str]) -> impl Fn(&str) -> Result<String> + 'a {
+		|path: &str| match existing.iter().any(|existing| *existing == path) {
+			true => Ok(path.to_string()),
+			false => Err(Error::NotFound(path.into())),
+		}
+	}
+
+	#[test]
+	fn localized_variant_hit() {
+		let read = read_existing(&["ui/uld/Logo.tex", "ui/uld/Logo_ja.tex"]);
+		let got = resolve_localized("ui/uld/Logo.tex", Some("ja"), read).unwrap();
+		assert_eq!(got, "ui/uld/Logo_ja.tex");
+	}
+
+	#[test]
+	fn localized_variant_fallback() {
+		let read = read_existing(&["ui/uld/Logo.tex"]);
+		let got = resolve_localized("ui/uld/Logo.tex", Some("de"), &read).unwrap();
+		assert_eq!(got, "ui/uld/Logo.tex");
+
+		let got = resolve_localized("ui/uld/Logo.tex", None, &read).unwrap();
+		assert_eq!(got, "ui/uld/Logo.tex");
+	}
+
+	#[test]
+	fn texture_info() {
+		let info = read_info(&texture_ironworks(), "ui/test.tex").unwrap();
+		assert_eq!(info.kind, "tex");
+		assert_eq!((info.width, info.height, info.mip_count), (4, 2, 1));
+	}
+
+	#[test]
+	fn info_unsupported_source() {
+		let ironworks = texture_ironworks();
+		assert!(matches!(
+			read_info(&ironworks, "ui/test.png"),
+			Err(Error::UnsupportedSource(..))
+		));
+		assert!(matches!(
+			read_info(&ironworks, "ui/missing.tex"),
+			Err(Error::NotFound(..))
+		));
+	}
+
+	#[test]
+	fn info_unknown_version() {
+		let service = Service::new(
+			Config {
+				max_dimension: 4096,
+			},
+			Arc::new(bm_data::Data::new()),
+		);
+		let key = "00000000000000ff".parse::<VersionKey>().unwrap();
+		assert!(matches!(
+			service.info(key, "ui/test.tex"),
+			Err(Error::Failure(..))
+		));
+	}
+}
@@ -1,19 +1,26 @@
-use std::io::Cursor;
+use std::io::Write;
 
-use anyhow::Context;
-use image::{DynamicImage, ImageBuffer, ImageFormat};
+use anyhow::{anyhow, Context};
+use image::{
+	codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+	DynamicImage, ImageBuffer, ImageFormat,
+};
 use image_dds::Surface;
 use ironworks::{file::tex, Ironworks};
 use itertools::Itertools;
 
 use super::error::{Error, Result};
 
-pub fn read(ironworks: &Ironworks, path: &str) -> Result<DynamicImage> {
-	let texture = match ironworks.file::<tex::Texture>(path) {
-		Ok(value) => value,
-		Err(ironworks::Error::NotFound(_)) => return Err(Error::NotFound(path.into())),
-		other => other.context("read file")?,
-	};
+// Animated textures do not store their frame timing - it's driven by the
+// consuming effect. Frames are emitted at a fixed rate of 10 per second.
+const FRAME_DELAY_NUMERATOR: u16 = 1;
+const FRAME_DELAY_DENOMINATOR: u16 = 10;
+
+// Matches the default speed used by the AVIF encoder.
+const AVIF_SPEED: u8 = 4;
+
+pub fn read(ironworks: &Ironworks, path: &str, mip: u32) -> Result<DynamicImage> {
+	let texture = open(ironworks, path)?;
 
 	if !matches!(texture.kind(), tex::TextureKind::D2) {
 		return Err(Error::UnsupportedSource(
@@ -22,45 +29,169 @@ pub fn read(ironworks: &Ironworks, path: &str) -> Result<DynamicImage> {
 		));
 	}
 
+	let mip_levels = u32::from(texture.mip_levels()).max(1);
+	if mip >= mip_levels {
+		return Err(Error::InvalidOptions(format!(
+			"mip level {mip} requested, but \"{path}\" has {mip_levels} levels"
+		)));
+	}
+
 	let buffer = match texture.format() {
-		tex::Format::A8Unorm => read_a8(texture)?,
-
-		tex::Format::Bgra4Unorm => read_bgra4(texture)?,
-		tex::Format::Bgr5a1Unorm => read_bgr5a1(texture)?,
-		tex::Format::Bgra8Unorm => read_bgra8(texture)?,
-
-		tex::Format::Bc1Unorm => read_texture_bc(texture, image_dds::ImageFormat::BC1RgbaUnorm)?,
-		tex::Format::Bc2Unorm => read_texture_bc(texture, image_dds::ImageFormat::BC2RgbaUnorm)?,
-		tex::Format::Bc3Unorm => read_texture_bc(texture, image_dds::ImageFormat::BC3RgbaUnorm)?,
-		tex::Format::Bc4Unorm => read_texture_bc(texture, image_dds::ImageFormat::BC4RUnorm)?,
-		tex::Format::Bc5Unorm => read_texture_bc(texture, image_dds::ImageFormat::BC5RgUnorm)?,
-		tex::Format::Bc6hFloat => read_texture_bc(texture, image_dds::ImageFormat::BC6hRgbSfloat)?,
-		tex::Format::Bc7Unorm => read_texture_bc(texture, image_dds::ImageFormat::BC7RgbaUnorm)?,
-
-		other => {
-			return Err(Error::UnsupportedSource(
-				path.into(),
-				format!("unhandled texture format {other:?}"),
-			))
-		}
+		tex::Format::A8Unorm => read_a8(texture, mip)?,
+
+		tex::Format::Bgra4Unorm => read_bgra4(texture, mip)?,
+		tex::Format::Bgr5a1Unorm => read_bgr5a1(texture, mip)?,
+		tex::Format::Bgra8Unorm => read_bgra8(texture, mip)?,
+
+		other => match bc_format(other) {
+			Some(image_format) => read_texture_bc(texture, mip, image_format)?,
+			None => {
+				return Err(Error::UnsupportedSource(
+					path.into(),
+					format!("unhandled texture format {other:?}"),
+				))
+			}
+		},
 	};
 
 	Ok(buffer)
 }
 
-fn read_a8(texture: tex::Texture) -> Result<DynamicImage> {
-	let buffer = ImageBuffer::from_raw(
+/// Metadata describing a texture.
+#[derive(Debug)]
+pub struct TextureInfo {
+	pub width: u32,
+	pub height: u32,
+	pub mip_count: u32,
+}
+
+/// Read the metadata of a texture, without decoding its contents.
+pub fn info(ironworks: &Ironworks, path: &str) -> Result<TextureInfo> {
+	let texture = open(ironworks, path)?;
+
+	Ok(TextureInfo {
+		width: texture.width().into(),
+		height: texture.height().into(),
+		mip_count: u32::from(texture.mip_levels()).max(1),
+	})
+}
+
+/// Read each frame of an animated texture. Animated textures are stored as 2D
+/// texture arrays, with one layer per frame.
+pub fn read_frames(ironworks: &Ironworks, path: &str) -> Result<Vec<DynamicImage>> {
+	let texture = open(ironworks, path)?;
+
+	let frame_count = u32::from(texture.array_size());
+	if !matches!(texture.kind(), tex::TextureKind::D2Array) || frame_count < 2 {
+		return Err(Error::InvalidOptions(format!(
+			"\"{path}\" is not an animated texture"
+		)));
+	}
+
+	let image_format = bc_format(texture.format()).ok_or_else(|| {
+		Error::UnsupportedSource(
+			path.into(),
+			format!("unhandled animated texture format {:?}", texture.format()),
+		)
+	})?;
+
+	// Decoded layers are stacked vertically in a single image.
+	let image = read_texture_bc(texture, 0, image_format)?;
+	split_frames(image, frame_count)
+}
+
+fn open(ironworks: &Ironworks, path: &str) -> Result<tex::Texture> {
+	let texture = match ironworks.file::<tex::Texture>(path) {
+		Ok(value) => value,
+		Err(ironworks::Error::NotFound(_)) => return Err(Error::NotFound(path.into())),
+		other => other.context("read file")?,
+	};
+
+	Ok(texture)
+}
+
+fn bc_format(format: tex::Format) -> Option<image_dds::ImageFormat> {
+	use image_dds::ImageFormat as IF;
+	let image_format = match format {
+		tex::Format::Bc1Unorm => IF::BC1RgbaUnorm,
+		tex::Format::Bc2Unorm => IF::BC2RgbaUnorm,
+		tex::Format::Bc3Unorm => IF::BC3RgbaUnorm,
+		tex::Format::Bc4Unorm => IF::BC4RUnorm,
+		tex::Format::Bc5Unorm => IF::BC5RgUnorm,
+		tex::Format::Bc6hFloat => IF::BC6hRgbSfloat,
+		tex::Format::Bc7Unorm => IF::BC7RgbaUnorm,
+		_ => return None,
+	};
+
+	Some(image_format)
+}
+
+fn split_frames(image: DynamicImage, frame_count: u32) -> Result<Vec<DynamicImage>> {
+	let width = image.width();
+	let height = image.height() / frame_count;
+	if height == 0 || height * frame_count != image.height() {
+		return Err(anyhow!(
+			"cannot split {} rows into {frame_count} frames",
+			image.height()
+		)
+		.into());
+	}
+
+	let frames = (0..frame_count)
+		.map(|index| image.crop_imm(0, index * height, width, height))
+		.collect();
+
+	Ok(frames)
+}
+
+/// Locate the data for a mip level of an uncompressed texture. Levels are
+/// stored sequentially, each half the dimensions of the previous.
+fn mip_data(
+	width: u32,
+	height: u32,
+	data: &[u8],
+	mip: u32,
+	bytes_per_pixel: usize,
+) -> Result<(u32, u32, &[u8])> {
+	let dimensions = |level: u32| ((width >> level).max(1), (height >> level).max(1));
+	let length = |level: u32| {
+		let (width, height) = dimensions(level);
+		width as usize * height as usize * bytes_per_pixel
+	};
+
+	let offset = (0..mip).map(length).sum::<usize>();
+	let data = data
+		.get(offset..offset + length(mip))
+		.with_context(|| format!("texture data too short for mip level {mip}"))?;
+
+	let (width, height) = dimensions(mip);
+	Ok((width, height, data))
+}
+
+fn texture_mip_data(
+	texture: &tex::Texture,
+	mip: u32,
+	bytes_per_pixel: usize,
+) -> Result<(u32, u32, &[u8])> {
+	mip_data(
 		texture.width().into(),
 		texture.height().into(),
-		texture.data().to_owned(),
+		texture.data(),
+		mip,
+		bytes_per_pixel,
 	)
-	.context("failed to build image buffer")?;
+}
+
+fn read_a8(texture: tex::Texture, mip: u32) -> Result<DynamicImage> {
+	let (width, height, data) = texture_mip_data(&texture, mip, 1)?;
+	let buffer = ImageBuffer::from_raw(width, height, data.to_owned())
+		.context("failed to build image buffer")?;
 	Ok(DynamicImage::ImageLuma8(buffer))
 }
 
-fn read_bgra4(texture: tex::Texture) -> Result<DynamicImage> {
-	let data = texture
-		.data()
+fn read_bgra4(texture: tex::Texture, mip: u32) -> Result<DynamicImage> {
+	let (width, height, data) = texture_mip_data(&texture, mip, 2)?;
+	let data = data
 		.iter()
 		.tuples()
 		.flat_map(|(gb, ar)| {
@@ -72,14 +203,14 @@ fn read_bgra4(texture: tex::Texture) -> Result<DynamicImage> {
 		})
 		.collect::<Vec<_>>();
 
-	let buffer = ImageBuffer::from_raw(texture.width().into(), texture.height().into(), data)
-		.context("failed to build image buffer")?;
+	let buffer =
+		ImageBuffer::from_raw(width, height, data).context("failed to build image buffer")?;
 	Ok(DynamicImage::ImageRgba8(buffer))
 }
 
-fn read_bgr5a1(texture: tex::Texture) -> Result<DynamicImage> {
-	let data = texture
-		.data()
+fn read_bgr5a1(texture: tex::Texture, mip: u32) -> Result<DynamicImage> {
+	let (width, height, data) = texture_mip_data(&texture, mip, 2)?;
+	let data = data
 		.iter()
 		.tuples()
 		.flat_map(|(b, a)| {
@@ -93,29 +224,30 @@ fn read_bgr5a1(texture: tex::Texture) -> Result<DynamicImage> {
 		.map(|value| u8::try_from(value).unwrap())
 		.collect::<Vec<_>>();
 
-	let buffer = ImageBuffer::from_raw(texture.width().into(), texture.height().into(), data)
-		.context("failed to build image buffer")?;
+	let buffer =
+		ImageBuffer::from_raw(width, height, data).context("failed to build image buffer")?;
 	Ok(DynamicImage::ImageRgba8(buffer))
 }
 
-fn read_bgra8(texture: tex::Texture) -> Result<DynamicImage> {
+fn read_bgra8(texture: tex::Texture, mip: u32) -> Result<DynamicImage> {
 	// TODO: seems really wasteful to copy the entire image in memory just to reassign the channels. think of a better way to do this.
 	// TODO: use array_chunks once it hits stable
-	let data = texture
-		.data()
+	let (width, height, data) = texture_mip_data(&texture, mip, 4)?;
+	let data = data
 		.iter()
 		.tuples()
 		.flat_map(|(b, g, r, a)| [r, g, b, a])
 		.copied()
 		.collect::<Vec<_>>();
 
-	let buffer = ImageBuffer::from_raw(texture.width().into(), texture.height().into(), data)
-		.context("failed to build image buffer")?;
+	let buffer =
+		ImageBuffer::from_raw(width, height, data).context("failed to build image buffer")?;
 	Ok(DynamicImage::ImageRgba8(buffer))
 }
 
 fn read_texture_bc(
 	texture: tex::Texture,
+	mip: u32,
 	image_format: image_dds::ImageFormat,
 ) -> Result<DynamicImage> {
 	let surface = Surface {
@@ -135,14 +267,26 @@ fn read_texture_bc(
 	let image = surface
 		.decode_rgba8()
 		.with_context(|| format!("failed to decode {image_format:?}"))?
-		.to_image(0)
+		.to_image(mip)
 		.context("failed to build image from buffer")?;
 
 	Ok(image.into())
 }
 
-pub fn write(image: impl Into<DynamicImage>, format: ImageFormat) -> Result<Vec<u8>> {
-	fn inner(mut image: DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+/// Write an image in the given format. If provided, quality (1-100) will be
+/// used by lossy encoders, otherwise encoder defaults are used.
+pub fn write(
+	image: impl Into<DynamicImage>,
+	format: ImageFormat,
+	quality: Option<u8>,
+	writer: impl Write,
+) -> Result<()> {
+	fn inner(
+		mut image: DynamicImage,
+		format: ImageFormat,
+		quality: Option<u8>,
+		writer: impl Write,
+	) -> Result<()> {
 		// JPEG encoder errors out on anything with an alpha channel.
 		if format == ImageFormat::Jpeg {
 			image = match image {
@@ -153,14 +297,154 @@ pub fn write(image: impl Into<DynamicImage>, format: ImageFormat) -> Result<Vec<
 			}
 		}
 
-		// TODO: are there any non-failure cases here?
-		let mut bytes = Cursor::new(vec![]);
-		image
-			.write_to(&mut bytes, format)
-			.context("failed to write output buffer")?;
+		// Encoders are constructed directly rather than via `write_to`, as they
+		// only require `Write`, allowing output to be streamed without seeking.
+		let quality = quality.map(|quality| quality.clamp(1, 100));
+		match (format, quality) {
+			(ImageFormat::Avif, Some(quality)) => image.write_with_encoder(
+				AvifEncoder::new_with_speed_quality(writer, AVIF_SPEED, quality),
+			),
+			(ImageFormat::Avif, None) => image.write_with_encoder(AvifEncoder::new(writer)),
+			(ImageFormat::Jpeg, Some(quality)) => {
+				image.write_with_encoder(JpegEncoder::new_with_quality(writer, quality))
+			}
+			(ImageFormat::Jpeg, None) => image.write_with_encoder(JpegEncoder::new(writer)),
+			// Remaining formats are lossless, and have no quality to configure.
+			(ImageFormat::Png, _) => image.write_with_encoder(PngEncoder::new(writer)),
+			(ImageFormat::WebP, _) => image.write_with_encoder(WebPEncoder::new_lossless(writer)),
+			(other, _) => return Err(anyhow!("unhandled output format {other:?}").into()),
+		}
+		.context("failed to write output buffer")?;
 
-		Ok(bytes.into_inner())
+		Ok(())
 	}
 
-	inner(image.into(), format)
+	inner(image.into(), format, quality, writer)
+}
+
+/// Write frames to an animated PNG. All frames must share the same dimensions.
+pub fn write_apng(frames: Vec<DynamicImage>, writer: impl Write) -> Result<()> {
+	let first = frames.first().context("animation has no frames")?;
+	let (width, height) = (first.width(), first.height());
+	let frame_count = u32::try_from(frames.len()).context("too many frames")?;
+
+	let mut encoder = png::Encoder::new(writer, width, height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder
+		.set_animated(frame_count, 0)
+		.context("failed to configure animation")?;
+	encoder
+		.set_frame_delay(FRAME_DELAY_NUMERATOR, FRAME_DELAY_DENOMINATOR)
+		.context("failed to configure frame delay")?;
+
+	let mut writer = encoder
+		.write_header()
+		.context("failed to write png header")?;
+	for frame in frames {
+		writer
+			.write_image_data(frame.into_rgba8().as_raw())
+			.context("failed to write frame")?;
+	}
+	writer.finish().context("failed to finish png")?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+
+	use image::{Rgba, RgbaImage};
+
+	use super::*;
+
+	#[test]
+	fn mip_level_data() {
+		// 4x2 single-channel texture, with 2x1 and 1x1 mips.
+		let data = (0..11).collect::<Vec<u8>>();
+
+		let (width, height, got) = mip_data(4, 2, &data, 0, 1).unwrap();
+		assert_eq!((width, height), (4, 2));
+		assert_eq!(got, &data[0..8]);
+
+		let (width, height, got) = mip_data(4, 2, &data, 1, 1).unwrap();
+		assert_eq!((width, height), (2, 1));
+		assert_eq!(got, &data[8..10]);
+
+		let (width, height, got) = mip_data(4, 2, &data, 2, 1).unwrap();
+		assert_eq!((width, height), (1, 1));
+		assert_eq!(got, &data[10..11]);
+
+		assert!(mip_data(4, 2, &data, 3, 1).is_err());
+	}
+
+	#[test]
+	fn split_stacked_frames() {
+		let mut image = RgbaImage::new(2, 6);
+		image.put_pixel(0, 2, Rgba([255, 0, 0, 255]));
+		image.put_pixel(0, 4, Rgba([0, 255, 0, 255]));
+
+		let frames = split_frames(image.into(), 3).unwrap();
+		assert_eq!(frames.len(), 3);
+		assert!(frames
+			.iter()
+			.all(|frame| (frame.width(), frame.height()) == (2, 2)));
+		assert_eq!(
+			frames[1].to_rgba8().get_pixel(0, 0),
+			&Rgba([255, 0, 0, 255])
+		);
+		assert_eq!(
+			frames[2].to_rgba8().get_pixel(0, 0),
+			&Rgba([0, 255, 0, 255])
+		);
+
+		assert!(split_frames(RgbaImage::new(2, 5).into(), 3).is_err());
+	}
+
+	#[test]
+	fn write_animated_png() {
+		let frames = [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])]
+			.map(|pixel| RgbaImage::from_pixel(4, 4, pixel).into())
+			.to_vec();
+
+		let mut bytes = vec![];
+		write_apng(frames, &mut bytes).expect("encode should not fail");
+
+		assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+		// Animation control chunk marks the file as an APNG.
+		assert!(bytes.windows(4).any(|window| window == b"acTL"));
+	}
+
+	#[test]
+	fn write_jpeg_quality() {
+		let image = RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, 0, 255]));
+		let write_jpeg = |quality| {
+			let mut bytes = vec![];
+			write(image.clone(), ImageFormat::Jpeg, quality, &mut bytes).unwrap();
+			bytes
+		};
+
+		// Omitting quality must leave output unchanged from the encoder defaults.
+		let mut default = Cursor::new(vec![]);
+		DynamicImage::from(image.clone())
+			.into_rgb8()
+			.write_to(&mut default, ImageFormat::Jpeg)
+			.unwrap();
+		assert_eq!(write_jpeg(None), default.into_inner());
+
+		assert!(write_jpeg(Some(10)).len() < write_jpeg(Some(95)).len());
+		assert_eq!(write_jpeg(Some(0)), write_jpeg(Some(1)));
+	}
+
+	#[test]
+	fn write_avif() {
+		let image = RgbaImage::from_pixel(16, 16, Rgba([255, 128, 0, 255]));
+
+		let mut bytes = vec![];
+		write(image, ImageFormat::Avif, None, &mut bytes).expect("encode should not fail");
+
+		// AVIF files open with an ISO BMFF `ftyp` box declaring the `avif` brand.
+		assert_eq!(&bytes[4..12], b"ftypavif");
+	}
 }
@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Context, Result};
+
+// Asset paths are stored as fixed-size, null-padded strings.
+const ASSET_PATH_LENGTH: usize = 44;
+
+/// UI layout data read from a `.uld` file.
+#[derive(Debug, PartialEq)]
+pub struct Layout {
+	/// Textures referenced by the layout.
+	pub textures: Vec<Texture>,
+	/// Lists of texture regions, as referenced by layout components.
+	pub part_lists: Vec<PartList>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Texture {
+	pub id: u32,
+	pub path: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PartList {
+	pub id: u32,
+	pub parts: Vec<Part>,
+}
+
+/// A rectangular region of a texture.
+#[derive(Debug, PartialEq)]
+pub struct Part {
+	pub texture_id: u32,
+	pub u: u16,
+	pub v: u16,
+	pub width: u16,
+	pub height: u16,
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Layout> {
+	expect_magic(bytes, 0, b"uldh")?;
+	let atk_offset = read_offset(bytes, 8)?;
+
+	// Offsets within the ATK header are relative to the header itself. An offset
+	// of 0 marks a section as absent.
+	expect_magic(bytes, atk_offset, b"atkh")?;
+	let textures = match read_offset(bytes, atk_offset + 8)? {
+		0 => vec![],
+		offset => parse_textures(bytes, atk_offset + offset)?,
+	};
+	let part_lists = match read_offset(bytes, atk_offset + 12)? {
+		0 => vec![],
+		offset => parse_part_lists(bytes, atk_offset + offset)?,
+	};
+
+	Ok(Layout {
+		textures,
+		part_lists,
+	})
+}
+
+fn parse_textures(bytes: &[u8], offset: usize) -> Result<Vec<Texture>> {
+	let version = expect_magic(bytes, offset, b"ashd")?;
+	let count = read_u32(bytes, offset + 8)?;
+
+	// Later revisions of the asset list include an additional trailing field.
+	let entry_size = match version {
+		b"0100" => 4 + ASSET_PATH_LENGTH + 4,
+		_ => 4 + ASSET_PATH_LENGTH + 8,
+	};
+
+	(0..usize::try_from(count)?)
+		.map(|index| {
+			let entry = offset + 16 + index * entry_size;
+			let path_bytes = read_bytes(bytes, entry + 4, ASSET_PATH_LENGTH)?;
+			let path_length = path_bytes
+				.iter()
+				.position(|byte| *byte == 0)
+				.unwrap_or(ASSET_PATH_LENGTH);
+
+			Ok(Texture {
+				id: read_u32(bytes, entry)?,
+				path: String::from_utf8_lossy(&path_bytes[..path_length]).into_owned(),
+			})
+		})
+		.collect()
+}
+
+fn parse_part_lists(bytes: &[u8], offset: usize) -> Result<Vec<PartList>> {
+	expect_magic(bytes, offset, b"tphd")?;
+	let count = read_u32(bytes, offset + 8)?;
+
+	let mut entry = offset + 16;
+	let mut part_lists = vec![];
+	for _ in 0..count {
+		let part_count = usize::try_from(read_u32(bytes, entry + 4)?)?;
+		let parts = (0..part_count)
+			.map(|index| {
+				let part = entry + 12 + index * 12;
+				Ok(Part {
+					texture_id: read_u32(bytes, part)?,
+					u: read_u16(bytes, part + 4)?,
+					v: read_u16(bytes, part + 6)?,
+					width: read_u16(bytes, part + 8)?,
+					height: read_u16(bytes, part + 10)?,
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		part_lists.push(PartList {
+			id: read_u32(bytes, entry)?,
+			parts,
+		});
+
+		// Each list records its own size - prefer that over the computed size.
+		entry += match read_offset(bytes, entry + 8)? {
+			0 => 12 + part_count * 12,
+			size => size,
+		};
+	}
+
+	Ok(part_lists)
+}
+
+/// Check the section magic at the given offset, returning the section version.
+fn expect_magic<'a>(bytes: &'a [u8], offset: usize, magic: &[u8; 4]) -> Result<&'a [u8]> {
+	let header = read_bytes(bytes, offset, 8)?;
+	if &header[..4] != magic {
+		return Err(anyhow!(
+			"expected {} section at {offset:#x}",
+			String::from_utf8_lossy(magic)
+		));
+	}
+
+	Ok(&header[4..])
+}
+
+fn read_bytes(bytes: &[u8], offset: usize, length: usize) -> Result<&[u8]> {
+	bytes
+		.get(offset..offset + length)
+		.with_context(|| format!("unexpected end of file reading {length} bytes at {offset:#x}"))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16> {
+	let slice = read_bytes(bytes, offset, 2)?;
+	Ok(u16::from_le_bytes(slice.try_into()?))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+	let slice = read_bytes(bytes, offset, 4)?;
+	Ok(u32::from_le_bytes(slice.try_into()?))
+}
+
+fn read_offset(bytes: &[u8], offset: usize) -> Result<usize> {
+	Ok(usize::try_from(read_u32(bytes, offset)?)?)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn fixture() -> Vec<u8> {
+		let mut bytes = vec![];
+
+		// uld header, atk header directly after.
+		bytes.extend(b"uldh0100");
+		bytes.extend(16u32.to_le_bytes());
+		bytes.extend(0u32.to_le_bytes());
+
+		// atk header: asset list, part list, then unused sections.
+		bytes.extend(b"atkh0100");
+		bytes.extend(36u32.to_le_bytes());
+		bytes.extend(108u32.to_le_bytes());
+		bytes.extend([0u8; 20]);
+
+		// Asset list with a single texture.
+		bytes.extend(b"ashd0101");
+		bytes.extend(1u32.to_le_bytes());
+		bytes.extend(0u32.to_le_bytes());
+		bytes.extend(7u32.to_le_bytes());
+		let mut path = b"ui/uld/Example.tex".to_vec();
+		path.resize(ASSET_PATH_LENGTH, 0);
+		bytes.extend(path);
+		bytes.extend([0u8; 8]);
+
+		// Part list containing one list of two parts.
+		bytes.extend(b"tphd0100");
+		bytes.extend(1u32.to_le_bytes());
+		bytes.extend(0u32.to_le_bytes());
+		bytes.extend(3u32.to_le_bytes());
+		bytes.extend(2u32.to_le_bytes());
+		bytes.extend(36u32.to_le_bytes());
+		for (u, v, width, height) in [(0u16, 0u16, 32u16, 32u16), (32, 8, 16, 24)] {
+			bytes.extend(7u32.to_le_bytes());
+			for value in [u, v, width, height] {
+				bytes.extend(value.to_le_bytes());
+			}
+		}
+
+		bytes
+	}
+
+	#[test]
+	fn parse_fixture() {
+		let got = parse(&fixture()).expect("parse should not fail");
+
+		let expected = Layout {
+			textures: vec![Texture {
+				id: 7,
+				path: "ui/uld/Example.tex".into(),
+			}],
+			part_lists: vec![PartList {
+				id: 3,
+				parts: vec![
+					Part {
+						texture_id: 7,
+						u: 0,
+						v: 0,
+						width: 32,
+						height: 32,
+					},
+					Part {
+						texture_id: 7,
+						u: 32,
+						v: 8,
+						width: 16,
+						height: 24,
+					},
+				],
+			}],
+		};
+
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_invalid_magic() {
+		let mut bytes = fixture();
+		bytes[0] = b'x';
+		assert!(parse(&bytes).is_err());
+	}
+}
@@ -4,8 +4,10 @@ use std::{
 };
 
 use anyhow::Context;
-use bm_version::{VersionKey, VersionMessage};
+use bm_version::{Repository, VersionKey, VersionMessage};
 use ironworks::{excel::Excel, sqpack::SqPack, zipatch, Ironworks};
+use mini_moka::sync as moka;
+use nonempty::NonEmpty;
 use tokio::{
 	select,
 	sync::{broadcast, watch},
@@ -14,6 +16,10 @@ use tokio_util::sync::CancellationToken;
 
 use super::error::{Error, Result};
 
+// Each patch version holds its own view of the patch chain - keep only a
+// handful of recently requested patches around.
+const PATCH_VERSION_CACHE_CAPACITY: u64 = 16;
+
 enum OnKnown {
 	Skip,
 	Prepare,
@@ -26,6 +32,9 @@ pub struct Data {
 	zipatch: zipatch::ZiPatch,
 
 	versions: RwLock<HashMap<VersionKey, Arc<Version>>>,
+
+	// Versions truncated to an intermediate patch, built on demand.
+	patch_versions: moka::Cache<(VersionKey, String), Arc<Version>>,
 }
 
 impl Data {
@@ -36,6 +45,7 @@ impl Data {
 			channel: sender,
 			zipatch: zipatch::ZiPatch::new().with_persisted_lookups(),
 			versions: Default::default(),
+			patch_versions: moka::Cache::new(PATCH_VERSION_CACHE_CAPACITY),
 		}
 	}
 
@@ -65,6 +75,7 @@ impl Data {
 				result = receiver.recv() => match result {
 					Ok(VersionMessage::Hydrate(keys)) => self.hydrate_versions(version, keys, OnKnown::Skip)?,
 					Ok(VersionMessage::Changed(key)) => self.prepare_version(version, key)?,
+					Ok(VersionMessage::Removed(keys)) => self.remove_versions(keys),
 					Err(broadcast::error::RecvError::Lagged(skipped)) => {
 						tracing::warn!(skipped, "re-hydrating due to channel lag");
 						self.hydrate_versions(version, version.keys(), OnKnown::Prepare)?;
@@ -129,32 +140,16 @@ impl Data {
 			.version(version_key)
 			.context("version does not exist")?;
 
-		let view = version
-			.repositories
-			.into_iter()
-			.map(|repository| zipatch::PatchRepository {
-				patches: repository
-					.patches
-					.into_iter()
-					.map(|patch| zipatch::Patch {
-						path: patch.path,
-						name: patch.name,
-					})
-					.collect(),
-			})
-			.zip(0u8..)
-			.fold(self.zipatch.view(), |builder, (repository, index)| {
-				builder.with_repository(index, repository)
-			})
-			.build();
-
 		// Build a version and save it out to the struct.
-		let version = Version::new(view);
+		let version = Version::new(self.view(&version.repositories), version.repositories);
 		self.versions
 			.write()
 			.expect("poisoned")
 			.insert(version_key, Arc::new(version));
 
+		// Any patch-level versions were built from the previous repositories.
+		self.invalidate_patch_versions(&[version_key]);
+
 		tracing::debug!(key = %version_key, "version prepared");
 
 		// Broadcast the update.
@@ -166,6 +161,40 @@ impl Data {
 		Ok(())
 	}
 
+	fn view(&self, repositories: &[Repository]) -> zipatch::View {
+		repositories
+			.iter()
+			.map(|repository| zipatch::PatchRepository {
+				patches: repository
+					.patches
+					.iter()
+					.map(|patch| zipatch::Patch {
+						path: patch.path.clone(),
+						name: patch.name.clone(),
+					})
+					.collect(),
+			})
+			.zip(0u8..)
+			.fold(self.zipatch.view(), |builder, (repository, index)| {
+				builder.with_repository(index, repository)
+			})
+			.build()
+	}
+
+	fn remove_versions(&self, keys: Vec<VersionKey>) {
+		let mut versions = self.versions.write().expect("poisoned");
+		for key in &keys {
+			versions.remove(key);
+		}
+		drop(versions);
+
+		self.invalidate_patch_versions(&keys);
+
+		tracing::debug!(?keys, "versions removed");
+
+		self.broadcast_version_list();
+	}
+
 	pub fn version(&self, version: VersionKey) -> Result<Arc<Version>> {
 		let versions = self.versions.read().expect("poisoned");
 
@@ -175,6 +204,38 @@ impl Data {
 			.cloned()
 	}
 
+	/// Get a version with the repository containing the named patch truncated
+	/// such that the patch is the last to be applied.
+	pub fn version_at_patch(&self, version_key: VersionKey, patch: &str) -> Result<Arc<Version>> {
+		let cache_key = (version_key, patch.to_string());
+		if let Some(version) = self.patch_versions.get(&cache_key) {
+			return Ok(version);
+		}
+
+		let version = self.version(version_key)?;
+		let repositories = truncate_repositories(version.repositories.clone(), patch)?;
+		let patch_version = Arc::new(Version::new(self.view(&repositories), repositories));
+
+		self.patch_versions.insert(cache_key, patch_version.clone());
+
+		tracing::debug!(key = %version_key, patch, "patch version prepared");
+
+		Ok(patch_version)
+	}
+
+	fn invalidate_patch_versions(&self, keys: &[VersionKey]) {
+		let stale = self
+			.patch_versions
+			.iter()
+			.filter(|entry| keys.contains(&entry.key().0))
+			.map(|entry| entry.key().clone())
+			.collect::<Vec<_>>();
+
+		for cache_key in stale {
+			self.patch_versions.invalidate(&cache_key);
+		}
+	}
+
 	fn broadcast_version_list(&self) {
 		let versions = self.versions.read().expect("poisoned");
 		let keys = versions.keys().copied().collect::<Vec<_>>();
@@ -190,16 +251,45 @@ impl Data {
 	}
 }
 
+/// Truncate the patch chain of the repository containing the named patch, such
+/// that it is the last patch applied. Other repositories are left as-is.
+fn truncate_repositories(
+	mut repositories: Vec<Repository>,
+	patch: &str,
+) -> Result<Vec<Repository>> {
+	let (repository, index) = repositories
+		.iter_mut()
+		.find_map(|repository| {
+			let index = repository
+				.patches
+				.iter()
+				.position(|candidate| candidate.name == patch)?;
+			Some((repository, index))
+		})
+		.ok_or_else(|| Error::UnknownPatch(patch.to_string()))?;
+
+	let patches = repository.patches.iter().take(index + 1).cloned().collect();
+	repository.patches =
+		NonEmpty::from_vec(patches).expect("truncated chain should contain the named patch");
+
+	Ok(repositories)
+}
+
 pub struct Version {
+	repositories: Vec<Repository>,
 	ironworks: Arc<Ironworks>,
 	excel: Arc<Excel>,
 }
 
 impl Version {
-	fn new(view: zipatch::View) -> Self {
+	fn new(view: zipatch::View, repositories: Vec<Repository>) -> Self {
 		let ironworks = Arc::new(Ironworks::new().with_resource(SqPack::new(view)));
 		let excel = Arc::new(Excel::new(ironworks.clone()));
-		Self { ironworks, excel }
+		Self {
+			repositories,
+			ironworks,
+			excel,
+		}
 	}
 
 	pub fn ironworks(&self) -> Arc<Ironworks> {
@@ -210,3 +300,87 @@ impl Version {
 		self.excel.clone()
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn repository(name: &str, patches: &[&str]) -> Repository {
+		let patches = patches
+			.iter()
+			.map(|&name| bm_version::Patch {
+				name: name.into(),
+				path: format!("{name}.patch").into(),
+			})
+			.collect();
+
+		Repository {
+			name: name.into(),
+			patches: NonEmpty::from_vec(patches).unwrap(),
+		}
+	}
+
+	fn patch_names(repository: &Repository) -> Vec<&str> {
+		repository
+			.patches
+			.iter()
+			.map(|patch| patch.name.as_str())
+			.collect()
+	}
+
+	#[test]
+	fn truncate_to_earlier_patch() {
+		let repositories = vec![
+			repository("ffxiv", &["H2017", "D2023", "D2024"]),
+			repository("ex1", &["H2018", "D2025"]),
+		];
+
+		let got = truncate_repositories(repositories, "D2023").expect("patch should be found");
+		assert_eq!(patch_names(&got[0]), vec!["H2017", "D2023"]);
+		assert_eq!(patch_names(&got[1]), vec!["H2018", "D2025"]);
+	}
+
+	#[test]
+	fn version_at_earlier_patch() {
+		let data = Data::new();
+		let key = "00000000000000ff".parse::<VersionKey>().unwrap();
+		let repositories = vec![
+			repository("ffxiv", &["H2017", "D2023", "D2024"]),
+			repository("ex1", &["H2018", "D2025"]),
+		];
+		data.versions.write().expect("poisoned").insert(
+			key,
+			Arc::new(Version::new(data.view(&repositories), repositories)),
+		);
+
+		let version = data
+			.version_at_patch(key, "D2023")
+			.expect("patch should be found");
+		assert_eq!(
+			patch_names(&version.repositories[0]),
+			vec!["H2017", "D2023"]
+		);
+		assert_eq!(
+			patch_names(&version.repositories[1]),
+			vec!["H2018", "D2025"]
+		);
+
+		// Patch versions are reused until their version is removed.
+		let cached = data.version_at_patch(key, "D2023").unwrap();
+		assert!(Arc::ptr_eq(&version, &cached));
+
+		data.remove_versions(vec![key]);
+		assert!(matches!(
+			data.version_at_patch(key, "D2023"),
+			Err(Error::UnknownVersion(..))
+		));
+	}
+
+	#[test]
+	fn truncate_to_unknown_patch() {
+		let repositories = vec![repository("ffxiv", &["H2017", "D2023"])];
+
+		let got = truncate_repositories(repositories, "D2099");
+		assert!(matches!(got, Err(Error::UnknownPatch(patch)) if patch == "D2099"));
+	}
+}
@@ -5,6 +5,9 @@ pub enum Error {
 	#[error("unknown version {0}")]
 	UnknownVersion(VersionKey),
 
+	#[error("unknown patch {0}")]
+	UnknownPatch(String),
+
 	#[error(transparent)]
 	Failure(#[from] anyhow::Error),
 }
@@ -5,7 +5,7 @@ use crate::http::HttpState;
 
 use super::{
 	auth::{basic_auth, BasicAuth},
-	version, versions,
+	maintenance, schema, strings, version, versions,
 };
 
 #[derive(Debug, Deserialize)]
@@ -16,6 +16,9 @@ pub struct Config {
 pub fn router(config: Config, state: HttpState) -> Router {
 	Router::new()
 		.merge(versions::router(state.clone()))
-		.merge(version::router(state))
+		.merge(version::router(state.clone()))
+		.merge(schema::router(state.clone()))
+		.merge(maintenance::router(state.clone()))
+		.merge(strings::router(state))
 		.layer(middleware::from_fn_with_state(config.auth, basic_auth))
 }
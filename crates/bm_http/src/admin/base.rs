@@ -23,6 +23,8 @@ impl Render for BaseTemplate {
 							}
 							ul {
 								li { a href="/admin" { "versions" } }
+								li { a href="/admin/schema" { "schema" } }
+								li { a href="/admin/maintenance" { "maintenance" } }
 							}
 						}
 					}
@@ -4,24 +4,28 @@ use axum::{
 };
 
 #[derive(Debug)]
-pub struct Error(anyhow::Error);
+pub enum Error {
+	NotFound(String),
+	Other(anyhow::Error),
+}
 
 impl<E> From<E> for Error
 where
 	E: Into<anyhow::Error>,
 {
 	fn from(value: E) -> Self {
-		Self(value.into())
+		Self::Other(value.into())
 	}
 }
 
 impl IntoResponse for Error {
 	fn into_response(self) -> Response {
-		(
-			StatusCode::INTERNAL_SERVER_ERROR,
-			format!("error: {}", self.0),
-		)
-			.into_response()
+		let (status, message) = match self {
+			Self::NotFound(message) => (StatusCode::NOT_FOUND, message),
+			Self::Other(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+		};
+
+		(status, format!("error: {message}")).into_response()
 	}
 }
 
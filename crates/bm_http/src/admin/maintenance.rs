@@ -0,0 +1,76 @@
+use axum::{
+	debug_handler,
+	extract::{OriginalUri, State},
+	response::{IntoResponse, Redirect},
+	routing::{get, post},
+	Router,
+};
+use maud::{html, Render};
+
+use crate::{http::HttpState, maintenance::Maintenance};
+
+use super::{base::BaseTemplate, error::Result};
+
+pub fn router(state: HttpState) -> Router {
+	Router::new()
+		.route(
+			"/maintenance",
+			get(get_maintenance).with_state(state.clone()),
+		)
+		.route(
+			"/maintenance/enable",
+			post(post_enable).with_state(state.clone()),
+		)
+		.route("/maintenance/disable", post(post_disable).with_state(state))
+}
+
+#[debug_handler(state = HttpState)]
+async fn get_maintenance(
+	OriginalUri(uri): OriginalUri,
+	State(maintenance): State<Maintenance>,
+) -> Result<impl IntoResponse> {
+	let enabled = maintenance.enabled();
+
+	Ok((BaseTemplate {
+		title: "maintenance".into(),
+		content: html! {
+			p {
+				"Maintenance mode is " strong { @if enabled { "enabled" } @else { "disabled" } } ". "
+				"While enabled, sheet reads and searches against versions that have not completed search ingestion are rejected."
+			}
+
+			@if enabled {
+				form action={ (uri) "/disable" } method="post" {
+					button type="submit" { "disable" }
+				}
+			} @else {
+				form action={ (uri) "/enable" } method="post" {
+					button type="submit" { "enable" }
+				}
+			}
+		},
+	})
+	.render())
+}
+
+#[debug_handler(state = HttpState)]
+async fn post_enable(
+	OriginalUri(uri): OriginalUri,
+	State(maintenance): State<Maintenance>,
+) -> Result<impl IntoResponse> {
+	maintenance.set_enabled(true);
+	Ok(redirect_back(uri.path(), "/enable"))
+}
+
+#[debug_handler(state = HttpState)]
+async fn post_disable(
+	OriginalUri(uri): OriginalUri,
+	State(maintenance): State<Maintenance>,
+) -> Result<impl IntoResponse> {
+	maintenance.set_enabled(false);
+	Ok(redirect_back(uri.path(), "/disable"))
+}
+
+fn redirect_back(path: &str, suffix: &str) -> Redirect {
+	Redirect::to(path.strip_suffix(suffix).unwrap_or(path))
+}
@@ -2,6 +2,9 @@ mod admin;
 mod auth;
 mod base;
 mod error;
+mod maintenance;
+mod schema;
+mod strings;
 mod version;
 mod versions;
 
@@ -0,0 +1,61 @@
+use axum::{
+	debug_handler,
+	extract::{OriginalUri, State},
+	response::{IntoResponse, Redirect},
+	routing::{get, post},
+	Router,
+};
+use maud::{html, Render};
+
+use crate::{http::HttpState, service::Service};
+
+use super::{base::BaseTemplate, error::Result};
+
+pub fn router(state: HttpState) -> Router {
+	Router::new()
+		.route("/schema", get(get_schema).with_state(state.clone()))
+		.route("/schema/refresh", post(post_refresh).with_state(state))
+}
+
+#[debug_handler(state = HttpState)]
+async fn get_schema(
+	OriginalUri(uri): OriginalUri,
+	State(Service { schema, .. }): State<Service>,
+) -> Result<impl IntoResponse> {
+	let sources = schema.sources();
+
+	Ok((BaseTemplate {
+		title: "schema".into(),
+		content: html! {
+			h2 { "sources" }
+			ul {
+				@for source in sources {
+					li {
+						(source.label)
+						" (" code { (source.name) } ")"
+						@if source.default { " - default" }
+					}
+				}
+			}
+
+			h2 { "refresh" }
+			p { "Fetch updates for all schema sources, discarding any previously resolved schema versions." }
+			form action={ (uri) "/refresh" } method="post" {
+				button type="submit" { "refresh" }
+			}
+		},
+	})
+	.render())
+}
+
+#[debug_handler(state = HttpState)]
+async fn post_refresh(
+	OriginalUri(uri): OriginalUri,
+	State(Service { schema, .. }): State<Service>,
+) -> Result<impl IntoResponse> {
+	schema.refresh().await?;
+
+	let path = uri.path();
+	let target = path.strip_suffix("/refresh").unwrap_or(path);
+	Ok(Redirect::to(target))
+}
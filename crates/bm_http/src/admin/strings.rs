@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+
+use axum::{
+	debug_handler,
+	extract::{Path, Query, State},
+	routing::get,
+	Json, Router,
+};
+use bm_read as read;
+use ironworks::{excel, file::exh};
+use ironworks_schema::Schema;
+use serde::Deserialize;
+
+use crate::{http::HttpState, service::Service};
+
+use super::error::{Error, Result};
+
+pub fn router(state: HttpState) -> Router {
+	Router::new().route("/sheet/{sheet}/strings", get(strings).with_state(state))
+}
+
+#[derive(Debug, Deserialize)]
+struct StringsQuery {
+	version: Option<String>,
+}
+
+/// Localised text for a single row, keyed by field path, then language.
+type RowStrings = BTreeMap<String, BTreeMap<String, String>>;
+
+#[debug_handler(state = HttpState)]
+async fn strings(
+	Path(sheet): Path<String>,
+	Query(query): Query<StringsQuery>,
+	State(Service {
+		data,
+		read,
+		schema,
+		version,
+		..
+	}): State<Service>,
+) -> Result<Json<BTreeMap<String, RowStrings>>> {
+	let version_key = version.resolve(query.version.as_deref()).ok_or_else(|| {
+		Error::NotFound(format!(
+			"unknown version {}",
+			query.version.as_deref().unwrap_or("latest")
+		))
+	})?;
+
+	let excel = data.version(version_key)?.excel();
+	let (_, schema) = schema.resolve(None, version_key)?;
+
+	// Reading every row of a sheet is slow and entirely synchronous - keep it
+	// off the async runtime.
+	let output =
+		tokio::task::spawn_blocking(move || sheet_strings(&excel, schema.as_ref(), &read, &sheet))
+			.await??;
+
+	Ok(Json(output))
+}
+
+/// Collect the localised strings of every row in a sheet, keyed by row
+/// specifier.
+fn sheet_strings(
+	excel: &excel::Excel,
+	schema: &dyn Schema,
+	read: &read::Read,
+	sheet: &str,
+) -> Result<BTreeMap<String, RowStrings>> {
+	let sheet_data = excel.sheet(sheet)?;
+	let subrows = sheet_data.kind()? == exh::SheetKind::Subrows;
+	let languages = sheet_data
+		.languages()?
+		.into_iter()
+		.filter(|language| read.language_enabled(*language))
+		.collect::<Vec<_>>();
+
+	let cache = read::ReadCache::default();
+	let mut output = BTreeMap::new();
+	for row in sheet_data.into_iter() {
+		let (row_id, subrow_id) = (row.row_id(), row.subrow_id());
+		let key = match subrows {
+			true => format!("{row_id}:{subrow_id}"),
+			false => row_id.to_string(),
+		};
+
+		let mut row_strings = RowStrings::new();
+		for &language in &languages {
+			let value = read.read(
+				excel,
+				schema,
+				sheet,
+				row_id,
+				subrow_id,
+				language,
+				&read::Filter::All,
+				0,
+				&read::ReadOptions::default(),
+				&cache,
+			)?;
+
+			let mut strings = vec![];
+			collect_strings(&value, String::new(), &mut strings);
+			merge_language(&mut row_strings, language, strings);
+		}
+
+		if !row_strings.is_empty() {
+			output.insert(key, row_strings);
+		}
+	}
+
+	Ok(output)
+}
+
+/// Collect every string field within a value, alongside its path.
+fn collect_strings(value: &read::Value, path: String, output: &mut Vec<(String, String)>) {
+	match value {
+		read::Value::Scalar(excel::Field::String(string)) => {
+			output.push((path, string.to_string()));
+		}
+
+		read::Value::Struct(fields) => {
+			for (name, value) in fields {
+				let path = match path.is_empty() {
+					true => name.clone(),
+					false => format!("{path}.{name}"),
+				};
+				collect_strings(value, path, output);
+			}
+		}
+
+		read::Value::Array(values) => {
+			for (index, value) in values.iter().enumerate() {
+				collect_strings(value, format!("{path}[{index}]"), output);
+			}
+		}
+
+		// Relations are not followed, and other scalars are not text.
+		_ => {}
+	}
+}
+
+fn merge_language(
+	row_strings: &mut RowStrings,
+	language: excel::Language,
+	strings: impl IntoIterator<Item = (String, String)>,
+) {
+	let language = read::LanguageString::from(language).to_string();
+	for (path, text) in strings {
+		row_strings
+			.entry(path)
+			.or_default()
+			.insert(language.clone(), text);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use axum::{http::StatusCode, response::IntoResponse};
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	#[test]
+	fn merge_languages() {
+		let mut row_strings = RowStrings::new();
+		merge_language(
+			&mut row_strings,
+			excel::Language::English,
+			[
+				("Name".to_string(), "Potion".to_string()),
+				("Description".to_string(), "Restores HP.".to_string()),
+			],
+		);
+		merge_language(
+			&mut row_strings,
+			excel::Language::Japanese,
+			[("Name".to_string(), "ポーション".to_string())],
+		);
+
+		let expected = RowStrings::from([
+			(
+				"Description".to_string(),
+				BTreeMap::from([("en".to_string(), "Restores HP.".to_string())]),
+			),
+			(
+				"Name".to_string(),
+				BTreeMap::from([
+					("en".to_string(), "Potion".to_string()),
+					("ja".to_string(), "ポーション".to_string()),
+				]),
+			),
+		]);
+
+		assert_eq!(row_strings, expected);
+	}
+
+	#[test]
+	fn collect_skips_non_strings() {
+		let value = read::Value::Struct(
+			[
+				("Id".to_string(), read::Value::Scalar(excel::Field::U32(1))),
+				(
+					"Values".to_string(),
+					read::Value::Array(vec![read::Value::Scalar(excel::Field::I8(-1))]),
+				),
+			]
+			.into(),
+		);
+
+		let mut strings = vec![];
+		collect_strings(&value, String::new(), &mut strings);
+		assert_eq!(strings, vec![]);
+	}
+
+	fn fixture_strings(
+		name: &str,
+		sheet: read::fixture::TestSheet,
+	) -> BTreeMap<String, RowStrings> {
+		use read::fixture::{scalar, struct_node, Fixture};
+
+		let fixture = Fixture::new(vec![(
+			sheet,
+			struct_node([("Name", scalar()), ("Level", scalar())]),
+		)]);
+		let config = serde_json::from_value::<read::Config>(serde_json::json!({
+			"language": {"default": "en", "exclude": []},
+		}))
+		.expect("config should deserialize");
+		let read = read::Read::new(config);
+
+		sheet_strings(&fixture.excel, &fixture.schema, &read, name).expect("strings should be read")
+	}
+
+	fn name_strings(name: &str) -> RowStrings {
+		RowStrings::from([(
+			"Name".to_string(),
+			BTreeMap::from([("none".to_string(), name.to_string())]),
+		)])
+	}
+
+	#[test]
+	fn sheet_strings_by_row() {
+		use exh::ColumnKind as CK;
+		use read::fixture::{Cell, TestSheet};
+
+		let output = fixture_strings(
+			"Item",
+			TestSheet::new("Item", [(CK::String, 0), (CK::UInt32, 4)])
+				.row(1, [Cell::String("Potion".into()), Cell::U32(1)])
+				.row(2, [Cell::String("Ether".into()), Cell::U32(5)]),
+		);
+
+		assert_eq!(
+			output,
+			BTreeMap::from([
+				("1".to_string(), name_strings("Potion")),
+				("2".to_string(), name_strings("Ether")),
+			])
+		);
+	}
+
+	#[test]
+	fn sheet_strings_by_subrow() {
+		use exh::ColumnKind as CK;
+		use read::fixture::{Cell, TestSheet};
+
+		let output = fixture_strings(
+			"Quest",
+			TestSheet::new("Quest", [(CK::String, 0), (CK::UInt32, 4)]).subrows(
+				1,
+				vec![
+					vec![Cell::String("Start".into()), Cell::U32(1)],
+					vec![Cell::String("End".into()), Cell::U32(2)],
+				],
+			),
+		);
+
+		assert_eq!(
+			output,
+			BTreeMap::from([
+				("1:0".to_string(), name_strings("Start")),
+				("1:1".to_string(), name_strings("End")),
+			])
+		);
+	}
+
+	#[test]
+	fn unknown_version_not_found() {
+		let response = Error::NotFound("unknown version 1.0".into()).into_response();
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	}
+}
@@ -5,6 +5,7 @@ use axum::{
 	debug_handler,
 	extract::{FromRef, State},
 	http::Uri,
+	middleware,
 	response::IntoResponse,
 	routing::get,
 	Json, Router,
@@ -17,7 +18,17 @@ use tower_http::cors::CorsLayer;
 
 use crate::{http::HttpState, service::Service};
 
-use super::{asset, read::RowReaderState, search, sheet, version};
+use super::{
+	asset,
+	envelope::EnvelopeConfig,
+	icon::IconConfig,
+	maintenance::{maintenance_layer, MaintenanceState},
+	read::{DepthConfig, RowReaderState},
+	schema, search, sheet,
+	timeout::timeout_layer,
+	timing::{timing_layer, TimingConfig},
+	version,
+};
 
 const OPENAPI_JSON_ROUTE: &str = "/openapi.json";
 
@@ -26,20 +37,42 @@ pub struct Config {
 	asset: asset::Config,
 	search: search::Config,
 	sheet: sheet::Config,
+	depth: DepthConfig,
+
+	#[serde(default)]
+	envelope: EnvelopeConfig,
+
+	#[serde(default)]
+	timing: TimingConfig,
+
+	#[serde(default)]
+	version: version::Config,
 }
 
 #[derive(Clone, FromRef)]
 pub struct ApiState {
 	pub services: Service,
 	pub reader_state: RowReaderState,
+	pub envelope_config: EnvelopeConfig,
+	pub depth_config: DepthConfig,
+	pub icon_config: IconConfig,
 }
 
-pub fn router(config: Config, state: HttpState) -> Router {
+pub fn router(config: Config, cors: CorsLayer, state: HttpState) -> Router {
 	let mut openapi = openapi::OpenApi::default();
 
+	let read_timeout = state.read_timeout;
+	let maintenance_state = MaintenanceState {
+		services: state.services.clone(),
+		maintenance: state.maintenance,
+	};
+
 	let state = ApiState {
 		services: state.services,
 		reader_state: RowReaderState::default(),
+		envelope_config: config.envelope,
+		depth_config: config.depth,
+		icon_config: config.asset.icon.clone(),
 	};
 
 	ApiRouter::new()
@@ -47,17 +80,33 @@ pub fn router(config: Config, state: HttpState) -> Router {
 			"/asset",
 			asset::router(config.asset, state.clone()).with_path_items(|item| item.tag("assets")),
 		)
+		.nest(
+			"/schema",
+			schema::router(state.clone()).with_path_items(|item| item.tag("schemas")),
+		)
 		.nest(
 			"/search",
-			search::router(config.search, state.clone()).with_path_items(|item| item.tag("search")),
+			search::router(config.search, state.clone())
+				.layer(middleware::from_fn_with_state(read_timeout, timeout_layer))
+				.layer(middleware::from_fn_with_state(
+					maintenance_state.clone(),
+					maintenance_layer,
+				))
+				.with_path_items(|item| item.tag("search")),
 		)
 		.nest(
 			"/sheet",
-			sheet::router(config.sheet, state.clone()).with_path_items(|item| item.tag("sheets")),
+			sheet::router(config.sheet, state.clone())
+				.layer(middleware::from_fn_with_state(read_timeout, timeout_layer))
+				.layer(middleware::from_fn_with_state(
+					maintenance_state,
+					maintenance_layer,
+				))
+				.with_path_items(|item| item.tag("sheets")),
 		)
 		.nest(
 			"/version",
-			version::router(state).with_path_items(|item| item.tag("versions")),
+			version::router(config.version, state).with_path_items(|item| item.tag("versions")),
 		)
 		.finish_api_with(&mut openapi, api_docs)
 		.route(
@@ -66,7 +115,8 @@ pub fn router(config: Config, state: HttpState) -> Router {
 				openapi: Arc::new(openapi),
 			}),
 		)
-		.layer(CorsLayer::permissive())
+		.layer(middleware::from_fn_with_state(config.timing, timing_layer))
+		.layer(cors)
 		.route("/docs", get(scalar))
 }
 
@@ -79,6 +129,11 @@ fn api_docs(api: TransformOpenApi) -> TransformOpenApi {
 			description: Some("Endpoints for accessing game data on a file-by-file basis. Commonly useful for fetching icons or other textures to display on the web.".into()),
 			..Default::default()
 		})
+		.tag(openapi::Tag {
+			name: "schemas".into(),
+			description: Some("Endpoints for querying metadata about the schemas used to read sheet data.".into()),
+			..Default::default()
+		})
 		.tag(openapi::Tag {
 			name: "search".into(),
 			description: Some("Endpoints for seaching and filtering the game's static relational data store.".into()),
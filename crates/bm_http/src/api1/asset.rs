@@ -15,12 +15,13 @@ use axum::{
 	http::{header, StatusCode},
 	middleware,
 	response::{IntoResponse, Response},
+	Json,
 };
 use axum_extra::{
 	headers::{CacheControl, ContentType, ETag, HeaderMapExt, IfNoneMatch},
 	TypedHeader,
 };
-use bm_asset::Format;
+use bm_asset::{uld, ConvertOptions, Format, MapLayer};
 use schemars::{
 	gen::SchemaGenerator,
 	schema::{InstanceType, Schema, SchemaObject},
@@ -33,17 +34,27 @@ use crate::service::Service;
 
 use super::{
 	api::ApiState,
-	error::Result,
+	error::{Error, Result},
 	extract::{Path, Query, VersionQuery},
+	icon::IconConfig,
 	jsonschema::impl_jsonschema,
+	language::file_language_code,
+	read::SchemaLanguage,
+	stream,
 };
 
 // NOTE: Bump this if changing any behavior that impacts output binary data for assets, to ensure ETag is cache-broken.
-const ASSET_ETAG_VERSION: usize = 2;
+const ASSET_ETAG_VERSION: usize = 3;
+
+// Icon IDs are zero-padded to six digits within game paths.
+const MAX_ICON_ID: u32 = 999_999;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
 	maxage: u64,
+
+	#[serde(default)]
+	pub icon: IconConfig,
 }
 
 #[derive(Clone, FromRef)]
@@ -60,7 +71,11 @@ pub fn router(config: Config, state: ApiState) -> ApiRouter {
 
 	ApiRouter::new()
 		.api_route("/", get_with(asset2, asset2_docs))
+		.api_route("/formats", get_with(formats, formats_docs))
+		.api_route("/icon/{id}", get_with(icon, icon_docs))
+		.api_route("/info", get_with(info, info_docs))
 		.api_route("/map/{territory}/{index}", get_with(map, map_docs))
+		.api_route("/uld", get_with(uld, uld_docs))
 		// Fall back to the old asset endpoint for compatibility.
 		.route("/{*path}", axum::routing::get(asset1))
 		.layer(middleware::from_fn_with_state(state.clone(), cache_layer))
@@ -89,7 +104,7 @@ async fn asset1(
 	// The endpoints are nearly identical - just call through to the new endpoint with an emulated query.
 	asset2(
 		query_version,
-		Query(AssetQuery { path, format }),
+		Query(AssetQuery::new(path, format)),
 		state_service,
 	)
 	.await
@@ -102,16 +117,62 @@ struct AssetQuery {
 	#[schemars(example = "example_path")]
 	path: String,
 
-	/// Format that the asset should be converted into.
+	/// Format that the asset should be converted into. The `raw` format returns
+	/// the game file as-is, ignoring other conversion parameters.
 	#[schemars(example = "example_format")]
 	format: SchemaFormat,
+
+	/// Language of the asset to retrieve. If the asset has a localized variant
+	/// for this language, it will be used, otherwise the asset at `path` is
+	/// returned as-is.
+	lang: Option<SchemaLanguage>,
+
+	/// Mip level to convert for texture assets, where `0` is the full
+	/// resolution image. Defaults to `0`.
+	mip: Option<u32>,
+
+	/// Maximum width of the converted image, in pixels. Images are scaled down
+	/// to fit, preserving their aspect ratio. Limited by the server configuration.
+	width: Option<u32>,
+
+	/// Maximum height of the converted image, in pixels. Images are scaled down
+	/// to fit, preserving their aspect ratio. Limited by the server configuration.
+	height: Option<u32>,
+
+	/// Convert all frames of an animated texture into an animated PNG. Only
+	/// supported when `format` is `png`. Frame timing is not stored by the
+	/// game files - frames are output at a fixed rate of 10 per second.
+	#[serde(default)]
+	animated: bool,
+
+	/// Quality (1-100) to encode lossy formats (`jpg`, `avif`) with. Values
+	/// outside this range are clamped. Ignored for lossless formats - note that
+	/// `webp` output is lossless. Defaults to the encoder's default quality.
+	quality: Option<u8>,
+}
+
+impl AssetQuery {
+	/// Build a query for the given path and format, with all conversion options
+	/// left at their defaults.
+	fn new(path: String, format: SchemaFormat) -> Self {
+		Self {
+			path,
+			format,
+			lang: None,
+			mip: None,
+			width: None,
+			height: None,
+			animated: false,
+			quality: None,
+		}
+	}
 }
 
 fn example_path() -> &'static str {
 	"ui/icon/051000/051474_hr1.tex"
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[repr(transparent)]
 struct SchemaFormat(Format);
 
@@ -156,15 +217,34 @@ async fn asset2(
 	Query(AssetQuery {
 		path,
 		format: SchemaFormat(format),
+		lang,
+		mip,
+		width,
+		height,
+		animated,
+		quality,
 	}): Query<AssetQuery>,
 	State(Service { asset, .. }): State<Service>,
 ) -> Result<impl IntoApiResponse> {
-	// Perform the conversion.
+	// Prepare the conversion. Encoding is deferred until the response body is
+	// streamed, so the full output is never buffered in memory.
 	// TODO: can this be made async?
-	let bytes = asset.convert(version_key, &path, format)?;
+	let language = lang.and_then(|SchemaLanguage(language)| file_language_code(language.into()));
+	let options = ConvertOptions {
+		mip: mip.unwrap_or(0),
+		width,
+		height,
+		animated,
+		quality,
+	};
+	let conversion = asset.convert(version_key, &path, format, language, &options)?;
 
-	// Try to derive a filename to use for the Content-Disposition header.
-	let filepath = std::path::Path::new(&path).with_extension(format.extension());
+	// Try to derive a filename to use for the Content-Disposition header. Raw
+	// files retain their original extension.
+	let filepath = match format {
+		Format::Raw => std::path::PathBuf::from(&path),
+		other => std::path::Path::new(&path).with_extension(other.extension()),
+	};
 	let disposition = match filepath.file_name().and_then(OsStr::to_str) {
 		Some(name) => format!("inline; filename=\"{name}\""),
 		None => "inline".to_string(),
@@ -174,17 +254,188 @@ async fn asset2(
 		TypedHeader(ContentType::from(format_mime(format))),
 		// TypedHeader only has a really naive inline value with no ability to customise :/
 		[(header::CONTENT_DISPOSITION, disposition)],
-		bytes,
+		stream::body(move |writer| conversion.write(writer)),
 	);
 
 	Ok(response.into_response())
 }
 
+/// Query parameters accepted by the asset formats endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct FormatsQuery {
+	/// Game path of the asset to list formats for.
+	#[schemars(example = "example_path")]
+	path: String,
+}
+
+fn formats_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("list asset formats")
+		.description("List the formats that the asset at the specified path can be converted into, based on the kind of the game file. The `raw` format is available for any file. The file is not read, and may not exist.")
+		.response_with::<200, Json<Vec<SchemaFormat>>, _>(|response| {
+			response.example(
+				[Format::Avif, Format::Jpeg, Format::Png, Format::Webp, Format::Raw]
+					.map(SchemaFormat)
+					.to_vec(),
+			)
+		})
+		.response_with::<304, (), _>(|res| res.description("not modified"))
+}
+
+#[debug_handler(state = AssetState)]
+async fn formats(
+	Query(FormatsQuery { path }): Query<FormatsQuery>,
+	State(Service { asset, .. }): State<Service>,
+) -> Json<Vec<SchemaFormat>> {
+	let formats = asset.formats(&path).into_iter().map(SchemaFormat).collect();
+	Json(formats)
+}
+
+/// Path segments expected by the asset icon endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct IconPath {
+	/// ID of the icon to retrieve, as referenced by sheet `Icon` fields.
+	#[schemars(example = "example_icon_id")]
+	id: i64,
+}
+
+fn example_icon_id() -> i64 {
+	51474
+}
+
+/// Query parameters accepted by the asset icon endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct IconQuery {
+	/// Format that the icon should be converted into.
+	#[schemars(example = "example_format")]
+	format: SchemaFormat,
+
+	/// Retrieve the high resolution (`_hr1`) variant of the icon.
+	#[serde(default)]
+	hr: bool,
+}
+
+fn icon_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("read an icon")
+		.description("Read the icon with the specified ID, converting it into a usable format. This is equivalent to reading the asset at the icon's game path.")
+		.response_with::<200, Vec<u8>, _>(|mut response| {
+			response.inner().content = Format::iter()
+				.map(|format| {
+					(
+						format_mime(format).to_string(),
+						openapi::MediaType::default(),
+					)
+				})
+				.collect();
+			response
+		})
+		.response_with::<304, (), _>(|res| res.description("not modified"))
+}
+
+#[debug_handler(state = AssetState)]
+async fn icon(
+	Path(IconPath { id }): Path<IconPath>,
+	query_version: VersionQuery,
+	Query(IconQuery { format, hr }): Query<IconQuery>,
+	State(config): State<Config>,
+	state_service: State<Service>,
+) -> Result<impl IntoApiResponse> {
+	let path = icon_path(&config.icon, id, hr)?;
+	asset2(
+		query_version,
+		Query(AssetQuery::new(path, format)),
+		state_service,
+	)
+	.await
+}
+
+/// Game path of the texture for the given icon ID.
+fn icon_path(config: &IconConfig, id: i64, hr: bool) -> Result<String> {
+	let id = u32::try_from(id)
+		.ok()
+		.filter(|id| *id <= MAX_ICON_ID)
+		.ok_or_else(|| {
+			Error::Invalid(format!(
+				"icon ID {id} is out of range, expected 0-{MAX_ICON_ID}"
+			))
+		})?;
+
+	let suffix = match hr {
+		true => "_hr1",
+		false => "",
+	};
+	Ok(format!("{}{suffix}.tex", config.path(id)))
+}
+
+/// Query parameters accepted by the asset info endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct InfoQuery {
+	/// Game path of the asset to retrieve metadata for.
+	#[schemars(example = "example_path")]
+	path: String,
+}
+
+/// Response structure for the asset info endpoint.
+#[derive(Serialize, JsonSchema)]
+struct InfoResponse {
+	/// Kind of the source file, i.e. `tex`.
+	kind: String,
+
+	/// Width of the asset, in pixels.
+	width: u32,
+
+	/// Height of the asset, in pixels.
+	height: u32,
+
+	/// Number of mip levels available for the asset.
+	mip_count: u32,
+}
+
+impl From<bm_asset::AssetInfo> for InfoResponse {
+	fn from(info: bm_asset::AssetInfo) -> Self {
+		Self {
+			kind: info.kind,
+			width: info.width,
+			height: info.height,
+			mip_count: info.mip_count,
+		}
+	}
+}
+
+fn info_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("read asset metadata")
+		.description("Read metadata about an asset in the game at the specified path, such as its native dimensions, without converting it. Metadata is currently only available for textures.")
+		.response_with::<200, Json<InfoResponse>, _>(|response| {
+			response.example(InfoResponse {
+				kind: "tex".into(),
+				width: 80,
+				height: 80,
+				mip_count: 1,
+			})
+		})
+		.response_with::<304, (), _>(|res| res.description("not modified"))
+}
+
+#[debug_handler(state = AssetState)]
+async fn info(
+	VersionQuery(version_key): VersionQuery,
+	Query(InfoQuery { path }): Query<InfoQuery>,
+	State(Service { asset, .. }): State<Service>,
+) -> Result<Json<InfoResponse>> {
+	let info = asset.info(version_key, &path)?;
+	Ok(Json(info.into()))
+}
+
+/// MIME type of converted output in the given format.
 fn format_mime(format: Format) -> mime::Mime {
 	match format {
+		Format::Avif => "image/avif".parse().expect("mime parse should not fail"),
 		Format::Jpeg => mime::IMAGE_JPEG,
 		Format::Png => mime::IMAGE_PNG,
 		Format::Webp => "image/webp".parse().expect("mime parse should not fail"),
+		Format::Raw => mime::APPLICATION_OCTET_STREAM,
 	}
 }
 
@@ -204,6 +455,20 @@ struct MapPath {
 	index: String,
 }
 
+/// Query parameters accepted by the asset map endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct MapQuery {
+	/// Comma-separated list of layers to compose, from `base` (the map itself)
+	/// and `mask` (the background multiplied into the map). Defaults to all
+	/// layers.
+	#[schemars(example = "example_layers")]
+	layers: Option<String>,
+}
+
+fn example_layers() -> &'static str {
+	"base"
+}
+
 fn example_territory() -> &'static str {
 	"s1d1"
 }
@@ -231,9 +496,19 @@ fn map_docs(operation: TransformOperation) -> TransformOperation {
 async fn map(
 	Path(MapPath { territory, index }): Path<MapPath>,
 	VersionQuery(version_key): VersionQuery,
+	Query(MapQuery { layers }): Query<MapQuery>,
 	State(Service { asset, .. }): State<Service>,
 ) -> Result<impl IntoApiResponse> {
-	let bytes = asset.map(version_key, &territory, &index)?;
+	let layers = layers
+		.map(|layers| {
+			layers
+				.split(',')
+				.map(|layer| layer.trim().parse::<MapLayer>())
+				.collect::<Result<Vec<_>, _>>()
+		})
+		.transpose()?;
+
+	let conversion = asset.map(version_key, &territory, &index, layers.as_deref())?;
 
 	let response = (
 		TypedHeader(ContentType::jpeg()),
@@ -241,12 +516,133 @@ async fn map(
 			header::CONTENT_DISPOSITION,
 			format!("inline; filename=\"{territory}_{index}.jpg\""),
 		)],
-		bytes,
+		stream::body(move |writer| conversion.write(writer)),
 	);
 
 	Ok(response.into_response())
 }
 
+/// Query parameters accepted by the asset UI layout endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct UldQuery {
+	/// Game path of the UI layout file to read.
+	#[schemars(example = "example_uld_path")]
+	path: String,
+}
+
+fn example_uld_path() -> &'static str {
+	"ui/uld/Character.uld"
+}
+
+/// Response structure for the asset UI layout endpoint.
+#[derive(Serialize, JsonSchema)]
+struct UldResponse {
+	/// Textures referenced by the layout.
+	textures: Vec<UldTexture>,
+
+	/// Lists of texture regions used by components within the layout.
+	part_lists: Vec<UldPartList>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct UldTexture {
+	/// ID of the texture, as referenced by parts.
+	id: u32,
+
+	/// Game path of the texture.
+	path: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct UldPartList {
+	/// ID of the part list, as referenced by components.
+	id: u32,
+
+	/// Texture regions in this list.
+	parts: Vec<UldPart>,
+}
+
+/// A rectangular region of a texture, in pixels.
+#[derive(Serialize, JsonSchema)]
+struct UldPart {
+	/// ID of the texture this part is a region of.
+	texture_id: u32,
+	u: u16,
+	v: u16,
+	width: u16,
+	height: u16,
+}
+
+impl From<uld::Layout> for UldResponse {
+	fn from(layout: uld::Layout) -> Self {
+		Self {
+			textures: layout
+				.textures
+				.into_iter()
+				.map(|texture| UldTexture {
+					id: texture.id,
+					path: texture.path,
+				})
+				.collect(),
+			part_lists: layout
+				.part_lists
+				.into_iter()
+				.map(|list| UldPartList {
+					id: list.id,
+					parts: list
+						.parts
+						.into_iter()
+						.map(|part| UldPart {
+							texture_id: part.texture_id,
+							u: part.u,
+							v: part.v,
+							width: part.width,
+							height: part.height,
+						})
+						.collect(),
+				})
+				.collect(),
+		}
+	}
+}
+
+fn uld_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("read a UI layout")
+		.description(
+			"Read the texture references and part rectangles from a UI layout (`.uld`) file.",
+		)
+		.response_with::<200, Json<UldResponse>, _>(|response| {
+			response.example(UldResponse {
+				textures: vec![UldTexture {
+					id: 1,
+					path: "ui/uld/Character.tex".into(),
+				}],
+				part_lists: vec![UldPartList {
+					id: 0,
+					parts: vec![UldPart {
+						texture_id: 1,
+						u: 0,
+						v: 0,
+						width: 32,
+						height: 32,
+					}],
+				}],
+			})
+		})
+		.response_with::<304, (), _>(|res| res.description("not modified"))
+}
+
+#[debug_handler]
+async fn uld(
+	VersionQuery(version_key): VersionQuery,
+	Query(UldQuery { path }): Query<UldQuery>,
+	State(Service { asset, .. }): State<Service>,
+) -> Result<Json<UldResponse>> {
+	let layout = asset.uld(version_key, &path)?;
+	Ok(Json(layout.into()))
+}
+
 async fn cache_layer(
 	uri: OriginalUri,
 	VersionQuery(version): VersionQuery,
@@ -255,7 +651,9 @@ async fn cache_layer(
 	request: Request,
 	next: middleware::Next,
 ) -> Response {
-	// Build ETag for this request.
+	// Build ETag for this request. The full URI is hashed, so query parameters
+	// affecting output (such as `lang`, `mip`, dimensions, `quality`, or map
+	// `layers`) are included.
 	let mut hasher = SeaHasher::new();
 	uri.hash(&mut hasher);
 	let uri_hash = hasher.finish();
@@ -286,3 +684,57 @@ async fn cache_layer(
 
 	response
 }
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	#[test]
+	fn icon_paths() {
+		let config = IconConfig::default();
+		assert_eq!(
+			icon_path(&config, 51474, false).unwrap(),
+			"ui/icon/051000/051474.tex"
+		);
+		assert_eq!(
+			icon_path(&config, 51474, true).unwrap(),
+			"ui/icon/051000/051474_hr1.tex"
+		);
+	}
+
+	#[test]
+	fn icon_ids_out_of_range() {
+		let config = IconConfig::default();
+		for id in [-1, 1_000_000, i64::from(u32::MAX) + 1] {
+			assert!(matches!(
+				icon_path(&config, id, false),
+				Err(Error::Invalid(..))
+			));
+		}
+	}
+
+	#[test]
+	fn info_response() {
+		let response = InfoResponse::from(bm_asset::AssetInfo {
+			kind: "tex".into(),
+			width: 128,
+			height: 64,
+			mip_count: 8,
+		});
+		assert_eq!(
+			serde_json::to_value(response).unwrap(),
+			serde_json::json!({"kind": "tex", "width": 128, "height": 64, "mip_count": 8})
+		);
+	}
+
+	#[test]
+	fn info_unsupported_source_invalid() {
+		let error = Error::from(bm_asset::Error::UnsupportedSource(
+			"ui/test.png".into(),
+			"metadata is only available for textures".into(),
+		));
+		assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+	}
+}
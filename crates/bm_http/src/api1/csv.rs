@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Context;
+use axum::{
+	http::header,
+	response::{IntoResponse, Response},
+};
+
+use super::{error::Result, read::RowResult};
+
+/// Columns that lead the output when present, ahead of the sorted field columns.
+const LEADING_COLUMNS: [&str; 2] = ["row_id", "subrow_id"];
+
+/// Build a CSV response for the provided rows.
+pub fn response(rows: &[RowResult]) -> Result<Response> {
+	let body = rows_csv(rows)?;
+	Ok(([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], body).into_response())
+}
+
+/// Serialize rows as CSV. Columns are the union of the flattened values of
+/// every row, so rows with differing fields share a single header - cells for
+/// columns a row does not contain are left empty.
+fn rows_csv(rows: &[RowResult]) -> Result<String> {
+	let rows = rows
+		.iter()
+		.map(|row| {
+			let value = serde_json::to_value(row).context("failed to serialize row")?;
+			let mut cells = BTreeMap::new();
+			flatten_value(String::new(), value, &mut cells);
+			Ok(cells)
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	let mut columns = rows
+		.iter()
+		.flat_map(|cells| cells.keys().map(String::as_str))
+		.collect::<BTreeSet<_>>();
+
+	let leading = LEADING_COLUMNS
+		.into_iter()
+		.filter(|column| columns.remove(column))
+		.collect::<Vec<_>>();
+	let mut fields = columns.into_iter().collect::<Vec<_>>();
+	fields.sort_by_cached_key(|column| column_key(column));
+	let columns = leading.into_iter().chain(fields).collect::<Vec<_>>();
+
+	let mut output = String::new();
+	write_record(&mut output, columns.iter().copied());
+	for cells in &rows {
+		write_record(
+			&mut output,
+			columns
+				.iter()
+				.map(|column| cells.get(*column).map_or("", String::as_str)),
+		);
+	}
+
+	Ok(output)
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ColumnSegment<'a> {
+	Name(&'a str),
+	Index(u64),
+}
+
+/// Sort key for a column, comparing array indices numerically such that `a[2]`
+/// precedes `a[10]`.
+fn column_key(column: &str) -> Vec<ColumnSegment<'_>> {
+	let mut segments = vec![];
+	let mut rest = column;
+	while !rest.is_empty() {
+		let index = rest.split_once('[').and_then(|(name, tail)| {
+			let (index, tail) = tail.split_once(']')?;
+			Some((name, index.parse::<u64>().ok()?, tail))
+		});
+
+		let Some((name, index, tail)) = index else {
+			segments.push(ColumnSegment::Name(rest));
+			break;
+		};
+
+		if !name.is_empty() {
+			segments.push(ColumnSegment::Name(name));
+		}
+		segments.push(ColumnSegment::Index(index));
+		rest = tail;
+	}
+	segments
+}
+
+fn flatten_value(path: String, value: serde_json::Value, cells: &mut BTreeMap<String, String>) {
+	use serde_json::Value as V;
+	match value {
+		V::Object(fields) => {
+			for (key, value) in fields {
+				let path = match path.is_empty() {
+					true => key,
+					false => format!("{path}.{key}"),
+				};
+				flatten_value(path, value, cells);
+			}
+		}
+		V::Array(values) => {
+			for (index, value) in values.into_iter().enumerate() {
+				flatten_value(format!("{path}[{index}]"), value, cells);
+			}
+		}
+		V::Null => {
+			cells.insert(path, String::new());
+		}
+		V::String(string) => {
+			cells.insert(path, string);
+		}
+		other => {
+			cells.insert(path, other.to_string());
+		}
+	}
+}
+
+fn write_record<'a>(output: &mut String, cells: impl IntoIterator<Item = &'a str>) {
+	for (index, cell) in cells.into_iter().enumerate() {
+		if index > 0 {
+			output.push(',');
+		}
+
+		// Quote fields containing characters that are otherwise meaningful to CSV.
+		match cell.contains([',', '"', '\n', '\r']) {
+			true => {
+				output.push('"');
+				output.push_str(&cell.replace('"', "\"\""));
+				output.push('"');
+			}
+			false => output.push_str(cell),
+		}
+	}
+
+	output.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use bm_read as read;
+	use ironworks::{excel, sestring::format::Input};
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+	use crate::api1::value::{FieldCase, ValueString};
+
+	fn test_row(
+		row_id: u32,
+		fields: impl IntoIterator<Item = (&'static str, read::Value)>,
+	) -> RowResult {
+		RowResult {
+			row_id,
+			subrow_id: None,
+			fields: ValueString(
+				read::Value::Struct(
+					fields
+						.into_iter()
+						.map(|(key, value)| (key.to_string(), value))
+						.collect::<HashMap<_, _>>(),
+				),
+				excel::Language::English,
+				Input::new().into(),
+				Default::default(),
+				FieldCase::Original,
+			),
+			transient: None,
+		}
+	}
+
+	fn scalar(value: u32) -> read::Value {
+		read::Value::Scalar(excel::Field::U32(value))
+	}
+
+	#[test]
+	fn ragged_rows_share_columns() {
+		let got = rows_csv(&[
+			test_row(1, [("A", scalar(10))]),
+			test_row(2, [("B", read::Value::Array(vec![scalar(20), scalar(21)]))]),
+		])
+		.unwrap();
+
+		assert_eq!(
+			got,
+			"row_id,fields.A,fields.B[0],fields.B[1]\r\n1,10,,\r\n2,,20,21\r\n"
+		);
+	}
+
+	#[test]
+	fn array_columns_ordered_numerically() {
+		let got = rows_csv(&[test_row(
+			1,
+			[("B", read::Value::Array((0..11).map(scalar).collect()))],
+		)])
+		.unwrap();
+
+		let header = got.split("\r\n").next().unwrap();
+		let columns = header.split(',').collect::<Vec<_>>();
+		assert_eq!(columns[1], "fields.B[0]");
+		assert_eq!(columns[3], "fields.B[2]");
+		assert_eq!(columns[11], "fields.B[10]");
+	}
+
+	#[test]
+	fn column_keys() {
+		assert!(column_key("a[2]") < column_key("a[10]"));
+		assert!(column_key("a[2].b") < column_key("a[10].a"));
+		assert!(column_key("a") < column_key("b[0]"));
+		assert_eq!(
+			column_key("a[1].b[x]"),
+			vec![
+				ColumnSegment::Name("a"),
+				ColumnSegment::Index(1),
+				ColumnSegment::Name(".b[x]"),
+			]
+		);
+	}
+
+	#[test]
+	fn nested_structs_flattened() {
+		let got = rows_csv(&[test_row(
+			1,
+			[(
+				"Outer",
+				read::Value::Struct(HashMap::from([("Inner".to_string(), scalar(5))])),
+			)],
+		)])
+		.unwrap();
+
+		assert_eq!(got, "row_id,fields.Outer.Inner\r\n1,5\r\n");
+	}
+
+	#[test]
+	fn cells_quoted() {
+		let mut output = String::new();
+		write_record(&mut output, ["plain", "a,b", "say \"hi\"", "line\nbreak"]);
+		assert_eq!(
+			output,
+			"plain,\"a,b\",\"say \"\"hi\"\"\",\"line\nbreak\"\r\n"
+		);
+	}
+}
@@ -0,0 +1,192 @@
+use std::time::Instant;
+
+use aide::OperationIo;
+use axum::{
+	extract::{FromRef, FromRequestParts},
+	http::request::Parts,
+	Json, RequestPartsExt,
+};
+use bm_version::VersionKey;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{ser::SerializeStruct, Deserialize, Serialize};
+
+use super::{error::Error, extract::Query, timing::Timings};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EnvelopeConfig {
+	enabled: bool,
+}
+
+/// Query parameters accepted by endpoints that support response envelopes.
+#[derive(Deserialize, JsonSchema)]
+struct EnvelopeQueryParams {
+	/// Wrap the response in an envelope of the shape `{data, meta}`, where
+	/// `data` is the regular response body, and `meta` contains information
+	/// about the request. Defaults to the server configuration if omitted.
+	envelope: Option<bool>,
+}
+
+#[derive(OperationIo)]
+#[aide(input_with = "Query<EnvelopeQueryParams>")]
+pub struct EnvelopeQuery {
+	enabled: bool,
+	start: Instant,
+	timings: Timings,
+}
+
+impl<S> FromRequestParts<S> for EnvelopeQuery
+where
+	S: Send + Sync,
+	EnvelopeConfig: FromRef<S>,
+{
+	type Rejection = Error;
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+		let start = Instant::now();
+
+		let Query(params) = parts.extract::<Query<EnvelopeQueryParams>>().await?;
+		let config = EnvelopeConfig::from_ref(state);
+
+		Ok(Self {
+			enabled: params.envelope.unwrap_or(config.enabled),
+			start,
+			timings: Timings::from_parts(parts),
+		})
+	}
+}
+
+impl EnvelopeQuery {
+	/// Wrap response data in an envelope, if requested. Metadata is only
+	/// included in the response when the envelope is enabled.
+	pub fn wrap<T>(
+		&self,
+		data: T,
+		version: Option<VersionKey>,
+		schema: Option<bm_schema::CanonicalSpecifier>,
+	) -> Json<Envelope<T>> {
+		let meta = self.enabled.then(|| EnvelopeMeta {
+			version,
+			schema,
+			elapsed_ms: u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX),
+		});
+
+		Json(Envelope {
+			data,
+			meta,
+			timings: self.timings.clone(),
+		})
+	}
+}
+
+/// Response data, optionally wrapped with metadata about the request.
+pub struct Envelope<T> {
+	data: T,
+	meta: Option<EnvelopeMeta>,
+	timings: Timings,
+}
+
+#[derive(Serialize)]
+struct EnvelopeMeta {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	version: Option<VersionKey>,
+
+	#[serde(skip_serializing_if = "Option::is_none")]
+	schema: Option<bm_schema::CanonicalSpecifier>,
+
+	elapsed_ms: u64,
+}
+
+impl<T> Serialize for Envelope<T>
+where
+	T: Serialize,
+{
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		self.timings.time("serialize", || {
+			let Some(meta) = &self.meta else {
+				return self.data.serialize(serializer);
+			};
+
+			let mut state = serializer.serialize_struct("Envelope", 2)?;
+			state.serialize_field("data", &self.data)?;
+			state.serialize_field("meta", meta)?;
+			state.end()
+		})
+	}
+}
+
+// Envelopes are opt-in, document the bare response shape.
+impl<T> JsonSchema for Envelope<T>
+where
+	T: JsonSchema,
+{
+	fn schema_name() -> String {
+		T::schema_name()
+	}
+
+	fn schema_id() -> std::borrow::Cow<'static, str> {
+		T::schema_id()
+	}
+
+	fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+		T::json_schema(generator)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+	use serde_json::json;
+
+	use super::*;
+
+	fn test_query(enabled: bool) -> EnvelopeQuery {
+		EnvelopeQuery {
+			enabled,
+			start: Instant::now(),
+			timings: Timings::default(),
+		}
+	}
+
+	#[test]
+	fn bare() {
+		let Json(envelope) = test_query(false).wrap(
+			json!({"row_id": 1}),
+			Some("00000000000000ff".parse().unwrap()),
+			None,
+		);
+
+		let got = serde_json::to_value(envelope).unwrap();
+		assert_eq!(got, json!({"row_id": 1}));
+	}
+
+	#[test]
+	fn enveloped() {
+		let Json(envelope) = test_query(true).wrap(
+			json!({"row_id": 1}),
+			Some("00000000000000ff".parse().unwrap()),
+			Some(bm_schema::CanonicalSpecifier {
+				source: "source".into(),
+				version: "version".into(),
+			}),
+		);
+
+		let mut got = serde_json::to_value(envelope).unwrap();
+		let elapsed = got["meta"]
+			.as_object_mut()
+			.and_then(|meta| meta.remove("elapsed_ms"));
+		assert!(matches!(elapsed, Some(serde_json::Value::Number(..))));
+		assert_eq!(
+			got,
+			json!({
+				"data": {"row_id": 1},
+				"meta": {
+					"version": "00000000000000ff",
+					"schema": "source@version",
+				},
+			})
+		);
+	}
+}
@@ -1,12 +1,15 @@
+use std::time::Duration;
+
 use aide::{openapi::Response as AideResponse, transform::TransformResponse, OperationOutput};
 use axum::{
-	extract::rejection::{PathRejection, QueryRejection},
-	http::StatusCode,
+	extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+	http::{header, StatusCode},
 	response::{IntoResponse, Response as AxumResponse},
 	Json,
 };
 use schemars::JsonSchema;
 use serde::Serialize;
+use uuid::Uuid;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -16,9 +19,30 @@ pub enum Error {
 	#[error("invalid request: {0}")]
 	Invalid(String),
 
+	#[error("unauthorized: {0}")]
+	Unauthorized(String),
+
+	#[error("cursor expired: {0}")]
+	CursorExpired(Uuid),
+
 	#[error("unavailable: {0}")]
 	Unavailable(String),
 
+	#[error("unavailable: server is in maintenance, retry after {}s", .0.as_secs())]
+	Maintenance(Duration),
+
+	#[error("timed out: request exceeded {}ms", .0.as_millis())]
+	Timeout(Duration),
+
+	#[error(
+		"schema outdated: schema for {sheet} expects {expected} columns, game data has {actual}"
+	)]
+	SchemaOutdated {
+		sheet: String,
+		expected: usize,
+		actual: usize,
+	},
+
 	#[error("internal server error")]
 	Other(#[from] anyhow::Error),
 }
@@ -28,9 +52,10 @@ impl From<bm_asset::Error> for Error {
 		use bm_asset::Error as AE;
 		match error {
 			AE::NotFound(..) => Self::NotFound(error.to_string()),
-			AE::UnsupportedSource(..) | AE::InvalidConversion(..) | AE::UnknownFormat(..) => {
-				Self::Invalid(error.to_string())
-			}
+			AE::UnsupportedSource(..)
+			| AE::InvalidConversion(..)
+			| AE::InvalidOptions(..)
+			| AE::UnknownFormat(..) => Self::Invalid(error.to_string()),
 			AE::Failure(inner) => Self::Other(inner),
 		}
 	}
@@ -40,7 +65,7 @@ impl From<bm_data::Error> for Error {
 	fn from(error: bm_data::Error) -> Self {
 		use bm_data::Error as DE;
 		match error {
-			DE::UnknownVersion(..) => Self::Invalid(error.to_string()),
+			DE::UnknownVersion(..) | DE::UnknownPatch(..) => Self::Invalid(error.to_string()),
 			DE::Failure(inner) => Self::Other(inner),
 		}
 	}
@@ -51,9 +76,11 @@ impl From<bm_read::Error> for Error {
 		use bm_read::Error as RE;
 		match error {
 			RE::NotFound(..) => Self::NotFound(error.to_string()),
-			RE::FilterSchemaMismatch(..) | RE::SchemaGameMismatch(..) | RE::InvalidLanguage(..) => {
-				Self::Invalid(error.to_string())
-			}
+			RE::FilterSchemaMismatch(..)
+			| RE::SchemaGameMismatch(..)
+			| RE::InvalidLanguage(..)
+			| RE::AmbiguousName(..) => Self::Invalid(error.to_string()),
+			RE::SchemaOutdated(drift) => drift.into(),
 			RE::Failure(inner) => Self::Other(inner),
 		}
 	}
@@ -78,13 +105,34 @@ impl From<bm_search::Error> for Error {
 			| SE::MalformedQuery(..)
 			| SE::QuerySchemaMismatch(..)
 			| SE::QueryGameMismatch(..)
-			| SE::SchemaGameMismatch(..)
-			| SE::UnknownCursor(..) => Self::Invalid(error.to_string()),
+			| SE::SchemaGameMismatch(..) => Self::Invalid(error.to_string()),
+			SE::UnknownCursor(..) => Self::NotFound(error.to_string()),
+			SE::CursorExpired(cursor) => Self::CursorExpired(cursor),
+			SE::SchemaOutdated(drift) => drift.into(),
 			SE::Failure(inner) => Self::Other(inner),
 		}
 	}
 }
 
+// Column drift is reported identically by read and search, despite coming from
+// separate crates.
+macro_rules! impl_from_drift {
+	($source:ty) => {
+		impl From<$source> for Error {
+			fn from(value: $source) -> Self {
+				Self::SchemaOutdated {
+					sheet: value.sheet,
+					expected: value.expected,
+					actual: value.actual,
+				}
+			}
+		}
+	};
+}
+
+impl_from_drift!(bm_read::ColumnDriftError);
+impl_from_drift!(bm_search::ColumnDriftError);
+
 impl From<PathRejection> for Error {
 	fn from(value: PathRejection) -> Self {
 		match value {
@@ -105,6 +153,17 @@ impl From<QueryRejection> for Error {
 	}
 }
 
+impl From<JsonRejection> for Error {
+	fn from(value: JsonRejection) -> Self {
+		match value {
+			JsonRejection::JsonDataError(error) => Self::Invalid(error.body_text()),
+			JsonRejection::JsonSyntaxError(error) => Self::Invalid(error.body_text()),
+			JsonRejection::MissingJsonContentType(error) => Self::Invalid(error.body_text()),
+			other => Self::Other(other.into()),
+		}
+	}
+}
+
 macro_rules! impl_to_failure {
 	($source:ty) => {
 		impl From<$source> for Error {
@@ -126,6 +185,34 @@ pub struct ErrorResponse {
 
 	/// Description of what went wrong.
 	message: String,
+
+	/// Machine-readable detail about the error, for errors that provide it.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	detail: Option<ErrorDetail>,
+}
+
+/// Machine-readable error detail, discriminated by `code`.
+#[derive(Debug, PartialEq, Serialize, JsonSchema)]
+#[serde(tag = "code", rename_all = "snake_case")]
+enum ErrorDetail {
+	/// The schema in use expects more columns than are present in the game data
+	/// for the sheet. This typically occurs shortly after a game update, before
+	/// schemas have been updated - consider requesting a different schema version.
+	SchemaOutdated {
+		/// Name of the sheet with mismatched columns.
+		sheet: String,
+		/// Number of columns expected by the schema.
+		expected: usize,
+		/// Number of columns present in the game data.
+		actual: usize,
+	},
+
+	/// The search cursor has outlived its lifetime. Repeat the original search
+	/// to obtain a new cursor.
+	CursorExpired {
+		/// The expired cursor.
+		cursor: Uuid,
+	},
 }
 
 #[derive(Serialize, JsonSchema)]
@@ -137,14 +224,32 @@ impl From<Error> for ErrorResponse {
 		// TODO: INCREDIBLY IMPORTANT: work out how to worm IM_A_TEAPOT into this
 		let status_code = match value {
 			Error::NotFound(..) => StatusCode::NOT_FOUND,
-			Error::Invalid(..) => StatusCode::BAD_REQUEST,
-			Error::Unavailable(..) => StatusCode::SERVICE_UNAVAILABLE,
+			Error::Unauthorized(..) => StatusCode::UNAUTHORIZED,
+			Error::CursorExpired(..) => StatusCode::GONE,
+			Error::Invalid(..) | Error::SchemaOutdated { .. } => StatusCode::BAD_REQUEST,
+			Error::Unavailable(..) | Error::Maintenance(..) => StatusCode::SERVICE_UNAVAILABLE,
+			Error::Timeout(..) => StatusCode::GATEWAY_TIMEOUT,
 			Error::Other(..) => StatusCode::INTERNAL_SERVER_ERROR,
 		};
 
+		let detail = match &value {
+			Error::SchemaOutdated {
+				sheet,
+				expected,
+				actual,
+			} => Some(ErrorDetail::SchemaOutdated {
+				sheet: sheet.clone(),
+				expected: *expected,
+				actual: *actual,
+			}),
+			Error::CursorExpired(cursor) => Some(ErrorDetail::CursorExpired { cursor: *cursor }),
+			_ => None,
+		};
+
 		Self {
 			code: status_code,
 			message: value.to_string(),
+			detail,
 		}
 	}
 }
@@ -156,9 +261,21 @@ impl IntoResponse for Error {
 			tracing::error!("{error:?}")
 		}
 
+		let retry_after = match self {
+			Self::Maintenance(duration) => Some(duration),
+			_ => None,
+		};
+
 		let response = ErrorResponse::from(self);
+		let mut response = (response.code, Json(response)).into_response();
 
-		(response.code, Json(response)).into_response()
+		if let Some(duration) = retry_after {
+			response
+				.headers_mut()
+				.insert(header::RETRY_AFTER, duration.as_secs().into());
+		}
+
+		response
 	}
 }
 
@@ -184,3 +301,74 @@ impl OperationOutput for Error {
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+	use serde_json::json;
+
+	use super::*;
+
+	fn drift() -> Error {
+		Error::SchemaOutdated {
+			sheet: "Item".into(),
+			expected: 92,
+			actual: 90,
+		}
+	}
+
+	#[test]
+	fn schema_outdated_detail() {
+		let response = ErrorResponse::from(drift());
+		assert_eq!(response.code, StatusCode::BAD_REQUEST);
+		assert_eq!(
+			response.detail,
+			Some(ErrorDetail::SchemaOutdated {
+				sheet: "Item".into(),
+				expected: 92,
+				actual: 90,
+			})
+		);
+	}
+
+	#[test]
+	fn serialize_schema_outdated() {
+		let got = serde_json::to_value(ErrorResponse::from(drift())).unwrap();
+		assert_eq!(got["code"], json!(400));
+		assert_eq!(
+			got["detail"],
+			json!({"code": "schema_outdated", "sheet": "Item", "expected": 92, "actual": 90})
+		);
+	}
+
+	#[test]
+	fn cursor_expired_detail() {
+		let cursor = Uuid::nil();
+		let got = serde_json::to_value(ErrorResponse::from(Error::from(
+			bm_search::Error::CursorExpired(cursor),
+		)))
+		.unwrap();
+		assert_eq!(got["code"], json!(410));
+		assert_eq!(
+			got["detail"],
+			json!({"code": "cursor_expired", "cursor": cursor})
+		);
+
+		let got = ErrorResponse::from(Error::from(bm_search::Error::UnknownCursor(cursor)));
+		assert_eq!(got.code, StatusCode::NOT_FOUND);
+		assert_eq!(got.detail, None);
+	}
+
+	#[test]
+	fn maintenance_retry_after() {
+		let response = Error::Maintenance(Duration::from_secs(120)).into_response();
+		assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+		assert_eq!(response.headers()[header::RETRY_AFTER], "120");
+	}
+
+	#[test]
+	fn generic_error_has_no_detail() {
+		let got = serde_json::to_value(ErrorResponse::from(Error::Invalid("bad".into()))).unwrap();
+		assert!(got.get("detail").is_none());
+	}
+}
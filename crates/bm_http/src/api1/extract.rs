@@ -1,6 +1,6 @@
 use aide::OperationIo;
 use axum::{
-	extract::{FromRef, FromRequestParts},
+	extract::{FromRef, FromRequest, FromRequestParts},
 	http::request::Parts,
 	RequestPartsExt,
 };
@@ -60,3 +60,8 @@ pub struct Path<T>(pub T);
 #[from_request(via(axum::extract::Query), rejection(Error))]
 #[aide(input_with = "axum::extract::Query<T>", json_schema)]
 pub struct Query<T>(pub T);
+
+#[derive(FromRequest, OperationIo)]
+#[from_request(via(axum::Json), rejection(Error))]
+#[aide(input_with = "axum::Json<T>", json_schema)]
+pub struct JsonBody<T>(pub T);
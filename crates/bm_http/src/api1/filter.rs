@@ -1,12 +1,19 @@
-use std::{collections::HashMap, fmt, str::FromStr};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+	str::FromStr,
+};
 
 use bm_read as read;
 use ironworks::excel;
+use mini_moka::sync as moka;
 use nom::{
 	branch::alt,
 	bytes::complete::{escaped_transform, is_not, tag},
-	character::complete::{alphanumeric1, char},
-	combinator::{all_consuming, consumed, cut, eof, map, map_res, value, verify},
+	character::complete::{alphanumeric1, char, digit1},
+	combinator::{
+		all_consuming, consumed, cut, eof, map, map_opt, map_res, opt, success, value, verify,
+	},
 	multi::{many0, separated_list0, separated_list1},
 	sequence::{delimited, preceded},
 	Finish, Parser,
@@ -31,6 +38,15 @@ use super::error;
 ///
 /// - `@as(<format>)`: Overrides the default output format for the decorated
 ///   field.
+///
+/// - `@round(<places>)`: Rounds a float field to the given number of decimal
+///   places, overriding the server's default precision. Applies to all float
+///   fields within the decorated field, and has no effect on other fields. May
+///   be combined with `@as`.
+///
+/// - `@depth(<depth>)`: Overrides the depth of relations followed within the
+///   decorated field, in place of the request's `depth`. Values exceeding the
+///   maximum depth are clamped.
 ///  
 /// Currently accepted `format`s for `@as`:
 ///
@@ -41,19 +57,65 @@ use super::error;
 ///   fields. Output will be a valid HTML fragment, however no stability
 ///   guarantees are made over the precise markup used.
 ///
+/// - `markdown`: Formats a string field as CommonMark. Invalid on non-string
+///   fields. Colors are represented with inline HTML, as CommonMark has no
+///   equivalent syntax.
+///
+/// - `plain`: Formats a string field as plain text, removing all formatting
+///   and non-text payloads such as icons. Invalid on non-string fields.
+///
+/// - `hex`: Formats an integer field as a hexadecimal string, i.e. `"0x1f"`.
+///   Signed values are formatted as their bit pattern. Invalid on non-integer
+///   fields.
+///
+/// - `label`: Replaces a reference field with the label of the row it targets,
+///   as configured per-sheet by the server. If no label is configured or the
+///   target row cannot be read, the field's value is returned as-is. Subject
+///   to the same depth limits as reference expansion.
+///
+/// A trailing `*` on a field name will select all fields with names starting
+/// with the preceding text, i.e. `Param*` will select both `ParamA` and
+/// `ParamB`. Asterisks elsewhere in a field name are literal - a trailing
+/// literal asterisk may be escaped as `\*`.
+///
 /// Nested fields may be selected using dot notation, i.e. `a.b` will select the
 /// field `b` contained in the struct `a`.
 ///
 /// Arrays must be targeted if selecting fields within them, i.e. `a[].b` will
 /// select _all_ `b` fields of structs within the array `a`, however `a.b` will
 /// select nothing.
+///
+/// Array elements may be selected by index, i.e. `a[2]` will select only the
+/// element at index `2` of the array `a`. Ranges of elements may be selected
+/// with `a[start:end]`, where `start` is inclusive and `end` is exclusive.
+/// Either bound may be omitted to extend the range to the start or end of the
+/// array, respectively. Indices outside the bounds of the array select nothing.
+/// Selections of differing elements are combined, i.e. `a[].b,a[0].c` will
+/// select `b` from every element of `a`, and `c` only from the first.
+///
+/// Selections that disagree on the shape of a field, i.e. `a[].b,a.c`, are
+/// invalid. Servers configured with lenient filters will instead use the first
+/// such selection, omitting the field from rows where it does not match.
+///
+/// Fields may be excluded by prefixing their name with `!`, i.e. `!a,!b` will
+/// select all fields other than `a` and `b`. Exclusions may only target fields
+/// at the top level, and may not be decorated. If the filter also contains
+/// inclusions, exclusions are instead removed from the included fields - for
+/// example, `a,b,!b` will select only `a`. Excluding a field removes it in all
+/// its decorated forms.
 #[derive(Debug, Clone, JsonSchema)]
 pub struct FilterString(#[schemars(with = "String")] FilterStringInner);
 
 #[derive(Debug, Clone)]
 enum FilterStringInner {
 	All,
-	Paths(Vec<Path>),
+	Paths(Vec<Selection>),
+}
+
+#[derive(Debug, Clone)]
+enum Selection {
+	Include(Path),
+	Exclude(String),
 }
 
 type Path = Vec<Entry>;
@@ -65,38 +127,84 @@ enum Entry {
 		field: String,
 		language: Option<excel::Language>,
 		read_as: Option<read::As>,
+		prefix: bool,
+		depth: Option<u8>,
+		round: Option<u8>,
 	},
 	Index,
+	Range {
+		start: u32,
+		end: Option<u32>,
+	},
 }
 
 impl FilterString {
 	pub fn is_empty(&self) -> bool {
 		match &self.0 {
 			FilterStringInner::All => false,
-			FilterStringInner::Paths(paths) => paths.is_empty(),
+			FilterStringInner::Paths(selections) => selections.is_empty(),
 		}
 	}
 
-	pub fn to_filter(self, default_language: excel::Language) -> error::Result<read::Filter> {
-		let paths = match self.0 {
+	/// Build the read filter selected by this string. Selections that disagree on
+	/// the shape of a field, i.e. `a[].b,a.c`, are rejected - if lenient, the
+	/// later selection is instead ignored.
+	pub fn to_filter(
+		self,
+		default_language: excel::Language,
+		lenient: bool,
+	) -> error::Result<read::Filter> {
+		let selections = match self.0 {
 			FilterStringInner::All => return Ok(read::Filter::All),
-			FilterStringInner::Paths(paths) => paths,
+			FilterStringInner::Paths(selections) => selections,
 		};
 
+		let mut paths = vec![];
+		let mut excluded = HashSet::new();
+		for selection in selections {
+			match selection {
+				Selection::Include(path) => paths.push(path),
+				Selection::Exclude(field) => {
+					excluded.insert(field);
+				}
+			}
+		}
+
 		let mut filters = paths
 			.into_iter()
 			.map(|entries| build_filter(entries, default_language));
 
 		let Some(mut output) = filters.next() else {
+			// A filter comprised solely of exclusions selects everything else.
+			if !excluded.is_empty() {
+				return Ok(read::Filter::AllExcept(excluded));
+			}
+
 			// TODO: Should I introduce an explicit "None" concept?
 			return Ok(read::Filter::Struct(HashMap::new()));
 		};
 
 		for filter in filters {
-			output = merge_filters(output, filter)?;
+			output = merge_filters(output, filter, lenient)?;
 		}
 
-		Ok(output)
+		Ok(exclude_fields(output, excluded))
+	}
+}
+
+fn exclude_fields(filter: read::Filter, excluded: HashSet<String>) -> read::Filter {
+	if excluded.is_empty() {
+		return filter;
+	}
+
+	match filter {
+		read::Filter::All => read::Filter::AllExcept(excluded),
+		read::Filter::Struct(mut fields) => {
+			fields.retain(|_key, entry| !excluded.contains(&entry.field));
+			read::Filter::Struct(fields)
+		}
+		// Top level filters are always structs - there's nothing to exclude from.
+		other => other,
 	}
 }
 
@@ -111,11 +219,20 @@ fn build_filter(mut path: Path, default_language: excel::Language) -> read::Filt
 	match entry {
 		Entry::Index => read::Filter::Array(build_filter(path, default_language).into()),
 
+		Entry::Range { start, end } => read::Filter::ArrayRanges(vec![read::ArrayRange {
+			start,
+			end,
+			filter: build_filter(path, default_language),
+		}]),
+
 		Entry::Key {
 			key,
 			field,
 			language,
 			read_as,
+			prefix,
+			depth,
+			round,
 		} => {
 			// Structs can override the default language of inner path entries.
 			let inner_language = language.unwrap_or(default_language);
@@ -126,6 +243,9 @@ fn build_filter(mut path: Path, default_language: excel::Language) -> read::Filt
 					field,
 					language: inner_language,
 					read_as: read_as.unwrap_or(read::As::Default),
+					prefix,
+					depth,
+					round,
 					filter: build_filter(path, inner_language),
 				},
 			)]))
@@ -133,7 +253,7 @@ fn build_filter(mut path: Path, default_language: excel::Language) -> read::Filt
 	}
 }
 
-fn merge_filters(a: read::Filter, b: read::Filter) -> error::Result<read::Filter> {
+fn merge_filters(a: read::Filter, b: read::Filter, lenient: bool) -> error::Result<read::Filter> {
 	use read::Filter as F;
 
 	let new_filter = match (a, b) {
@@ -142,7 +262,19 @@ fn merge_filters(a: read::Filter, b: read::Filter) -> error::Result<read::Filter
 
 		// Arrays can directly merge their inner filter.
 		(F::Array(a_inner), F::Array(b_inner)) => {
-			F::Array(merge_filters(*a_inner, *b_inner)?.into())
+			F::Array(merge_filters(*a_inner, *b_inner, lenient)?.into())
+		}
+
+		// Ranges are merged element-wise, with whole arrays treated as a range over
+		// every element.
+		(F::ArrayRanges(a_ranges), F::ArrayRanges(b_ranges)) => {
+			merge_ranges(a_ranges, b_ranges, lenient)?
+		}
+		(F::Array(a_inner), F::ArrayRanges(b_ranges)) => {
+			merge_ranges(vec![unbounded_range(*a_inner)], b_ranges, lenient)?
+		}
+		(F::ArrayRanges(a_ranges), F::Array(b_inner)) => {
+			merge_ranges(a_ranges, vec![unbounded_range(*b_inner)], lenient)?
 		}
 
 		// Structs need to have entry filters merged for matching keys.
@@ -157,7 +289,7 @@ fn merge_filters(a: read::Filter, b: read::Filter) -> error::Result<read::Filter
 					// parser, there is no real opportunity for a mismatching entry for
 					// a matching key.
 					Some(a_entry) => read::StructEntry {
-						filter: merge_filters(a_entry.filter, b_entry.filter)?,
+						filter: merge_filters(a_entry.filter, b_entry.filter, lenient)?,
 						..a_entry
 					},
 				};
@@ -166,12 +298,27 @@ fn merge_filters(a: read::Filter, b: read::Filter) -> error::Result<read::Filter
 			F::Struct(a_fields)
 		}
 
-		// Other patterns are invalid. Explicitly checking the first element to
-		// ensure this code path will error if new filter types are added.
-		(F::Array(_), _) | (F::Struct(_), _) => {
+		// Other patterns disagree on the shape of the field. Leniently, the first
+		// selection is kept, and the read skips the field if it does not match.
+		// Explicitly checking the first element to ensure this code path will
+		// error if new filter types are added.
+		(a @ (F::Array(_) | F::ArrayRanges(_) | F::Struct(_)), b) => match lenient {
+			true => {
+				tracing::debug!(?a, ?b, "ignoring mismatched filter selection");
+				a
+			}
+			false => {
+				return Err(error::Error::Invalid(
+					// TODO: improve this error message
+					"invalid filter: tried to merge array and struct".into(),
+				));
+			}
+		},
+
+		// Exclusions are applied after merging, and should never be seen here.
+		(F::AllExcept(_), _) => {
 			return Err(error::Error::Invalid(
-				// TODO: improve this error message
-				"invalid filter: tried to merge array and struct".into(),
+				"invalid filter: tried to merge exclusions".into(),
 			));
 		}
 	};
@@ -179,6 +326,128 @@ fn merge_filters(a: read::Filter, b: read::Filter) -> error::Result<read::Filter
 	Ok(new_filter)
 }
 
+fn unbounded_range(filter: read::Filter) -> read::ArrayRange {
+	read::ArrayRange {
+		start: 0,
+		end: None,
+		filter,
+	}
+}
+
+/// Merge two sets of array ranges into a single set of disjoint ranges. Each
+/// element is read with the merged filters of every range selecting it.
+fn merge_ranges(
+	a: Vec<read::ArrayRange>,
+	b: Vec<read::ArrayRange>,
+	lenient: bool,
+) -> error::Result<read::Filter> {
+	// Unbounded ends are treated as the largest possible bound.
+	let bound = |end: Option<u32>| end.map_or(u64::MAX, u64::from);
+
+	let ranges = a.into_iter().chain(b).collect::<Vec<_>>();
+	let mut bounds = ranges
+		.iter()
+		.flat_map(|range| [u64::from(range.start), bound(range.end)])
+		.collect::<Vec<_>>();
+	bounds.sort_unstable();
+	bounds.dedup();
+
+	let mut merged: Vec<read::ArrayRange> = vec![];
+	for window in bounds.windows(2) {
+		let (start, end) = (window[0], window[1]);
+
+		let mut filters = ranges
+			.iter()
+			.filter(|range| u64::from(range.start) <= start && bound(range.end) >= end)
+			.map(|range| range.filter.clone());
+		let Some(first) = filters.next() else {
+			continue;
+		};
+		let filter = filters.try_fold(first, |a, b| merge_filters(a, b, lenient))?;
+
+		let start = u32::try_from(start).expect("bounds other than the maximum are u32");
+		let end = (end != u64::MAX)
+			.then(|| u32::try_from(end).expect("bounds other than the maximum are u32"));
+
+		// Adjacent ranges with the same filter are combined.
+		if let Some(last) = merged.last_mut() {
+			if last.end == Some(start) && last.filter == filter {
+				last.end = end;
+				continue;
+			}
+		}
+
+		merged.push(read::ArrayRange { start, end, filter });
+	}
+
+	// Ranges spanning the entire array are equivalent to selecting it whole.
+	let whole = matches!(
+		merged.as_slice(),
+		[read::ArrayRange {
+			start: 0,
+			end: None,
+			..
+		}]
+	);
+	if whole {
+		return Ok(read::Filter::Array(merged.remove(0).filter.into()));
+	}
+
+	Ok(read::Filter::ArrayRanges(merged))
+}
+
+// Filters are small, an entry count bound is sufficient.
+const FILTER_CACHE_CAPACITY: u64 = 1024;
+
+/// Cache of filters built from raw filter strings, avoiding re-parsing filter
+/// strings that are repeated across requests.
+#[derive(Debug, Clone)]
+pub struct FilterCache {
+	cache: moka::Cache<(String, excel::Language, bool), read::Filter>,
+}
+
+impl Default for FilterCache {
+	fn default() -> Self {
+		Self {
+			cache: moka::Cache::new(FILTER_CACHE_CAPACITY),
+		}
+	}
+}
+
+impl FilterCache {
+	/// Get the filter for the given raw filter string, parsing it if it has not
+	/// been seen recently.
+	pub fn filter(
+		&self,
+		raw: &str,
+		default_language: excel::Language,
+		lenient: bool,
+	) -> error::Result<read::Filter> {
+		self.get_or_build(raw, default_language, lenient, || {
+			raw.parse::<FilterString>()?
+				.to_filter(default_language, lenient)
+		})
+	}
+
+	fn get_or_build(
+		&self,
+		raw: &str,
+		default_language: excel::Language,
+		lenient: bool,
+		build: impl FnOnce() -> error::Result<read::Filter>,
+	) -> error::Result<read::Filter> {
+		let key = (raw.to_string(), default_language, lenient);
+		if let Some(filter) = self.cache.get(&key) {
+			return Ok(filter);
+		}
+
+		let filter = build()?;
+		self.cache.insert(key, filter.clone());
+
+		Ok(filter)
+	}
+}
+
 impl<'de> Deserialize<'de> for FilterString {
 	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 	where
@@ -245,13 +514,30 @@ fn filter(input: &str) -> IResult<&str, FilterStringInner> {
 		map(eof, |_| FilterStringInner::Paths(vec![])),
 		value(FilterStringInner::All, char('*')),
 		map(
-			separated_list0(char(','), cut(path)),
+			separated_list0(char(','), cut(selection)),
 			FilterStringInner::Paths,
 		),
 	))
 	.parse(input)
 }
 
+fn selection(input: &str) -> IResult<&str, Selection> {
+	alt((
+		map(preceded(char('!'), cut(exclusion)), Selection::Exclude),
+		map(path, Selection::Include),
+	))
+	.parse(input)
+}
+
+fn exclusion(input: &str) -> IResult<&str, String> {
+	// Exclusions select fields by name alone - decorators are meaningless.
+	map_opt(key, |entry| match entry {
+		Entry::Key { key, field, .. } if key == field => Some(field),
+		_ => None,
+	})
+	.parse(input)
+}
+
 fn path(input: &str) -> IResult<&str, Path> {
 	map(separated_list1(char('.'), path_part), |parts| {
 		parts.into_iter().flatten().collect()
@@ -275,6 +561,8 @@ fn key(input: &str) -> IResult<&str, Entry> {
 		alt((
 			value("\\", char('\\')),
 			value("@", char('@')),
+			value("!", char('!')),
+			value("*", char('*')),
 			value("[", char('[')),
 			// NOTE: we don't actually need to support this, but it's nice QoL to permit balanced escapes.
 			value("]", char(']')),
@@ -283,20 +571,34 @@ fn key(input: &str) -> IResult<&str, Entry> {
 		)),
 	);
 
-	let (rest, (field, (decorator_input, decorators))) = (
-		verify(escaped_key, |t: &str| !t.is_empty()),
+	let (rest, ((raw_field, field), (decorator_input, decorators))) = (
+		verify(consumed(escaped_key), |(_, t): &(&str, String)| {
+			!t.is_empty()
+		}),
 		consumed(many0(decorator)),
 	)
 		.parse(input)?;
 
+	// A trailing unescaped `*` marks the key as a prefix. Asterisks elsewhere in
+	// the key are treated literally.
+	let prefix = is_prefix_glob(raw_field);
+	let field = match prefix {
+		true => field[..field.len() - 1].to_string(),
+		false => field,
+	};
+
 	let mut language = None;
 	let mut read_as = None;
+	let mut depth = None;
+	let mut round = None;
 
 	(|| -> Result<(), &'static str> {
 		for decorator in decorators {
 			match decorator {
 				Decorator::Language(d_lang) => set_option_once(&mut language, d_lang)?,
 				Decorator::As(d_as) => set_option_once(&mut read_as, d_as)?,
+				Decorator::Depth(d_depth) => set_option_once(&mut depth, d_depth)?,
+				Decorator::Round(d_round) => set_option_once(&mut round, d_round)?,
 			}
 		}
 		Ok(())
@@ -308,14 +610,27 @@ fn key(input: &str) -> IResult<&str, Entry> {
 	Ok((
 		rest,
 		Entry::Key {
-			key: format!("{field}{decorator_input}"),
+			key: format!("{field}{}{decorator_input}", if prefix { "*" } else { "" }),
 			field: field.into(),
 			language,
 			read_as,
+			prefix,
+			depth,
+			round,
 		},
 	))
 }
 
+fn is_prefix_glob(raw_field: &str) -> bool {
+	let Some(rest) = raw_field.strip_suffix('*') else {
+		return false;
+	};
+
+	// An odd number of preceding backslashes means the asterisk itself is escaped.
+	let escapes = rest.chars().rev().take_while(|char| *char == '\\').count();
+	escapes % 2 == 0
+}
+
 fn set_option_once<T>(option: &mut Option<T>, value: T) -> Result<(), &'static str> {
 	if option.is_some() {
 		return Err("duplicate decorator");
@@ -327,13 +642,39 @@ fn set_option_once<T>(option: &mut Option<T>, value: T) -> Result<(), &'static s
 }
 
 fn index(input: &str) -> IResult<&str, Entry> {
-	value(Entry::Index, tag("[]")).parse(input)
+	delimited(char('['), index_selection, char(']')).parse(input)
+}
+
+fn index_selection(input: &str) -> IResult<&str, Entry> {
+	alt((
+		map(
+			(opt(array_index), char(':'), opt(array_index)),
+			|(start, _, end)| Entry::Range {
+				start: start.unwrap_or(0),
+				end,
+			},
+		),
+		map_opt(array_index, |index| {
+			Some(Entry::Range {
+				start: index,
+				end: Some(index.checked_add(1)?),
+			})
+		}),
+		success(Entry::Index),
+	))
+	.parse(input)
+}
+
+fn array_index(input: &str) -> IResult<&str, u32> {
+	map_res(digit1, str::parse::<u32>).parse(input)
 }
 
 #[derive(Debug, Clone)]
 enum Decorator {
 	Language(excel::Language),
 	As(read::As),
+	Depth(u8),
+	Round(u8),
 }
 
 fn decorator(input: &str) -> IResult<&str, Decorator> {
@@ -345,6 +686,8 @@ fn decorator(input: &str) -> IResult<&str, Decorator> {
 			// Call-syntax decorators
 			map(call("lang", language), Decorator::Language),
 			map(call("as", read_as), Decorator::As),
+			map(call("round", round), Decorator::Round),
+			map(call("depth", depth), Decorator::Depth),
 		)),
 	)
 	.parse(input)
@@ -371,10 +714,29 @@ fn read_as(input: &str) -> IResult<&str, read::As> {
 	alt((
 		value(read::As::Raw, tag("raw")),
 		value(read::As::Html, tag("html")),
+		value(read::As::Markdown, tag("markdown")),
+		value(read::As::Plain, tag("plain")),
+		value(read::As::Hex, tag("hex")),
+		value(read::As::Label, tag("label")),
 	))
 	.parse(input)
 }
 
+fn round(input: &str) -> IResult<&str, u8> {
+	map_res(digit1, str::parse::<u8>).parse(input)
+}
+
+fn depth(input: &str) -> IResult<&str, u8> {
+	// Depths are clamped to the configured maximum when read - saturate rather
+	// than reject values that don't fit.
+	map_res(digit1, |depth: &str| {
+		depth
+			.parse::<u32>()
+			.map(|depth| u8::try_from(depth).unwrap_or(u8::MAX))
+	})
+	.parse(input)
+}
+
 #[cfg(test)]
 mod test {
 	use pretty_assertions::assert_eq;
@@ -387,7 +749,7 @@ mod test {
 			.parse::<FilterString>()
 			.expect("parse should not fail");
 		filter_string
-			.to_filter(excel::Language::English)
+			.to_filter(excel::Language::English, false)
 			.expect("conversion should not fail")
 	}
 
@@ -414,6 +776,9 @@ mod test {
 							field: field.to_string(),
 							language,
 							read_as: read::As::Default,
+							prefix: false,
+							depth: None,
+							round: None,
 							filter,
 						},
 					)
@@ -480,6 +845,103 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn parse_struct_decorator_round() {
+		let expected = read::Filter::Struct(HashMap::from([(
+			"a@round(2)".to_string(),
+			StructEntry {
+				field: "a".into(),
+				language: excel::Language::English,
+				read_as: read::As::Default,
+				prefix: false,
+				depth: None,
+				round: Some(2),
+				filter: read::Filter::All,
+			},
+		)]));
+
+		let got = test_parse("a@round(2)");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_struct_decorator_as_markdown() {
+		let expected = read::Filter::Struct(HashMap::from([(
+			"a@as(markdown)".to_string(),
+			StructEntry {
+				field: "a".into(),
+				language: excel::Language::English,
+				read_as: read::As::Markdown,
+				prefix: false,
+				depth: None,
+				round: None,
+				filter: read::Filter::All,
+			},
+		)]));
+
+		let got = test_parse("a@as(markdown)");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_struct_decorator_depth() {
+		let expected = read::Filter::Struct(HashMap::from([
+			(
+				"a@depth(0)".to_string(),
+				StructEntry {
+					field: "a".into(),
+					language: excel::Language::English,
+					read_as: read::As::Default,
+					prefix: false,
+					depth: Some(0),
+					round: None,
+					filter: read::Filter::All,
+				},
+			),
+			(
+				"b".to_string(),
+				StructEntry {
+					field: "b".into(),
+					language: excel::Language::English,
+					read_as: read::As::Default,
+					prefix: false,
+					depth: None,
+					round: None,
+					filter: read::Filter::All,
+				},
+			),
+		]));
+
+		let got = test_parse("a@depth(0),b");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_struct_decorator_depth_saturates() {
+		let got = test_parse("a@depth(1000)");
+		let read::Filter::Struct(fields) = got else {
+			panic!("expected struct filter");
+		};
+		assert_eq!(fields["a@depth(1000)"].depth, Some(u8::MAX));
+	}
+
+	#[test]
+	fn parse_struct_decorator_round_with_as() {
+		let got = test_parse("a@as(raw)@round(2)");
+		let read::Filter::Struct(fields) = got else {
+			panic!("expected struct filter");
+		};
+		let entry = &fields["a@as(raw)@round(2)"];
+		assert_eq!(entry.read_as, read::As::Raw);
+		assert_eq!(entry.round, Some(2));
+	}
+
+	#[test]
+	fn parse_struct_decorator_round_duplicated() {
+		let got = "a@round(1)@round(2)".parse::<FilterString>();
+		assert!(matches!(got, Err(error::Error::Invalid(_))));
+	}
+
 	#[test]
 	fn parse_struct_nested() {
 		let expected = test_struct([(
@@ -532,6 +994,258 @@ mod test {
 		assert_eq!(got, expected);
 	}
 
+	fn test_range(start: u32, end: Option<u32>, child: read::Filter) -> read::Filter {
+		test_ranges([(start, end, child)])
+	}
+
+	fn test_ranges(
+		ranges: impl IntoIterator<Item = (u32, Option<u32>, read::Filter)>,
+	) -> read::Filter {
+		read::Filter::ArrayRanges(
+			ranges
+				.into_iter()
+				.map(|(start, end, filter)| read::ArrayRange { start, end, filter })
+				.collect(),
+		)
+	}
+
+	#[test]
+	fn parse_array_single_index() {
+		let expected = test_struct([(
+			"a",
+			test_range(2, Some(3), test_struct([("b", read::Filter::All)])),
+		)]);
+
+		let got = test_parse("a[2].b");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_array_range() {
+		let expected = test_struct([("a", test_range(1, Some(4), read::Filter::All))]);
+
+		let got = test_parse("a[1:4]");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_array_range_open_ended() {
+		let got = test_parse("a[3:]");
+		assert_eq!(
+			got,
+			test_struct([("a", test_range(3, None, read::Filter::All))])
+		);
+
+		let got = test_parse("a[:2]");
+		assert_eq!(
+			got,
+			test_struct([("a", test_range(0, Some(2), read::Filter::All))])
+		);
+	}
+
+	#[test]
+	fn parse_array_range_merge() {
+		let got = test_parse("a[1].b,a[1].c");
+		assert_eq!(
+			got,
+			test_struct([(
+				"a",
+				test_range(
+					1,
+					Some(2),
+					test_struct([("b", read::Filter::All), ("c", read::Filter::All)])
+				)
+			)])
+		);
+
+		let got = test_parse("a[1].b,a[2].c");
+		assert_eq!(
+			got,
+			test_struct([(
+				"a",
+				test_ranges([
+					(1, Some(2), test_struct([("b", read::Filter::All)])),
+					(2, Some(3), test_struct([("c", read::Filter::All)])),
+				])
+			)])
+		);
+	}
+
+	#[test]
+	fn parse_array_range_merge_overlapping() {
+		let b = || test_struct([("b", read::Filter::All)]);
+		let got = test_parse("a[].b,a[0].c");
+		assert_eq!(
+			got,
+			test_struct([(
+				"a",
+				test_ranges([
+					(
+						0,
+						Some(1),
+						test_struct([("b", read::Filter::All), ("c", read::Filter::All)])
+					),
+					(1, None, b()),
+				])
+			)])
+		);
+
+		// Selections covering the whole array collapse back to a plain array.
+		let got = test_parse("a[:2].b,a[2:].b");
+		assert_eq!(got, test_struct([("a", test_array(b()))]));
+	}
+
+	fn lenient_parse(input: &str) -> error::Result<read::Filter> {
+		input
+			.parse::<FilterString>()
+			.expect("parse should not fail")
+			.to_filter(excel::Language::English, true)
+	}
+
+	#[test]
+	fn merge_mismatch_strict() {
+		for input in ["a[].b,a.c", "a.c,a[].b", "a[0].b,a.c", "a[].b[].c,a[].b.d"] {
+			let got = input
+				.parse::<FilterString>()
+				.expect("parse should not fail")
+				.to_filter(excel::Language::English, false);
+			assert!(
+				matches!(got, Err(error::Error::Invalid(_))),
+				"{input} should fail, got {got:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn merge_mismatch_lenient() {
+		// The first selection of a mismatched field is kept.
+		for (input, expected) in [
+			("a[].b,a.c", "a[].b"),
+			("a.c,a[].b", "a.c"),
+			("a[0].b,a.c", "a[0].b"),
+			("a[].b[].c,a[].b.d", "a[].b[].c"),
+		] {
+			let got = lenient_parse(input).expect("lenient merge should not fail");
+			assert_eq!(got, test_parse(expected), "{input}");
+		}
+
+		// Other selections are unaffected.
+		let got = lenient_parse("a[].b,a.c,d").expect("lenient merge should not fail");
+		assert_eq!(got, test_parse("a[].b,d"));
+	}
+
+	#[test]
+	fn cache_keyed_by_leniency() {
+		let cache = FilterCache::default();
+		assert!(cache
+			.filter("a[].b,a.c", excel::Language::English, false)
+			.is_err());
+		assert!(cache
+			.filter("a[].b,a.c", excel::Language::English, true)
+			.is_ok());
+	}
+
+	#[test]
+	fn parse_exclusions() {
+		let got = test_parse("!a,!b");
+		assert_eq!(
+			got,
+			read::Filter::AllExcept(HashSet::from(["a".to_string(), "b".to_string()]))
+		);
+	}
+
+	#[test]
+	fn parse_exclusions_with_inclusions() {
+		// Exclusions are removed from the inclusions, including decorated variants.
+		let got = test_parse("a,b.c,b@lang(ja),!b,!d");
+		assert_eq!(got, test_struct([("a", read::Filter::All)]));
+	}
+
+	#[test]
+	fn parse_exclusion_invalid() {
+		assert!("!a@lang(ja)".parse::<FilterString>().is_err());
+		assert!("!a.b".parse::<FilterString>().is_err());
+		assert!("!a[]".parse::<FilterString>().is_err());
+	}
+
+	#[test]
+	fn parse_prefix_glob() {
+		let expected = read::Filter::Struct(HashMap::from([
+			(
+				"Param*".to_string(),
+				StructEntry {
+					field: "Param".into(),
+					language: excel::Language::English,
+					read_as: read::As::Default,
+					prefix: true,
+					depth: None,
+					round: None,
+					filter: read::Filter::All,
+				},
+			),
+			(
+				"Param".to_string(),
+				StructEntry {
+					field: "Param".into(),
+					language: excel::Language::English,
+					read_as: read::As::Default,
+					prefix: false,
+					depth: None,
+					round: None,
+					filter: read::Filter::All,
+				},
+			),
+			(
+				"Name".to_string(),
+				StructEntry {
+					field: "Name".into(),
+					language: excel::Language::English,
+					read_as: read::As::Default,
+					prefix: false,
+					depth: None,
+					round: None,
+					filter: read::Filter::All,
+				},
+			),
+		]));
+
+		let got = test_parse("Param*,Param,Name");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_prefix_glob_decorated() {
+		let Some(entry) = (match test_parse("Param*@lang(ja)") {
+			read::Filter::Struct(mut fields) => fields.remove("Param*@lang(ja)"),
+			_ => None,
+		}) else {
+			panic!("expected decorated prefix entry");
+		};
+
+		assert_eq!(entry.field, "Param");
+		assert!(entry.prefix);
+		assert_eq!(entry.language, excel::Language::Japanese);
+	}
+
+	#[test]
+	fn parse_escaped_asterisk() {
+		let got = test_parse("asterisk\\*example,trailing\\*");
+		assert_eq!(
+			got,
+			test_struct([
+				("asterisk*example", read::Filter::All),
+				("trailing*", read::Filter::All),
+			])
+		);
+
+		// An escaped backslash does not escape the following asterisk.
+		let got = test_parse("backslash\\\\*");
+		let read::Filter::Struct(fields) = got else {
+			panic!("expected struct filter");
+		};
+		assert!(fields["backslash\\*"].prefix);
+	}
+
 	#[test]
 	fn parse_complex_struct_keys() {
 		let expected = test_struct([
@@ -551,4 +1265,33 @@ mod test {
 		);
 		assert_eq!(got, expected);
 	}
+
+	#[test]
+	fn cache_parses_once() {
+		let cache = FilterCache::default();
+		let mut parses = 0;
+
+		for _ in 0..3 {
+			let got = cache
+				.get_or_build("a,b", excel::Language::English, false, || {
+					parses += 1;
+					Ok(test_parse("a,b"))
+				})
+				.expect("cache should not fail");
+			assert_eq!(
+				got,
+				test_struct([("a", read::Filter::All), ("b", read::Filter::All)])
+			);
+		}
+
+		assert_eq!(parses, 1);
+	}
+
+	#[test]
+	fn cache_keyed_by_language() {
+		let cache = FilterCache::default();
+		let english = cache.filter("a", excel::Language::English, false).unwrap();
+		let german = cache.filter("a", excel::Language::German, false).unwrap();
+		assert_ne!(english, german);
+	}
 }
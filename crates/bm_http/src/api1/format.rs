@@ -0,0 +1,114 @@
+use aide::OperationIo;
+use axum::{
+	extract::FromRequestParts,
+	http::{header, request::Parts, HeaderMap, HeaderValue},
+	response::Response,
+	RequestPartsExt,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::{error::Error, extract::Query};
+
+/// Query parameters accepted by endpoints that support alternate output formats.
+#[derive(Deserialize, JsonSchema)]
+struct FormatQueryParams {
+	/// Format of the response body. If omitted, the format is chosen by the
+	/// request's `Accept` header, defaulting to `json`.
+	///
+	/// - `csv` (`text/csv`): A column per value, with nested fields flattened
+	///   into dotted (`a.b`) and indexed (`a[0]`) column names.
+	///
+	/// - `ndjson` (`application/x-ndjson`): One row per line, streamed as rows
+	///   are read. Only supported when listing rows in a sheet. An error while
+	///   reading is reported as a final `{"error": ...}` line.
+	///
+	/// The `envelope` parameter is ignored for formats other than `json`.
+	format: Option<OutputFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+	Json,
+	Csv,
+	Ndjson,
+}
+
+#[derive(OperationIo)]
+#[aide(input_with = "Query<FormatQueryParams>")]
+pub struct FormatQuery(pub OutputFormat);
+
+impl<S> FromRequestParts<S> for FormatQuery
+where
+	S: Send + Sync,
+{
+	type Rejection = Error;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+		let Query(params) = parts.extract::<Query<FormatQueryParams>>().await?;
+
+		let format = params
+			.format
+			.or_else(|| accepted_format(&parts.headers))
+			.unwrap_or(OutputFormat::Json);
+
+		Ok(Self(format))
+	}
+}
+
+/// Mark a response as varying by the `Accept` header, which may select its
+/// format, such that caches do not serve one format in place of another.
+pub fn vary_accept(mut response: Response) -> Response {
+	response
+		.headers_mut()
+		.append(header::VARY, HeaderValue::from_static("accept"));
+	response
+}
+
+/// First non-JSON output format named by the `Accept` header, if any.
+fn accepted_format(headers: &HeaderMap) -> Option<OutputFormat> {
+	headers
+		.get_all(header::ACCEPT)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.filter_map(|media_range| media_range.split(';').next())
+		.find_map(
+			|media_type| match media_type.trim().to_ascii_lowercase().as_str() {
+				"text/csv" => Some(OutputFormat::Csv),
+				"application/x-ndjson" => Some(OutputFormat::Ndjson),
+				_ => None,
+			},
+		)
+}
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	fn accept(value: &'static str) -> Option<OutputFormat> {
+		let mut headers = HeaderMap::new();
+		headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+		accepted_format(&headers)
+	}
+
+	#[test]
+	fn accept_header() {
+		assert_eq!(accepted_format(&HeaderMap::new()), None);
+		assert_eq!(accept("application/json"), None);
+		assert_eq!(
+			accept("application/json, text/csv;q=0.9"),
+			Some(OutputFormat::Csv)
+		);
+		assert_eq!(accept("application/x-ndjson"), Some(OutputFormat::Ndjson));
+	}
+
+	#[test]
+	fn vary_accept_appended() {
+		let response = vary_accept(Response::new(Default::default()));
+		assert_eq!(response.headers()[header::VARY], "accept");
+	}
+}
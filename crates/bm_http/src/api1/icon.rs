@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+// Icons are stored in directories grouping each thousand IDs, unless configured otherwise.
+const DEFAULT_BUCKET_SIZE: u32 = 1000;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IconConfig {
+	/// Overrides for the directory bucketing of specific ranges of icon IDs.
+	#[serde(default)]
+	buckets: Vec<IconBucket>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct IconBucket {
+	/// First icon ID in the range, inclusive.
+	start: u32,
+	/// Last icon ID in the range, inclusive.
+	end: u32,
+	/// Number of icon IDs grouped into each directory within the range.
+	size: u32,
+}
+
+impl IconConfig {
+	/// Game path of the icon with the given ID, without extension.
+	pub fn path(&self, id: u32) -> String {
+		let directory = self.directory(id);
+		format!("ui/icon/{directory:0>6}/{id:0>6}")
+	}
+
+	fn directory(&self, id: u32) -> u32 {
+		let size = self
+			.buckets
+			.iter()
+			.find(|bucket| (bucket.start..=bucket.end).contains(&id))
+			.map_or(DEFAULT_BUCKET_SIZE, |bucket| bucket.size.max(1));
+
+		(id / size) * size
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	#[test]
+	fn standard_bucketing() {
+		let config = IconConfig::default();
+		assert_eq!(config.path(51474), "ui/icon/051000/051474");
+		assert_eq!(config.path(999), "ui/icon/000000/000999");
+	}
+
+	#[test]
+	fn configured_bucketing() {
+		let config = IconConfig {
+			buckets: vec![IconBucket {
+				start: 200000,
+				end: 299999,
+				size: 10000,
+			}],
+		};
+
+		assert_eq!(config.path(215432), "ui/icon/210000/215432");
+		// IDs outside the configured range use the standard bucketing.
+		assert_eq!(config.path(51474), "ui/icon/051000/051474");
+	}
+}
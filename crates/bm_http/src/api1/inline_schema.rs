@@ -0,0 +1,304 @@
+use ironworks_schema as schema;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InlineSchemaConfig {
+	/// Whether requests may provide their own sheet schema definitions.
+	enabled: bool,
+}
+
+impl InlineSchemaConfig {
+	pub fn enabled(&self) -> bool {
+		self.enabled
+	}
+}
+
+/// Schema definition for a single sheet, provided inline with a request. This
+/// mirrors the structure of an `ironworks_schema` sheet, i.e.
+/// `{"order": "Offset", "node": {"Struct": [...]}}`. A sheet name, if present,
+/// is ignored in favour of the requested sheet.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct InlineSheet {
+	/// Order of the sheet's columns that field offsets refer to.
+	#[serde(default)]
+	order: InlineOrder,
+
+	/// Root node of the sheet's schema. This is typically a struct.
+	node: InlineNode,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+enum InlineOrder {
+	Index,
+	#[default]
+	Offset,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+enum InlineNode {
+	/// A fixed-length array of the inner node.
+	Array { count: u32, node: Box<InlineNode> },
+	/// A set of named fields.
+	Struct(Vec<InlineField>),
+	/// A single column.
+	Scalar(InlineScalar),
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct InlineField {
+	name: String,
+	offset: u32,
+	node: InlineNode,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+enum InlineScalar {
+	/// A column read as-is.
+	Default,
+	/// A column containing an icon ID.
+	Icon,
+	/// A column containing a row ID within one of the target sheets.
+	Reference(Vec<InlineReferenceTarget>),
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct InlineReferenceTarget {
+	sheet: String,
+	#[serde(default)]
+	selector: Option<String>,
+	#[serde(default)]
+	condition: Option<InlineReferenceCondition>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct InlineReferenceCondition {
+	selector: String,
+	value: u32,
+}
+
+impl InlineNode {
+	fn to_node(&self) -> schema::Node {
+		match self {
+			Self::Array { count, node } => schema::Node::Array {
+				count: *count,
+				node: Box::new(node.to_node()),
+			},
+			Self::Struct(fields) => schema::Node::Struct(
+				fields
+					.iter()
+					.map(|field| schema::StructField {
+						name: field.name.clone(),
+						offset: field.offset,
+						node: field.node.to_node(),
+					})
+					.collect(),
+			),
+			Self::Scalar(scalar) => schema::Node::Scalar(scalar.to_scalar()),
+		}
+	}
+}
+
+impl InlineScalar {
+	fn to_scalar(&self) -> schema::Scalar {
+		match self {
+			Self::Default => schema::Scalar::Default,
+			Self::Icon => schema::Scalar::Icon,
+			Self::Reference(targets) => schema::Scalar::Reference(
+				targets
+					.iter()
+					.map(|target| schema::ReferenceTarget {
+						sheet: target.sheet.clone(),
+						selector: target.selector.clone(),
+						condition: target.condition.as_ref().map(|condition| {
+							schema::ReferenceCondition {
+								selector: condition.selector.clone(),
+								value: condition.value,
+							}
+						}),
+					})
+					.collect(),
+			),
+		}
+	}
+}
+
+/// Source reported for responses read with an inline schema.
+const INLINE_SOURCE: &str = "inline";
+
+/// Specifier reported for responses read with an inline schema, such that they
+/// are not mistaken for responses of the resolved schema. The resolved schema,
+/// still used for other sheets, is retained as the version, i.e.
+/// `inline@exdschema@<version>`.
+pub fn inline_specifier(fallback: &bm_schema::CanonicalSpecifier) -> bm_schema::CanonicalSpecifier {
+	bm_schema::CanonicalSpecifier {
+		source: INLINE_SOURCE.into(),
+		version: fallback.to_string(),
+	}
+}
+
+/// Schema using an inline definition for a single sheet, deferring to another
+/// schema for all other sheets.
+pub struct InlineSchema {
+	sheet: String,
+	definition: InlineSheet,
+	fallback: Box<dyn schema::Schema + Send>,
+}
+
+impl InlineSchema {
+	pub fn new(
+		sheet: String,
+		definition: InlineSheet,
+		fallback: Box<dyn schema::Schema + Send>,
+	) -> Self {
+		Self {
+			sheet,
+			definition,
+			fallback,
+		}
+	}
+}
+
+impl schema::Schema for InlineSchema {
+	fn sheet(&self, name: &str) -> schema::Result<schema::Sheet> {
+		if name != self.sheet {
+			return self.fallback.sheet(name);
+		}
+
+		Ok(schema::Sheet {
+			name: self.sheet.clone(),
+			order: match self.definition.order {
+				InlineOrder::Index => schema::Order::Index,
+				InlineOrder::Offset => schema::Order::Offset,
+			},
+			node: self.definition.node.to_node(),
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use bm_read::fixture::{scalar, struct_node, Cell, Fixture, TestSheet};
+	use ironworks::{excel, file::exh};
+	use schema::Schema;
+
+	use super::*;
+
+	struct NoSchema;
+
+	impl Schema for NoSchema {
+		fn sheet(&self, name: &str) -> schema::Result<schema::Sheet> {
+			Err(schema::Error::NotFound(schema::ErrorValue::Sheet(
+				name.into(),
+			)))
+		}
+	}
+
+	fn test_schema() -> InlineSchema {
+		let definition = serde_json::from_value::<InlineSheet>(serde_json::json!({
+			"name": "Ignored",
+			"order": "Offset",
+			"node": {"Struct": [
+				{"name": "Name", "offset": 0, "node": {"Scalar": "Default"}},
+				{"name": "Icon", "offset": 1, "node": {"Scalar": "Icon"}},
+				{"name": "Item", "offset": 2, "node": {"Scalar": {"Reference": [
+					{"sheet": "Item", "condition": {"selector": "Kind", "value": 1}},
+				]}}},
+			]},
+		}))
+		.expect("definition should deserialize");
+
+		InlineSchema::new("Item".into(), definition, Box::new(NoSchema))
+	}
+
+	#[test]
+	fn inline_sheet() {
+		let sheet = test_schema().sheet("Item").expect("should not fail");
+
+		assert_eq!(sheet.name, "Item");
+		assert!(matches!(sheet.order, schema::Order::Offset));
+		let schema::Node::Struct(fields) = sheet.node else {
+			panic!("expected struct node");
+		};
+		let offsets = fields
+			.iter()
+			.map(|field| (field.name.as_str(), field.offset))
+			.collect::<Vec<_>>();
+		assert_eq!(offsets, vec![("Name", 0), ("Icon", 1), ("Item", 2)]);
+		assert!(matches!(
+			&fields[2].node,
+			schema::Node::Scalar(schema::Scalar::Reference(targets))
+				if targets[0].sheet == "Item"
+					&& targets[0].condition.as_ref().is_some_and(|condition| condition.value == 1)
+		));
+	}
+
+	#[test]
+	fn other_sheets_fall_back() {
+		let got = test_schema().sheet("Action");
+		assert!(matches!(
+			got,
+			Err(schema::Error::NotFound(schema::ErrorValue::Sheet(name))) if name == "Action"
+		));
+	}
+
+	#[test]
+	fn specifier_marked_inline() {
+		let got = inline_specifier(&bm_schema::CanonicalSpecifier {
+			source: "exdschema".into(),
+			version: "abc".into(),
+		});
+		assert_eq!(got.to_string(), "inline@exdschema@abc");
+	}
+
+	#[test]
+	fn read_row_with_inline_schema() {
+		use exh::ColumnKind as CK;
+		// The configured schema only names the first column.
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Item", [(CK::String, 0), (CK::UInt32, 4)])
+				.row(1, [Cell::String("Potion".into()), Cell::U32(5)]),
+			struct_node([("Name", scalar())]),
+		)]);
+		let Fixture { excel, schema, .. } = fixture;
+
+		let definition = serde_json::from_value::<InlineSheet>(serde_json::json!({
+			"node": {"Struct": [
+				{"name": "Label", "offset": 0, "node": {"Scalar": "Default"}},
+				{"name": "Count", "offset": 1, "node": {"Scalar": "Default"}},
+			]},
+		}))
+		.expect("definition should deserialize");
+		let schema = InlineSchema::new("Item".into(), definition, Box::new(schema));
+
+		let config = serde_json::from_value::<bm_read::Config>(serde_json::json!({
+			"language": {"default": "en", "exclude": []},
+		}))
+		.expect("config should deserialize");
+		let value = bm_read::Read::new(config)
+			.read(
+				&excel,
+				&schema,
+				"Item",
+				1,
+				0,
+				excel::Language::English,
+				&bm_read::Filter::All,
+				0,
+				&bm_read::ReadOptions::default(),
+				&bm_read::ReadCache::default(),
+			)
+			.expect("read should not fail");
+
+		let bm_read::Value::Struct(fields) = value else {
+			panic!("expected struct, got {value:?}");
+		};
+		let mut keys = fields.keys().collect::<Vec<_>>();
+		keys.sort();
+		assert_eq!(keys, vec!["Count", "Label"]);
+		assert!(matches!(
+			fields["Count"],
+			bm_read::Value::Scalar(excel::Field::U32(5))
+		));
+	}
+}
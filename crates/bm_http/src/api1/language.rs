@@ -0,0 +1,21 @@
+use ironworks::excel;
+
+/// Code used by game file paths to identify localized variants of a file, i.e.
+/// `exd/Item_0_en.exd`. Unlocalized files have no code.
+///
+/// Note that this differs from the API's language strings, which use `kr` for
+/// Korean.
+pub fn file_language_code(language: excel::Language) -> Option<&'static str> {
+	use excel::Language as L;
+	let code = match language {
+		L::None => return None,
+		L::Japanese => "ja",
+		L::English => "en",
+		L::German => "de",
+		L::French => "fr",
+		L::ChineseSimplified => "chs",
+		L::ChineseTraditional => "cht",
+		L::Korean => "ko",
+	};
+	Some(code)
+}
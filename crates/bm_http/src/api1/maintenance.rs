@@ -0,0 +1,123 @@
+use axum::{
+	extract::{FromRef, Request, State},
+	middleware::Next,
+	response::{IntoResponse, Response},
+	RequestPartsExt,
+};
+
+use crate::{maintenance::Maintenance, service::Service};
+
+use super::{error::Error, extract::VersionQuery};
+
+#[derive(Clone, FromRef)]
+pub struct MaintenanceState {
+	pub services: Service,
+	pub maintenance: Maintenance,
+}
+
+/// Middleware rejecting requests against versions that have not completed
+/// search ingestion while maintenance mode is enabled.
+pub async fn maintenance_layer(
+	State(state): State<MaintenanceState>,
+	request: Request,
+	next: Next,
+) -> Response {
+	if !state.maintenance.enabled() {
+		return next.run(request).await;
+	}
+
+	let (mut parts, body) = request.into_parts();
+
+	// Requests with an invalid version are left for the handler to report.
+	let ready = match parts.extract_with_state::<VersionQuery, _>(&state).await {
+		Ok(VersionQuery(version)) => state.services.search.version_ready(version),
+		Err(_) => true,
+	};
+
+	guard(
+		&state.maintenance,
+		ready,
+		Request::from_parts(parts, body),
+		next,
+	)
+	.await
+}
+
+/// Reject the request if maintenance mode does not permit operations against
+/// a version with the given readiness.
+async fn guard(
+	maintenance: &Maintenance,
+	version_ready: bool,
+	request: Request,
+	next: Next,
+) -> Response {
+	if !maintenance.permits(version_ready) {
+		return Error::Maintenance(maintenance.retry_after()).into_response();
+	}
+
+	next.run(request).await
+}
+
+#[cfg(test)]
+mod test {
+	use axum::{
+		body::Body,
+		http::{header, StatusCode},
+		middleware,
+		routing::get,
+		Router,
+	};
+	use pretty_assertions::assert_eq;
+	use tower::ServiceExt;
+
+	use crate::maintenance::Config;
+
+	use super::*;
+
+	/// Router with a guarded route against a version of the given readiness,
+	/// alongside an unguarded route.
+	fn router(maintenance: Maintenance, version_ready: bool) -> Router {
+		let guarded = Router::new()
+			.route("/guarded", get(|| async { "ok" }))
+			.layer(middleware::from_fn(move |request: Request, next: Next| {
+				let maintenance = maintenance.clone();
+				async move { guard(&maintenance, version_ready, request, next).await }
+			}));
+
+		Router::new()
+			.route("/open", get(|| async { "ok" }))
+			.merge(guarded)
+	}
+
+	async fn request(router: Router, path: &str) -> Response {
+		router
+			.oneshot(Request::get(path).body(Body::empty()).unwrap())
+			.await
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn guarded_routes_unavailable() {
+		let maintenance = Maintenance::new(Config::default());
+		maintenance.set_enabled(true);
+
+		let response = request(router(maintenance.clone(), false), "/guarded").await;
+		assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+		assert_eq!(response.headers()[header::RETRY_AFTER], "300");
+
+		let response = request(router(maintenance.clone(), false), "/open").await;
+		assert_eq!(response.status(), StatusCode::OK);
+
+		let response = request(router(maintenance, true), "/guarded").await;
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	#[tokio::test]
+	async fn disabled_permits_all() {
+		let maintenance = Maintenance::new(Config::default());
+
+		let response = request(router(maintenance, false), "/guarded").await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert!(response.headers().get(header::RETRY_AFTER).is_none());
+	}
+}
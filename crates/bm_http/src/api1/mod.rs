@@ -1,14 +1,26 @@
 mod api;
 mod asset;
+mod csv;
+mod envelope;
 mod error;
 mod extract;
 mod filter;
+mod format;
+mod icon;
+mod inline_schema;
 mod jsonschema;
+mod language;
+mod maintenance;
+mod ndjson;
 mod query;
 mod read;
+mod schema;
 mod search;
 mod sheet;
+mod stream;
 mod string;
+mod timeout;
+mod timing;
 mod value;
 mod version;
 
@@ -0,0 +1,110 @@
+use std::io::{self, Write};
+
+use axum::{
+	body::Body,
+	http::header,
+	response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use super::{
+	error::{Error, ErrorResponse, Result},
+	read::RowResult,
+};
+
+/// Final line written in place of a row that could not be read.
+#[derive(Serialize)]
+struct ErrorLine {
+	error: ErrorResponse,
+}
+
+/// Build a newline-delimited JSON response from a streamed body.
+pub fn response(body: Body) -> Response {
+	([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response()
+}
+
+/// Write rows as newline-delimited JSON, one row per line. A row that fails to
+/// read ends the output with an error line, rather than aborting the stream.
+pub fn write_rows(
+	writer: &mut dyn Write,
+	rows: impl IntoIterator<Item = Result<RowResult>>,
+) -> io::Result<()> {
+	for row in rows {
+		match row {
+			Ok(row) => serde_json::to_writer(&mut *writer, &row)?,
+			Err(error) => {
+				if let Error::Other(ref inner) = error {
+					tracing::error!("{inner:?}");
+				}
+				let line = ErrorLine {
+					error: ErrorResponse::from(error),
+				};
+				serde_json::to_writer(&mut *writer, &line)?;
+				writer.write_all(b"\n")?;
+				return Ok(());
+			}
+		}
+		writer.write_all(b"\n")?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use bm_read as read;
+	use ironworks::{excel, sestring::format::Input};
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+	use crate::api1::value::{FieldCase, ValueString};
+
+	fn test_row(row_id: u32) -> RowResult {
+		RowResult {
+			row_id,
+			subrow_id: None,
+			fields: ValueString(
+				read::Value::Struct(HashMap::new()),
+				excel::Language::English,
+				Input::new().into(),
+				Default::default(),
+				FieldCase::Original,
+			),
+			transient: None,
+		}
+	}
+
+	#[test]
+	fn rows_per_line() {
+		let mut output = vec![];
+		write_rows(&mut output, [Ok(test_row(1)), Ok(test_row(2))]).unwrap();
+		assert_eq!(
+			String::from_utf8(output).unwrap(),
+			"{\"row_id\":1,\"fields\":{}}\n{\"row_id\":2,\"fields\":{}}\n"
+		);
+	}
+
+	#[test]
+	fn error_ends_output() {
+		let mut output = vec![];
+		write_rows(
+			&mut output,
+			[
+				Ok(test_row(1)),
+				Err(Error::NotFound("row 2".into())),
+				Ok(test_row(3)),
+			],
+		)
+		.unwrap();
+
+		let output = String::from_utf8(output).unwrap();
+		let lines = output.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 2);
+		assert_eq!(
+			lines[1],
+			r#"{"error":{"code":404,"message":"not found: row 2"}}"#
+		);
+	}
+}
@@ -4,10 +4,12 @@ use bm_search::query;
 use ironworks::excel;
 use nom::{
 	branch::alt,
-	bytes::complete::{escaped_transform, is_not, tag},
-	character::complete::{alphanumeric1, char, digit1, multispace1, one_of},
-	combinator::{all_consuming, cut, map, map_res, not, opt, success, value as nom_value},
-	multi::separated_list1,
+	bytes::complete::{escaped_transform, is_not, tag, take_while1},
+	character::complete::{alphanumeric1, anychar, char, digit1, multispace1, one_of},
+	combinator::{
+		all_consuming, cut, map, map_res, not, opt, recognize, success, value as nom_value,
+	},
+	multi::{many0, separated_list1},
 	number::complete::double,
 	sequence::{delimited, preceded, terminated},
 	Finish, IResult, Parser,
@@ -29,10 +31,26 @@ use super::error;
 /// inside structs and relations (i.e. `Foo.Bar=1)`, as well as language tags to
 /// target fields in particular languages (i.e. `Foo@ja=1`).
 ///
+/// The specifier may be omitted (i.e. `~"sprint"`), in which case the clause
+/// will match against every string field of the sheet, or of the current
+/// struct when used within a relation (i.e. `Foo.~"sprint"`). Only string
+/// operations may be used without a specifier.
+///
 /// Arrays must be selected explicitly (i.e. `Foo[]=1`), resulting in a match
 /// for any value within the array. An index can be used to reduce the search
 /// space (i.e. `Foo[1]=1`).
 ///
+/// Columns not covered by the schema may be targeted by their offset with
+/// `@col(offset)`, i.e. `@col(37)=5`. Packed boolean columns additionally
+/// require their bit position, i.e. `@col(12_2)=true`. These match the names of
+/// `unknown` fields in row data.
+///
+/// Rows may be filtered by the rows of other sheets that reference them with
+/// `@ref(Sheet.Field)`, followed by a relation query against the referencing
+/// sheet. `@ref(Recipe.ItemResult).CraftType=1` will match rows referenced by
+/// the `ItemResult` of any `Recipe` with a `CraftType` of 1. Each result is
+/// returned once, regardless of the number of rows referencing it.
+///
 /// By default, results will match at least one clause, with higher relevance
 /// scores for those that match more. To modify this behavior, clauses can
 /// decorated. `+clause` specifies that the clause _must_ be matched for any
@@ -46,8 +64,34 @@ use super::error;
 ///
 ///   - partial string match: `key~"value"`
 ///
+///   - case-sensitive partial string match: `key~~"value"`
+///
+///   - prefix string match: `key~^"value"`
+///
+///   - suffix string match: `key~$"value"`
+///
+///   - negated partial string match: `key!~"value"`
+///
+///   - fuzzy string match: `key~%"value"`. Matches values within a small
+///     number of character edits of the entire string, ignoring case, with
+///     closer matches scoring higher. Like regular expressions, fuzzy matches
+///     cannot make use of any indexing, and require every row of the searched
+///     sheets to be scanned. Combine them with `+` clauses that narrow the
+///     results where possible, i.e. `+Level=50 Name~%"sprint"`.
+///
 ///   - exact equality: `key=value`
 ///
+///   - regular expression match: `key=/pattern/`. Forward slashes within the
+///     pattern must be escaped as `\/`. Regular expressions cannot make use of
+///     any indexing, and require every row of the searched sheets to be
+///     scanned. Prefer other operations where possible.
+///
+///   - equality with any of a set of values: `key=(value|value|value)`. Values
+///     in a set must all be of the same type.
+///
+///   - inequality: `key!=value`. When targeting every element of an array (i.e.
+///     `Foo[]!=1`), no element may be equal to the value.
+///
 ///   - numeric comparison: `key>=value`, `key>value`, `key<=value`, `key<value`
 ///
 /// Supported value types:
@@ -57,7 +101,7 @@ use super::error;
 ///   - number: `1`, `-1`, `1.0`
 ///
 ///   - boolean: `true`, `false`
-#[derive(Debug, JsonSchema)]
+#[derive(Debug, Clone, JsonSchema)]
 pub struct QueryString(#[schemars(with = "String")] query::Node);
 
 impl From<QueryString> for query::Node {
@@ -90,8 +134,82 @@ impl FromStr for QueryString {
 	}
 }
 
+/// Ordering to apply to search results.
+///
+/// Takes the form `[key][:direction]`. The key may be `score`, sorting by
+/// relevance to the query, or a field specifier as used in query clauses, i.e.
+/// `Name`, `Name@ja`, or `@col(12)`. Direction may be `asc` or `desc`, and
+/// defaults to `desc` for `score`, and `asc` for fields.
+///
+/// Results that are tied on the sort key are ordered by score, and then by row
+/// ID. Sheets searched that do not contain the sort field are sorted as if the
+/// field was empty.
+#[derive(Debug, Clone, JsonSchema)]
+pub struct SortString(#[schemars(with = "String")] query::Sort);
+
+impl From<SortString> for query::Sort {
+	fn from(value: SortString) -> Self {
+		value.0
+	}
+}
+
+impl<'de> Deserialize<'de> for SortString {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let raw = String::deserialize(deserializer)?;
+		raw.parse().map_err(de::Error::custom)
+	}
+}
+
+impl FromStr for SortString {
+	type Err = error::Error;
+
+	fn from_str(input: &str) -> Result<Self, Self::Err> {
+		let (_rest, sort) = all_consuming(sort)
+			.parse(input)
+			.finish()
+			.map_err(|error| error::Error::Invalid(error.to_string()))?;
+
+		Ok(Self(sort))
+	}
+}
+
 type ParseResult<'a, T> = IResult<&'a str, T>;
 
+fn sort(input: &str) -> ParseResult<query::Sort> {
+	map(
+		(
+			alt((
+				nom_value(
+					query::SortKey::Score,
+					terminated(tag("score"), not(alphanumeric1)),
+				),
+				map(
+					alt((column_specifier, struct_specifier)),
+					query::SortKey::Field,
+				),
+			)),
+			opt(preceded(
+				char(':'),
+				cut(alt((
+					nom_value(query::SortDirection::Ascending, tag("asc")),
+					nom_value(query::SortDirection::Descending, tag("desc")),
+				))),
+			)),
+		),
+		|(key, direction)| {
+			let direction = direction.unwrap_or(match key {
+				query::SortKey::Score => query::SortDirection::Descending,
+				query::SortKey::Field(_) => query::SortDirection::Ascending,
+			});
+			query::Sort { key, direction }
+		},
+	)
+	.parse(input)
+}
+
 fn node(input: &str) -> ParseResult<query::Node> {
 	alt((
 		map(delimited(char('('), group, char(')')), query::Node::Group),
@@ -117,23 +235,34 @@ fn occur(input: &str) -> ParseResult<query::Occur> {
 }
 
 fn leaf(input: &str) -> ParseResult<query::Leaf> {
-	map(
-		(struct_specifier, opt(array_specifier), operation),
-		|(struct_field, maybe_array_field, operation)| {
-			let operation = match maybe_array_field {
-				None => operation,
-				Some(array_field) => operation_relation(query::Node::Leaf(query::Leaf {
-					field: Some(array_field),
-					operation: operation,
-				})),
-			};
-
-			query::Leaf {
-				field: Some(struct_field),
+	alt((
+		map(
+			(
+				alt((column_specifier, reverse_specifier, struct_specifier)),
+				opt(array_specifier),
 				operation,
-			}
-		},
-	)
+			),
+			|(struct_field, maybe_array_field, operation)| {
+				let operation = match maybe_array_field {
+					None => operation,
+					Some(array_field) => operation_relation(query::Node::Leaf(query::Leaf {
+						field: Some(array_field),
+						operation: operation,
+					})),
+				};
+
+				query::Leaf {
+					field: Some(struct_field),
+					operation,
+				}
+			},
+		),
+		// Clauses without a specifier are unbound, targeting any field.
+		map(operation, |operation| query::Leaf {
+			field: None,
+			operation,
+		}),
+	))
 	.parse(input)
 }
 
@@ -148,6 +277,47 @@ fn struct_specifier(input: &str) -> ParseResult<query::FieldSpecifier> {
 	.parse(input)
 }
 
+fn column_specifier(input: &str) -> ParseResult<query::FieldSpecifier> {
+	map(
+		preceded(
+			tag("@col("),
+			cut(terminated(
+				(
+					map_res(digit1, str::parse),
+					opt(preceded(char('_'), map_res(digit1, str::parse))),
+				),
+				char(')'),
+			)),
+		),
+		|(offset, bit)| query::FieldSpecifier::Column(offset, bit),
+	)
+	.parse(input)
+}
+
+fn reverse_specifier(input: &str) -> ParseResult<query::FieldSpecifier> {
+	map(
+		preceded(
+			tag("@ref("),
+			cut(terminated(
+				(
+					take_while1(|char: char| {
+						char.is_ascii_alphanumeric() || char == '/' || char == '_'
+					}),
+					preceded(
+						char('.'),
+						take_while1(|char: char| char.is_ascii_alphanumeric() || char == '_'),
+					),
+				),
+				char(')'),
+			)),
+		),
+		|(sheet, field): (&str, &str)| {
+			query::FieldSpecifier::ReverseRelation(sheet.into(), field.into())
+		},
+	)
+	.parse(input)
+}
+
 // TODO: this is duplicated with filter - share?
 fn language(input: &str) -> ParseResult<excel::Language> {
 	map_res(alphanumeric1, |str: &str| {
@@ -168,8 +338,21 @@ fn array_specifier(input: &str) -> ParseResult<query::FieldSpecifier> {
 fn operation(input: &str) -> ParseResult<query::Operation> {
 	alt((
 		preceded(char('.'), cut(map(node, operation_relation))),
+		preceded(tag("!~"), cut(map(string, query::Operation::NotMatch))),
+		preceded(tag("!="), cut(map(value, query::Operation::Ne))),
+		preceded(tag("~~"), cut(map(string, query::Operation::MatchCase))),
+		preceded(tag("~^"), cut(map(string, query::Operation::StartsWith))),
+		preceded(tag("~$"), cut(map(string, query::Operation::EndsWith))),
+		preceded(tag("~%"), cut(map(string, query::Operation::Fuzzy))),
 		preceded(char('~'), cut(map(string, query::Operation::Match))),
-		preceded(char('='), cut(map(value, query::Operation::Eq))),
+		preceded(
+			char('='),
+			cut(alt((
+				map(value_set, query::Operation::In),
+				map(regex, query::Operation::Regex),
+				map(value, query::Operation::Eq),
+			))),
+		),
 		preceded(tag(">="), cut(map(number, query::Operation::Gte))),
 		preceded(char('>'), cut(map(number, query::Operation::Gt))),
 		preceded(tag("<="), cut(map(number, query::Operation::Lte))),
@@ -187,6 +370,26 @@ fn value(input: &str) -> ParseResult<query::Value> {
 	.parse(input)
 }
 
+fn regex(input: &str) -> ParseResult<String> {
+	delimited(
+		char('/'),
+		map(
+			recognize(many0(alt((
+				is_not("\\/"),
+				recognize(preceded(char('\\'), anychar)),
+			)))),
+			// Escaped slashes are only meaningful to the query syntax.
+			|pattern: &str| pattern.replace("\\/", "/"),
+		),
+		char('/'),
+	)
+	.parse(input)
+}
+
+fn value_set(input: &str) -> ParseResult<Vec<query::Value>> {
+	delimited(char('('), cut(separated_list1(char('|'), value)), char(')')).parse(input)
+}
+
 fn boolean(input: &str) -> ParseResult<bool> {
 	alt((nom_value(true, tag("true")), nom_value(false, tag("false")))).parse(input)
 }
@@ -353,6 +556,126 @@ mod test {
 		assert_eq!(got, expected);
 	}
 
+	#[test]
+	fn parse_unbound() {
+		fn unbound(operation: query::Operation) -> query::Node {
+			query::Node::Leaf(query::Leaf {
+				field: None,
+				operation,
+			})
+		}
+
+		let expected = group(vec![
+			(
+				query::Occur::Should,
+				unbound(query::Operation::Match("sprint".into())),
+			),
+			(
+				query::Occur::Must,
+				unbound(query::Operation::Eq(query::Value::String("Sprint".into()))),
+			),
+		]);
+		let got = test_parse(r#"~"sprint" +="Sprint""#);
+		assert_eq!(got, expected);
+
+		let expected = group(vec![(
+			query::Occur::Should,
+			leaf(
+				field_struct("A"),
+				operation_relation(unbound(query::Operation::Match("sprint".into()))),
+			),
+		)]);
+		let got = test_parse(r#"A.~"sprint""#);
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_column() {
+		let expected = group(vec![(
+			query::Occur::Should,
+			leaf(
+				query::FieldSpecifier::Column(37, None),
+				query::Operation::Eq(u64(5)),
+			),
+		)]);
+
+		let got = test_parse("@col(37)=5");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_column_packed_bool() {
+		let expected = group(vec![(
+			query::Occur::Should,
+			leaf(
+				query::FieldSpecifier::Column(12, Some(2)),
+				query::Operation::Eq(query::Value::Boolean(true)),
+			),
+		)]);
+
+		let got = test_parse("@col(12_2)=true");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_boolean() {
+		let expected = group(vec![(
+			query::Occur::Should,
+			leaf(
+				field_struct("Flag"),
+				query::Operation::Eq(query::Value::Boolean(true)),
+			),
+		)]);
+
+		let got = test_parse("Flag=true");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_reverse_relation() {
+		let expected = group(vec![(
+			query::Occur::Should,
+			leaf(
+				query::FieldSpecifier::ReverseRelation("Recipe".into(), "ItemResult".into()),
+				operation_relation(leaf(
+					field_struct("CraftType"),
+					query::Operation::Eq(u64(1)),
+				)),
+			),
+		)]);
+
+		let got = test_parse("@ref(Recipe.ItemResult).CraftType=1");
+		assert_eq!(got, expected);
+
+		assert!("@ref(Recipe)=1".parse::<QueryString>().is_err());
+	}
+
+	#[test]
+	fn parse_reverse_relation_underscores() {
+		let expected = group(vec![(
+			query::Occur::Should,
+			leaf(
+				query::FieldSpecifier::ReverseRelation(
+					"custom/Recipe_Log".into(),
+					"Item_Result".into(),
+				),
+				operation_relation(leaf(
+					field_struct("CraftType"),
+					query::Operation::Eq(u64(1)),
+				)),
+			),
+		)]);
+
+		let got = test_parse("@ref(custom/Recipe_Log.Item_Result).CraftType=1");
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn parse_column_invalid() {
+		assert!("@col(abc)=5".parse::<QueryString>().is_err());
+		assert!("@col(70000)=5".parse::<QueryString>().is_err());
+	}
+
 	#[test]
 	fn parse_multiple() {
 		let expected = group(vec![
@@ -428,8 +751,58 @@ mod test {
 			harness(query::Operation::Match("hello".into()))
 		);
 
+		assert_eq!(
+			test_parse("A~~\"hello\""),
+			harness(query::Operation::MatchCase("hello".into()))
+		);
+
+		assert_eq!(
+			test_parse("A~^\"hello\""),
+			harness(query::Operation::StartsWith("hello".into()))
+		);
+
+		assert_eq!(
+			test_parse("A~$\"hello\""),
+			harness(query::Operation::EndsWith("hello".into()))
+		);
+
+		assert_eq!(
+			test_parse("A~%\"hello\""),
+			harness(query::Operation::Fuzzy("hello".into()))
+		);
+
+		assert_eq!(
+			test_parse("A!~\"hello\""),
+			harness(query::Operation::NotMatch("hello".into()))
+		);
+
 		assert_eq!(test_parse("A=1"), harness(query::Operation::Eq(u64(1))));
 
+		assert_eq!(test_parse("A!=1"), harness(query::Operation::Ne(u64(1))));
+
+		assert_eq!(
+			test_parse(r"A=/^\d+ (a|b)\/c$/"),
+			harness(query::Operation::Regex(r"^\d+ (a|b)/c$".into()))
+		);
+
+		assert_eq!(
+			test_parse("A=(1|2|3)"),
+			harness(query::Operation::In(vec![u64(1), u64(2), u64(3)]))
+		);
+
+		assert_eq!(
+			test_parse("A=(\"a\"|\"b\")"),
+			harness(query::Operation::In(vec![
+				query::Value::String("a".into()),
+				query::Value::String("b".into()),
+			]))
+		);
+
+		assert_eq!(
+			test_parse("A!=\"hello\""),
+			harness(query::Operation::Ne(query::Value::String("hello".into())))
+		);
+
 		assert_eq!(
 			test_parse("A>=1"),
 			harness(query::Operation::Gte(query::Number::U64(1)))
@@ -503,4 +876,54 @@ mod test {
 		assert_eq!(test_parse(r#"A~"he\"llo""#), harness(r#"he"llo"#));
 		assert_eq!(test_parse(r#"A~"he\\llo""#), harness(r#"he\llo"#));
 	}
+
+	#[test]
+	fn parse_sort() {
+		fn test_sort(input: &str) -> query::Sort {
+			input
+				.parse::<SortString>()
+				.expect("sort should parse")
+				.into()
+		}
+
+		assert_eq!(
+			test_sort("score"),
+			query::Sort {
+				key: query::SortKey::Score,
+				direction: query::SortDirection::Descending,
+			}
+		);
+		assert_eq!(
+			test_sort("score:asc"),
+			query::Sort {
+				key: query::SortKey::Score,
+				direction: query::SortDirection::Ascending,
+			}
+		);
+		assert_eq!(
+			test_sort("scoreboard"),
+			query::Sort {
+				key: query::SortKey::Field(field_struct("scoreboard")),
+				direction: query::SortDirection::Ascending,
+			}
+		);
+		assert_eq!(
+			test_sort("Name@ja:desc"),
+			query::Sort {
+				key: query::SortKey::Field(query::FieldSpecifier::Struct(
+					"Name".into(),
+					Some(excel::Language::Japanese)
+				)),
+				direction: query::SortDirection::Descending,
+			}
+		);
+		assert_eq!(
+			test_sort("@col(12)"),
+			query::Sort {
+				key: query::SortKey::Field(query::FieldSpecifier::Column(12, None)),
+				direction: query::SortDirection::Ascending,
+			}
+		);
+		assert!("Name:sideways".parse::<SortString>().is_err());
+	}
 }
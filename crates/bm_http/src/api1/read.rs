@@ -1,10 +1,13 @@
 use std::{
-	collections::HashMap,
-	sync::{Arc, RwLock},
+	borrow::Cow,
+	collections::{BTreeMap, HashMap},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, RwLock,
+	},
 };
 
 use aide::OperationIo;
-use anyhow::anyhow;
 use axum::{
 	extract::{FromRef, FromRequestParts},
 	http::request::Parts,
@@ -12,7 +15,7 @@ use axum::{
 };
 use bm_read as read;
 use bm_version::VersionKey;
-use ironworks::{excel, file::exh, sestring::format::Input};
+use ironworks::{excel, file::exh, sestring::format::Input, Ironworks};
 use schemars::{
 	gen::SchemaGenerator,
 	schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation},
@@ -25,21 +28,98 @@ use crate::service;
 use super::{
 	error::{Error, Result},
 	extract::{Query, VersionQuery},
-	filter::FilterString,
+	filter::{FilterCache, FilterString},
+	icon::IconConfig,
+	inline_schema::{inline_specifier, InlineSchema, InlineSheet},
 	jsonschema::impl_jsonschema,
 	string::build_input,
-	value::ValueString,
+	timeout::Cancellation,
+	timing::Timings,
+	value::{FieldCase, ValueString},
 };
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct RowReaderConfig {
 	fields: HashMap<String, FilterString>,
 	transient: HashMap<String, FilterString>,
+
+	/// Report `subrow_id` for rows in sheets without subrows by default.
+	#[serde(default)]
+	always_subrow: bool,
+}
+
+impl RowReaderConfig {
+	/// Default field filter for a schema source. Sources without configured
+	/// defaults, such as the empty schema, read every field. Configured filters
+	/// are always built strictly.
+	fn default_fields(&self, source: &str, language: excel::Language) -> Result<read::Filter> {
+		match self.fields.get(source) {
+			Some(fields) => fields.clone().to_filter(language, false),
+			None => Ok(read::Filter::All),
+		}
+	}
+
+	/// Default transient filter for a schema source. Sources without configured
+	/// defaults read no transient fields.
+	fn default_transient(
+		&self,
+		source: &str,
+		language: excel::Language,
+	) -> Result<Option<read::Filter>> {
+		match self.transient.get(source) {
+			Some(transient) if !transient.is_empty() => {
+				Ok(Some(transient.clone().to_filter(language, false)?))
+			}
+			_ => Ok(None),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepthConfig {
+	/// Maximum depth that may be requested.
+	max: u8,
+
+	/// Behavior when a request exceeds the maximum depth.
+	#[serde(default)]
+	exceeded: DepthExceeded,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DepthExceeded {
+	#[default]
+	Clamp,
+	Error,
+}
+
+impl DepthConfig {
+	fn resolve(&self, requested: Option<u8>, default: u8) -> Result<u8> {
+		let Some(requested) = requested else {
+			return Ok(default.min(self.max));
+		};
+
+		if requested <= self.max {
+			return Ok(requested);
+		}
+
+		match self.exceeded {
+			DepthExceeded::Clamp => {
+				tracing::warn!(requested, max = self.max, "clamping requested depth");
+				Ok(self.max)
+			}
+			DepthExceeded::Error => Err(Error::Invalid(format!(
+				"requested depth {requested} exceeds maximum of {}",
+				self.max
+			))),
+		}
+	}
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct RowReaderState {
 	string_input: Arc<RwLock<HashMap<VersionKey, Arc<Input>>>>,
+	filters: FilterCache,
 }
 
 impl RowReaderState {
@@ -69,15 +149,72 @@ struct RowReaderQuery {
 	schema: Option<SchemaSpecifier>,
 
 	/// Data fields to read for selected rows.
-	fields: Option<FilterString>,
+	#[schemars(with = "Option<FilterString>")]
+	fields: Option<String>,
 
 	/// Data fields to read for selected rows' transient row, if any is present.
-	transient: Option<FilterString>,
+	#[schemars(with = "Option<FilterString>")]
+	transient: Option<String>,
+
+	/// Maximum depth of relations to follow when reading rows. Defaults to a
+	/// value set by the endpoint, and is limited by the server configuration.
+	depth: Option<u8>,
+
+	/// Include fields requested by the `fields` or `transient` filters that
+	/// could not be read as `null`, rather than omitting them.
+	#[serde(default)]
+	explicit_nulls: bool,
+
+	/// Include `subrow_id` in results for rows in sheets without subrows, as
+	/// `0`. Defaults to the server configuration.
+	always_subrow: Option<bool>,
+
+	/// Read followed references with only the named field (typically `Name`),
+	/// rather than the full target row. Ignored for references narrowed by the
+	/// `fields` or `transient` filters.
+	compact_refs: Option<String>,
+
+	/// Maximum number of references to expand within each row read. Further
+	/// references are left as bare IDs. Limited by the server configuration.
+	max_refs: Option<usize>,
+
+	/// Read references targeting sheets with subrows as an array of every
+	/// subrow of the target row, rather than only subrow `0`.
+	#[serde(default)]
+	all_subrow_refs: bool,
+
+	/// Include fields for columns not described by the schema, as `unknownN`.
+	/// Defaults to `true`. When disabled, such fields are omitted even if
+	/// requested by the `fields` or `transient` filters.
+	unknowns: Option<bool>,
+
+	/// Casing to apply to field names. Names that collide after transformation
+	/// are suffixed with a counter (i.e. `name_2`). Defaults to the names as
+	/// defined by the schema.
+	case: Option<FieldCase>,
+
+	/// Name of a patch within the requested version to read data as of. The
+	/// repository containing the patch is read without any later patches applied.
+	/// Search requests do not support this parameter.
+	patch: Option<String>,
+
+	/// Read every column of the row as its native value, keyed as `unknownN`
+	/// fields, without applying any schema or following references. The
+	/// `schema`, `fields`, and `transient` parameters are ignored.
+	#[serde(default)]
+	raw_sheet: bool,
+
+	/// Hoist the fields of followed references into the containing row as
+	/// dotted keys (i.e. `ClassJob.Name`), rather than nesting them. Arrays of
+	/// references use indexed keys (i.e. `Item[0].Name`). Existing keys take
+	/// precedence over hoisted keys that collide with them.
+	#[serde(default)]
+	flatten: bool,
 }
 
 #[derive(Deserialize)]
 #[repr(transparent)]
-struct SchemaLanguage(read::LanguageString);
+pub struct SchemaLanguage(pub read::LanguageString);
 
 impl_jsonschema!(SchemaLanguage, languagestring_schema);
 fn languagestring_schema(_generator: &mut SchemaGenerator) -> Schema {
@@ -113,7 +250,7 @@ fn languagestring_schema(_generator: &mut SchemaGenerator) -> Schema {
 
 #[derive(Deserialize)]
 #[repr(transparent)]
-struct SchemaSpecifier(bm_schema::Specifier);
+pub struct SchemaSpecifier(pub bm_schema::Specifier);
 
 impl_jsonschema!(SchemaSpecifier, specifier_jsonschema);
 fn specifier_jsonschema(_generator: &mut SchemaGenerator) -> Schema {
@@ -161,6 +298,8 @@ impl RowResult {
 				)])),
 				excel::Language::English,
 				Input::new().into(),
+				Default::default(),
+				FieldCase::Original,
 			),
 			// TODO: should this have an example?
 			transient: None,
@@ -168,17 +307,78 @@ impl RowResult {
 	}
 }
 
+/// Number of rows that may be read by a single reader. Readers reused across a
+/// long-running response may opt into a larger budget, or reset the budget
+/// explicitly between batches of rows.
+#[derive(Debug, Default)]
+pub struct RowBudget {
+	limit: Option<usize>,
+	read: AtomicUsize,
+}
+
+impl RowBudget {
+	pub fn new(limit: Option<usize>) -> Self {
+		Self {
+			limit,
+			read: AtomicUsize::new(0),
+		}
+	}
+
+	/// Record a row read, failing if doing so exceeds the budget.
+	pub fn track(&self) -> Result<()> {
+		let read = self.read.fetch_add(1, Ordering::Relaxed) + 1;
+		match self.limit {
+			Some(limit) if read > limit => Err(Error::Invalid(format!(
+				"request exceeded the limit of {limit} rows read"
+			))),
+			_ => Ok(()),
+		}
+	}
+
+	/// Read a row, recording it against the budget. Rows are only counted once
+	/// they have been read, such that missing rows do not count.
+	pub fn track_with<T>(&self, read: impl FnOnce() -> Result<T>) -> Result<T> {
+		let row = read()?;
+		self.track()?;
+		Ok(row)
+	}
+
+	/// Make the full budget available again.
+	pub fn reset(&self) {
+		self.read.store(0, Ordering::Relaxed);
+	}
+}
+
 #[derive(OperationIo)]
 #[aide(input_with = "Query<RowReaderQuery>")]
 pub struct RowReader {
 	read: service::Read,
+	rows: RowBudget,
+	cancellation: Cancellation,
+	data: service::Data,
+	schema_provider: service::Schema,
+	schema_request: Option<bm_schema::Specifier>,
+	state: RowReaderState,
+	pub version: VersionKey,
+	patch: Option<String>,
 	pub excel: Arc<excel::Excel>,
+	pub ironworks: Arc<Ironworks>,
 	pub schema_specifier: bm_schema::CanonicalSpecifier,
 	schema: Box<dyn ironworks_schema::Schema + Send>,
 	pub language: excel::Language,
 	fields: read::Filter,
 	transient: Option<read::Filter>,
 	string_input: Arc<Input>,
+	icons: Arc<IconConfig>,
+	timings: Timings,
+	depth: Option<u8>,
+	depth_config: DepthConfig,
+	options: read::ReadOptions,
+	cache: read::ReadCache,
+	always_subrow: bool,
+	case: FieldCase,
+	raw_sheet: bool,
+	flatten: bool,
 }
 
 // todo maybe an extra bit of state requirements on this for the filters? that would allow the filters to be wired up per-handler i think. not sure how that aligns with existing state though
@@ -188,10 +388,15 @@ where
 	service::Service: FromRef<S>,
 	RowReaderConfig: FromRef<S>,
 	RowReaderState: FromRef<S>,
+	DepthConfig: FromRef<S>,
+	IconConfig: FromRef<S>,
 {
 	type Rejection = Error;
 
 	async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+		let timings = Timings::from_parts(parts);
+		let cancellation = Cancellation::from_parts(parts);
+
 		let VersionQuery(version_key) = parts.extract_with_state::<VersionQuery, _>(state).await?;
 		let Query(query) = parts.extract::<Query<RowReaderQuery>>().await?;
 
@@ -202,54 +407,213 @@ where
 			..
 		} = service::Service::from_ref(state);
 		let config = RowReaderConfig::from_ref(state);
+		let depth_config = DepthConfig::from_ref(state);
+		let icons = Arc::new(IconConfig::from_ref(state));
 		let state = RowReaderState::from_ref(state);
 
-		let excel = data.version(version_key)?.excel();
+		let version = match &query.patch {
+			Some(patch) => data.version_at_patch(version_key, patch)?,
+			None => data.version(version_key)?,
+		};
+		let excel = version.excel();
+		let ironworks = version.ironworks();
+
+		let schema_request = query.schema.map(|wrap| wrap.0);
 
 		// TODO: should this be a bit like versionquery for the schema shit?
-		let schema_specifier =
-			schema_provider.canonicalize(query.schema.map(|wrap| wrap.0), version_key)?;
+		let (schema_specifier, schema) = timings.time("schema", || {
+			resolve_schema(
+				&schema_provider,
+				schema_request.clone(),
+				query.raw_sheet,
+				version_key,
+			)
+		})?;
 
 		let language = query
 			.language
 			.map(|wrap| excel::Language::from(wrap.0))
 			.unwrap_or_else(|| read.default_language());
 
+		// NOTE: String input is cached per version, and shared by patch-level reads.
 		let string_input = state.input(version_key, &excel)?;
 
-		let fields = query
-			.fields
-			.or_else(|| config.fields.get(&schema_specifier.source).cloned())
-			.ok_or_else(|| anyhow!("missing default fields for {}", schema_specifier.source))?
-			.to_filter(language)?;
-
-		let transient_string = query
-			.transient
-			.or_else(|| config.transient.get(&schema_specifier.source).cloned())
-			.ok_or_else(|| anyhow!("missing default transient for {}", schema_specifier.source))?;
-
-		let transient = match transient_string.is_empty() {
-			true => None,
-			false => Some(transient_string.to_filter(language)?),
+		// Filters provided by the request are cached, as clients tend to repeat them.
+		let fields = match query.fields {
+			Some(raw) => state
+				.filters
+				.filter(&raw, language, read.lenient_filters())?,
+			None => config.default_fields(&schema_specifier.source, language)?,
 		};
 
-		let schema = schema_provider.schema(schema_specifier.clone())?;
+		let transient = match query.transient {
+			Some(raw) => match raw.is_empty() {
+				true => None,
+				false => Some(
+					state
+						.filters
+						.filter(&raw, language, read.lenient_filters())?,
+				),
+			},
+			None => config.default_transient(&schema_specifier.source, language)?,
+		};
 
 		Ok(Self {
 			read,
+			rows: RowBudget::default(),
+			cancellation,
+			data,
+			schema_provider,
+			schema_request,
+			state,
+			version: version_key,
+			patch: query.patch,
 			excel,
+			ironworks,
 			schema_specifier,
 			schema,
 			language,
 			fields,
 			transient,
 			string_input,
+			icons,
+			timings,
+			depth: query.depth,
+			depth_config,
+			options: read::ReadOptions {
+				explicit_nulls: query.explicit_nulls,
+				compact_references: query.compact_refs,
+				max_references: query.max_refs,
+				all_subrows: query.all_subrow_refs,
+				omit_unknowns: !query.unknowns.unwrap_or(true),
+				max_depth: Some(depth_config.max),
+			},
+			cache: read::ReadCache::default(),
+			always_subrow: query.always_subrow.unwrap_or(config.always_subrow),
+			case: query.case.unwrap_or_default(),
+			raw_sheet: query.raw_sheet,
+			flatten: query.flatten,
 		})
 	}
 }
 
+/// Resolve the schema to read a version with. Raw sheet reads do not use a
+/// schema, and are reported as the empty schema without resolving one.
+fn resolve_schema(
+	provider: &service::Schema,
+	request: Option<bm_schema::Specifier>,
+	raw_sheet: bool,
+	version_key: VersionKey,
+) -> Result<
+	(
+		bm_schema::CanonicalSpecifier,
+		Box<dyn ironworks_schema::Schema + Send>,
+	),
+	bm_schema::Error,
+> {
+	match raw_sheet {
+		true => Ok(bm_schema::empty_schema()),
+		false => provider.resolve(request, version_key),
+	}
+}
+
 impl RowReader {
-	// todo: should i move the depth somewhere else? it _is_ effectively static config
+	/// Resolve a requested sheet name to the name used by the game data.
+	pub fn resolve_sheet<'a>(&self, sheet: &'a str) -> Result<Cow<'a, str>> {
+		Ok(self.read.resolve_sheet(&self.excel, sheet)?)
+	}
+
+	/// Name of the patch this reader reads data as of, if any.
+	pub fn patch(&self) -> Option<&str> {
+		self.patch.as_deref()
+	}
+
+	/// Whether names should fall back to case-insensitive matching.
+	pub fn case_insensitive(&self) -> bool {
+		self.read.case_insensitive()
+	}
+
+	/// Resolve the depth to read rows at, given the endpoint's default depth.
+	pub fn depth(&self, default: u8) -> Result<u8> {
+		self.depth_config.resolve(self.depth, default)
+	}
+
+	/// Create a reader for the specified version, retaining the request options
+	/// of this reader. The schema is resolved again for the new version.
+	pub fn with_version(&self, version_key: VersionKey) -> Result<Self> {
+		let version = self.data.version(version_key)?;
+		let excel = version.excel();
+
+		let (schema_specifier, schema) = self.timings.time("schema", || {
+			resolve_schema(
+				&self.schema_provider,
+				self.schema_request.clone(),
+				self.raw_sheet,
+				version_key,
+			)
+		})?;
+
+		let string_input = self.state.input(version_key, &excel)?;
+
+		Ok(Self {
+			read: self.read.clone(),
+			rows: RowBudget::new(self.rows.limit),
+			cancellation: self.cancellation.clone(),
+			data: self.data.clone(),
+			schema_provider: self.schema_provider.clone(),
+			schema_request: self.schema_request.clone(),
+			state: self.state.clone(),
+			version: version_key,
+			// Patches are specific to a version, the new version is read in full.
+			patch: None,
+			excel,
+			ironworks: version.ironworks(),
+			schema_specifier,
+			schema,
+			language: self.language,
+			fields: self.fields.clone(),
+			transient: self.transient.clone(),
+			string_input,
+			icons: self.icons.clone(),
+			timings: self.timings.clone(),
+			depth: self.depth,
+			depth_config: self.depth_config.clone(),
+			options: self.options.clone(),
+			// Cached data is specific to the version it was read from.
+			cache: read::ReadCache::default(),
+			always_subrow: self.always_subrow,
+			case: self.case,
+			raw_sheet: self.raw_sheet,
+			flatten: self.flatten,
+		})
+	}
+
+	/// Limit the number of rows this reader may read, replacing any existing
+	/// budget. Readers are otherwise unlimited.
+	pub fn with_row_budget(self, limit: usize) -> Self {
+		Self {
+			rows: RowBudget::new(Some(limit)),
+			..self
+		}
+	}
+
+	/// Read the specified sheet using the provided definition rather than the
+	/// resolved schema. Other sheets, such as reference targets, are unaffected.
+	/// The reported schema is marked as inline, wrapping the resolved schema.
+	pub fn with_inline_schema(self, sheet: String, definition: InlineSheet) -> Self {
+		Self {
+			schema: Box::new(InlineSchema::new(sheet, definition, self.schema)),
+			schema_specifier: inline_specifier(&self.schema_specifier),
+			cache: read::ReadCache::default(),
+			..self
+		}
+	}
+
+	/// Make the full row budget of this reader available again.
+	pub fn reset_row_budget(&self) {
+		self.rows.reset();
+	}
+
 	pub fn read_row(
 		&self,
 		sheet: &str,
@@ -257,20 +621,55 @@ impl RowReader {
 		subrow_id: u16,
 		depth: u8,
 	) -> Result<RowResult> {
-		let fields = ValueString(
-			self.read.read(
-				&self.excel,
-				self.schema.as_ref(),
-				sheet,
-				row_id,
-				subrow_id,
-				self.language,
-				&self.fields,
-				depth,
-			)?,
+		// Stop reading once the request has timed out, rather than continuing
+		// to read rows no one is waiting for.
+		self.cancellation.check()?;
+
+		self.rows.track_with(|| {
+			self.timings.time("read", || {
+				self.read_row_untimed(sheet, row_id, subrow_id, depth)
+			})
+		})
+	}
+
+	/// Read every subrow of the specified row, keyed by subrow ID. Rows in
+	/// sheets without subrows are returned as a single subrow `0`.
+	pub fn read_subrows(
+		&self,
+		sheet: &str,
+		row_id: u32,
+		depth: u8,
+	) -> Result<BTreeMap<u16, RowResult>> {
+		let sheet_data = self.excel.sheet(sheet)?;
+
+		collect_subrows(&sheet_data, row_id, |subrow_id| {
+			self.read_row(sheet, row_id, subrow_id, depth)
+		})
+	}
+
+	fn read_row_untimed(
+		&self,
+		sheet: &str,
+		row_id: u32,
+		subrow_id: u16,
+		depth: u8,
+	) -> Result<RowResult> {
+		if self.raw_sheet {
+			return self.read_row_raw(sheet, row_id, subrow_id);
+		}
+
+		let fields = self.value_string(self.flatten(self.read.read(
+			&self.excel,
+			self.schema.as_ref(),
+			sheet,
+			row_id,
+			subrow_id,
 			self.language,
-			self.string_input.clone(),
-		);
+			&self.fields,
+			depth,
+			&self.options,
+			&self.cache,
+		)?));
 
 		// Try to read a transient row.
 		let transient = match self.transient.as_ref() {
@@ -284,8 +683,10 @@ impl RowReader {
 				self.language,
 				filter,
 				depth,
+				&self.options,
+				&self.cache,
 			) {
-				Ok(value) => Some(ValueString(value, self.language, self.string_input.clone())),
+				Ok(value) => Some(self.value_string(self.flatten(value))),
 				Err(read::Error::NotFound(_)) => None,
 				Err(error) => Err(error)?,
 			},
@@ -293,10 +694,11 @@ impl RowReader {
 
 		// Check the kind of the sheet to determine if we should report a subrow id.
 		// TODO: this is theoretically wasteful, though IW will have cached it anyway.
-		let result_subrow_id = match self.excel.sheet(&sheet)?.kind()? {
-			exh::SheetKind::Subrows => Some(subrow_id),
-			_ => None,
-		};
+		let result_subrow_id = result_subrow_id(
+			self.excel.sheet(&sheet)?.kind()?,
+			subrow_id,
+			self.always_subrow,
+		);
 
 		Ok(RowResult {
 			row_id,
@@ -305,4 +707,288 @@ impl RowReader {
 			transient,
 		})
 	}
+
+	/// Prepare a value read by this reader for output.
+	pub fn value_string(&self, value: read::Value) -> ValueString {
+		ValueString(
+			value,
+			self.language,
+			self.string_input.clone(),
+			self.icons.clone(),
+			self.case,
+		)
+	}
+
+	fn flatten(&self, value: read::Value) -> read::Value {
+		match self.flatten {
+			true => value.flatten(),
+			false => value,
+		}
+	}
+
+	fn read_row_raw(&self, sheet: &str, row_id: u32, subrow_id: u16) -> Result<RowResult> {
+		let fields = self.value_string(self.read.read_raw(
+			&self.excel,
+			sheet,
+			row_id,
+			subrow_id,
+			self.language,
+		)?);
+
+		Ok(RowResult {
+			row_id,
+			subrow_id: result_subrow_id(
+				self.excel.sheet(&sheet)?.kind()?,
+				subrow_id,
+				self.always_subrow,
+			),
+			fields,
+			transient: None,
+		})
+	}
+}
+
+/// IDs of each subrow of a row, in ascending order. Subrows are contiguous from
+/// 0, and rows in sheets without subrows have a single subrow `0`. Rows that do
+/// not exist have no subrows.
+pub fn subrow_ids(
+	sheet: &excel::Sheet<&str>,
+	kind: exh::SheetKind,
+	row_id: u32,
+) -> Result<Vec<u16>> {
+	let candidates = match kind {
+		exh::SheetKind::Subrows => 0..=u16::MAX,
+		_ => 0..=0,
+	};
+
+	let mut subrow_ids = vec![];
+	for subrow_id in candidates {
+		match sheet.subrow(row_id, subrow_id) {
+			Ok(_) => subrow_ids.push(subrow_id),
+			// Only the row running out ends the subrows - other failures, such as
+			// missing sheet files, are reported.
+			Err(ironworks::Error::NotFound(ironworks::ErrorValue::Row { .. })) => break,
+			Err(error) => Err(error)?,
+		}
+	}
+
+	Ok(subrow_ids)
+}
+
+/// Read every subrow of a row using the provided function.
+fn collect_subrows<T>(
+	sheet: &excel::Sheet<&str>,
+	row_id: u32,
+	mut read: impl FnMut(u16) -> Result<T>,
+) -> Result<BTreeMap<u16, T>> {
+	let subrow_ids = subrow_ids(sheet, sheet.kind()?, row_id)?;
+	if subrow_ids.is_empty() {
+		return Err(Error::NotFound(format!(
+			"row {row_id} does not exist in sheet {}",
+			sheet.name()
+		)));
+	}
+
+	subrow_ids
+		.into_iter()
+		.map(|subrow_id| Ok((subrow_id, read(subrow_id)?)))
+		.collect()
+}
+
+fn result_subrow_id(kind: exh::SheetKind, subrow_id: u16, always: bool) -> Option<u16> {
+	match (kind, always) {
+		(exh::SheetKind::Subrows, _) | (_, true) => Some(subrow_id),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	fn test_config(exceeded: DepthExceeded) -> DepthConfig {
+		DepthConfig { max: 3, exceeded }
+	}
+
+	#[test]
+	fn depth_default() {
+		let config = test_config(DepthExceeded::Error);
+		assert_eq!(config.resolve(None, 2).unwrap(), 2);
+		assert_eq!(config.resolve(None, 5).unwrap(), 3);
+	}
+
+	#[test]
+	fn depth_within_max() {
+		let config = test_config(DepthExceeded::Error);
+		assert_eq!(config.resolve(Some(3), 2).unwrap(), 3);
+	}
+
+	#[test]
+	fn depth_clamped() {
+		let config = test_config(DepthExceeded::Clamp);
+		assert_eq!(config.resolve(Some(10), 2).unwrap(), 3);
+	}
+
+	#[test]
+	fn subrow_id_regular_sheet() {
+		assert_eq!(result_subrow_id(exh::SheetKind::Default, 0, false), None);
+		assert_eq!(result_subrow_id(exh::SheetKind::Default, 0, true), Some(0));
+	}
+
+	#[test]
+	fn subrow_id_subrow_sheet() {
+		assert_eq!(result_subrow_id(exh::SheetKind::Subrows, 2, false), Some(2));
+		assert_eq!(result_subrow_id(exh::SheetKind::Subrows, 2, true), Some(2));
+	}
+
+	fn read_subrows_of(row_id: u32, reads: &mut Vec<u16>) -> Result<BTreeMap<u16, read::Value>> {
+		use exh::ColumnKind as CK;
+		use read::fixture::{scalar, struct_node, Cell, Fixture, TestSheet};
+
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Quest", [(CK::UInt32, 0)]).subrows(
+				1,
+				vec![
+					vec![Cell::U32(10)],
+					vec![Cell::U32(11)],
+					vec![Cell::U32(12)],
+				],
+			),
+			struct_node([("Value", scalar())]),
+		)]);
+		let config = serde_json::from_value::<read::Config>(serde_json::json!({
+			"language": {"default": "en", "exclude": []},
+		}))
+		.expect("config should deserialize");
+		let read = read::Read::new(config);
+
+		let sheet = fixture.excel.sheet("Quest").expect("sheet should exist");
+		collect_subrows(&sheet, row_id, |subrow_id| {
+			reads.push(subrow_id);
+			let value = read.read(
+				&fixture.excel,
+				&fixture.schema,
+				"Quest",
+				row_id,
+				subrow_id,
+				excel::Language::English,
+				&read::Filter::All,
+				0,
+				&read::ReadOptions::default(),
+				&read::ReadCache::default(),
+			)?;
+			Ok(value)
+		})
+	}
+
+	#[test]
+	fn subrows_read_once() {
+		let mut reads = vec![];
+		let got = read_subrows_of(1, &mut reads).expect("read should not fail");
+
+		assert_eq!(got.keys().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+		// Each subrow is read once, and reads stop at the last subrow.
+		assert_eq!(reads, vec![0, 1, 2]);
+	}
+
+	#[test]
+	fn subrows_missing_row() {
+		let got = read_subrows_of(2, &mut vec![]);
+		assert!(matches!(got, Err(Error::NotFound(_))));
+	}
+
+	fn reader_config() -> RowReaderConfig {
+		RowReaderConfig {
+			fields: HashMap::from([("exdschema".into(), "Name".parse().unwrap())]),
+			transient: HashMap::from([("exdschema".into(), "Description".parse().unwrap())]),
+			always_subrow: false,
+		}
+	}
+
+	#[test]
+	fn default_filters_configured_source() {
+		let config = reader_config();
+		let fields = config
+			.default_fields("exdschema", excel::Language::English)
+			.unwrap();
+		assert_ne!(fields, read::Filter::All);
+		let transient = config
+			.default_transient("exdschema", excel::Language::English)
+			.unwrap();
+		assert!(transient.is_some());
+	}
+
+	#[test]
+	fn default_filters_unconfigured_source() {
+		let config = reader_config();
+		let fields = config
+			.default_fields("unconfigured", excel::Language::English)
+			.unwrap();
+		assert_eq!(fields, read::Filter::All);
+		let transient = config
+			.default_transient("unconfigured", excel::Language::English)
+			.unwrap();
+		assert_eq!(transient, None);
+	}
+
+	#[test]
+	fn default_filters_raw_sheet() {
+		// Raw sheet reads use the empty schema, which has no configured defaults.
+		let config = reader_config();
+		let fields = config
+			.default_fields(bm_schema::EMPTY_SOURCE, excel::Language::English)
+			.unwrap();
+		assert_eq!(fields, read::Filter::All);
+		let transient = config
+			.default_transient(bm_schema::EMPTY_SOURCE, excel::Language::English)
+			.unwrap();
+		assert_eq!(transient, None);
+	}
+
+	#[test]
+	fn depth_exceeded_error() {
+		let config = test_config(DepthExceeded::Error);
+		let got = config.resolve(Some(10), 2);
+		assert!(matches!(got, Err(Error::Invalid(_))));
+	}
+
+	#[test]
+	fn row_budget_exceeded() {
+		let budget = RowBudget::new(Some(2));
+		assert!(budget.track().is_ok());
+		assert!(budget.track().is_ok());
+		assert!(matches!(budget.track(), Err(Error::Invalid(_))));
+	}
+
+	#[test]
+	fn row_budget_ignores_missing_rows() {
+		let budget = RowBudget::new(Some(1));
+		let missing = budget.track_with(|| -> Result<()> { Err(Error::NotFound("row".into())) });
+		assert!(matches!(missing, Err(Error::NotFound(_))));
+		assert!(budget.track_with(|| Ok(())).is_ok());
+		assert!(matches!(
+			budget.track_with(|| Ok(())),
+			Err(Error::Invalid(_))
+		));
+	}
+
+	#[test]
+	fn row_budget_reset() {
+		let budget = RowBudget::new(Some(2));
+		for _ in 0..3 {
+			assert!(budget.track().is_ok());
+			assert!(budget.track().is_ok());
+			budget.reset();
+		}
+	}
+
+	#[test]
+	fn row_budget_unlimited() {
+		let budget = RowBudget::default();
+		for _ in 0..1000 {
+			assert!(budget.track().is_ok());
+		}
+	}
 }
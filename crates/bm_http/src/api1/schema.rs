@@ -0,0 +1,74 @@
+use aide::{
+	axum::{routing::get_with, ApiRouter},
+	transform::TransformOperation,
+};
+use axum::{debug_handler, extract::State, Json};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::service::Service;
+
+use super::api::ApiState;
+
+pub fn router(state: ApiState) -> ApiRouter {
+	ApiRouter::new().api_route("/", get_with(sources, sources_docs).with_state(state))
+}
+
+/// Response structure for the schema endpoint.
+#[derive(Serialize, JsonSchema)]
+struct SourcesResponse {
+	/// Array of schema sources available in the API.
+	sources: Vec<SourceMetadata>,
+}
+
+/// Metadata about a single schema source.
+#[derive(Debug, PartialEq, Serialize, JsonSchema)]
+struct SourceMetadata {
+	/// Name of the source. Accepted as the source portion of the `schema` query
+	/// parameter throughout the API.
+	name: String,
+
+	/// Human-readable label for the source.
+	label: String,
+
+	/// Description of the source, if any.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	description: Option<String>,
+
+	/// Whether this source is used when no schema is specified.
+	default: bool,
+}
+
+impl From<bm_schema::SourceMetadata> for SourceMetadata {
+	fn from(value: bm_schema::SourceMetadata) -> Self {
+		Self {
+			name: value.name,
+			label: value.label,
+			description: value.description,
+			default: value.default,
+		}
+	}
+}
+
+fn sources_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("list schema sources")
+		.description("List the schema sources that rows may be read with.")
+		.response_with::<200, Json<SourcesResponse>, _>(|response| {
+			response.example(SourcesResponse {
+				sources: vec![SourceMetadata {
+					name: "exdschema".into(),
+					label: "EXDSchema".into(),
+					description: Some("Community-maintained schema.".into()),
+					default: true,
+				}],
+			})
+		})
+}
+
+#[debug_handler(state = ApiState)]
+async fn sources(State(Service { schema, .. }): State<Service>) -> Json<SourcesResponse> {
+	Json(SourcesResponse {
+		sources: schema.sources().into_iter().map(Into::into).collect(),
+	})
+}
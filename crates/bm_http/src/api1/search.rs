@@ -1,4 +1,8 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{
+	borrow::Cow,
+	collections::{BTreeMap, HashSet},
+	str::FromStr,
+};
 
 use aide::{
 	axum::{routing::get_with, ApiRouter, IntoApiResponse},
@@ -10,6 +14,8 @@ use axum::{
 	Json,
 };
 use bm_search::{SearchRequest as InnerSearchRequest, SearchRequestQuery};
+use bm_version::VersionKey;
+use ironworks::file::exh;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -18,16 +24,23 @@ use crate::service::Service;
 
 use super::{
 	api::ApiState,
+	envelope::{EnvelopeConfig, EnvelopeQuery},
 	error::{Error, Result},
 	extract::{Query, VersionQuery},
-	query::QueryString,
-	read::{RowReader, RowReaderConfig, RowReaderState, RowResult},
+	icon::IconConfig,
+	query::{QueryString, SortString},
+	read::{DepthConfig, RowReader, RowReaderConfig, RowReaderState, RowResult},
+	timeout::blocking,
+	version::unix_seconds,
 };
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
 	limit: LimitConfig,
 
+	#[serde(default)]
+	defaults: DefaultsConfig,
+
 	#[serde(flatten)]
 	reader: RowReaderConfig,
 }
@@ -37,6 +50,25 @@ struct LimitConfig {
 	default: usize,
 	max: usize,
 	depth: u8,
+
+	/// Maximum number of sheets a single query may search. Unlimited if omitted.
+	#[serde(default)]
+	sheets: Option<usize>,
+
+	/// Maximum number of row IDs a single query may be constrained to.
+	#[serde(default = "default_max_row_ids")]
+	row_ids: usize,
+}
+
+fn default_max_row_ids() -> usize {
+	1000
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct DefaultsConfig {
+	/// Comma-separated list of sheets to search when a request does not specify
+	/// any. If omitted, requests must always specify sheets.
+	sheets: Option<String>,
 }
 
 #[derive(Clone, FromRef)]
@@ -45,6 +77,10 @@ struct RowsState {
 	reader_config: RowReaderConfig,
 	reader_state: RowReaderState,
 	limit_config: LimitConfig,
+	defaults_config: DefaultsConfig,
+	envelope_config: EnvelopeConfig,
+	depth_config: DepthConfig,
+	icon_config: IconConfig,
 }
 
 pub fn router(config: Config, state: ApiState) -> ApiRouter {
@@ -53,9 +89,16 @@ pub fn router(config: Config, state: ApiState) -> ApiRouter {
 		reader_config: config.reader,
 		reader_state: state.reader_state,
 		limit_config: config.limit,
+		defaults_config: config.defaults,
+		envelope_config: state.envelope_config,
+		depth_config: state.depth_config,
+		icon_config: state.icon_config,
 	};
 
-	ApiRouter::new().api_route("/", get_with(search, search_docs).with_state(state))
+	ApiRouter::new()
+		.api_route("/", get_with(search, search_docs))
+		.api_route("/explain", get_with(explain, explain_docs))
+		.with_state(state)
 }
 
 /// Query paramters accepted by the search endpoint.
@@ -66,8 +109,10 @@ struct SearchQuery {
 	/// mis-parses of the query.
 	query: Option<QueryString>,
 
-	/// List of excel sheets that the query should be run against. At least one
-	/// must be specified if not querying a cursor.
+	/// List of excel sheets that the query should be run against. If omitted,
+	/// a default list set by the server configuration is used, if any. Without
+	/// a default, every sheet the query can target is searched, if enabled by
+	/// the server configuration.
 	sheets: Option<String>,
 
 	/// Continuation token to retrieve further results from a prior search
@@ -75,8 +120,64 @@ struct SearchQuery {
 	cursor: Option<Uuid>,
 
 	/// Maximum number of rows to return. To paginate, provide the cursor token
-	/// provided in `next` to the `cursor` parameter.
+	/// provided in `next` to the `cursor` parameter before it expires at
+	/// `expires_at`. Expired cursors are rejected with a `410 Gone` response.
 	limit: Option<usize>,
+
+	/// Ordering to apply to results. If omitted, results are sorted by their
+	/// relevance to the query.
+	sort: Option<SortString>,
+
+	/// Comma-separated list of row IDs to constrain results to, i.e. `1,2,3`.
+	/// Applies to the rows of every searched sheet. Constraints do not apply to
+	/// rows of related sheets targeted by relation clauses.
+	rows: Option<String>,
+
+	/// Restrict the searched sheets to those of the specified kind. Sheets in
+	/// `sheets` that do not match are skipped. Requires a list of sheets.
+	kind: Option<SheetKindFilter>,
+
+	/// Include details of the physical columns matched by each result, and the
+	/// parsed and per-sheet normalized query trees. Intended for debugging
+	/// queries and schemas.
+	#[serde(default)]
+	debug: bool,
+
+	/// Include snippets of the fields that satisfied string match clauses (such
+	/// as `~` and regular expressions) in each result. Clauses targeting related
+	/// sheets are not highlighted.
+	#[serde(default)]
+	highlights: bool,
+
+	/// Return only the sheet, row ID, and subrow ID of each result, skipping
+	/// reading the rows' fields entirely.
+	#[serde(default)]
+	ids_only: bool,
+
+	/// Group results by the sheet they were found in, returning an object keyed
+	/// by sheet name. Results within each group retain their requested ordering.
+	#[serde(default)]
+	group_by_sheet: bool,
+}
+
+/// Kind of sheet to be included in a search.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum SheetKindFilter {
+	/// Sheets without subrows.
+	Rows,
+	/// Sheets containing subrows.
+	Subrows,
+}
+
+impl SheetKindFilter {
+	fn matches(self, kind: exh::SheetKind) -> bool {
+		let subrows = kind == exh::SheetKind::Subrows;
+		match self {
+			Self::Rows => !subrows,
+			Self::Subrows => subrows,
+		}
+	}
 }
 
 /// Response structure for the search endpoint.
@@ -86,12 +187,51 @@ struct SearchResponse {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	next: Option<Uuid>,
 
+	/// Time until which the `next` cursor is guaranteed to remain usable, in
+	/// seconds since the UNIX epoch. Present whenever `next` is.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	expires_at: Option<u64>,
+
 	/// The canonical specifier for the schema used in this response.
 	#[schemars(with = "String")]
 	schema: bm_schema::CanonicalSpecifier,
 
-	/// Array of results found by the query, sorted by their relevance.
-	results: Vec<SearchResult>,
+	/// Results found by the query, in the requested sort order.
+	results: SearchResults,
+
+	/// The query as parsed, and as normalized against each searched sheet. Only
+	/// present when `debug` is enabled for a query (rather than cursor) search.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[schemars(with = "Option<serde_json::Value>")]
+	debug: Option<bm_search::SearchDebug>,
+}
+
+/// Response structure for the search explain endpoint.
+#[derive(Serialize, JsonSchema)]
+struct ExplainResponse {
+	/// The canonical specifier for the schema used in this response.
+	#[schemars(with = "String")]
+	schema: bm_schema::CanonicalSpecifier,
+
+	/// The query as normalized against each searched sheet, keyed by sheet name.
+	/// Sheets the query could not be normalized against are omitted.
+	#[schemars(with = "serde_json::Value")]
+	normalized: BTreeMap<String, serde_json::Value>,
+
+	/// SQL that would be executed to retrieve the first page of results, with
+	/// values inlined. Searches executed separately for each sheet produce
+	/// multiple statements, separated by semicolons. The structure of this SQL is
+	/// an implementation detail, and may change at any time.
+	sql: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(untagged)]
+enum SearchResults {
+	/// Array of results.
+	List(Vec<SearchResult>),
+	/// Arrays of results, keyed by the sheet they were found in.
+	Grouped(BTreeMap<String, Vec<SearchResult>>),
 }
 
 /// Result found by a search query, hydrated with data from the underlying excel
@@ -108,8 +248,78 @@ struct SearchResult {
 	/// Excel sheet this result was found in.
 	sheet: String,
 
+	/// Physical columns of the sheet that matched the query. Only present when
+	/// `debug` is enabled.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	matched_columns: Option<Vec<MatchedColumn>>,
+
+	/// Snippets of the columns of the sheet that satisfied string match clauses.
+	/// Only present when `highlights` is enabled.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	highlights: Option<Vec<SearchHighlight>>,
+
 	#[serde(flatten)]
-	row: RowResult,
+	row: SearchResultRow,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(untagged)]
+enum SearchResultRow {
+	Row(RowResult),
+	Id(RowIdResult),
+}
+
+/// Identifiers of a result's row, returned when field reads are skipped.
+#[derive(Debug, Serialize, JsonSchema)]
+struct RowIdResult {
+	/// ID of this row.
+	row_id: u32,
+
+	/// Subrow ID of this row.
+	subrow_id: u16,
+}
+
+/// A physical column that matched a clause of a search query.
+#[derive(Debug, Serialize, JsonSchema)]
+struct MatchedColumn {
+	/// Byte offset of the column within the row data.
+	offset: u16,
+
+	/// Kind of data stored in the column.
+	kind: String,
+}
+
+impl From<bm_search::MatchedColumn> for MatchedColumn {
+	fn from(value: bm_search::MatchedColumn) -> Self {
+		Self {
+			offset: value.offset,
+			kind: value.kind,
+		}
+	}
+}
+
+/// A snippet of a column value that satisfied a string match clause.
+#[derive(Debug, Serialize, JsonSchema)]
+struct SearchHighlight {
+	/// Byte offset of the column within the row data.
+	offset: u16,
+
+	/// Language of the matched column value.
+	language: String,
+
+	/// Region of the column value surrounding the match. Truncated ends of the
+	/// value are marked with an ellipsis.
+	snippet: String,
+}
+
+impl From<bm_search::MatchHighlight> for SearchHighlight {
+	fn from(value: bm_search::MatchHighlight) -> Self {
+		Self {
+			offset: value.offset,
+			language: bm_read::LanguageString::from(value.language).to_string(),
+			snippet: value.snippet,
+		}
+	}
 }
 
 fn search_docs(operation: TransformOperation) -> TransformOperation {
@@ -119,84 +329,498 @@ fn search_docs(operation: TransformOperation) -> TransformOperation {
 		.response_with::<200, Json<SearchResponse>, _>(|response| {
 			response.example(SearchResponse {
 				next: Some(Uuid::from_str("bbe61a5e-7d22-41ec-9f5a-711c967c5624").expect("static")),
+				expires_at: Some(1720000300),
 				schema: bm_schema::CanonicalSpecifier{
 					source: "source".into(),
 					version: "version".into()
 				},
-				results: vec![SearchResult {
+				results: SearchResults::List(vec![SearchResult {
 					score: 1.413,
 					sheet: "SheetName".into(),
-					row: RowResult::example(1),
-				}],
+					matched_columns: None,
+					highlights: None,
+					row: SearchResultRow::Row(RowResult::example(1)),
+				}]),
+				debug: None,
+			})
+		})
+}
+
+fn explain_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("explain a search query")
+		.description("Normalize the provided search query against each searched sheet, and generate the SQL that would be executed for it, without executing the search. Accepts the same parameters as a search; cursors cannot be explained.")
+		.response_with::<200, Json<ExplainResponse>, _>(|response| {
+			response.example(ExplainResponse {
+				schema: bm_schema::CanonicalSpecifier {
+					source: "source".into(),
+					version: "version".into(),
+				},
+				normalized: BTreeMap::from([(
+					"SheetName".into(),
+					serde_json::json!({ "leaf": {
+						"field": { "offset": 0, "kind": "String", "language": "en" },
+						"operation": { "match": "example" },
+					} }),
+				)]),
+				sql: r#"SELECT 'SheetName' AS "sheet", ... LIMIT 101"#.into(),
 			})
 		})
 }
 
 #[debug_handler(state = RowsState)]
 async fn search(
+	envelope: EnvelopeQuery,
 	// TODO: this is a second versionquery extract for this, and it is being run twice. it's idempotent, but would be good to avoid
 	VersionQuery(version_key): VersionQuery,
 	Query(query): Query<SearchQuery>,
 	State(Service { search, .. }): State<Service>,
 	State(config): State<LimitConfig>,
+	State(defaults): State<DefaultsConfig>,
 	reader: RowReader,
 ) -> Result<impl IntoApiResponse> {
+	check_full_version(&reader)?;
+
 	// Resolve search request into something the search service understands.
-	// TODO: seperate fn?
 	let request = match query.cursor {
 		// Cursor always has priority
 		Some(cursor) => InnerSearchRequest::Cursor(cursor),
-		None => {
-			let Some(search_query) = query.query else {
-				return Err(Error::Invalid(
-					"search queries must contain a query or cursor".into(),
-				));
+		None => InnerSearchRequest::Query(build_request_query(
+			&query,
+			version_key,
+			&config,
+			&defaults,
+			&reader,
+		)?),
+	};
+
+	let limit = query.limit.unwrap_or(config.default).min(config.max);
+	let depth = reader.depth(config.depth)?;
+
+	// Run the actual search request.
+	let (results, next_cursor, debug) = search.search(request, limit).await?;
+
+	// Reading rows blocks - hydrate off the runtime so the timeout can trigger.
+	let ids_only = query.ids_only;
+	let (http_results, reader) = blocking(move || {
+		let http_results = results
+			.into_iter()
+			.map(|result| {
+				hydrate_result(result, ids_only, |sheet, row_id, subrow_id| {
+					reader.read_row(sheet, row_id, subrow_id, depth)
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+		Ok((http_results, reader))
+	})
+	.await?;
+
+	let http_results = match query.group_by_sheet {
+		true => SearchResults::Grouped(group_results(http_results)),
+		false => SearchResults::List(http_results),
+	};
+
+	let response = SearchResponse {
+		next: next_cursor.map(|cursor| cursor.id),
+		expires_at: next_cursor.map(|cursor| unix_seconds(cursor.expires_at)),
+		schema: reader.schema_specifier.clone(),
+		results: http_results,
+		debug,
+	};
+
+	Ok(envelope.wrap(
+		response,
+		Some(reader.version),
+		Some(reader.schema_specifier),
+	))
+}
+
+#[debug_handler(state = RowsState)]
+async fn explain(
+	envelope: EnvelopeQuery,
+	VersionQuery(version_key): VersionQuery,
+	Query(query): Query<SearchQuery>,
+	State(Service { search, .. }): State<Service>,
+	State(config): State<LimitConfig>,
+	State(defaults): State<DefaultsConfig>,
+	reader: RowReader,
+) -> Result<impl IntoApiResponse> {
+	// Cursors are built from queries that have already been normalized, there's
+	// nothing left to explain.
+	if query.cursor.is_some() {
+		return Err(Error::Invalid(
+			"cursors cannot be explained, provide a query instead".into(),
+		));
+	}
+	check_full_version(&reader)?;
+
+	let request = build_request_query(&query, version_key, &config, &defaults, &reader)?;
+	let limit = query.limit.unwrap_or(config.default).min(config.max);
+
+	let explained = search.explain(request, limit)?;
+
+	let response = ExplainResponse {
+		schema: reader.schema_specifier.clone(),
+		normalized: explained
+			.normalized
+			.into_iter()
+			.map(|(sheet, node)| {
+				Ok((
+					sheet,
+					serde_json::to_value(node).map_err(anyhow::Error::from)?,
+				))
+			})
+			.collect::<Result<_>>()?,
+		sql: explained.sql,
+	};
+
+	Ok(envelope.wrap(
+		response,
+		Some(reader.version),
+		Some(reader.schema_specifier),
+	))
+}
+
+// Search indices are only built for full versions. Searching them while
+// reading rows at an earlier patch would match against data the rows may not
+// contain.
+fn check_full_version(reader: &RowReader) -> Result<()> {
+	match reader.patch() {
+		None => Ok(()),
+		Some(patch) => Err(Error::Invalid(format!(
+			"search cannot be performed at patch \"{patch}\", omit the patch parameter"
+		))),
+	}
+}
+
+fn build_request_query(
+	query: &SearchQuery,
+	version_key: VersionKey,
+	config: &LimitConfig,
+	defaults: &DefaultsConfig,
+	reader: &RowReader,
+) -> Result<SearchRequestQuery> {
+	let Some(search_query) = query.query.clone() else {
+		return Err(Error::Invalid(
+			"search queries must contain a query or cursor".into(),
+		));
+	};
+
+	let sheets = match requested_sheets(query.sheets.as_deref(), defaults.sheets.as_deref()) {
+		Some(sheets) => {
+			let sheets = split_sheets(sheets, config.sheets)?
+				.into_iter()
+				.map(|sheet_name| reader.resolve_sheet(sheet_name).map(Cow::into_owned))
+				.collect::<Result<HashSet<_>>>()?;
+
+			let sheets = match query.kind {
+				None => sheets,
+				Some(kind) => filter_sheet_kind(sheets, kind, |sheet| {
+					Ok(reader.excel.sheet(sheet)?.kind()?)
+				})?,
 			};
 
-			// TODO: This can be made optional, which will allow search queries that can theoretically search the entire db in one massive query. Will need extensive testing.
-			let Some(sheets) = query.sheets else {
+			Some(sheets)
+		}
+
+		// Without any sheets, the search service will search every sheet, if it
+		// has been configured to allow it.
+		None => {
+			if query.kind.is_some() {
 				return Err(Error::Invalid(
-					"query-based searches must specify a list of sheets to search".into(),
+					"sheet kind filters require a list of sheets to search".into(),
 				));
-			};
-
-			let sheets = sheets
-				.split(',')
-				.map(|sheet_name| sheet_name.to_owned())
-				.collect::<HashSet<_>>();
-
-			InnerSearchRequest::Query(SearchRequestQuery {
-				version: version_key,
-				query: search_query.into(),
-				language: reader.language,
-				sheets: Some(sheets),
-				schema: reader.schema_specifier.clone(),
-			})
+			}
+			None
 		}
 	};
 
-	let limit = query.limit.unwrap_or(config.default).min(config.max);
+	Ok(SearchRequestQuery {
+		version: version_key,
+		query: search_query.into(),
+		language: reader.language,
+		sheets,
+		schema: reader.schema_specifier.clone(),
+		case_insensitive: reader.case_insensitive(),
+		sort: query.sort.clone().map(Into::into).unwrap_or_default(),
+		rows: query
+			.rows
+			.as_deref()
+			.map(|rows| split_rows(rows, config.row_ids))
+			.transpose()?,
+		debug: query.debug,
+		highlights: query.highlights,
+	})
+}
 
-	// Run the actual search request.
-	let (results, next_cursor) = search.search(request, limit).await?;
+fn hydrate_result(
+	result: bm_search::SearchResult,
+	ids_only: bool,
+	read_row: impl Fn(&str, u32, u16) -> Result<RowResult>,
+) -> Result<SearchResult> {
+	let row = match ids_only {
+		true => SearchResultRow::Id(RowIdResult {
+			row_id: result.row_id,
+			subrow_id: result.subrow_id,
+		}),
+		false => SearchResultRow::Row(read_row(&result.sheet, result.row_id, result.subrow_id)?),
+	};
+
+	Ok(SearchResult {
+		score: result.score,
+		sheet: result.sheet,
+		matched_columns: result
+			.matched_columns
+			.map(|columns| columns.into_iter().map(MatchedColumn::from).collect()),
+		highlights: result
+			.highlights
+			.map(|highlights| highlights.into_iter().map(SearchHighlight::from).collect()),
+		row,
+	})
+}
+
+fn group_results(results: Vec<SearchResult>) -> BTreeMap<String, Vec<SearchResult>> {
+	// Results arrive in the requested sort order, which may not be by score -
+	// preserve it within each group.
+	let mut groups = BTreeMap::<String, Vec<SearchResult>>::new();
+	for result in results {
+		groups.entry(result.sheet.clone()).or_default().push(result);
+	}
+
+	groups
+}
+
+/// Select the sheets to search, falling back to the configured defaults.
+fn requested_sheets<'a>(requested: Option<&'a str>, default: Option<&'a str>) -> Option<&'a str> {
+	requested.or(default)
+}
+
+fn split_sheets(sheets: &str, max_sheets: Option<usize>) -> Result<Vec<&str>> {
+	let sheets = sheets.split(',').collect::<Vec<_>>();
 
-	let http_results = results
+	if let Some(max) = max_sheets {
+		if sheets.len() > max {
+			return Err(Error::Invalid(format!(
+				"search requested {} sheets, but at most {max} may be searched at once",
+				sheets.len()
+			)));
+		}
+	}
+
+	Ok(sheets)
+}
+
+fn split_rows(rows: &str, max_rows: usize) -> Result<Vec<u32>> {
+	let rows = rows
+		.split(',')
+		.map(|row| {
+			row.trim()
+				.parse::<u32>()
+				.map_err(|error| Error::Invalid(format!("invalid row ID {row:?}: {error}")))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	if rows.len() > max_rows {
+		return Err(Error::Invalid(format!(
+			"search requested {} row IDs, but at most {max_rows} may be searched at once",
+			rows.len()
+		)));
+	}
+
+	Ok(rows)
+}
+
+fn filter_sheet_kind(
+	sheets: HashSet<String>,
+	kind: SheetKindFilter,
+	sheet_kind: impl Fn(&str) -> Result<exh::SheetKind>,
+) -> Result<HashSet<String>> {
+	let mut filtered = HashSet::new();
+	for sheet in sheets {
+		if kind.matches(sheet_kind(&sheet)?) {
+			filtered.insert(sheet);
+		}
+	}
+
+	if filtered.is_empty() {
+		return Err(Error::Invalid(format!(
+			"none of the requested sheets are of kind {kind:?}"
+		)));
+	}
+
+	Ok(filtered)
+}
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	#[test]
+	fn sheets_within_limit() {
+		let got = split_sheets("Item,Action", Some(2)).expect("should not fail");
+		assert_eq!(got, vec!["Item", "Action"]);
+	}
+
+	#[test]
+	fn sheets_unlimited() {
+		let got = split_sheets("Item,Action,Status", None).expect("should not fail");
+		assert_eq!(got, vec!["Item", "Action", "Status"]);
+	}
+
+	#[test]
+	fn sheets_exceeding_limit() {
+		let got = split_sheets("Item,Action,Status", Some(2));
+		assert!(matches!(got, Err(Error::Invalid(_))));
+	}
+
+	#[test]
+	fn rows_parsed() {
+		let got = split_rows("1, 2,3", 3).expect("should not fail");
+		assert_eq!(got, vec![1, 2, 3]);
+
+		assert!(matches!(split_rows("1,a", 3), Err(Error::Invalid(_))));
+		assert!(matches!(split_rows("", 3), Err(Error::Invalid(_))));
+		assert!(matches!(split_rows("1,2,3,4", 3), Err(Error::Invalid(_))));
+	}
+
+	#[test]
+	fn sheets_default_when_omitted() {
+		let got = requested_sheets(None, Some("Item,Action"));
+		assert_eq!(got, Some("Item,Action"));
+
+		let got = requested_sheets(Some("Status"), Some("Item,Action"));
+		assert_eq!(got, Some("Status"));
+
+		let got = requested_sheets(None, None);
+		assert_eq!(got, None);
+	}
+
+	fn search_results() -> Vec<bm_search::SearchResult> {
+		[("Item", 1), ("Action", 5)]
+			.into_iter()
+			.map(|(sheet, row_id)| bm_search::SearchResult {
+				score: 1.,
+				sheet: sheet.into(),
+				row_id,
+				subrow_id: 0,
+				matched_columns: None,
+				highlights: None,
+			})
+			.collect()
+	}
+
+	fn hydrate_all(ids_only: bool) -> Vec<serde_json::Value> {
+		search_results()
+			.into_iter()
+			.map(|result| {
+				let result = hydrate_result(result, ids_only, |_sheet, row_id, _subrow_id| {
+					Ok(RowResult::example(row_id))
+				})
+				.expect("should not fail");
+				serde_json::to_value(result).unwrap()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn ids_only_results() {
+		let full = hydrate_all(false);
+		let ids = hydrate_all(true);
+
+		let membership = |results: &[serde_json::Value]| {
+			results
+				.iter()
+				.map(|result| (result["sheet"].clone(), result["row_id"].clone()))
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(membership(&ids), membership(&full));
+
+		assert!(full.iter().all(|result| result.get("fields").is_some()));
+		assert_eq!(
+			ids[0],
+			serde_json::json!({
+				"score": 1.0,
+				"sheet": "Item",
+				"row_id": 1,
+				"subrow_id": 0,
+			})
+		);
+	}
+
+	#[test]
+	fn grouped_results() {
+		let results = [
+			("Item", 1, 0.5),
+			("Action", 2, 2.),
+			("Item", 3, 1.5),
+			("Action", 4, 1.),
+		]
 		.into_iter()
-		.map(|result| {
-			let row =
-				reader.read_row(&result.sheet, result.row_id, result.subrow_id, config.depth)?;
-
-			Ok(SearchResult {
-				score: result.score,
-				sheet: result.sheet,
-				row,
+		.map(|(sheet, row_id, score)| SearchResult {
+			score,
+			sheet: sheet.into(),
+			matched_columns: None,
+			highlights: None,
+			row: SearchResultRow::Id(RowIdResult {
+				row_id,
+				subrow_id: 0,
+			}),
+		})
+		.collect();
+
+		let got = group_results(results)
+			.into_iter()
+			.map(|(sheet, group)| {
+				let scores = group.iter().map(|result| result.score).collect::<Vec<_>>();
+				(sheet, scores)
 			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			got,
+			vec![
+				("Action".to_string(), vec![2., 1.]),
+				("Item".to_string(), vec![0.5, 1.5]),
+			]
+		);
+	}
+
+	fn mixed_sheets() -> HashSet<String> {
+		["Item", "Quest", "GilShopItem"]
+			.into_iter()
+			.map(String::from)
+			.collect()
+	}
+
+	fn test_sheet_kind(sheet: &str) -> Result<exh::SheetKind> {
+		Ok(match sheet {
+			"GilShopItem" => exh::SheetKind::Subrows,
+			_ => exh::SheetKind::Default,
 		})
-		.collect::<Result<Vec<_>>>()?;
+	}
 
-	Ok(Json(SearchResponse {
-		next: next_cursor,
-		schema: reader.schema_specifier,
-		results: http_results,
-	}))
+	#[test]
+	fn filter_kind_rows() {
+		let got = filter_sheet_kind(mixed_sheets(), SheetKindFilter::Rows, test_sheet_kind)
+			.expect("should not fail");
+		let expected = ["Item", "Quest"].into_iter().map(String::from).collect();
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn filter_kind_subrows() {
+		let got = filter_sheet_kind(mixed_sheets(), SheetKindFilter::Subrows, test_sheet_kind)
+			.expect("should not fail");
+		let expected = ["GilShopItem"].into_iter().map(String::from).collect();
+		assert_eq!(got, expected);
+	}
+
+	#[test]
+	fn filter_kind_no_matches() {
+		let sheets = ["Item".to_string()].into_iter().collect();
+		let got = filter_sheet_kind(sheets, SheetKindFilter::Subrows, test_sheet_kind);
+		assert!(matches!(got, Err(Error::Invalid(_))));
+	}
 }
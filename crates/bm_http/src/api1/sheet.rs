@@ -1,15 +1,27 @@
-use std::{num::ParseIntError, str::FromStr};
+use std::{
+	collections::{BTreeMap, VecDeque},
+	fmt,
+	num::ParseIntError,
+	str::FromStr,
+};
 
 use aide::{
 	axum::{routing::get_with, ApiRouter, IntoApiResponse},
+	openapi,
 	transform::TransformOperation,
 };
 use axum::{
 	debug_handler,
 	extract::{FromRef, State},
+	http::header,
+	response::{IntoResponse, Response},
 	Json,
 };
+use axum_extra::{headers::ContentType, TypedHeader};
+use bm_read as read;
+use bm_version::VersionKey;
 use either::Either;
+use ironworks::{excel, file::exh, Ironworks};
 use schemars::{
 	gen::SchemaGenerator,
 	schema::{InstanceType, Schema, SchemaObject, StringValidation},
@@ -17,14 +29,27 @@ use schemars::{
 };
 use serde::{de, Deserialize, Deserializer, Serialize};
 
-use crate::service::Service;
+use crate::service::{self, Service};
 
 use super::{
 	api::ApiState,
+	csv,
+	envelope::{Envelope, EnvelopeConfig, EnvelopeQuery},
 	error::{Error, Result},
-	extract::{Path, Query, VersionQuery},
+	extract::{JsonBody, Path, Query, VersionQuery},
+	format::{vary_accept, FormatQuery, OutputFormat},
+	icon::IconConfig,
+	inline_schema::{InlineSchemaConfig, InlineSheet},
 	jsonschema::impl_jsonschema,
-	read::{RowReader, RowReaderConfig, RowReaderState, RowResult},
+	language::file_language_code,
+	ndjson,
+	read::{
+		subrow_ids, DepthConfig, RowReader, RowReaderConfig, RowReaderState, RowResult,
+		SchemaLanguage, SchemaSpecifier,
+	},
+	stream,
+	timeout::blocking,
+	value::ValueString,
 };
 
 #[derive(Debug, Clone, Deserialize)]
@@ -33,6 +58,9 @@ pub struct Config {
 
 	list: RowReaderConfig,
 	entry: RowReaderConfig,
+
+	#[serde(default)]
+	inline_schema: InlineSchemaConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -40,6 +68,24 @@ struct LimitConfig {
 	default: usize,
 	max: usize,
 	depth: u8,
+
+	/// Maximum number of versions that may be read by a single row history
+	/// request.
+	#[serde(default = "default_history")]
+	history: usize,
+
+	/// Maximum number of rows that may be read by a single streamed (`ndjson`)
+	/// sheet response. Paged responses are limited to `max`.
+	#[serde(default = "default_export")]
+	export: usize,
+}
+
+fn default_history() -> usize {
+	20
+}
+
+fn default_export() -> usize {
+	100_000
 }
 
 #[derive(Clone, FromRef)]
@@ -48,9 +94,24 @@ struct RowsState {
 	reader_config: RowReaderConfig,
 	reader_state: RowReaderState,
 	limit_config: LimitConfig,
+	envelope_config: EnvelopeConfig,
+	depth_config: DepthConfig,
+	icon_config: IconConfig,
+	inline_schema_config: InlineSchemaConfig,
 }
 
 pub fn router(config: Config, api_state: ApiState) -> ApiRouter {
+	let entry_state = RowsState {
+		services: api_state.services.clone(),
+		reader_config: config.entry,
+		reader_state: api_state.reader_state.clone(),
+		limit_config: config.limit.clone(),
+		envelope_config: api_state.envelope_config.clone(),
+		depth_config: api_state.depth_config.clone(),
+		icon_config: api_state.icon_config.clone(),
+		inline_schema_config: config.inline_schema.clone(),
+	};
+
 	ApiRouter::new()
 		.api_route("/", get_with(list, list_docs).with_state(api_state.clone()))
 		.api_route(
@@ -60,16 +121,37 @@ pub fn router(config: Config, api_state: ApiState) -> ApiRouter {
 				reader_config: config.list,
 				reader_state: api_state.reader_state.clone(),
 				limit_config: config.limit.clone(),
+				envelope_config: api_state.envelope_config.clone(),
+				depth_config: api_state.depth_config.clone(),
+				icon_config: api_state.icon_config.clone(),
+				inline_schema_config: config.inline_schema.clone(),
 			}),
 		)
+		.api_route(
+			"/{sheet}/raw",
+			get_with(raw_page, raw_page_docs).with_state(api_state.clone()),
+		)
+		.api_route(
+			"/{sheet}/raw/header",
+			get_with(raw_header, raw_header_docs).with_state(api_state.clone()),
+		)
+		.api_route(
+			"/{sheet}/coverage",
+			get_with(coverage, coverage_docs).with_state(api_state.clone()),
+		)
 		.api_route(
 			"/{sheet}/{row}",
-			get_with(row, row_docs).with_state(RowsState {
-				services: api_state.services,
-				reader_config: config.entry,
-				reader_state: api_state.reader_state,
-				limit_config: config.limit,
-			}),
+			get_with(row, row_docs)
+				.post_with(row_inline, row_inline_docs)
+				.with_state(entry_state.clone()),
+		)
+		.api_route(
+			"/{sheet}/{row}/history",
+			get_with(row_history, row_history_docs).with_state(entry_state.clone()),
+		)
+		.api_route(
+			"/{sheet}/{row}/diff",
+			get_with(row_diff, row_diff_docs).with_state(entry_state),
 		)
 }
 
@@ -85,6 +167,10 @@ struct ListResponse {
 struct SheetMetadata {
 	/// The name of the sheet.
 	name: String,
+
+	/// Languages this sheet contains data for in the requested version. Sheets
+	/// without localised data will only report `none`.
+	languages: Vec<String>,
 }
 
 fn list_docs(operation: TransformOperation) -> TransformOperation {
@@ -96,12 +182,15 @@ fn list_docs(operation: TransformOperation) -> TransformOperation {
 				sheets: vec![
 					SheetMetadata {
 						name: "Action".into(),
+						languages: vec!["ja".into(), "en".into(), "de".into(), "fr".into()],
 					},
 					SheetMetadata {
 						name: "Item".into(),
+						languages: vec!["ja".into(), "en".into(), "de".into(), "fr".into()],
 					},
 					SheetMetadata {
 						name: "Status".into(),
+						languages: vec!["ja".into(), "en".into(), "de".into(), "fr".into()],
 					},
 				],
 			})
@@ -111,7 +200,7 @@ fn list_docs(operation: TransformOperation) -> TransformOperation {
 #[debug_handler(state = ApiState)]
 async fn list(
 	VersionQuery(version_key): VersionQuery,
-	State(Service { data, .. }): State<Service>,
+	State(Service { data, read, .. }): State<Service>,
 ) -> Result<Json<ListResponse>> {
 	let excel = data.version(version_key)?.excel();
 
@@ -122,14 +211,49 @@ async fn list(
 		.collect::<Vec<_>>();
 	names.sort();
 
-	let metadata = names
-		.into_iter()
-		.map(|name| SheetMetadata { name })
-		.collect();
+	let metadata = sheet_metadata(
+		names,
+		|name| Ok(excel.sheet(name)?.languages()?),
+		|language| read.language_enabled(language),
+	);
 
 	Ok(Json(ListResponse { sheets: metadata }))
 }
 
+/// Build metadata for each named sheet. Sheets whose languages cannot be read,
+/// such as those with a missing or malformed header, are logged and omitted
+/// rather than failing the entire listing.
+fn sheet_metadata<L: IntoIterator<Item = excel::Language>>(
+	names: Vec<String>,
+	languages: impl Fn(&str) -> Result<L>,
+	enabled: impl Fn(excel::Language) -> bool,
+) -> Vec<SheetMetadata> {
+	names
+		.into_iter()
+		.filter_map(|name| match languages(&name) {
+			Ok(languages) => Some(SheetMetadata {
+				languages: sheet_languages(languages, &enabled),
+				name,
+			}),
+			Err(error) => {
+				tracing::warn!(sheet = %name, ?error, "omitting unreadable sheet from listing");
+				None
+			}
+		})
+		.collect()
+}
+
+fn sheet_languages(
+	languages: impl IntoIterator<Item = excel::Language>,
+	enabled: impl Fn(excel::Language) -> bool,
+) -> Vec<String> {
+	languages
+		.into_iter()
+		.filter(|language| enabled(*language))
+		.map(|language| read::LanguageString::from(language).to_string())
+		.collect()
+}
+
 /// Path variables accepted by the sheet endpoint.
 #[derive(Deserialize, JsonSchema)]
 struct SheetPath {
@@ -137,12 +261,27 @@ struct SheetPath {
 	sheet: String,
 }
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 struct RowSpecifier {
 	row_id: u32,
 	subrow_id: u16,
 }
 
+impl fmt::Display for RowSpecifier {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{}:{}", self.row_id, self.subrow_id)
+	}
+}
+
+impl Serialize for RowSpecifier {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		serializer.collect_str(self)
+	}
+}
+
 impl FromStr for RowSpecifier {
 	type Err = ParseIntError;
 
@@ -195,11 +334,18 @@ struct SheetQuery {
 	#[schemars(schema_with = "rows_schema")]
 	rows: Option<Vec<RowSpecifier>>,
 
-	/// Maximum number of rows to return. To paginate, provide the last returned row to the next request's `after` parameter.
+	/// Maximum number of rows to return. To paginate, provide the last returned row to the next request's `after` parameter, or the first returned row to `before` to paginate backwards. Streamed (`ndjson`) responses return every remaining row by default, up to a larger limit set by the server configuration.
 	limit: Option<usize>,
 
-	/// Fetch rows after the specified row. Behavior is undefined if both `rows` and `after` are provided.
+	/// Fetch rows after the specified row. Behavior is undefined if both `rows` and `after` are provided. May not be combined with `before`.
 	after: Option<RowSpecifier>,
+
+	/// Fetch the rows immediately preceding the specified row. Rows are still returned in ascending order. May not be combined with `after`.
+	before: Option<RowSpecifier>,
+
+	/// Include pagination details, such as the total number of rows in the sheet, in the response. Ignored for formats other than `json`.
+	#[serde(default)]
+	include_count: bool,
 }
 
 // TODO: this can probably be made as a general purpose "comma seperated" deserializer struct
@@ -246,6 +392,32 @@ struct SheetResponse {
 
 	/// Array of rows retrieved by the query.
 	rows: Vec<RowResult>,
+
+	/// Pagination details for this page of rows. Only present if requested with
+	/// `include_count`.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pagination: Option<Pagination>,
+}
+
+/// Pagination details for a page of sheet rows.
+#[derive(Serialize, JsonSchema)]
+struct Pagination {
+	/// Total number of rows in the sheet. For sheets with subrows, this is the
+	/// number of rows rather than subrows - as each subrow is returned
+	/// individually, more entries than this may be paginated through.
+	total_rows: u32,
+
+	/// Maximum number of rows returned for this page, after server limits have
+	/// been applied.
+	limit: usize,
+
+	/// Row this page was read after, if any.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	after: Option<RowSpecifier>,
+
+	/// Row this page was read before, if any.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	before: Option<RowSpecifier>,
 }
 
 fn sheet_docs(operation: TransformOperation) -> TransformOperation {
@@ -259,22 +431,123 @@ fn sheet_docs(operation: TransformOperation) -> TransformOperation {
 					version: "version".into(),
 				},
 				rows: vec![RowResult::example(1), RowResult::example(2)],
+				pagination: None,
 			})
 		})
 }
 
 #[debug_handler(state = RowsState)]
 async fn sheet(
+	envelope: EnvelopeQuery,
+	FormatQuery(format): FormatQuery,
 	Path(path): Path<SheetPath>,
 	Query(query): Query<SheetQuery>,
 	State(config): State<LimitConfig>,
 	reader: RowReader,
 ) -> Result<impl IntoApiResponse> {
-	// Get a reference to the sheet we'll be reading from.
-	// TODO: should this be in super::error as a default extract? minus the sheet specialised case, that is
+	if format == OutputFormat::Ndjson {
+		let reader = reader.with_row_budget(config.export);
+		return stream_sheet_response(path, query, config, reader)
+			.await
+			.map(vary_accept);
+	}
+
+	blocking(move || read_sheet_response(envelope, format, path, query, config, reader))
+		.await
+		.map(vary_accept)
+}
+
+fn read_sheet_response(
+	envelope: EnvelopeQuery,
+	format: OutputFormat,
+	path: SheetPath,
+	query: SheetQuery,
+	config: LimitConfig,
+	reader: RowReader,
+) -> Result<Response> {
+	let sheet_name = reader.resolve_sheet(&path.sheet)?;
+	let depth = reader.depth(config.depth)?;
+
+	let limit = page_limit(&query, &config);
+	let rows = sheet_rows(&reader, &sheet_name, &query, Some(limit), depth)?
+		.collect::<Result<Vec<_>>>()?;
+
+	if format == OutputFormat::Csv {
+		return csv::response(&rows);
+	}
+
+	let pagination = sheet_pagination(&reader.ironworks, &sheet_name, &query, limit)?;
+
+	let response = SheetResponse {
+		schema: reader.schema_specifier.clone(),
+		rows,
+		pagination,
+	};
+
+	Ok(envelope
+		.wrap(
+			response,
+			Some(reader.version),
+			Some(reader.schema_specifier),
+		)
+		.into_response())
+}
+
+/// Stream rows of a sheet as newline-delimited JSON, reading each row as the
+/// response is written. Streams are not paged, and are instead limited by the
+/// reader's row budget - a stream exceeding it ends with an error line.
+async fn stream_sheet_response(
+	path: SheetPath,
+	query: SheetQuery,
+	config: LimitConfig,
+	reader: RowReader,
+) -> Result<Response> {
+	// Check the sheet can be read before the response begins, so failures are
+	// reported with an appropriate status.
+	let limit = query.limit;
+	let (reader, sheet_name, query, depth) = blocking(move || {
+		let sheet_name = reader.resolve_sheet(&path.sheet)?.into_owned();
+		let depth = reader.depth(config.depth)?;
+		reader_sheet(&reader, &sheet_name)?;
+		validate_pagination(&query)?;
+		Ok((reader, sheet_name, query, depth))
+	})
+	.await?;
+
+	let body = stream::body(move |writer| -> Result<()> {
+		let rows = sheet_rows(&reader, &sheet_name, &query, limit, depth)?;
+		ndjson::write_rows(writer, rows).map_err(|error| Error::Other(error.into()))
+	});
+
+	Ok(ndjson::response(body))
+}
+
+/// Read the rows of a sheet selected by the query. Rows are read lazily as the
+/// iterator is consumed.
+fn sheet_rows<'a>(
+	reader: &'a RowReader,
+	sheet_name: &'a str,
+	query: &'a SheetQuery,
+	limit: Option<usize>,
+	depth: u8,
+) -> Result<impl Iterator<Item = Result<RowResult>> + 'a> {
+	let sheet = reader_sheet(reader, sheet_name)?;
+
+	// Build Results for the targeted rows.
+	let sheet_iterator =
+		sheet_specifiers(&reader.ironworks, sheet, query, limit)?.map(move |specifier| {
+			reader.read_row(sheet_name, specifier.row_id, specifier.subrow_id, depth)
+		});
+
+	Ok(sheet_iterator)
+}
+
+/// Get a reference to a sheet in the reader's language.
+// TODO: should this be in super::error as a default extract? minus the sheet specialised case, that is
+fn reader_sheet<'a>(reader: &RowReader, sheet_name: &'a str) -> Result<excel::Sheet<&'a str>> {
 	let sheet = reader
 		.excel
-		.sheet(&path.sheet)
+		.sheet(sheet_name)
 		.map_err(|error| match error {
 			ironworks::Error::NotFound(ironworks::ErrorValue::Sheet(..)) => {
 				Error::NotFound(error.to_string())
@@ -283,10 +556,47 @@ async fn sheet(
 		})?
 		.with_default_language(reader.language);
 
-	// Iterate over the sheet, building row results.
-	let sheet_iterator = match query.rows {
+	Ok(sheet)
+}
+
+fn validate_pagination(query: &SheetQuery) -> Result<()> {
+	match (query.after, query.before) {
+		(Some(_), Some(_)) => Err(Error::Invalid(
+			"after and before may not be used together".into(),
+		)),
+		_ => Ok(()),
+	}
+}
+
+/// Select the rows of a sheet targeted by the query, without reading them.
+fn sheet_specifiers<'a>(
+	ironworks: &Ironworks,
+	sheet: excel::Sheet<&'a str>,
+	query: &'a SheetQuery,
+	limit: Option<usize>,
+) -> Result<impl Iterator<Item = RowSpecifier> + 'a> {
+	validate_pagination(query)?;
+
+	// Rows preceding a cursor are collected in full, so that they can be
+	// returned in ascending order.
+	if let Some(before) = query.before {
+		let window = match &query.rows {
+			Some(specifiers) => last_specifiers(
+				specifiers
+					.iter()
+					.copied()
+					.filter(|specifier| *specifier < before),
+				limit,
+			),
+			None => sheet_specifiers_before(ironworks, &sheet, before, limit)?,
+		};
+		return Ok(Either::Left(window.into_iter()));
+	}
+
+	// Iterate over the sheet, building row specifiers.
+	let sheet_iterator = match &query.rows {
 		// One or more row specifiers were provided, iterate over those specifically.
-		Some(specifiers) => Either::Left(specifiers.into_iter()),
+		Some(specifiers) => Either::Left(specifiers.iter().copied()),
 
 		// None were provided, iterate over the sheet itself.
 		// TODO: Currently, read:: does _all_ the row fetching itself, which means that we're effectively iterating the sheet here _just_ to get the row IDs, then re-fetching in the read:: code. This... probably isn't too problematic, but worth considering how to approach more betterer. If read:: can be modified to take a row, then the Some() case above can be specailised to the read-row logic and this case can be simplified.
@@ -296,31 +606,344 @@ async fn sheet(
 		})),
 	};
 
-	// Paginate the results.
-	let limit = query.limit.unwrap_or(config.default).min(config.max);
-	let sheet_iterator = sheet_iterator
-		// TODO: Improve this - introducing an explicit "after" method on a sheet iterator would allow skipping a lot of busywork. As-is, this is fetching every single row's data.
-		.skip_while(|specifier| Some(specifier) <= query.after.as_ref())
-		.take(limit);
+	let after = query.after;
+	Ok(Either::Right(
+		sheet_iterator
+			// TODO: Improve this - introducing an explicit "after" method on a sheet iterator would allow skipping a lot of busywork. As-is, this is fetching every single row's data.
+			.skip_while(move |specifier| Some(*specifier) <= after)
+			.take(limit.unwrap_or(usize::MAX)),
+	))
+}
 
-	// Build Results for the targeted rows.
-	let sheet_iterator = sheet_iterator.map(|specifier| {
-		reader.read_row(
-			&path.sheet,
-			specifier.row_id,
-			specifier.subrow_id,
-			config.depth,
+/// Keep the last `limit` specifiers of an iterator, in their original order.
+fn last_specifiers(
+	specifiers: impl Iterator<Item = RowSpecifier>,
+	limit: Option<usize>,
+) -> Vec<RowSpecifier> {
+	let mut window = VecDeque::new();
+	for specifier in specifiers {
+		window.push_back(specifier);
+		if limit.is_some_and(|limit| window.len() > limit) {
+			window.pop_front();
+		}
+	}
+	window.into()
+}
+
+/// Find the rows of a sheet immediately preceding the cursor, in ascending
+/// order. Sheets can only be iterated forwards, so row IDs are instead probed
+/// backwards from the cursor, page by page, until the limit is reached - rows
+/// after the cursor or before the window are never visited.
+fn sheet_specifiers_before(
+	ironworks: &Ironworks,
+	sheet: &excel::Sheet<&str>,
+	before: RowSpecifier,
+	limit: Option<usize>,
+) -> Result<Vec<RowSpecifier>> {
+	let limit = limit.unwrap_or(usize::MAX);
+	let kind = sheet.kind()?;
+
+	let header = sheet_header(ironworks, &sheet.name())?;
+	let mut pages = header
+		.pages()
+		.iter()
+		.map(|page| (page.start_id(), page.row_count()))
+		.collect::<Vec<_>>();
+	pages.sort_unstable();
+
+	let mut window = vec![];
+	'pages: for (start_id, row_count) in pages.into_iter().rev() {
+		let end_id = start_id
+			.saturating_add(row_count)
+			.min(before.row_id.saturating_add(1));
+		for row_id in (start_id..end_id).rev() {
+			let specifiers = row_specifiers(sheet, kind, row_id)?;
+			window.extend(
+				specifiers
+					.into_iter()
+					.rev()
+					.filter(|specifier| *specifier < before),
+			);
+			if window.len() >= limit {
+				window.truncate(limit);
+				break 'pages;
+			}
+		}
+	}
+
+	window.reverse();
+	Ok(window)
+}
+
+/// Specifiers of each subrow of a row, in ascending order. Rows that do not
+/// exist have no specifiers.
+fn row_specifiers(
+	sheet: &excel::Sheet<&str>,
+	kind: exh::SheetKind,
+	row_id: u32,
+) -> Result<Vec<RowSpecifier>> {
+	let specifiers = subrow_ids(sheet, kind, row_id)?
+		.into_iter()
+		.map(|subrow_id| RowSpecifier { row_id, subrow_id })
+		.collect();
+
+	Ok(specifiers)
+}
+
+fn page_limit(query: &SheetQuery, config: &LimitConfig) -> usize {
+	query.limit.unwrap_or(config.default).min(config.max)
+}
+
+/// Pagination details for a page of rows read by the given query, if they were
+/// requested. Rows are counted from the sheet's header, without reading them.
+fn sheet_pagination(
+	ironworks: &Ironworks,
+	sheet_name: &str,
+	query: &SheetQuery,
+	limit: usize,
+) -> Result<Option<Pagination>> {
+	if !query.include_count {
+		return Ok(None);
+	}
+
+	let header = sheet_header(ironworks, sheet_name)?;
+	Ok(Some(Pagination {
+		total_rows: read::header_row_count(&header),
+		limit,
+		after: query.after,
+		before: query.before,
+	}))
+}
+
+fn sheet_header(ironworks: &Ironworks, sheet_name: &str) -> Result<exh::ExcelHeader> {
+	read::sheet_header(ironworks, sheet_name).map_err(raw_error)
+}
+
+/// Query parameters accepted by the coverage endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct CoverageQuery {
+	/// Schema to measure the coverage of.
+	schema: Option<SchemaSpecifier>,
+}
+
+/// Response structure for the coverage endpoint.
+#[derive(Serialize, JsonSchema)]
+struct CoverageResponse {
+	/// The canonical specifier for the schema used in this response.
+	#[schemars(with = "String")]
+	schema: bm_schema::CanonicalSpecifier,
+
+	/// Total number of columns in the sheet.
+	columns: usize,
+
+	/// Number of columns covered by named schema fields.
+	covered: usize,
+
+	/// Number of columns not covered by the schema, which are read as `unknownN`
+	/// fields.
+	unknown: usize,
+
+	/// Percentage of columns covered by named schema fields.
+	percentage: f64,
+}
+
+impl CoverageResponse {
+	fn new(schema: bm_schema::CanonicalSpecifier, coverage: read::Coverage) -> Self {
+		Self {
+			schema,
+			columns: coverage.columns,
+			covered: coverage.covered,
+			unknown: coverage.unknown(),
+			percentage: coverage.percentage(),
+		}
+	}
+}
+
+fn coverage_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("report schema coverage of a sheet")
+		.description(
+			"Count the columns of a sheet that are described by named fields in the schema, versus those that are read as `unknownN` fields.",
 		)
-	});
+		.response_with::<200, Json<CoverageResponse>, _>(|response| {
+			response.example(CoverageResponse::new(
+				bm_schema::CanonicalSpecifier {
+					source: "source".into(),
+					version: "version".into(),
+				},
+				read::Coverage {
+					columns: 8,
+					covered: 6,
+				},
+			))
+		})
+}
 
-	let rows = sheet_iterator.collect::<Result<Vec<_>>>()?;
+#[debug_handler(state = ApiState)]
+async fn coverage(
+	envelope: EnvelopeQuery,
+	VersionQuery(version_key): VersionQuery,
+	Path(path): Path<SheetPath>,
+	Query(query): Query<CoverageQuery>,
+	State(Service {
+		data, read, schema, ..
+	}): State<Service>,
+) -> Result<Json<Envelope<CoverageResponse>>> {
+	let excel = data.version(version_key)?.excel();
+	let (schema_specifier, schema) =
+		schema.resolve(query.schema.map(|wrap| wrap.0), version_key)?;
 
-	let response = SheetResponse {
-		schema: reader.schema_specifier,
-		rows,
+	let sheet_name = read.resolve_sheet(&excel, &path.sheet)?;
+	let coverage = read.coverage(&excel, schema.as_ref(), &sheet_name)?;
+
+	Ok(envelope.wrap(
+		CoverageResponse::new(schema_specifier.clone(), coverage),
+		Some(version_key),
+		Some(schema_specifier),
+	))
+}
+
+/// Query parameters accepted by the raw page endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct RawPageQuery {
+	/// Language of the page to retrieve. Defaults to the configured default
+	/// language, falling back to `none` for sheets without localised data.
+	language: Option<SchemaLanguage>,
+
+	/// Index of the page to retrieve, in the order listed by the sheet header.
+	/// Defaults to the first page.
+	#[serde(default)]
+	page: usize,
+}
+
+fn raw_docs(operation: TransformOperation) -> TransformOperation {
+	operation.response_with::<200, Vec<u8>, _>(|mut response| {
+		let content = &mut response.inner().content;
+		content.clear();
+		content.insert(
+			mime::APPLICATION_OCTET_STREAM.to_string(),
+			openapi::MediaType::default(),
+		);
+		response
+	})
+}
+
+fn raw_page_docs(operation: TransformOperation) -> TransformOperation {
+	raw_docs(operation)
+		.summary("read raw sheet page")
+		.description(
+			"Retrieve the unparsed EXD file for a single page of a sheet. No schema or read logic is applied; this is intended for tooling that parses excel data itself.",
+		)
+}
+
+fn raw_header_docs(operation: TransformOperation) -> TransformOperation {
+	raw_docs(operation)
+		.summary("read raw sheet header")
+		.description(
+			"Retrieve the unparsed EXH file for a sheet. No schema or read logic is applied; this is intended for tooling that parses excel data itself.",
+		)
+}
+
+#[debug_handler(state = ApiState)]
+async fn raw_page(
+	VersionQuery(version_key): VersionQuery,
+	Path(path): Path<SheetPath>,
+	Query(query): Query<RawPageQuery>,
+	State(Service { data, read, .. }): State<Service>,
+) -> Result<impl IntoApiResponse> {
+	let version = data.version(version_key)?;
+	let sheet_name = read
+		.resolve_sheet(&version.excel(), &path.sheet)?
+		.into_owned();
+
+	let ironworks = version.ironworks();
+	let header = sheet_header(&ironworks, &sheet_name)?;
+
+	let page = header.pages().get(query.page).ok_or_else(|| {
+		Error::NotFound(format!(
+			"page {} does not exist in sheet {sheet_name}",
+			query.page
+		))
+	})?;
+
+	let languages = header.languages();
+	let language = select_raw_language(
+		query.language.map(|wrap| excel::Language::from(wrap.0)),
+		read.default_language(),
+		|language| languages.contains(&language),
+	)?;
+
+	let file_path = exd_path(&sheet_name, page.start_id(), language);
+	let bytes = ironworks.file::<Vec<u8>>(&file_path).map_err(raw_error)?;
+
+	Ok(raw_response(&file_path, bytes))
+}
+
+#[debug_handler(state = ApiState)]
+async fn raw_header(
+	VersionQuery(version_key): VersionQuery,
+	Path(path): Path<SheetPath>,
+	State(Service { data, read, .. }): State<Service>,
+) -> Result<impl IntoApiResponse> {
+	let version = data.version(version_key)?;
+	let sheet_name = read.resolve_sheet(&version.excel(), &path.sheet)?;
+
+	let file_path = read::sheet_header_path(&sheet_name);
+	let bytes = version
+		.ironworks()
+		.file::<Vec<u8>>(&file_path)
+		.map_err(raw_error)?;
+
+	Ok(raw_response(&file_path, bytes))
+}
+
+fn raw_error(error: ironworks::Error) -> Error {
+	match error {
+		ironworks::Error::NotFound(..) => Error::NotFound(error.to_string()),
+		other => other.into(),
+	}
+}
+
+fn raw_response(file_path: &str, bytes: Vec<u8>) -> axum::response::Response {
+	let name = file_path.rsplit('/').next().unwrap_or(file_path);
+	(
+		TypedHeader(ContentType::octet_stream()),
+		[(
+			header::CONTENT_DISPOSITION,
+			format!("attachment; filename=\"{name}\""),
+		)],
+		bytes,
+	)
+		.into_response()
+}
+
+fn exd_path(sheet: &str, start_id: u32, language: excel::Language) -> String {
+	match file_language_code(language) {
+		Some(code) => format!("exd/{sheet}_{start_id}_{code}.exd"),
+		None => format!("exd/{sheet}_{start_id}.exd"),
+	}
+}
+
+fn select_raw_language(
+	requested: Option<excel::Language>,
+	default: excel::Language,
+	available: impl Fn(excel::Language) -> bool,
+) -> Result<excel::Language> {
+	// Explicit requests must be honoured exactly - otherwise mimic read fallback behavior.
+	let candidates = match requested {
+		Some(language) => vec![language],
+		None => vec![default, excel::Language::None],
 	};
 
-	Ok(Json(response))
+	candidates
+		.into_iter()
+		.find(|language| available(*language))
+		.ok_or_else(|| {
+			let language = read::LanguageString::from(requested.unwrap_or(default));
+			Error::Invalid(format!(
+				"language {language} is not available for this sheet"
+			))
+		})
 }
 
 /// Path variables accepted by the row endpoint.
@@ -332,15 +955,42 @@ struct RowPath {
 	row: RowSpecifier,
 }
 
+/// Query parameters accepted by the row endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct RowQuery {
+	/// Read every subrow of the requested row, returning them as a map keyed by
+	/// subrow ID. Any subrow ID in the path is ignored.
+	#[serde(default)]
+	flatten_subrows: bool,
+}
+
 /// Response structure for the row endpoint.
 #[derive(Serialize, JsonSchema)]
 struct RowResponse {
-	/// The canonical specifier for the schema used in this response.
+	/// The canonical specifier for the schema used in this response. Rows read
+	/// with an inline schema report `inline@`, followed by the configured schema.
 	#[schemars(with = "String")]
 	schema: bm_schema::CanonicalSpecifier,
 
 	#[serde(flatten)]
-	row: RowResult,
+	row: RowResponseData,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(untagged)]
+enum RowResponseData {
+	Row(RowResult),
+	Subrows(SubrowsResult),
+}
+
+/// All subrows of a single row.
+#[derive(Serialize, JsonSchema)]
+struct SubrowsResult {
+	/// ID of this row.
+	row_id: u32,
+
+	/// Subrows of this row, keyed by subrow ID.
+	subrows: BTreeMap<u16, RowResult>,
 }
 
 fn row_docs(operation: TransformOperation) -> TransformOperation {
@@ -355,26 +1005,804 @@ fn row_docs(operation: TransformOperation) -> TransformOperation {
 					source: "source".into(),
 					version: "version".into(),
 				},
-				row: RowResult::example(1),
+				row: RowResponseData::Row(RowResult::example(1)),
 			})
 		})
 }
 
 #[debug_handler(state = RowsState)]
 async fn row(
+	envelope: EnvelopeQuery,
+	FormatQuery(format): FormatQuery,
 	Path(path): Path<RowPath>,
+	Query(query): Query<RowQuery>,
 	State(config): State<LimitConfig>,
 	reader: RowReader,
-) -> Result<Json<RowResponse>> {
-	let row = reader.read_row(
-		&path.sheet,
-		path.row.row_id,
-		path.row.subrow_id,
-		config.depth,
-	)?;
+) -> Result<Response> {
+	let sheet_name = reader.resolve_sheet(&path.sheet)?.into_owned();
+	blocking(move || {
+		read_row_response(
+			envelope,
+			format,
+			&sheet_name,
+			path.row,
+			query,
+			config,
+			reader,
+		)
+	})
+	.await
+	.map(vary_accept)
+}
 
-	Ok(Json(RowResponse {
-		schema: reader.schema_specifier,
+fn row_inline_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("read a sheet row with an inline schema")
+		.description(
+			"Read a single sheet row using a schema definition provided in the request body, rather than the configured schema. The definition takes the JSON form of an `ironworks_schema` sheet, i.e. `{\"order\": \"Offset\", \"node\": {\"Struct\": [...]}}`. The provided definition is used only for the requested sheet; related sheets continue to use the configured schema. The response schema is reported as `inline@<schema>`, where `<schema>` is the configured schema. This endpoint may be disabled by server configuration.",
+		)
+		.response_with::<200, Json<RowResponse>, _>(|response| {
+			response.example(RowResponse {
+				schema: bm_schema::CanonicalSpecifier {
+					source: "inline".into(),
+					version: "source@version".into(),
+				},
+				row: RowResponseData::Row(RowResult::example(1)),
+			})
+		})
+}
+
+#[debug_handler(state = RowsState)]
+async fn row_inline(
+	envelope: EnvelopeQuery,
+	FormatQuery(format): FormatQuery,
+	Path(path): Path<RowPath>,
+	Query(query): Query<RowQuery>,
+	State(config): State<LimitConfig>,
+	State(inline_config): State<InlineSchemaConfig>,
+	reader: RowReader,
+	JsonBody(definition): JsonBody<InlineSheet>,
+) -> Result<Response> {
+	if !inline_config.enabled() {
+		return Err(Error::Invalid(
+			"inline schemas are not enabled on this server".into(),
+		));
+	}
+
+	let sheet_name = reader.resolve_sheet(&path.sheet)?.into_owned();
+	let reader = reader.with_inline_schema(sheet_name.clone(), definition);
+	blocking(move || {
+		read_row_response(
+			envelope,
+			format,
+			&sheet_name,
+			path.row,
+			query,
+			config,
+			reader,
+		)
+	})
+	.await
+	.map(vary_accept)
+}
+
+fn read_row_response(
+	envelope: EnvelopeQuery,
+	format: OutputFormat,
+	sheet_name: &str,
+	specifier: RowSpecifier,
+	query: RowQuery,
+	config: LimitConfig,
+	reader: RowReader,
+) -> Result<Response> {
+	if format == OutputFormat::Ndjson {
+		return Err(Error::Invalid(
+			"ndjson format is only supported when listing rows in a sheet".into(),
+		));
+	}
+
+	let depth = reader.depth(config.depth)?;
+
+	let row = match query.flatten_subrows {
+		true => RowResponseData::Subrows(SubrowsResult {
+			row_id: specifier.row_id,
+			subrows: reader.read_subrows(sheet_name, specifier.row_id, depth)?,
+		}),
+		false => RowResponseData::Row(reader.read_row(
+			sheet_name,
+			specifier.row_id,
+			specifier.subrow_id,
+			depth,
+		)?),
+	};
+
+	if format == OutputFormat::Csv {
+		let rows = match row {
+			RowResponseData::Row(row) => vec![row],
+			RowResponseData::Subrows(subrows) => subrows.subrows.into_values().collect(),
+		};
+		return csv::response(&rows);
+	}
+
+	let response = RowResponse {
+		schema: reader.schema_specifier.clone(),
 		row,
-	}))
+	};
+
+	Ok(envelope
+		.wrap(
+			response,
+			Some(reader.version),
+			Some(reader.schema_specifier),
+		)
+		.into_response())
+}
+
+/// Query parameters accepted by the row history endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct RowHistoryQuery {
+	/// Version to read the row history from. Versions are ordered by the time
+	/// they were ingested by the API.
+	from: String,
+
+	/// Version to read the row history up to, inclusive.
+	to: String,
+}
+
+/// Response structure for the row history endpoint.
+#[derive(Serialize, JsonSchema)]
+struct RowHistoryResponse {
+	/// The row as read in each version within the requested range, ordered from
+	/// earliest to latest.
+	versions: Vec<RowHistoryEntry>,
+}
+
+/// A row as read at a single version.
+#[derive(Serialize, JsonSchema)]
+struct RowHistoryEntry {
+	/// Key of the version this row was read from.
+	#[schemars(with = "String")]
+	version: VersionKey,
+
+	/// The canonical specifier for the schema used to read this version.
+	#[schemars(with = "String")]
+	schema: bm_schema::CanonicalSpecifier,
+
+	/// Field values for the row in this version. `null` if the row (or sheet)
+	/// does not exist in this version.
+	fields: Option<ValueString>,
+
+	/// Field values for the row's transient row in this version, if any is
+	/// present.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	transient: Option<ValueString>,
+}
+
+fn row_history_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("read a row across versions")
+		.description(
+			"Read a single sheet row in every version between two versions, inclusive. Versions are ordered by the time they were ingested, and both bounds must have a known ingestion time. The number of versions in the range is limited by server configuration. The `version` parameter is ignored.",
+		)
+		.response_with::<200, Json<RowHistoryResponse>, _>(|response| {
+			response.example(RowHistoryResponse {
+				versions: vec![RowHistoryEntry {
+					version: "b2f6bd0e8c5dc9a1".parse().expect("static"),
+					schema: bm_schema::CanonicalSpecifier {
+						source: "source".into(),
+						version: "version".into(),
+					},
+					fields: Some(RowResult::example(1).fields),
+					transient: None,
+				}],
+			})
+		})
+}
+
+#[debug_handler(state = RowsState)]
+async fn row_history(
+	envelope: EnvelopeQuery,
+	Path(path): Path<RowPath>,
+	Query(query): Query<RowHistoryQuery>,
+	State(config): State<LimitConfig>,
+	State(Service { version, .. }): State<Service>,
+	reader: RowReader,
+) -> Result<impl IntoApiResponse> {
+	let (from, to) = (
+		resolve_version(&version, &query.from)?,
+		resolve_version(&version, &query.to)?,
+	);
+
+	// Versions ingested before ingestion times were recorded cannot be ordered.
+	for key in [from, to] {
+		if version
+			.version(key)
+			.is_some_and(|info| info.ingest_time.is_none())
+		{
+			return Err(Error::Invalid(format!(
+				"version {key} has no known ingestion time, and cannot bound a history range"
+			)));
+		}
+	}
+
+	let keys = version
+		.range(from, to)
+		.ok_or_else(|| Error::NotFound("version range is no longer available".into()))?;
+
+	if keys.len() > config.history {
+		return Err(Error::Invalid(format!(
+			"version range contains {} versions, maximum is {}",
+			keys.len(),
+			config.history
+		)));
+	}
+
+	blocking(move || {
+		let versions = keys
+			.into_iter()
+			.map(|version_key| read_history_entry(&reader, version_key, &path, &config))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(envelope
+			.wrap(RowHistoryResponse { versions }, None, None)
+			.into_response())
+	})
+	.await
+}
+
+fn read_history_entry(
+	reader: &RowReader,
+	version_key: VersionKey,
+	path: &RowPath,
+	config: &LimitConfig,
+) -> Result<RowHistoryEntry> {
+	let reader = reader.with_version(version_key)?;
+	let depth = reader.depth(config.depth)?;
+
+	// Rows (and their sheets) may not exist across the entire range.
+	let row = reader.resolve_sheet(&path.sheet).and_then(|sheet_name| {
+		reader.read_row(&sheet_name, path.row.row_id, path.row.subrow_id, depth)
+	});
+	let (fields, transient) = match row {
+		Ok(row) => (Some(row.fields), row.transient),
+		Err(Error::NotFound(_)) => (None, None),
+		Err(error) => return Err(error),
+	};
+
+	Ok(RowHistoryEntry {
+		version: version_key,
+		schema: reader.schema_specifier,
+		fields,
+		transient,
+	})
+}
+
+/// Resolve a version name provided by a request parameter.
+fn resolve_version(version: &service::Version, name: &str) -> Result<VersionKey> {
+	version
+		.resolve(Some(name))
+		.ok_or_else(|| Error::NotFound(format!("unknown version \"{name}\"")))
+}
+
+/// Query parameters accepted by the row diff endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct RowDiffQuery {
+	/// Version to compare from.
+	base: String,
+
+	/// Version to compare to.
+	target: String,
+}
+
+/// Response structure for the row diff endpoint.
+#[derive(Serialize, JsonSchema)]
+struct RowDiffResponse {
+	/// Key of the version compared from.
+	#[schemars(with = "String")]
+	base: VersionKey,
+
+	/// Key of the version compared to.
+	#[schemars(with = "String")]
+	target: VersionKey,
+
+	/// Changes to the row's fields between the base and target versions. Fields
+	/// are located by their path, i.e. `ClassJob.Name` or `Item[0]`.
+	changes: Vec<RowDiffChange>,
+
+	/// Changes to the fields of the row's transient row, located as for
+	/// `changes`. Omitted when the transient row is unchanged.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	transient: Vec<RowDiffChange>,
+}
+
+/// A single change to a row's fields.
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum RowDiffChange {
+	/// A field present in the target version, but not the base.
+	Added { path: String, value: ValueString },
+
+	/// A field present in the base version, but not the target.
+	Removed { path: String, value: ValueString },
+
+	/// A field present in both versions with differing values. Fields that have
+	/// changed shape between versions are reported as a single change.
+	Changed {
+		path: String,
+		before: ValueString,
+		after: ValueString,
+	},
+}
+
+fn row_diff_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("compare a row between versions")
+		.description(
+			"Read a single sheet row in two versions, and report the changes to its fields between them. Rows that do not exist in a version are treated as having no fields. Changes to transient rows are reported separately. The `version` parameter is ignored.",
+		)
+		.response_with::<200, Json<RowDiffResponse>, _>(|response| {
+			response.example(RowDiffResponse {
+				base: "94b31fd6b0b39a75".parse().expect("static"),
+				target: "b2f6bd0e8c5dc9a1".parse().expect("static"),
+				changes: vec![RowDiffChange::Added {
+					path: "FieldName".into(),
+					value: RowResult::example(1).fields,
+				}],
+				transient: vec![],
+			})
+		})
+}
+
+#[debug_handler(state = RowsState)]
+async fn row_diff(
+	envelope: EnvelopeQuery,
+	Path(path): Path<RowPath>,
+	Query(query): Query<RowDiffQuery>,
+	State(config): State<LimitConfig>,
+	State(Service { version, .. }): State<Service>,
+	reader: RowReader,
+) -> Result<impl IntoApiResponse> {
+	let base = resolve_version(&version, &query.base)?;
+	let target = resolve_version(&version, &query.target)?;
+
+	blocking(move || {
+		let base_entry = read_history_entry(&reader, base, &path, &config)?;
+		let target_entry = read_history_entry(&reader, target, &path, &config)?;
+
+		let response = RowDiffResponse {
+			base,
+			target,
+			changes: diff_fields(base_entry.fields, target_entry.fields),
+			transient: diff_fields(base_entry.transient, target_entry.transient),
+		};
+
+		Ok(envelope.wrap(response, None, None).into_response())
+	})
+	.await
+}
+
+/// Diff the fields read for a row in two versions. Missing fields are treated
+/// as an empty struct.
+fn diff_fields(
+	mut base: Option<ValueString>,
+	mut target: Option<ValueString>,
+) -> Vec<RowDiffChange> {
+	// The remainder of the fields are retained to format changed values.
+	let take_value = |fields: &mut Option<ValueString>| match fields {
+		Some(fields) => std::mem::replace(&mut fields.0, read::Value::Null),
+		None => read::Value::Struct(Default::default()),
+	};
+	let (base_value, target_value) = (take_value(&mut base), take_value(&mut target));
+
+	// Changed values are formatted as per the version they were read from.
+	let wrap = |fields: &Option<ValueString>, value| {
+		let ValueString(_, language, input, icons, case) = fields
+			.as_ref()
+			.expect("changed values should be read from existing fields");
+		ValueString(value, *language, input.clone(), icons.clone(), *case)
+	};
+
+	read::diff(base_value, target_value)
+		.into_iter()
+		.map(|change| match change {
+			read::Change::Added { path, value } => RowDiffChange::Added {
+				path,
+				value: wrap(&target, value),
+			},
+			read::Change::Removed { path, value } => RowDiffChange::Removed {
+				path,
+				value: wrap(&base, value),
+			},
+			read::Change::Changed {
+				path,
+				before,
+				after,
+			} => RowDiffChange::Changed {
+				path,
+				before: wrap(&base, before),
+				after: wrap(&target, after),
+			},
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use ironworks::sestring::format::Input;
+	use pretty_assertions::assert_eq;
+	use serde_json::json;
+
+	use crate::api1::{read::RowBudget, value::FieldCase};
+
+	use super::*;
+
+	#[test]
+	fn languages_excluded() {
+		let got = sheet_languages(
+			[
+				excel::Language::Japanese,
+				excel::Language::English,
+				excel::Language::Korean,
+			],
+			|language| language != excel::Language::Korean,
+		);
+		assert_eq!(got, vec!["ja", "en"]);
+	}
+
+	#[test]
+	fn unreadable_sheets_omitted() {
+		let got = sheet_metadata(
+			vec!["Action".into(), "Broken".into(), "Item".into()],
+			|name| match name {
+				"Broken" => Err(Error::NotFound("missing header".into())),
+				_ => Ok(vec![excel::Language::English]),
+			},
+			|_| true,
+		);
+
+		let names = got
+			.iter()
+			.map(|sheet| sheet.name.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(names, vec!["Action", "Item"]);
+		assert_eq!(got[0].languages, vec!["en"]);
+	}
+
+	#[test]
+	fn raw_paths() {
+		assert_eq!(read::sheet_header_path("Item"), "exd/Item.exh");
+		assert_eq!(
+			exd_path("Item", 500, excel::Language::English),
+			"exd/Item_500_en.exd"
+		);
+		assert_eq!(
+			exd_path("Item", 0, excel::Language::Korean),
+			"exd/Item_0_ko.exd"
+		);
+		assert_eq!(
+			exd_path("Level", 0, excel::Language::None),
+			"exd/Level_0.exd"
+		);
+	}
+
+	#[test]
+	fn raw_page_parses() {
+		use exh::ColumnKind as CK;
+		use read::fixture::{scalar, struct_node, Cell, Fixture, TestSheet};
+
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Item", [(CK::UInt32, 0)])
+				.row(10, [Cell::U32(1)])
+				.row(11, [Cell::U32(2)]),
+			struct_node([("Value", scalar())]),
+		)]);
+
+		let header = sheet_header(&fixture.ironworks, "Item").expect("header should parse");
+		let page = &header.pages()[0];
+		let languages = header.languages();
+		let language = select_raw_language(None, excel::Language::English, |language| {
+			languages.contains(&language)
+		})
+		.unwrap();
+
+		let path = exd_path("Item", page.start_id(), language);
+		assert_eq!(path, "exd/Item_10.exd");
+		let bytes = fixture
+			.ironworks
+			.file::<Vec<u8>>(&path)
+			.expect("page should exist");
+		assert!(bytes.starts_with(b"EXDF"));
+		fixture
+			.ironworks
+			.file::<ironworks::file::exd::ExcelData>(&path)
+			.expect("page should parse");
+	}
+
+	#[test]
+	fn raw_language_fallback() {
+		let unlocalised = |language| language == excel::Language::None;
+		let got = select_raw_language(None, excel::Language::English, unlocalised).unwrap();
+		assert_eq!(got, excel::Language::None);
+
+		let got = select_raw_language(
+			Some(excel::Language::English),
+			excel::Language::English,
+			unlocalised,
+		);
+		assert!(matches!(got, Err(Error::Invalid(..))));
+	}
+
+	#[test]
+	fn coverage_response() {
+		let response = CoverageResponse::new(
+			bm_schema::CanonicalSpecifier {
+				source: "source".into(),
+				version: "version".into(),
+			},
+			read::Coverage {
+				columns: 8,
+				covered: 6,
+			},
+		);
+
+		let got = serde_json::to_value(response).unwrap();
+		assert_eq!(got["unknown"], json!(2));
+		assert_eq!(got["percentage"], json!(75.0));
+	}
+
+	#[test]
+	fn serialize_flattened_subrows() {
+		let response = RowResponse {
+			schema: bm_schema::CanonicalSpecifier {
+				source: "source".into(),
+				version: "version".into(),
+			},
+			row: RowResponseData::Subrows(SubrowsResult {
+				row_id: 1,
+				subrows: BTreeMap::from([(0, RowResult::example(1)), (1, RowResult::example(1))]),
+			}),
+		};
+
+		let got = serde_json::to_value(response).unwrap();
+		let subrows = got["subrows"].as_object().expect("subrows should be a map");
+		assert_eq!(subrows.keys().collect::<Vec<_>>(), vec!["0", "1"]);
+		assert_eq!(got["row_id"], json!(1));
+		assert!(subrows["1"]["fields"].is_object());
+	}
+
+	#[test]
+	fn diff_missing_fields() {
+		let fields = |values: &[(&str, u32)]| {
+			Some(ValueString(
+				read::Value::Struct(
+					values
+						.iter()
+						.map(|(name, value)| {
+							(
+								name.to_string(),
+								read::Value::Scalar(excel::Field::U32(*value)),
+							)
+						})
+						.collect(),
+				),
+				excel::Language::English,
+				Input::new().into(),
+				Default::default(),
+				FieldCase::Original,
+			))
+		};
+		let changes = |base, target| {
+			serde_json::to_value(diff_fields(base, target)).expect("changes should serialize")
+		};
+
+		// Rows missing from a version, such as an absent transient row, diff as
+		// though they had no fields.
+		assert_eq!(
+			changes(None, fields(&[("Value", 1)])),
+			json!([{"kind": "added", "path": "Value", "value": 1}])
+		);
+		assert_eq!(
+			changes(fields(&[("Value", 1)]), None),
+			json!([{"kind": "removed", "path": "Value", "value": 1}])
+		);
+		assert_eq!(
+			changes(fields(&[("Value", 1)]), fields(&[("Value", 2)])),
+			json!([{"kind": "changed", "path": "Value", "before": 1, "after": 2}])
+		);
+		assert_eq!(changes(None, None), json!([]));
+	}
+
+	fn pagination_fixture() -> read::fixture::Fixture {
+		use exh::ColumnKind as CK;
+		use read::fixture::{scalar, struct_node, Cell, Fixture, TestSheet};
+
+		Fixture::new(vec![
+			(
+				[1, 2, 3, 5, 8].into_iter().fold(
+					TestSheet::new("Item", [(CK::UInt32, 0)]),
+					|sheet, row_id| sheet.row(row_id, [Cell::U32(row_id)]),
+				),
+				struct_node([("Value", scalar())]),
+			),
+			(
+				TestSheet::new("Quest", [(CK::UInt32, 0)])
+					.subrows(1, vec![vec![Cell::U32(10)], vec![Cell::U32(11)]])
+					.subrows(3, vec![vec![Cell::U32(30)], vec![Cell::U32(31)]]),
+				struct_node([("Value", scalar())]),
+			),
+		])
+	}
+
+	fn paginate(
+		fixture: &read::fixture::Fixture,
+		sheet: &str,
+		query: serde_json::Value,
+		limit: Option<usize>,
+	) -> Result<Vec<String>> {
+		let query: SheetQuery = serde_json::from_value(query).unwrap();
+		let sheet = fixture.excel.sheet(sheet).expect("sheet should exist");
+		let specifiers = sheet_specifiers(&fixture.ironworks, sheet, &query, limit)?
+			.map(|specifier| specifier.to_string())
+			.collect();
+		Ok(specifiers)
+	}
+
+	#[test]
+	fn pagination_counts_rows() {
+		use exh::ColumnKind as CK;
+		use read::fixture::{scalar, struct_node, Cell, Fixture, TestSheet};
+
+		// Page row counts span every ID in the page, keep IDs contiguous.
+		let fixture = Fixture::new(vec![
+			(
+				(1..=5).fold(
+					TestSheet::new("Item", [(CK::UInt32, 0)]),
+					|sheet, row_id| sheet.row(row_id, [Cell::U32(row_id)]),
+				),
+				struct_node([("Value", scalar())]),
+			),
+			(
+				TestSheet::new("Quest", [(CK::UInt32, 0)])
+					.subrows(1, vec![vec![Cell::U32(10)], vec![Cell::U32(11)]])
+					.subrows(2, vec![vec![Cell::U32(20)], vec![Cell::U32(21)]]),
+				struct_node([("Value", scalar())]),
+			),
+		]);
+		let pagination = |sheet, query| {
+			let query: SheetQuery = serde_json::from_value(query).unwrap();
+			sheet_pagination(&fixture.ironworks, sheet, &query, 2)
+				.expect("pagination should not fail")
+				.map(|pagination| serde_json::to_value(pagination).unwrap())
+		};
+
+		assert_eq!(pagination("Item", json!({})), None);
+		assert_eq!(
+			pagination("Item", json!({ "include_count": true, "after": "2" })),
+			Some(json!({ "total_rows": 5, "limit": 2, "after": "2:0" }))
+		);
+
+		// Sheets with subrows count their rows, not subrows.
+		assert_eq!(
+			pagination("Quest", json!({ "include_count": true, "before": "2:1" })),
+			Some(json!({ "total_rows": 2, "limit": 2, "before": "2:1" }))
+		);
+	}
+
+	#[test]
+	fn before_reads_preceding_window() {
+		let fixture = pagination_fixture();
+		let page = |before, limit| {
+			paginate(&fixture, "Item", json!({ "before": before }), limit)
+				.expect("pagination should not fail")
+		};
+
+		assert_eq!(page("8", Some(2)), vec!["3:0", "5:0"]);
+		assert_eq!(page("5", Some(10)), vec!["1:0", "2:0", "3:0"]);
+		assert_eq!(page("4", Some(1)), vec!["3:0"]);
+		assert_eq!(page("1", Some(10)), Vec::<String>::new());
+		assert_eq!(page("100", None), vec!["1:0", "2:0", "3:0", "5:0", "8:0"]);
+	}
+
+	#[test]
+	fn before_reads_preceding_subrows() {
+		let fixture = pagination_fixture();
+		let got = paginate(&fixture, "Quest", json!({ "before": "3:1" }), Some(2))
+			.expect("pagination should not fail");
+		assert_eq!(got, vec!["1:1", "3:0"]);
+	}
+
+	#[test]
+	fn before_filters_unsorted_rows() {
+		let fixture = pagination_fixture();
+		let got = paginate(
+			&fixture,
+			"Item",
+			json!({ "rows": "8,1,5,3", "before": "5" }),
+			Some(10),
+		)
+		.expect("pagination should not fail");
+		assert_eq!(got, vec!["1:0", "3:0"]);
+	}
+
+	#[test]
+	fn after_and_before_rejected() {
+		let fixture = pagination_fixture();
+		let got = paginate(
+			&fixture,
+			"Item",
+			json!({ "after": "1", "before": "5" }),
+			Some(10),
+		);
+		assert!(matches!(got, Err(Error::Invalid(_))));
+	}
+
+	#[test]
+	fn stream_truncated_by_export_budget() {
+		use exh::ColumnKind as CK;
+		use read::fixture::{scalar, struct_node, Cell, Fixture, TestSheet};
+
+		let fixture = Fixture::new(vec![(
+			(1..=5).fold(
+				TestSheet::new("Item", [(CK::UInt32, 0)]),
+				|sheet, row_id| sheet.row(row_id, [Cell::U32(row_id * 10)]),
+			),
+			struct_node([("Value", scalar())]),
+		)]);
+		let config = serde_json::from_value::<read::Config>(json!({
+			"language": {"default": "en", "exclude": []},
+		}))
+		.expect("config should deserialize");
+		let read = read::Read::new(config);
+
+		// Streams are not limited by the page size, only by the budget.
+		let query: SheetQuery = serde_json::from_value(json!({})).unwrap();
+		let budget = RowBudget::new(Some(3));
+		let sheet = fixture.excel.sheet("Item").expect("sheet should exist");
+		let rows = sheet_specifiers(&fixture.ironworks, sheet, &query, query.limit)
+			.expect("rows should be selected")
+			.map(|specifier| {
+				budget.track_with(|| {
+					let value = read.read(
+						&fixture.excel,
+						&fixture.schema,
+						"Item",
+						specifier.row_id,
+						specifier.subrow_id,
+						excel::Language::English,
+						&read::Filter::All,
+						0,
+						&read::ReadOptions::default(),
+						&read::ReadCache::default(),
+					)?;
+					Ok(RowResult {
+						row_id: specifier.row_id,
+						subrow_id: None,
+						fields: ValueString(
+							value,
+							excel::Language::English,
+							Input::new().into(),
+							Default::default(),
+							FieldCase::Original,
+						),
+						transient: None,
+					})
+				})
+			});
+
+		let mut output = vec![];
+		ndjson::write_rows(&mut output, rows).expect("write should not fail");
+
+		let lines = String::from_utf8(output)
+			.unwrap()
+			.lines()
+			.map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(lines.len(), 4);
+		let row_ids = lines[..3]
+			.iter()
+			.map(|line| line["row_id"].clone())
+			.collect::<Vec<_>>();
+		assert_eq!(row_ids, vec![json!(1), json!(2), json!(3)]);
+		assert_eq!(lines[3]["error"]["code"], json!(400));
+	}
 }
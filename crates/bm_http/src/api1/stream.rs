@@ -0,0 +1,92 @@
+use std::{
+	fmt::Display,
+	io::{self, BufWriter, Write},
+};
+
+use axum::body::Body;
+use tokio::sync::mpsc;
+
+// Size of chunks sent to the response body, and the number of chunks that may
+// be buffered before the writer waits for the client to catch up.
+const CHUNK_SIZE: usize = 64 * 1024;
+const CHUNK_BUFFER: usize = 4;
+
+type Chunk = Result<Vec<u8>, io::Error>;
+
+/// Run a blocking write on a dedicated thread, streaming the output to the
+/// response body as it is written. Any failure after the response has begun
+/// aborts the body, rather than completing it with truncated data.
+pub fn body<E>(write: impl FnOnce(&mut dyn Write) -> Result<(), E> + Send + 'static) -> Body
+where
+	E: Display,
+{
+	let (sender, mut receiver) = mpsc::channel::<Chunk>(CHUNK_BUFFER);
+
+	let error_sender = sender.clone();
+	tokio::task::spawn_blocking(move || {
+		let mut writer = BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter(sender));
+		let result = write(&mut writer)
+			.map_err(|error| error.to_string())
+			.and_then(|()| writer.flush().map_err(|error| error.to_string()));
+
+		if let Err(error) = result {
+			// A closed channel means the client went away - there's nobody to report to.
+			if error_sender.is_closed() {
+				return;
+			}
+			tracing::warn!(%error, "streamed response failed");
+			let _ = error_sender.blocking_send(Err(io::Error::other(error)));
+		}
+	});
+
+	Body::from_stream(futures::stream::poll_fn(move |context| {
+		receiver.poll_recv(context)
+	}))
+}
+
+struct ChannelWriter(mpsc::Sender<Chunk>);
+
+impl Write for ChannelWriter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0
+			.blocking_send(Ok(buf.to_vec()))
+			.map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use axum::body::to_bytes;
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn streams_written_output() {
+		let body = body(|writer| {
+			for index in 0..CHUNK_SIZE {
+				writer.write_all(&[(index % 256) as u8])?;
+			}
+			writer.write_all(b"end")
+		});
+
+		let bytes = to_bytes(body, usize::MAX).await.unwrap();
+		assert_eq!(bytes.len(), CHUNK_SIZE + 3);
+		assert_eq!(&bytes[CHUNK_SIZE..], b"end");
+	}
+
+	#[tokio::test]
+	async fn write_failure_aborts_body() {
+		let body = body(|writer| {
+			writer.write_all(b"partial")?;
+			Err(io::Error::other("encode failed"))
+		});
+
+		assert!(to_bytes(body, usize::MAX).await.is_err());
+	}
+}
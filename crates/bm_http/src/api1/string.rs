@@ -5,7 +5,7 @@ use anyhow::Context;
 use ironworks::{
 	excel::Excel,
 	sestring::{
-		format::{format, Color, ColorUsage, Input, Write},
+		format::{format, Color, ColorUsage, Input, Style, Write},
 		Error as SeStringError, SeString,
 	},
 };
@@ -67,10 +67,7 @@ impl Write for HtmlWriter {
 			return Ok(());
 		}
 
-		let Color { r, g, b, a } = color;
-		let a = f32::from(a) / 255.;
-		self.buffer
-			.push_str(&format!(r#"<span style="color:rgba({r},{g},{b},{a});">"#));
+		self.buffer.push_str(&color_span(color));
 
 		Ok(())
 	}
@@ -85,3 +82,152 @@ impl Write for HtmlWriter {
 		Ok(())
 	}
 }
+
+pub fn as_markdown(string: SeString, input: &Input) -> Result<String, SeStringError> {
+	let mut writer = MarkdownWriter::default();
+	format(string, input, &mut writer)?;
+	Ok(writer.buffer)
+}
+
+#[derive(Debug, Default)]
+struct MarkdownWriter {
+	buffer: String,
+}
+
+impl Write for MarkdownWriter {
+	fn write_str(&mut self, str: &str) -> Result<(), SeStringError> {
+		for char in str.chars() {
+			match char {
+				// CommonMark hard line break.
+				'\n' => self.buffer.push_str("\\\n"),
+				'\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '~' | '|' | '&' => {
+					self.buffer.push('\\');
+					self.buffer.push(char);
+				}
+				other => self.buffer.push(other),
+			}
+		}
+
+		Ok(())
+	}
+
+	fn set_style(&mut self, style: Style, _enabled: bool) -> Result<(), SeStringError> {
+		// Emphasis delimiters are symmetrical, so enabling and disabling a style
+		// both emit the same marker.
+		let marker = match style {
+			Style::Bold => "**",
+			Style::Italic => "*",
+			_ => return Ok(()),
+		};
+
+		self.buffer.push_str(marker);
+
+		Ok(())
+	}
+
+	// CommonMark has no syntax for color - inline HTML is permitted, however, and
+	// used in the same manner as the HTML output.
+
+	fn push_color(&mut self, usage: ColorUsage, color: Color) -> Result<(), SeStringError> {
+		if usage != ColorUsage::Foreground {
+			return Ok(());
+		}
+
+		self.buffer.push_str(&color_span(color));
+
+		Ok(())
+	}
+
+	fn pop_color(&mut self, usage: ColorUsage) -> Result<(), SeStringError> {
+		if usage != ColorUsage::Foreground {
+			return Ok(());
+		}
+
+		self.buffer.push_str("</span>");
+
+		Ok(())
+	}
+}
+
+pub fn as_plain(string: SeString, input: &Input) -> Result<String, SeStringError> {
+	let mut writer = PlainWriter::default();
+	format(string, input, &mut writer)?;
+	Ok(writer.buffer)
+}
+
+/// Writer retaining only text. Payloads that do not resolve to text, such as
+/// icons and formatting, are dropped rather than represented in the output.
+#[derive(Debug, Default)]
+struct PlainWriter {
+	buffer: String,
+}
+
+impl Write for PlainWriter {
+	fn write_str(&mut self, str: &str) -> Result<(), SeStringError> {
+		self.buffer.push_str(str);
+		Ok(())
+	}
+}
+
+fn color_span(color: Color) -> String {
+	let Color { r, g, b, a } = color;
+	let a = f32::from(a) / 255.;
+	format!(r#"<span style="color:rgba({r},{g},{b},{a});">"#)
+}
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	fn plain(bytes: &[u8]) -> String {
+		as_plain(SeString::new(bytes), &Input::new()).expect("format should not fail")
+	}
+
+	#[test]
+	fn plain_drops_icon() {
+		// Icon macro (0x12) with an ID of 5. Integers, including the payload
+		// length, are encoded offset by one.
+		let got = plain(b"Gil\x02\x12\x02\x06\x03: 100");
+		assert_eq!(got, "Gil: 100");
+	}
+
+	#[test]
+	fn plain_drops_auto_translate() {
+		// Auto-translate macro (0x2E) referencing group 1, key 4.
+		let got = plain(b"Say \x02\x2E\x03\x02\x05\x03 please");
+		assert!(got.starts_with("Say ") && got.ends_with(" please"));
+		assert!(!got.contains(['\x02', '\x03', '<']));
+	}
+
+	#[test]
+	fn markdown_escapes_and_breaks() {
+		let mut writer = MarkdownWriter::default();
+		writer.write_str("*Not* bold\n[link]").unwrap();
+		assert_eq!(writer.buffer, "\\*Not\\* bold\\\n\\[link\\]");
+	}
+
+	#[test]
+	fn markdown_styles_and_colors() {
+		let mut writer = MarkdownWriter::default();
+		writer.set_style(Style::Bold, true).unwrap();
+		writer.write_str("Bold").unwrap();
+		writer.set_style(Style::Bold, false).unwrap();
+		writer.write_str(" ").unwrap();
+		let color = Color {
+			r: 255,
+			g: 0,
+			b: 0,
+			a: 255,
+		};
+		writer.push_color(ColorUsage::Foreground, color).unwrap();
+		writer.write_str("Red").unwrap();
+		writer.pop_color(ColorUsage::Foreground).unwrap();
+
+		assert_eq!(
+			writer.buffer,
+			r#"**Bold** <span style="color:rgba(255,0,0,1);">Red</span>"#
+		);
+	}
+}
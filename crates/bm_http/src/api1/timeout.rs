@@ -0,0 +1,182 @@
+use std::{
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use axum::{
+	extract::{Request, State},
+	http::request::Parts,
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+
+use super::error::{Error, Result};
+
+/// Cancellation state of a request subject to a timeout. Blocking work checks
+/// it between units of work, such as rows, and stops once the request has
+/// timed out. Requests without a timeout are never cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Option<(Duration, Arc<AtomicBool>)>);
+
+impl Cancellation {
+	fn new(timeout: Duration) -> Self {
+		Self(Some((timeout, Default::default())))
+	}
+
+	/// Get the cancellation state for the request the given parts belong to.
+	pub fn from_parts(parts: &Parts) -> Self {
+		parts.extensions.get::<Self>().cloned().unwrap_or_default()
+	}
+
+	fn cancel(&self) {
+		if let Some((_, cancelled)) = &self.0 {
+			cancelled.store(true, Ordering::Relaxed);
+		}
+	}
+
+	/// Fail if the request has been cancelled.
+	pub fn check(&self) -> Result<()> {
+		match &self.0 {
+			Some((timeout, cancelled)) if cancelled.load(Ordering::Relaxed) => {
+				Err(Error::Timeout(*timeout))
+			}
+			_ => Ok(()),
+		}
+	}
+}
+
+/// Middleware responding with an error to requests that take longer than the
+/// configured timeout, if any.
+///
+/// Timed out requests are cancelled - work running on a blocking thread, such
+/// as a sheet read, stops at the next row it reads. A single row read or search
+/// query already in progress will run to completion, though its result is
+/// discarded.
+pub async fn timeout_layer(
+	State(timeout): State<Option<Duration>>,
+	mut request: Request,
+	next: Next,
+) -> Response {
+	let Some(timeout) = timeout else {
+		return next.run(request).await;
+	};
+
+	let cancellation = Cancellation::new(timeout);
+	request.extensions_mut().insert(cancellation.clone());
+
+	match tokio::time::timeout(timeout, next.run(request)).await {
+		Ok(response) => response,
+		Err(_elapsed) => {
+			cancellation.cancel();
+			Error::Timeout(timeout).into_response()
+		}
+	}
+}
+
+/// Run blocking read work on a dedicated thread. Reads do not yield to the
+/// runtime, so running them inline would prevent the timeout from triggering
+/// until the read has completed. Work should check the request's
+/// [`Cancellation`] to stop once the request has timed out.
+pub async fn blocking<T>(function: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T>
+where
+	T: Send + 'static,
+{
+	tokio::task::spawn_blocking(function)
+		.await
+		.map_err(|error| Error::Other(error.into()))?
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::atomic::AtomicUsize;
+
+	use axum::{
+		body::{to_bytes, Body},
+		http::StatusCode,
+		middleware,
+		routing::get,
+		Extension, Router,
+	};
+	use pretty_assertions::assert_eq;
+	use tower::ServiceExt;
+
+	use super::*;
+
+	const ROWS: usize = 50;
+
+	async fn slow_read(
+		State(rows): State<Arc<AtomicUsize>>,
+		cancellation: Option<Extension<Cancellation>>,
+	) -> Result<&'static str> {
+		let cancellation = cancellation
+			.map(|Extension(cancellation)| cancellation)
+			.unwrap_or_default();
+
+		blocking(move || {
+			for _ in 0..ROWS {
+				cancellation.check()?;
+				std::thread::sleep(Duration::from_millis(10));
+				rows.fetch_add(1, Ordering::Relaxed);
+			}
+			Ok("done")
+		})
+		.await
+	}
+
+	async fn request(timeout: Option<Duration>) -> (Response, Arc<AtomicUsize>) {
+		let rows = Arc::new(AtomicUsize::new(0));
+		let router = Router::new()
+			.route("/", get(slow_read).with_state(rows.clone()))
+			.layer(middleware::from_fn_with_state(timeout, timeout_layer));
+
+		let response = router
+			.oneshot(Request::get("/").body(Body::empty()).unwrap())
+			.await
+			.unwrap();
+
+		(response, rows)
+	}
+
+	#[tokio::test]
+	async fn slow_read_aborted() {
+		let start = std::time::Instant::now();
+		let (response, rows) = request(Some(Duration::from_millis(20))).await;
+
+		assert!(start.elapsed() < Duration::from_millis(500));
+		assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+		let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+		let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+		assert_eq!(body["code"], 504);
+
+		// The read stops at the next row after the timeout, rather than running
+		// to completion in the background.
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		let stopped = rows.load(Ordering::Relaxed);
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		assert_eq!(rows.load(Ordering::Relaxed), stopped);
+		assert!(stopped < ROWS);
+	}
+
+	#[tokio::test]
+	async fn no_timeout() {
+		let (response, rows) = request(None).await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(rows.load(Ordering::Relaxed), ROWS);
+	}
+
+	#[test]
+	fn cancellation_check() {
+		let cancellation = Cancellation::new(Duration::from_millis(20));
+		assert!(cancellation.check().is_ok());
+		cancellation.cancel();
+		assert!(matches!(
+			cancellation.check(),
+			Err(Error::Timeout(timeout)) if timeout == Duration::from_millis(20)
+		));
+		assert!(Cancellation::default().check().is_ok());
+	}
+}
@@ -0,0 +1,150 @@
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use axum::{
+	extract::{Request, State},
+	http::{request::Parts, HeaderName, HeaderValue},
+	middleware::Next,
+	response::Response,
+};
+use serde::Deserialize;
+
+const SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TimingConfig {
+	enabled: bool,
+}
+
+/// Durations recorded over the course of a request. When timing is disabled,
+/// recording is a no-op.
+#[derive(Debug, Clone, Default)]
+pub struct Timings(Option<Arc<Mutex<Vec<(&'static str, Duration)>>>>);
+
+impl Timings {
+	fn enabled() -> Self {
+		Self(Some(Default::default()))
+	}
+
+	/// Get the timings for the request the given parts belong to.
+	pub fn from_parts(parts: &Parts) -> Self {
+		parts.extensions.get::<Self>().cloned().unwrap_or_default()
+	}
+
+	/// Run the provided function, recording its duration under the given name.
+	/// Repeated recordings of the same name are summed.
+	pub fn time<T>(&self, name: &'static str, function: impl FnOnce() -> T) -> T {
+		if self.0.is_none() {
+			return function();
+		}
+
+		let start = Instant::now();
+		let result = function();
+		self.record(name, start.elapsed());
+		result
+	}
+
+	fn record(&self, name: &'static str, duration: Duration) {
+		let Some(entries) = &self.0 else { return };
+		let mut entries = entries.lock().expect("poisoned");
+		match entries.iter_mut().find(|(entry, _)| *entry == name) {
+			Some((_, total)) => *total += duration,
+			None => entries.push((name, duration)),
+		}
+	}
+
+	fn header_value(&self) -> Option<HeaderValue> {
+		let entries = self.0.as_ref()?.lock().expect("poisoned");
+		let value = entries
+			.iter()
+			.map(|(name, duration)| format!("{name};dur={:.3}", duration.as_secs_f64() * 1000.))
+			.collect::<Vec<_>>()
+			.join(", ");
+
+		HeaderValue::from_str(&value).ok()
+	}
+}
+
+/// Middleware recording a `Server-Timing` header on responses, if enabled.
+pub async fn timing_layer(
+	State(config): State<TimingConfig>,
+	mut request: Request,
+	next: Next,
+) -> Response {
+	if !config.enabled {
+		return next.run(request).await;
+	}
+
+	let timings = Timings::enabled();
+	request.extensions_mut().insert(timings.clone());
+
+	let start = Instant::now();
+	let mut response = next.run(request).await;
+	timings.record("total", start.elapsed());
+
+	if let Some(value) = timings.header_value() {
+		response.headers_mut().insert(SERVER_TIMING, value);
+	}
+
+	response
+}
+
+#[cfg(test)]
+mod test {
+	use axum::{body::Body, middleware, routing::get, Extension, Router};
+	use pretty_assertions::assert_eq;
+	use tower::ServiceExt;
+
+	use super::*;
+
+	async fn handler(timings: Option<Extension<Timings>>) {
+		if let Some(Extension(timings)) = timings {
+			timings.time("read", || ());
+			timings.time("read", || ());
+		}
+	}
+
+	async fn request(enabled: bool) -> Response {
+		let router = Router::new()
+			.route("/", get(handler))
+			.layer(middleware::from_fn_with_state(
+				TimingConfig { enabled },
+				timing_layer,
+			));
+
+		router
+			.oneshot(Request::get("/").body(Body::empty()).unwrap())
+			.await
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn header_enabled() {
+		let response = request(true).await;
+		let header = response
+			.headers()
+			.get(SERVER_TIMING)
+			.expect("header should be present")
+			.to_str()
+			.unwrap();
+
+		let names = header
+			.split(", ")
+			.map(|metric| {
+				let (name, duration) = metric.split_once(";dur=").expect("metric has duration");
+				duration.parse::<f64>().expect("duration is numeric");
+				name
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(names, vec!["read", "total"]);
+	}
+
+	#[tokio::test]
+	async fn header_disabled() {
+		let response = request(false).await;
+		assert!(response.headers().get(SERVER_TIMING).is_none());
+	}
+}
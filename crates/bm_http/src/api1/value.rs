@@ -1,22 +1,124 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	borrow::Cow,
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
 
 use bm_read as read;
 use ironworks::{excel, sestring};
 use schemars::{
 	gen::SchemaGenerator,
 	schema::{InstanceType, Schema, SchemaObject},
+	JsonSchema,
+};
+use serde::{
+	ser::{Error as SerError, Serialize, SerializeMap, SerializeSeq, SerializeStruct},
+	Deserialize,
 };
-use serde::ser::{Error as SerError, Serialize, SerializeMap, SerializeSeq, SerializeStruct};
 
-use super::{jsonschema::impl_jsonschema, string};
+use super::{icon::IconConfig, jsonschema::impl_jsonschema, string};
 
 #[derive(Debug)]
 pub struct ValueString(
 	pub read::Value,
 	pub excel::Language,
 	pub Arc<sestring::format::Input>,
+	pub Arc<IconConfig>,
+	pub FieldCase,
 );
 
+/// Casing applied to field names read from the schema.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldCase {
+	/// Field names as defined by the schema.
+	#[default]
+	Original,
+	/// `camelCase` field names.
+	Camel,
+	/// `snake_case` field names.
+	Snake,
+}
+
+impl FieldCase {
+	/// Transform a struct key to this casing. Flattened keys (i.e.
+	/// `Item[0].Name`) are transformed per field name, and indices and
+	/// decorations following a field name (i.e. `@lang(en)`) are left as-is.
+	fn transform<'a>(&self, key: &'a str) -> Cow<'a, str> {
+		if *self == Self::Original {
+			return Cow::Borrowed(key);
+		}
+
+		let segments = key
+			.split('.')
+			.map(|segment| {
+				let (name, suffix) =
+					segment.split_at(segment.find(['[', '@']).unwrap_or(segment.len()));
+				format!("{}{suffix}", self.transform_name(name))
+			})
+			.collect::<Vec<_>>();
+
+		Cow::Owned(segments.join("."))
+	}
+
+	fn transform_name(&self, name: &str) -> String {
+		match self {
+			Self::Original => name.to_string(),
+			Self::Snake => split_words(name)
+				.iter()
+				.map(|word| word.to_lowercase())
+				.collect::<Vec<_>>()
+				.join("_"),
+			Self::Camel => split_words(name)
+				.iter()
+				.enumerate()
+				.map(|(index, word)| match index {
+					0 => word.to_lowercase(),
+					_ => capitalize(word),
+				})
+				.collect(),
+		}
+	}
+}
+
+/// Split a field name into words at underscores and casing boundaries, keeping
+/// acronyms (i.e. the `UI` in `ItemUICategory`) together.
+fn split_words(name: &str) -> Vec<&str> {
+	let mut words = vec![];
+
+	for part in name.split('_').filter(|part| !part.is_empty()) {
+		let chars = part.char_indices().collect::<Vec<_>>();
+		let mut start = 0;
+		for window in 1..chars.len() {
+			let (index, current) = chars[window];
+			let previous = chars[window - 1].1;
+			let next = chars.get(window + 1).map(|(_, char)| *char);
+
+			let boundary = current.is_uppercase()
+				&& (!previous.is_uppercase() || next.is_some_and(|next| next.is_lowercase()));
+
+			if boundary {
+				words.push(&part[start..index]);
+				start = index;
+			}
+		}
+		words.push(&part[start..]);
+	}
+
+	words
+}
+
+fn capitalize(word: &str) -> String {
+	let mut chars = word.chars();
+	match chars.next() {
+		None => String::new(),
+		Some(first) => first
+			.to_uppercase()
+			.chain(chars.flat_map(char::to_lowercase))
+			.collect(),
+	}
+}
+
 impl Serialize for ValueString {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
@@ -26,6 +128,8 @@ impl Serialize for ValueString {
 			value: &self.0,
 			language: self.1,
 			string_input: &self.2,
+			icons: &self.3,
+			case: self.4,
 		}
 		.serialize(serializer)
 	}
@@ -43,6 +147,8 @@ struct ValueReference<'a> {
 	value: &'a read::Value,
 	language: excel::Language,
 	string_input: &'a sestring::format::Input,
+	icons: &'a IconConfig,
+	case: FieldCase,
 }
 
 impl Serialize for ValueReference<'_> {
@@ -53,8 +159,12 @@ impl Serialize for ValueReference<'_> {
 		use read::Value as V;
 		match self.value {
 			V::Array(values) => self.serialize_array(serializer, values),
+			V::Hex(bits) => serializer.serialize_str(&format!("{bits:#x}")),
 			V::Html(string) => self.serialize_html(serializer, string),
 			V::Icon(id) => self.serialize_icon(serializer, *id),
+			V::Markdown(string) => self.serialize_markdown(serializer, string),
+			V::Plain(string) => self.serialize_plain(serializer, string),
+			V::Null => serializer.serialize_none(),
 			V::Reference(reference) => self.serialize_reference(serializer, reference),
 			V::Scalar(field) => self.serialize_scalar(serializer, field),
 			V::Struct(fields) => self.serialize_struct(serializer, fields),
@@ -73,6 +183,8 @@ impl ValueReference<'_> {
 				value,
 				language: self.language,
 				string_input: self.string_input,
+				icons: self.icons,
+				case: self.case,
 			})?;
 		}
 		sequence.end()
@@ -91,6 +203,32 @@ impl ValueReference<'_> {
 		serializer.serialize_str(&output)
 	}
 
+	fn serialize_markdown<S>(
+		&self,
+		serializer: S,
+		string: &sestring::SeString,
+	) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		let output =
+			string::as_markdown(string.as_ref(), self.string_input).map_err(SerError::custom)?;
+		serializer.serialize_str(&output)
+	}
+
+	fn serialize_plain<S>(
+		&self,
+		serializer: S,
+		string: &sestring::SeString,
+	) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		let output =
+			string::as_plain(string.as_ref(), self.string_input).map_err(SerError::custom)?;
+		serializer.serialize_str(&output)
+	}
+
 	fn serialize_icon<S>(&self, serializer: S, id: i32) -> Result<S::Ok, S::Error>
 	where
 		S: serde::Serializer,
@@ -103,8 +241,7 @@ impl ValueReference<'_> {
 			}
 
 			true => {
-				let group = (id / 1000) * 1000;
-				let icon_path = format!("ui/icon/{group:0>6}/{id:0>6}");
+				let icon_path = self.icons.path(id.unsigned_abs());
 
 				let mut state = serializer.serialize_struct("Icon", 3)?;
 				state.serialize_field("id", &id)?;
@@ -130,6 +267,25 @@ impl ValueReference<'_> {
 				state.end()
 			}
 
+			read::Reference::Unpopulated {
+				value,
+				sheet,
+				row_id,
+			} => {
+				let mut state = serializer.serialize_struct("Reference", 3)?;
+				state.serialize_field("value", value)?;
+				state.serialize_field("sheet", sheet)?;
+				state.serialize_field("row_id", row_id)?;
+				state.end()
+			}
+
+			read::Reference::Unresolved { value, sheets } => {
+				let mut state = serializer.serialize_struct("Reference", 2)?;
+				state.serialize_field("value", value)?;
+				state.serialize_field("sheets", sheets)?;
+				state.end()
+			}
+
 			read::Reference::Populated {
 				value,
 				sheet,
@@ -147,6 +303,8 @@ impl ValueReference<'_> {
 						value: fields,
 						language: self.language,
 						string_input: self.string_input,
+						icons: self.icons,
+						case: self.case,
 					},
 				)?;
 				state.end()
@@ -187,17 +345,140 @@ impl ValueReference<'_> {
 
 		fields.sort_unstable_by(|a, b| a.0.cmp(&b.0));
 
+		// Distinct field names may share a transformed name - suffix any later
+		// occurrences with a counter to avoid emitting duplicate keys.
+		let mut seen = HashSet::new();
+
 		let mut map = serializer.serialize_map(Some(fields.len()))?;
 		for (name, value) in fields {
+			let transformed = self.case.transform(name);
+			let mut key = transformed.to_string();
+			let mut counter = 1;
+			while seen.contains(&key) {
+				counter += 1;
+				key = format!("{transformed}_{counter}");
+			}
+			if counter > 1 {
+				tracing::debug!(name, key, "transformed field name collision");
+			}
+
 			map.serialize_entry(
-				&name,
+				&key,
 				&ValueReference {
 					value,
 					language: self.language,
 					string_input: self.string_input,
+					icons: self.icons,
+					case: self.case,
 				},
 			)?;
+			seen.insert(key);
 		}
 		map.end()
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use pretty_assertions::assert_eq;
+	use serde_json::json;
+
+	use super::*;
+
+	fn nested_struct() -> read::Value {
+		let scalar = |value| read::Value::Scalar(excel::Field::U32(value));
+		read::Value::Struct(HashMap::from([
+			("Name@lang(ja)".into(), scalar(1)),
+			(
+				"ItemUICategory".into(),
+				read::Value::Struct(HashMap::from([
+					("Icon_Priority".into(), scalar(2)),
+					("Unknown0".into(), scalar(3)),
+				])),
+			),
+			("IsPvP".into(), scalar(4)),
+		]))
+	}
+
+	fn serialize(value: read::Value, case: FieldCase) -> serde_json::Value {
+		let value = ValueString(
+			value,
+			excel::Language::English,
+			sestring::format::Input::new().into(),
+			Default::default(),
+			case,
+		);
+		serde_json::to_value(value).expect("serialization should not fail")
+	}
+
+	#[test]
+	fn camel_case_fields() {
+		assert_eq!(
+			serialize(nested_struct(), FieldCase::Camel),
+			json!({
+				"itemUiCategory": { "iconPriority": 2, "unknown0": 3 },
+				"isPvP": 4,
+				"name@lang(ja)": 1,
+			})
+		);
+	}
+
+	#[test]
+	fn snake_case_fields() {
+		assert_eq!(
+			serialize(nested_struct(), FieldCase::Snake),
+			json!({
+				"item_ui_category": { "icon_priority": 2, "unknown0": 3 },
+				"is_pv_p": 4,
+				"name@lang(ja)": 1,
+			})
+		);
+	}
+
+	#[test]
+	fn snake_case_flattened_fields() {
+		let scalar = |value| read::Value::Scalar(excel::Field::U32(value));
+		let populated = |row_id| {
+			read::Value::Reference(read::Reference::Populated {
+				value: row_id,
+				sheet: "ClassJob".into(),
+				row_id,
+				fields: read::Value::Struct(HashMap::from([("Name".into(), scalar(row_id))]))
+					.into(),
+			})
+		};
+		let value = read::Value::Struct(HashMap::from([
+			("ClassJob".into(), populated(1)),
+			(
+				"ClassJobs@lang(ja)".into(),
+				read::Value::Array(vec![populated(2)]),
+			),
+		]))
+		.flatten();
+
+		let unpopulated =
+			|row_id| json!({ "value": row_id, "sheet": "ClassJob", "row_id": row_id });
+		assert_eq!(
+			serialize(value, FieldCase::Snake),
+			json!({
+				"class_job": unpopulated(1),
+				"class_job.name": 1,
+				"class_jobs@lang(ja)": [unpopulated(2)],
+				"class_jobs@lang(ja)[0].name": 2,
+			})
+		);
+	}
+
+	#[test]
+	fn colliding_fields_suffixed() {
+		let value = read::Value::Struct(HashMap::from([
+			("ItemId".into(), read::Value::Null),
+			("Item_Id".into(), read::Value::Null),
+		]));
+
+		assert_eq!(
+			serialize(value, FieldCase::Snake),
+			json!({ "item_id": null, "item_id_2": null })
+		);
+	}
+}
@@ -1,17 +1,124 @@
+use std::{
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
 use aide::{
-	axum::{routing::get_with, ApiRouter},
+	axum::{
+		routing::{delete_with, get_with, put_with},
+		ApiRouter,
+	},
 	transform::TransformOperation,
 };
-use axum::{debug_handler, extract::State, Json};
+use axum::{
+	debug_handler,
+	extract::{Request, State},
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+	Json,
+};
+use axum_extra::{
+	headers::{authorization::Bearer, Authorization},
+	typed_header::TypedHeaderRejection,
+	TypedHeader,
+};
+use bm_version::VersionKey;
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use crate::service::{self, Service};
+
+use super::{
+	api::ApiState,
+	envelope::{Envelope, EnvelopeQuery},
+	error::{Error, Result},
+	extract::{JsonBody, Path, Query},
+};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+	/// Bearer token required by endpoints that modify version names. If unset,
+	/// those endpoints are not served.
+	admin_token: Option<String>,
+}
+
+pub fn router(config: Config, state: ApiState) -> ApiRouter {
+	let router = ApiRouter::new()
+		.api_route(
+			"/",
+			get_with(versions, versions_docs).with_state(state.clone()),
+		)
+		.api_route(
+			"/resolve",
+			get_with(resolve, resolve_docs).with_state(state.clone()),
+		);
+
+	match config.admin_token {
+		Some(token) => router.merge(names_router(
+			AdminToken(token.into()),
+			state.services.version,
+		)),
+		None => router,
+	}
+}
+
+// Name management is kept in its own router so that only it is guarded by the
+// admin token.
+fn names_router(admin_token: AdminToken, version: service::Version) -> ApiRouter {
+	ApiRouter::new()
+		.api_route(
+			"/{key}/names",
+			put_with(put_names, put_names_docs).with_state(version.clone()),
+		)
+		.api_route(
+			"/names/{name}",
+			delete_with(delete_name, delete_name_docs).with_state(version),
+		)
+		.layer(middleware::from_fn_with_state(admin_token, admin_layer))
+}
+
+#[derive(Debug, Clone)]
+struct AdminToken(Arc<str>);
+
+/// Middleware rejecting requests that do not provide the configured admin
+/// token as a bearer token.
+async fn admin_layer(
+	State(AdminToken(expected)): State<AdminToken>,
+	authorization: Result<TypedHeader<Authorization<Bearer>>, TypedHeaderRejection>,
+	request: Request,
+	next: Next,
+) -> Response {
+	// Malformed headers are treated as missing, rather than rejected outright.
+	let provided = authorization
+		.as_ref()
+		.ok()
+		.map(|TypedHeader(authorization)| authorization.token());
+
+	match check_admin_token(&expected, provided) {
+		Ok(()) => next.run(request).await,
+		Err(error) => error.into_response(),
+	}
+}
 
-use crate::service::Service;
+fn check_admin_token(expected: &str, provided: Option<&str>) -> Result<()> {
+	// Compared in constant time, such that the token can't be guessed from
+	// response timings.
+	let valid =
+		provided.is_some_and(|provided| bool::from(provided.as_bytes().ct_eq(expected.as_bytes())));
 
-use super::api::ApiState;
+	match valid {
+		true => Ok(()),
+		false => Err(Error::Unauthorized("missing or invalid admin token".into())),
+	}
+}
 
-pub fn router(state: ApiState) -> ApiRouter {
-	ApiRouter::new().api_route("/", get_with(versions, versions_docs).with_state(state))
+/// Query parameters accepted by the version endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct VersionsQuery {
+	/// Comma-separated list of version keys to fetch metadata for. If omitted,
+	/// all versions are listed.
+	keys: Option<String>,
 }
 
 /// Response structure for the version endpoint.
@@ -24,26 +131,66 @@ struct VersionsResponse {
 /// Metadata about a single version supported by the API.
 #[derive(Serialize, JsonSchema)]
 struct VersionMetadata {
+	/// Unique key of this version. Keys are accepted by the `version` query
+	/// parameter throughout the API, and will never change their meaning.
+	#[schemars(with = "String")]
+	key: VersionKey,
+
 	/// Names associated with this version. Version names specified here are
 	/// accepted by the `version` query parameter throughout the API.
 	names: Vec<String>,
+
+	/// Time this version was first ingested by the API, in seconds since the
+	/// UNIX epoch. May be absent for versions ingested before this was recorded.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	ingested_at: Option<u64>,
+
+	/// Whether the search index for this version has been prepared. Search
+	/// requests against versions that are not ready will fail.
+	search_ready: bool,
+
+	/// Game data repositories that make up this version.
+	repositories: Vec<RepositoryMetadata>,
+}
+
+/// Metadata about a game data repository within a version.
+#[derive(Serialize, JsonSchema)]
+struct RepositoryMetadata {
+	/// Name of the repository, i.e. `ffxiv` or `ex1`.
+	name: String,
+
+	/// Name of the most recent patch applied to the repository.
+	patch: String,
 }
 
 fn versions_docs(operation: TransformOperation) -> TransformOperation {
 	operation
 		.summary("list versions")
-		.description("List versions understood by the API.")
+		.description(
+			"List versions understood by the API, or fetch metadata for specific versions by key.",
+		)
 		.response_with::<200, Json<VersionsResponse>, _>(|response| {
 			response.example(VersionsResponse {
 				versions: vec![
 					VersionMetadata {
-						names: vec!["latest".into()],
+						key: "b2f6bd0e8c5dc9a1".parse().expect("static"),
+						names: vec!["latest".into(), "7.01".into()],
+						ingested_at: Some(1720000000),
+						search_ready: true,
+						repositories: vec![RepositoryMetadata {
+							name: "ffxiv".into(),
+							patch: "2024.07.10.0000.0000".into(),
+						}],
 					},
 					VersionMetadata {
+						key: "94b31fd6b0b39a75".parse().expect("static"),
 						names: vec!["7.0".into()],
-					},
-					VersionMetadata {
-						names: vec!["7.01".into()],
+						ingested_at: Some(1719000000),
+						search_ready: true,
+						repositories: vec![RepositoryMetadata {
+							name: "ffxiv".into(),
+							patch: "2024.06.18.0000.0000".into(),
+						}],
 					},
 				],
 			})
@@ -51,14 +198,605 @@ fn versions_docs(operation: TransformOperation) -> TransformOperation {
 }
 
 #[debug_handler(state = ApiState)]
-async fn versions(State(Service { version, .. }): State<Service>) -> Json<VersionsResponse> {
-	let mut names = version.all_names();
+async fn versions(
+	envelope: EnvelopeQuery,
+	Query(query): Query<VersionsQuery>,
+	State(Service {
+		version, search, ..
+	}): State<Service>,
+) -> Result<Json<Envelope<VersionsResponse>>> {
+	let metadata = collect_metadata(query.keys.as_deref(), version.keys(), |key| {
+		version_metadata(&version, key, |key| search.version_ready(key))
+	})?;
+
+	Ok(envelope.wrap(VersionsResponse { versions: metadata }, None, None))
+}
+
+fn version_metadata(
+	version: &bm_version::Manager,
+	key: VersionKey,
+	search_ready: impl Fn(VersionKey) -> bool,
+) -> Option<VersionMetadata> {
+	let info = version.version(key)?;
+	let mut names = version.names(key)?;
+	names.sort_unstable();
+
+	Some(VersionMetadata {
+		key,
+		names,
+		ingested_at: info.ingest_time.map(unix_seconds),
+		search_ready: search_ready(key),
+		repositories: info
+			.repositories
+			.iter()
+			.map(|repository| RepositoryMetadata {
+				name: repository.name.clone(),
+				patch: repository.latest().name.clone(),
+			})
+			.collect(),
+	})
+}
+
+fn collect_metadata(
+	keys: Option<&str>,
+	all_keys: Vec<VersionKey>,
+	metadata: impl Fn(VersionKey) -> Option<VersionMetadata>,
+) -> Result<Vec<VersionMetadata>> {
+	// Explicitly requested keys are returned in request order, and must exist.
+	if let Some(keys) = keys {
+		return keys
+			.split(',')
+			.map(|key| {
+				let parsed = key
+					.parse::<VersionKey>()
+					.map_err(|_error| Error::Invalid(format!("invalid version key \"{key}\"")))?;
+				metadata(parsed)
+					.ok_or_else(|| Error::NotFound(format!("unknown version key \"{key}\"")))
+			})
+			.collect();
+	}
+
+	let mut metadata = all_keys
+		.into_iter()
+		.filter_map(metadata)
+		.collect::<Vec<_>>();
+
+	// Most recently ingested versions first, falling back to names for stability.
+	metadata.sort_unstable_by(|a, b| {
+		b.ingested_at
+			.cmp(&a.ingested_at)
+			.then_with(|| a.names.cmp(&b.names))
+	});
+
+	Ok(metadata)
+}
+
+/// Query parameters accepted by the version resolve endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct ResolveQuery {
+	/// Version name to resolve, i.e. `latest`.
+	name: String,
+}
+
+/// Response structure for the version resolve endpoint.
+#[derive(Debug, PartialEq, Serialize, JsonSchema)]
+struct ResolveResponse {
+	/// Unique key of the version the requested name refers to.
+	#[schemars(with = "String")]
+	key: VersionKey,
+
+	/// All names associated with the resolved version.
+	names: Vec<String>,
+}
+
+fn resolve_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("resolve a version name")
+		.description(
+			"Resolve a version name to the unique key of the version it currently refers to.",
+		)
+		.response_with::<200, Json<ResolveResponse>, _>(|response| {
+			response.example(ResolveResponse {
+				key: "b2f6bd0e8c5dc9a1".parse().expect("static"),
+				names: vec!["7.01".into(), "latest".into()],
+			})
+		})
+}
+
+#[debug_handler(state = ApiState)]
+async fn resolve(
+	envelope: EnvelopeQuery,
+	Query(query): Query<ResolveQuery>,
+	State(Service { version, .. }): State<Service>,
+) -> Result<Json<Envelope<ResolveResponse>>> {
+	let response = resolve_version(
+		&query.name,
+		|name| version.resolve(Some(name)),
+		|key| version.names(key),
+	)?;
+
+	let key = response.key;
+	Ok(envelope.wrap(response, Some(key), None))
+}
+
+fn resolve_version(
+	name: &str,
+	resolve: impl Fn(&str) -> Option<VersionKey>,
+	names: impl Fn(VersionKey) -> Option<Vec<String>>,
+) -> Result<ResolveResponse> {
+	let not_found = || Error::NotFound(format!("unknown version \"{name}\""));
+
+	let key = resolve(name).ok_or_else(not_found)?;
+	let mut names = names(key).ok_or_else(not_found)?;
+	names.sort_unstable();
+
+	Ok(ResolveResponse { key, names })
+}
+
+/// Path parameters accepted by the version names endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct NamesPath {
+	/// Key of the version to set the names of.
+	key: String,
+}
+
+/// Request body accepted by the version names endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct NamesRequest {
+	/// Names to associate with the version. Replaces any names the version
+	/// currently has. Names currently associated with other versions will be
+	/// moved to this version.
+	names: Vec<String>,
+}
+
+/// Response structure for endpoints modifying version names.
+#[derive(Debug, PartialEq, Serialize, JsonSchema)]
+struct NamesResponse {
+	/// Unique key of the modified version.
+	#[schemars(with = "String")]
+	key: VersionKey,
+
+	/// Names associated with the version after modification.
+	names: Vec<String>,
+}
+
+fn put_names_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("set version names")
+		.description(
+			"Replace the names associated with a version, i.e. to pin `latest` to a specific version. Requires the server's admin token as a bearer token.",
+		)
+		.response_with::<200, Json<NamesResponse>, _>(|response| {
+			response.example(NamesResponse {
+				key: "b2f6bd0e8c5dc9a1".parse().expect("static"),
+				names: vec!["7.01".into(), "latest".into()],
+			})
+		})
+}
+
+#[debug_handler(state = service::Version)]
+async fn put_names(
+	Path(path): Path<NamesPath>,
+	State(version): State<service::Version>,
+	JsonBody(request): JsonBody<NamesRequest>,
+) -> Result<Json<NamesResponse>> {
+	let not_found = || Error::NotFound(format!("unknown version key \"{}\"", path.key));
+
+	// Unknown and malformed keys are treated identically.
+	let key = path
+		.key
+		.parse::<VersionKey>()
+		.map_err(|_error| not_found())?;
+	version.names(key).ok_or_else(not_found)?;
+
+	let names = validate_names(request.names)?;
+	version.set_names(key, &names).await?;
+
+	let mut names = version.names(key).ok_or_else(not_found)?;
 	names.sort_unstable();
 
-	let metadata = names
+	Ok(Json(NamesResponse { key, names }))
+}
+
+fn validate_names(names: Vec<String>) -> Result<Vec<String>> {
+	names
 		.into_iter()
-		.map(|name| VersionMetadata { names: vec![name] })
-		.collect();
+		.map(|name| {
+			let trimmed = name.trim();
+			match trimmed.is_empty() {
+				true => Err(Error::Invalid("version names must not be empty".into())),
+				false => Ok(trimmed.to_string()),
+			}
+		})
+		.collect()
+}
+
+/// Path parameters accepted by the version name deletion endpoint.
+#[derive(Deserialize, JsonSchema)]
+struct NamePath {
+	/// Version name to remove.
+	name: String,
+}
+
+fn delete_name_docs(operation: TransformOperation) -> TransformOperation {
+	operation
+		.summary("remove a version name")
+		.description(
+			"Remove a name from the version it currently refers to. Requires the server's admin token as a bearer token.",
+		)
+		.response_with::<200, Json<NamesResponse>, _>(|response| {
+			response.example(NamesResponse {
+				key: "b2f6bd0e8c5dc9a1".parse().expect("static"),
+				names: vec!["7.01".into()],
+			})
+		})
+}
+
+#[debug_handler(state = service::Version)]
+async fn delete_name(
+	Path(path): Path<NamePath>,
+	State(version): State<service::Version>,
+) -> Result<Json<NamesResponse>> {
+	let not_found = || Error::NotFound(format!("unknown version \"{}\"", path.name));
+
+	let key = version
+		.remove_name(&path.name)
+		.await?
+		.ok_or_else(not_found)?;
+	let mut names = version.names(key).ok_or_else(not_found)?;
+	names.sort_unstable();
+
+	Ok(Json(NamesResponse { key, names }))
+}
+
+pub(super) fn unix_seconds(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs())
+		.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+	use std::{fs, path::PathBuf};
+
+	use axum::{
+		body::{to_bytes, Body},
+		http::{header, Method, StatusCode},
+	};
+	use pretty_assertions::assert_eq;
+	use serde_json::json;
+	use tower::ServiceExt;
+
+	use super::*;
+
+	fn test_resolve(name: &str) -> Result<ResolveResponse> {
+		let key = "00000000000000ff".parse::<VersionKey>().unwrap();
+		resolve_version(
+			name,
+			|name| (name == "latest" || name == "7.0").then_some(key),
+			|_key| Some(vec!["latest".into(), "7.0".into()]),
+		)
+	}
+
+	#[test]
+	fn resolve_latest() {
+		let got = test_resolve("latest").expect("should not fail");
+		assert_eq!(
+			got,
+			ResolveResponse {
+				key: "00000000000000ff".parse().unwrap(),
+				names: vec!["7.0".into(), "latest".into()],
+			}
+		);
+	}
+
+	#[test]
+	fn resolve_unknown() {
+		let got = test_resolve("unknown");
+		assert!(matches!(got, Err(Error::NotFound(_))));
+	}
+
+	#[test]
+	fn validate_names_trims() {
+		let got = validate_names(vec![" latest ".into(), "7.0".into()]).expect("should not fail");
+		assert_eq!(got, vec!["latest", "7.0"]);
+		assert!(matches!(
+			validate_names(vec!["latest".into(), " ".into()]),
+			Err(Error::Invalid(_))
+		));
+	}
+
+	#[test]
+	fn admin_token() {
+		assert!(check_admin_token("secret", Some("secret")).is_ok());
+		assert!(matches!(
+			check_admin_token("secret", Some("wrong")),
+			Err(Error::Unauthorized(_))
+		));
+		assert!(matches!(
+			check_admin_token("secret", Some("secret2")),
+			Err(Error::Unauthorized(_))
+		));
+		assert!(matches!(
+			check_admin_token("secret", None),
+			Err(Error::Unauthorized(_))
+		));
+	}
+
+	/// Names router over a single version named `latest` and `7.0`, guarded by
+	/// the token `secret`.
+	fn names_fixture(test: &str) -> (axum::Router, service::Version, PathBuf) {
+		let directory = std::env::temp_dir().join(format!("bm_http-{test}-{}", std::process::id()));
+		let version = Arc::new(bm_version::Manager::fixture(
+			&directory,
+			[(
+				"00000000000000ff".parse().unwrap(),
+				fixture_version(None),
+				vec!["latest".into(), "7.0".into()],
+			)],
+		));
+
+		let router = names_router(AdminToken("secret".into()), version.clone()).into();
+		(router, version, directory)
+	}
+
+	async fn request(
+		router: axum::Router,
+		method: Method,
+		path: &str,
+		authorization: Option<&str>,
+		body: Option<serde_json::Value>,
+	) -> (StatusCode, serde_json::Value) {
+		let mut request = Request::builder().method(method).uri(path);
+		if let Some(authorization) = authorization {
+			request = request.header(header::AUTHORIZATION, authorization);
+		}
+		let body = match body {
+			Some(body) => {
+				request = request.header(header::CONTENT_TYPE, "application/json");
+				Body::from(body.to_string())
+			}
+			None => Body::empty(),
+		};
+
+		let response = router.oneshot(request.body(body).unwrap()).await.unwrap();
+		let status = response.status();
+		let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+		(status, serde_json::from_slice(&bytes).unwrap())
+	}
+
+	#[tokio::test]
+	async fn names_require_admin_token() {
+		let (router, version, directory) = names_fixture("names_require_admin_token");
+
+		for authorization in [None, Some("Bearer wrong"), Some("not a bearer token")] {
+			let (status, body) = request(
+				router.clone(),
+				Method::DELETE,
+				"/names/latest",
+				authorization,
+				None,
+			)
+			.await;
+			assert_eq!(status, StatusCode::UNAUTHORIZED);
+			assert_eq!(body["code"], 401);
+		}
+
+		assert!(version.resolve(Some("latest")).is_some());
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	#[tokio::test]
+	async fn names_unknown() {
+		let (router, _version, directory) = names_fixture("names_unknown");
+		let authorization = Some("Bearer secret");
+
+		let (status, body) = request(
+			router.clone(),
+			Method::DELETE,
+			"/names/unknown",
+			authorization,
+			None,
+		)
+		.await;
+		assert_eq!(status, StatusCode::NOT_FOUND);
+		assert_eq!(body["code"], 404);
+
+		let (status, _body) = request(
+			router.clone(),
+			Method::PUT,
+			"/00000000000000aa/names",
+			authorization,
+			Some(json!({"names": ["latest"]})),
+		)
+		.await;
+		assert_eq!(status, StatusCode::NOT_FOUND);
+
+		// Malformed bodies are reported as invalid requests.
+		let (status, body) = request(
+			router,
+			Method::PUT,
+			"/00000000000000ff/names",
+			authorization,
+			Some(json!({"names": "latest"})),
+		)
+		.await;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body["code"], 400);
+
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	#[tokio::test]
+	async fn names_modified() {
+		let (router, version, directory) = names_fixture("names_modified");
+		let authorization = Some("Bearer secret");
+
+		let (status, body) = request(
+			router.clone(),
+			Method::DELETE,
+			"/names/latest",
+			authorization,
+			None,
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body, json!({"key": "00000000000000ff", "names": ["7.0"]}));
+		assert_eq!(version.resolve(Some("latest")), None);
+
+		let (status, body) = request(
+			router,
+			Method::PUT,
+			"/00000000000000ff/names",
+			authorization,
+			Some(json!({"names": [" latest ", "7.01"]})),
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(
+			body,
+			json!({"key": "00000000000000ff", "names": ["7.01", "latest"]})
+		);
+
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	fn fixture_version(ingested: Option<u64>) -> bm_version::Version {
+		bm_version::Version {
+			repositories: vec![],
+			ban_time: None,
+			ingest_time: ingested.map(|secs| UNIX_EPOCH + std::time::Duration::from_secs(secs)),
+		}
+	}
+
+	#[test]
+	fn list_hydrated_versions() {
+		let directory = std::env::temp_dir().join(format!(
+			"bm_http-list_hydrated_versions-{}",
+			std::process::id()
+		));
+		let ready = "00000000000000aa".parse().unwrap();
+		let version = bm_version::Manager::fixture(
+			&directory,
+			[
+				(
+					ready,
+					fixture_version(Some(60)),
+					vec!["latest".into(), "7.01".into()],
+				),
+				(
+					"00000000000000bb".parse().unwrap(),
+					fixture_version(Some(120)),
+					vec![],
+				),
+				(
+					"00000000000000cc".parse().unwrap(),
+					fixture_version(None),
+					vec!["7.0".into()],
+				),
+			],
+		);
+
+		let metadata = collect_metadata(None, version.keys(), |key| {
+			version_metadata(&version, key, |key| key == ready)
+		})
+		.expect("should not fail");
+
+		// Most recently ingested first, with versions of unknown ingestion last.
+		let got = serde_json::to_value(metadata).unwrap();
+		assert_eq!(
+			got,
+			json!([
+				{
+					"key": "00000000000000bb",
+					"names": [],
+					"ingested_at": 120,
+					"search_ready": false,
+					"repositories": [],
+				},
+				{
+					"key": "00000000000000aa",
+					"names": ["7.01", "latest"],
+					"ingested_at": 60,
+					"search_ready": true,
+					"repositories": [],
+				},
+				{
+					"key": "00000000000000cc",
+					"names": ["7.0"],
+					"search_ready": false,
+					"repositories": [],
+				},
+			])
+		);
+
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	#[test]
+	fn serialize_hydrated_version() {
+		let metadata = VersionMetadata {
+			key: "00000000000000ff".parse().unwrap(),
+			names: vec!["latest".into()],
+			ingested_at: Some(unix_seconds(
+				UNIX_EPOCH + std::time::Duration::from_secs(60),
+			)),
+			search_ready: true,
+			repositories: vec![RepositoryMetadata {
+				name: "ffxiv".into(),
+				patch: "2024.07.10.0000.0000".into(),
+			}],
+		};
+
+		let got = serde_json::to_value(metadata).unwrap();
+		assert_eq!(
+			got,
+			json!({
+				"key": "00000000000000ff",
+				"names": ["latest"],
+				"ingested_at": 60,
+				"search_ready": true,
+				"repositories": [{"name": "ffxiv", "patch": "2024.07.10.0000.0000"}],
+			})
+		);
+	}
+
+	fn test_metadata(key: VersionKey) -> Option<VersionMetadata> {
+		let known = ["00000000000000aa", "00000000000000bb", "00000000000000cc"];
+		known
+			.contains(&key.to_string().as_str())
+			.then(|| VersionMetadata {
+				key,
+				names: vec![key.to_string()],
+				ingested_at: None,
+				search_ready: true,
+				repositories: vec![],
+			})
+	}
+
+	#[test]
+	fn metadata_for_keys() {
+		let got = collect_metadata(
+			Some("00000000000000cc,00000000000000aa"),
+			vec![],
+			test_metadata,
+		)
+		.expect("should not fail");
+
+		let keys = got
+			.iter()
+			.map(|metadata| metadata.key.to_string())
+			.collect::<Vec<_>>();
+		assert_eq!(keys, vec!["00000000000000cc", "00000000000000aa"]);
+	}
 
-	Json(VersionsResponse { versions: metadata })
+	#[test]
+	fn metadata_for_unknown_key() {
+		let got = collect_metadata(
+			Some("00000000000000aa,00000000000000ff"),
+			vec![],
+			test_metadata,
+		);
+		assert!(matches!(got, Err(Error::NotFound(_))));
+	}
 }
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::http::HeaderName;
+use serde::Deserialize;
+use tower_http::cors::{Any, CorsLayer, ExposeHeaders};
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+	/// Duration, in seconds, that browsers may cache preflight responses for. If
+	/// unset, browsers will fall back to their own (typically very short) default.
+	max_age: Option<u64>,
+
+	/// Response headers that browser scripts are permitted to read. A `*` entry
+	/// exposes every header.
+	#[serde(default = "default_expose_headers")]
+	expose_headers: Vec<String>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			max_age: None,
+			expose_headers: default_expose_headers(),
+		}
+	}
+}
+
+fn default_expose_headers() -> Vec<String> {
+	vec!["*".into()]
+}
+
+impl Config {
+	pub fn layer(&self) -> Result<CorsLayer> {
+		let expose_headers = match self.expose_headers.iter().any(|header| header == "*") {
+			true => ExposeHeaders::from(Any),
+			false => self
+				.expose_headers
+				.iter()
+				.map(|header| HeaderName::try_from(header.as_str()))
+				.collect::<Result<Vec<_>, _>>()?
+				.into(),
+		};
+
+		let mut layer = CorsLayer::new()
+			.allow_origin(Any)
+			.allow_methods(Any)
+			.allow_headers(Any)
+			.expose_headers(expose_headers);
+
+		if let Some(max_age) = self.max_age {
+			layer = layer.max_age(Duration::from_secs(max_age));
+		}
+
+		Ok(layer)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use axum::{
+		body::Body,
+		http::{header, Method, Request},
+		routing::get,
+		Router,
+	};
+	use pretty_assertions::assert_eq;
+	use tower::ServiceExt;
+
+	use super::*;
+
+	async fn request(config: &Config, method: Method) -> axum::response::Response {
+		let router = Router::new()
+			.route("/", get(|| async { "ok" }))
+			.layer(config.layer().expect("layer should build"));
+
+		let request = Request::builder()
+			.method(method)
+			.uri("/")
+			.header(header::ORIGIN, "https://example.com")
+			.header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+			.body(Body::empty())
+			.unwrap();
+
+		router.oneshot(request).await.unwrap()
+	}
+
+	#[tokio::test]
+	async fn preflight_max_age() {
+		let config = Config {
+			max_age: Some(3600),
+			..Default::default()
+		};
+
+		let response = request(&config, Method::OPTIONS).await;
+		assert_eq!(response.headers()[header::ACCESS_CONTROL_MAX_AGE], "3600");
+	}
+
+	#[tokio::test]
+	async fn exposed_headers_default_any() {
+		let response = request(&Config::default(), Method::GET).await;
+		assert_eq!(
+			response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+			"*"
+		);
+	}
+
+	#[tokio::test]
+	async fn exposed_headers() {
+		let config = Config {
+			expose_headers: vec!["etag".into(), "retry-after".into()],
+			..Default::default()
+		};
+
+		let response = request(&config, Method::GET).await;
+		assert_eq!(
+			response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+			"etag,retry-after"
+		);
+	}
+}
@@ -1,4 +1,7 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{
+	net::{IpAddr, Ipv4Addr, SocketAddr},
+	time::Duration,
+};
 
 use anyhow::Result;
 use axum::{
@@ -12,7 +15,11 @@ use tokio_util::sync::CancellationToken;
 use tower_http::trace::{DefaultOnFailure, DefaultOnRequest, DefaultOnResponse, TraceLayer};
 use tracing::Level;
 
-use super::{admin, api1, health, service};
+use super::{
+	admin, api1, cors, health,
+	maintenance::{self, Maintenance},
+	service,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
@@ -21,11 +28,24 @@ pub struct Config {
 
 	address: Option<IpAddr>,
 	port: u16,
+
+	#[serde(default)]
+	cors: cors::Config,
+
+	#[serde(default)]
+	maintenance: maintenance::Config,
+
+	/// Maximum duration of sheet reads and searches, in milliseconds, after which
+	/// a timeout error is returned. Work already in progress is not interrupted.
+	/// Unlimited if not specified.
+	read_timeout: Option<u64>,
 }
 
 #[derive(Clone, FromRef)]
 pub struct HttpState {
 	pub services: service::Service,
+	pub read_timeout: Option<Duration>,
+	pub maintenance: Maintenance,
 }
 
 pub async fn serve(
@@ -54,11 +74,16 @@ pub async fn serve(
 			search,
 			version,
 		},
+		read_timeout: config.read_timeout.map(Duration::from_millis),
+		maintenance: Maintenance::new(config.maintenance),
 	};
 
 	let router = Router::new()
 		.nest("/admin", admin::router(config.admin, state.clone()))
-		.nest("/api/1", api1::router(config.api1, state.clone()))
+		.nest(
+			"/api/1",
+			api1::router(config.api1, config.cors.layer()?, state.clone()),
+		)
 		.nest("/health", health::router(state))
 		.layer(
 			TraceLayer::new_for_http()
@@ -1,7 +1,9 @@
 mod admin;
 mod api1;
+mod cors;
 mod health;
 mod http;
+mod maintenance;
 mod service;
 
 pub use http::{serve, Config};
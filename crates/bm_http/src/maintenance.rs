@@ -0,0 +1,75 @@
+use std::{
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+	/// Seconds that clients rejected during maintenance are advised to wait
+	/// before retrying.
+	retry_after: u64,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self { retry_after: 300 }
+	}
+}
+
+/// Runtime-toggleable read-only maintenance mode. While enabled, operations
+/// against versions that are not yet fully ready are rejected.
+#[derive(Debug, Clone)]
+pub struct Maintenance {
+	enabled: Arc<AtomicBool>,
+	retry_after: Duration,
+}
+
+impl Maintenance {
+	pub fn new(config: Config) -> Self {
+		Self {
+			enabled: Arc::default(),
+			retry_after: Duration::from_secs(config.retry_after),
+		}
+	}
+
+	pub fn enabled(&self) -> bool {
+		self.enabled.load(Ordering::Relaxed)
+	}
+
+	pub fn set_enabled(&self, enabled: bool) {
+		self.enabled.store(enabled, Ordering::Relaxed);
+		tracing::info!(enabled, "maintenance mode updated");
+	}
+
+	pub fn retry_after(&self) -> Duration {
+		self.retry_after
+	}
+
+	/// Check if an operation against a version may proceed.
+	pub fn permits(&self, version_ready: bool) -> bool {
+		!self.enabled() || version_ready
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn unready_versions_rejected() {
+		let maintenance = Maintenance::new(Config::default());
+		assert!(maintenance.permits(false));
+
+		maintenance.set_enabled(true);
+		assert!(!maintenance.permits(false));
+		assert!(maintenance.permits(true));
+
+		maintenance.set_enabled(false);
+		assert!(maintenance.permits(false));
+	}
+}
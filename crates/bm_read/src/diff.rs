@@ -0,0 +1,287 @@
+use std::collections::BTreeSet;
+
+use ironworks::excel;
+
+use crate::value::{Reference, Value};
+
+/// A single difference between two values, located by its path within the
+/// value, i.e. `ClassJob.Name` or `Item[0]`.
+#[derive(Debug)]
+pub enum Change {
+	/// A value is present in the target, but not the base.
+	Added { path: String, value: Value },
+	/// A value is present in the base, but not the target.
+	Removed { path: String, value: Value },
+	/// A value is present in both, but differs.
+	Changed {
+		path: String,
+		before: Value,
+		after: Value,
+	},
+}
+
+/// Compare two values, returning the changes required to transform the base
+/// into the target. Structs and arrays are compared member-wise, and populated
+/// references are compared by their fields if they target the same row.
+///
+/// Values that differ in shape, such as a field that was a scalar in the base
+/// and a struct in the target, are reported as a single change of the entire
+/// value rather than being compared member-wise.
+pub fn diff(base: Value, target: Value) -> Vec<Change> {
+	let mut changes = vec![];
+	diff_value(String::new(), base, target, &mut changes);
+	changes
+}
+
+fn diff_value(path: String, base: Value, target: Value, changes: &mut Vec<Change>) {
+	match (base, target) {
+		(Value::Struct(mut base), Value::Struct(mut target)) => {
+			let keys = base
+				.keys()
+				.chain(target.keys())
+				.cloned()
+				.collect::<BTreeSet<_>>();
+
+			for key in keys {
+				let path = match path.is_empty() {
+					true => key.clone(),
+					false => format!("{path}.{key}"),
+				};
+				diff_member(path, base.remove(&key), target.remove(&key), changes);
+			}
+		}
+
+		(Value::Array(base), Value::Array(target)) => {
+			let (mut base, mut target) = (base.into_iter(), target.into_iter());
+			for index in 0.. {
+				match (base.next(), target.next()) {
+					(None, None) => break,
+					(base, target) => {
+						diff_member(format!("{path}[{index}]"), base, target, changes)
+					}
+				}
+			}
+		}
+
+		(
+			Value::Reference(Reference::Populated {
+				value: base_value,
+				sheet: base_sheet,
+				row_id: base_row_id,
+				fields: base_fields,
+			}),
+			Value::Reference(Reference::Populated {
+				value: target_value,
+				sheet: target_sheet,
+				row_id: target_row_id,
+				fields: target_fields,
+			}),
+		) => {
+			// References to the same row can be compared by their content - otherwise,
+			// the reference itself has changed.
+			if (base_value, &base_sheet, base_row_id)
+				== (target_value, &target_sheet, target_row_id)
+			{
+				diff_value(path, *base_fields, *target_fields, changes);
+				return;
+			}
+
+			changes.push(Change::Changed {
+				path,
+				before: Value::Reference(Reference::Populated {
+					value: base_value,
+					sheet: base_sheet,
+					row_id: base_row_id,
+					fields: base_fields,
+				}),
+				after: Value::Reference(Reference::Populated {
+					value: target_value,
+					sheet: target_sheet,
+					row_id: target_row_id,
+					fields: target_fields,
+				}),
+			});
+		}
+
+		(base, target) => {
+			if !leaf_eq(&base, &target) {
+				changes.push(Change::Changed {
+					path,
+					before: base,
+					after: target,
+				});
+			}
+		}
+	}
+}
+
+fn diff_member(
+	path: String,
+	base: Option<Value>,
+	target: Option<Value>,
+	changes: &mut Vec<Change>,
+) {
+	match (base, target) {
+		(Some(base), Some(target)) => diff_value(path, base, target, changes),
+		(Some(value), None) => changes.push(Change::Removed { path, value }),
+		(None, Some(value)) => changes.push(Change::Added { path, value }),
+		(None, None) => {}
+	}
+}
+
+fn leaf_eq(base: &Value, target: &Value) -> bool {
+	match (base, target) {
+		(Value::Scalar(base), Value::Scalar(target)) => field_eq(base, target),
+		(Value::Hex(base), Value::Hex(target)) => base == target,
+		(Value::Icon(base), Value::Icon(target)) => base == target,
+		(Value::Null, Value::Null) => true,
+
+		// Formatted strings are compared by their text content.
+		(Value::Html(base), Value::Html(target))
+		| (Value::Markdown(base), Value::Markdown(target))
+		| (Value::Plain(base), Value::Plain(target)) => base.to_string() == target.to_string(),
+
+		(Value::Reference(base), Value::Reference(target)) => reference_eq(base, target),
+
+		_ => false,
+	}
+}
+
+fn reference_eq(base: &Reference, target: &Reference) -> bool {
+	use Reference as R;
+	match (base, target) {
+		(R::Scalar(base), R::Scalar(target)) => base == target,
+		(
+			R::Unpopulated {
+				value: base_value,
+				sheet: base_sheet,
+				row_id: base_row_id,
+			},
+			R::Unpopulated {
+				value: target_value,
+				sheet: target_sheet,
+				row_id: target_row_id,
+			},
+		) => (base_value, base_sheet, base_row_id) == (target_value, target_sheet, target_row_id),
+		(
+			R::Unresolved {
+				value: base_value,
+				sheets: base_sheets,
+			},
+			R::Unresolved {
+				value: target_value,
+				sheets: target_sheets,
+			},
+		) => base_value == target_value && base_sheets == target_sheets,
+		_ => false,
+	}
+}
+
+fn field_eq(base: &excel::Field, target: &excel::Field) -> bool {
+	use excel::Field as F;
+	match (base, target) {
+		(F::String(base), F::String(target)) => base.to_string() == target.to_string(),
+		(F::Bool(base), F::Bool(target)) => base == target,
+		(F::I8(base), F::I8(target)) => base == target,
+		(F::I16(base), F::I16(target)) => base == target,
+		(F::I32(base), F::I32(target)) => base == target,
+		(F::I64(base), F::I64(target)) => base == target,
+		(F::U8(base), F::U8(target)) => base == target,
+		(F::U16(base), F::U16(target)) => base == target,
+		(F::U32(base), F::U32(target)) => base == target,
+		(F::U64(base), F::U64(target)) => base == target,
+		// Compared bitwise, such that identical NaN values are unchanged.
+		(F::F32(base), F::F32(target)) => base.to_bits() == target.to_bits(),
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use super::*;
+
+	fn scalar(value: u32) -> Value {
+		Value::Scalar(excel::Field::U32(value))
+	}
+
+	fn fields(fields: impl IntoIterator<Item = (&'static str, Value)>) -> Value {
+		Value::Struct(
+			fields
+				.into_iter()
+				.map(|(key, value)| (key.to_string(), value))
+				.collect::<HashMap<_, _>>(),
+		)
+	}
+
+	fn summary(changes: Vec<Change>) -> Vec<(&'static str, String)> {
+		changes
+			.into_iter()
+			.map(|change| match change {
+				Change::Added { path, .. } => ("added", path),
+				Change::Removed { path, .. } => ("removed", path),
+				Change::Changed { path, .. } => ("changed", path),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn unchanged() {
+		let got = diff(
+			fields([("A", scalar(1)), ("B", Value::Array(vec![scalar(2)]))]),
+			fields([("A", scalar(1)), ("B", Value::Array(vec![scalar(2)]))]),
+		);
+		assert!(got.is_empty());
+	}
+
+	#[test]
+	fn members_compared_by_path() {
+		let got = diff(
+			fields([
+				("A", scalar(1)),
+				("B", Value::Array(vec![scalar(2), scalar(3)])),
+				("C", fields([("D", scalar(4))])),
+			]),
+			fields([
+				("A", scalar(10)),
+				("B", Value::Array(vec![scalar(2)])),
+				("C", fields([("D", scalar(4)), ("E", scalar(5))])),
+			]),
+		);
+
+		assert_eq!(
+			summary(got),
+			vec![
+				("changed", "A".to_string()),
+				("removed", "B[1]".to_string()),
+				("added", "C.E".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn shape_change() {
+		let got = diff(
+			fields([("A", scalar(1))]),
+			fields([("A", fields([("B", scalar(1))]))]),
+		);
+
+		assert!(matches!(
+			got.as_slice(),
+			[Change::Changed {
+				path,
+				before: Value::Scalar(excel::Field::U32(1)),
+				after: Value::Struct(_),
+			}] if path == "A"
+		));
+	}
+
+	#[test]
+	fn floats_compared_bitwise() {
+		let nan = excel::Field::F32(f32::NAN);
+		assert!(field_eq(&nan, &nan));
+		assert!(!field_eq(&excel::Field::F32(0.0), &excel::Field::F32(-0.0)));
+		assert!(field_eq(&excel::Field::F32(1.5), &excel::Field::F32(1.5)));
+	}
+}
@@ -7,6 +7,10 @@ pub enum Error {
 	#[error("invalid or unsupported language \"{0}\"")]
 	InvalidLanguage(String),
 
+	/// The requested name matched more than one candidate.
+	#[error("ambiguous name \"{0}\" matches multiple candidates")]
+	AmbiguousName(String),
+
 	/// The provided filter does not map cleanly onto the sheet schema.
 	#[error("filter <-> schema mismatch on {}: {}", .0.field, .0.reason)]
 	FilterSchemaMismatch(MismatchError),
@@ -15,6 +19,10 @@ pub enum Error {
 	#[error("schema <-> game mismatch on {}: {}", .0.field, .0.reason)]
 	SchemaGameMismatch(MismatchError),
 
+	/// The sheet schema expects more columns than the game data contains.
+	#[error("schema for {} is outdated: expected {} columns, game data has {}", .0.sheet, .0.expected, .0.actual)]
+	SchemaOutdated(ColumnDriftError),
+
 	#[error(transparent)]
 	Failure(#[from] anyhow::Error),
 }
@@ -25,6 +33,15 @@ pub struct MismatchError {
 	pub(super) reason: String,
 }
 
+/// Column counts for a sheet whose schema expects more columns than are present
+/// in the game data, typically as the result of a game update.
+#[derive(Debug)]
+pub struct ColumnDriftError {
+	pub sheet: String,
+	pub expected: usize,
+	pub actual: usize,
+}
+
 impl From<ironworks::Error> for Error {
 	fn from(error: ironworks::Error) -> Self {
 		use ironworks::Error as IE;
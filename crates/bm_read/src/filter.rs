@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use ironworks::excel;
 
@@ -6,7 +6,21 @@ use ironworks::excel;
 pub enum Filter {
 	Struct(HashMap<String, StructEntry>),
 	Array(Box<Filter>),
+	/// Select the elements of an array within a set of ranges, each with its own
+	/// filter. Ranges are expected to be disjoint, and in ascending order.
+	ArrayRanges(Vec<ArrayRange>),
 	All,
+	/// Select all fields of a struct, other than those named.
+	AllExcept(HashSet<String>),
+}
+
+/// Elements of an array with indices in the range `start..end`. If `end` is not
+/// specified, the range extends to the end of the array.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayRange {
+	pub start: u32,
+	pub end: Option<u32>,
+	pub filter: Filter,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,6 +28,15 @@ pub struct StructEntry {
 	pub field: String,
 	pub language: excel::Language,
 	pub read_as: As,
+	/// Match all fields with names starting with `field`, rather than only the
+	/// field named exactly `field`.
+	pub prefix: bool,
+	/// Override the depth of references followed within this entry, in place of
+	/// the depth of the surrounding read. Limited by the read's maximum depth.
+	pub depth: Option<u8>,
+	/// Round float fields within this entry to the given number of decimal
+	/// places, overriding the configured precision.
+	pub round: Option<u8>,
 	pub filter: Filter,
 }
 
@@ -26,4 +49,12 @@ pub enum As {
 	// some tree other than a filter while it gets read, which also kinda sucks.
 	// Would need some intermediary format.
 	Html,
+	Markdown,
+	/// Format string fields as plain text, with all non-text payloads removed.
+	Plain,
+	/// Format integer fields as hexadecimal strings.
+	Hex,
+	/// Substitute reference fields with the configured label field of the
+	/// target row, where one exists.
+	Label,
 }
@@ -0,0 +1,385 @@
+//! In-memory game data and schemas for read tests.
+
+use std::{collections::HashMap, io::Cursor, sync::Arc};
+
+use ironworks::{
+	excel,
+	file::{exh, File},
+	Ironworks,
+};
+use ironworks_schema as schema;
+
+/// Value of a single column within a test row.
+#[derive(Debug, Clone)]
+pub enum Cell {
+	U8(u8),
+	U32(u32),
+	I32(i32),
+	F32(f32),
+	Bool(bool),
+	String(String),
+}
+
+/// A sheet of test data. Sheets are written with a single page, in the `none`
+/// language, such that they may be read in any language.
+pub struct TestSheet {
+	name: String,
+	kind: exh::SheetKind,
+	columns: Vec<(exh::ColumnKind, u16)>,
+	rows: Vec<(u32, Vec<Vec<Cell>>)>,
+}
+
+impl TestSheet {
+	/// Create a sheet with the given columns, as pairs of kind and byte offset.
+	pub fn new(name: &str, columns: impl IntoIterator<Item = (exh::ColumnKind, u16)>) -> Self {
+		Self {
+			name: name.into(),
+			kind: exh::SheetKind::Default,
+			columns: columns.into_iter().collect(),
+			rows: vec![],
+		}
+	}
+
+	/// Add a row, with one cell per column, in column order.
+	pub fn row(mut self, row_id: u32, cells: impl IntoIterator<Item = Cell>) -> Self {
+		self.rows.push((row_id, vec![cells.into_iter().collect()]));
+		self
+	}
+
+	/// Add a row made up of subrows, marking the sheet as a subrow sheet.
+	pub fn subrows(mut self, row_id: u32, subrows: Vec<Vec<Cell>>) -> Self {
+		self.kind = exh::SheetKind::Subrows;
+		self.rows.push((row_id, subrows));
+		self
+	}
+
+	fn row_size(&self) -> u16 {
+		let end = self
+			.columns
+			.iter()
+			.map(|(kind, offset)| offset + column_size(*kind))
+			.max()
+			.unwrap_or(0);
+		end.next_multiple_of(4)
+	}
+
+	fn header(&self) -> Vec<u8> {
+		// Single page spanning every row.
+		let start = self.rows.first().map_or(0, |(row_id, _)| *row_id);
+		let end = self.rows.last().map_or(0, |(row_id, _)| *row_id);
+
+		header_bytes(
+			self.kind,
+			self.row_size(),
+			&self.columns,
+			&[(start, end - start + 1)],
+			u32::try_from(self.rows.len()).unwrap(),
+		)
+	}
+
+	fn page_start(&self) -> u32 {
+		self.rows.first().map_or(0, |(row_id, _)| *row_id)
+	}
+
+	fn data(&self) -> Vec<u8> {
+		const HEADER_SIZE: u32 = 0x20;
+		let row_size = usize::from(self.row_size());
+
+		let rows = self
+			.rows
+			.iter()
+			.map(|(row_id, subrows)| (*row_id, self.row_data(row_size, subrows)))
+			.collect::<Vec<_>>();
+
+		let index_size = u32::try_from(rows.len() * 8).unwrap();
+		let data_size = rows.iter().map(|(_, data)| data.len()).sum::<usize>();
+
+		let mut bytes = b"EXDF".to_vec();
+		bytes.extend(2u16.to_be_bytes());
+		bytes.extend([0, 0]);
+		bytes.extend(index_size.to_be_bytes());
+		bytes.extend(u32::try_from(data_size).unwrap().to_be_bytes());
+		bytes.extend([0; 16]);
+
+		let mut offset = HEADER_SIZE + index_size;
+		for (row_id, data) in &rows {
+			bytes.extend(row_id.to_be_bytes());
+			bytes.extend(offset.to_be_bytes());
+			offset += u32::try_from(data.len()).unwrap();
+		}
+
+		for (_, data) in rows {
+			bytes.extend(data);
+		}
+
+		bytes
+	}
+
+	/// Row data, prefixed by its size and subrow count. Strings of every subrow
+	/// are stored after the fixed-size data.
+	fn row_data(&self, row_size: usize, subrows: &[Vec<Cell>]) -> Vec<u8> {
+		let mut fixed = vec![];
+		let mut strings = vec![];
+
+		for (subrow_id, cells) in subrows.iter().enumerate() {
+			if self.kind == exh::SheetKind::Subrows {
+				fixed.extend(u16::try_from(subrow_id).unwrap().to_be_bytes());
+			}
+
+			let mut row = vec![0u8; row_size];
+			for ((kind, offset), cell) in self.columns.iter().zip(cells) {
+				write_cell(&mut row[usize::from(*offset)..], *kind, cell, &mut strings);
+			}
+			fixed.extend(row);
+		}
+
+		let mut data = vec![];
+		data.extend(
+			u32::try_from(fixed.len() + strings.len())
+				.unwrap()
+				.to_be_bytes(),
+		);
+		data.extend(u16::try_from(subrows.len()).unwrap().to_be_bytes());
+		data.extend(fixed);
+		data.extend(strings);
+		data
+	}
+}
+
+/// Build a sheet header with the given columns, as pairs of kind and byte
+/// offset, and pages, as pairs of start row ID and row count.
+pub fn header(columns: &[(exh::ColumnKind, u16)], pages: &[(u32, u32)]) -> exh::ExcelHeader {
+	let rows = pages.iter().map(|(_, count)| count).sum::<u32>();
+	// Row size is only used when reading row data, and is left empty.
+	let bytes = header_bytes(exh::SheetKind::Default, 0, columns, pages, rows);
+	exh::ExcelHeader::read(Cursor::new(bytes)).expect("test header should parse")
+}
+
+/// Definition of a single column of the given kind and offset.
+pub fn column(kind: exh::ColumnKind, offset: u16) -> exh::ColumnDefinition {
+	header(&[(kind, offset)], &[(0, 1)]).columns()[0].clone()
+}
+
+fn header_bytes(
+	kind: exh::SheetKind,
+	row_size: u16,
+	columns: &[(exh::ColumnKind, u16)],
+	pages: &[(u32, u32)],
+	rows: u32,
+) -> Vec<u8> {
+	let mut bytes = b"EXHF".to_vec();
+	bytes.extend(3u16.to_be_bytes());
+	bytes.extend(row_size.to_be_bytes());
+	bytes.extend(u16::try_from(columns.len()).unwrap().to_be_bytes());
+	bytes.extend(u16::try_from(pages.len()).unwrap().to_be_bytes());
+	// Language count.
+	bytes.extend(1u16.to_be_bytes());
+	bytes.extend([0, 0, 0]);
+	bytes.push(match kind {
+		exh::SheetKind::Subrows => 2,
+		_ => 1,
+	});
+	bytes.extend([0, 0]);
+	bytes.extend(rows.to_be_bytes());
+	bytes.extend([0; 8]);
+
+	for (kind, offset) in columns {
+		bytes.extend(column_kind_id(*kind).to_be_bytes());
+		bytes.extend(offset.to_be_bytes());
+	}
+
+	for (start, count) in pages {
+		bytes.extend(start.to_be_bytes());
+		bytes.extend(count.to_be_bytes());
+	}
+
+	// Language `none`.
+	bytes.extend([0, 0]);
+
+	bytes
+}
+
+fn write_cell(row: &mut [u8], kind: exh::ColumnKind, cell: &Cell, strings: &mut Vec<u8>) {
+	use exh::ColumnKind as CK;
+	match (kind, cell) {
+		(CK::String, Cell::String(value)) => {
+			let offset = u32::try_from(strings.len()).unwrap();
+			row[..4].copy_from_slice(&offset.to_be_bytes());
+			strings.extend(value.as_bytes());
+			strings.push(0);
+		}
+		(CK::UInt8, Cell::U8(value)) => row[0] = *value,
+		(CK::UInt32, Cell::U32(value)) => row[..4].copy_from_slice(&value.to_be_bytes()),
+		(CK::Int32, Cell::I32(value)) => row[..4].copy_from_slice(&value.to_be_bytes()),
+		(CK::Float32, Cell::F32(value)) => row[..4].copy_from_slice(&value.to_be_bytes()),
+		(CK::Bool, Cell::Bool(value)) => row[0] = u8::from(*value),
+		// Packed boolean kinds are numbered sequentially by bit.
+		(kind, Cell::Bool(value)) if (0x19..=0x20).contains(&column_kind_id(kind)) => {
+			if *value {
+				row[0] |= 1 << (column_kind_id(kind) - 0x19);
+			}
+		}
+		(kind, cell) => panic!("cannot write {cell:?} to {kind:?} column"),
+	}
+}
+
+fn column_size(kind: exh::ColumnKind) -> u16 {
+	use exh::ColumnKind as CK;
+	match kind {
+		CK::String | CK::UInt32 | CK::Int32 | CK::Float32 => 4,
+		CK::Int64 | CK::UInt64 => 8,
+		CK::Int16 | CK::UInt16 => 2,
+		_ => 1,
+	}
+}
+
+fn column_kind_id(kind: exh::ColumnKind) -> u16 {
+	use exh::ColumnKind as CK;
+	match kind {
+		CK::String => 0x0,
+		CK::Bool => 0x1,
+		CK::Int8 => 0x2,
+		CK::UInt8 => 0x3,
+		CK::Int16 => 0x4,
+		CK::UInt16 => 0x5,
+		CK::Int32 => 0x6,
+		CK::UInt32 => 0x7,
+		CK::Float32 => 0x9,
+		CK::Int64 => 0xA,
+		CK::UInt64 => 0xB,
+		CK::PackedBool0 => 0x19,
+		CK::PackedBool1 => 0x1A,
+		CK::PackedBool2 => 0x1B,
+		CK::PackedBool3 => 0x1C,
+		CK::PackedBool4 => 0x1D,
+		CK::PackedBool5 => 0x1E,
+		CK::PackedBool6 => 0x1F,
+		CK::PackedBool7 => 0x20,
+		other => panic!("unsupported test column kind {other:?}"),
+	}
+}
+
+/// Resource serving the files of a set of test sheets.
+struct TestResource {
+	files: HashMap<String, Vec<u8>>,
+}
+
+impl TestResource {
+	fn new(sheets: &[TestSheet]) -> Self {
+		let mut root = "EXLT,2\r\n".to_string();
+		let mut files = HashMap::new();
+
+		for sheet in sheets {
+			root.push_str(&format!("{},-1\r\n", sheet.name));
+			let name = sheet.name.to_lowercase();
+			files.insert(format!("exd/{name}.exh"), sheet.header());
+			files.insert(
+				format!("exd/{name}_{}.exd", sheet.page_start()),
+				sheet.data(),
+			);
+		}
+
+		files.insert("exd/root.exl".into(), root.into_bytes());
+
+		Self { files }
+	}
+}
+
+impl ironworks::Resource for TestResource {
+	fn version(&self, _path: &str) -> ironworks::Result<String> {
+		Ok("test".into())
+	}
+
+	fn file(&self, path: &str) -> ironworks::Result<Box<dyn ironworks::FileStream>> {
+		let bytes = self
+			.files
+			.get(&path.to_lowercase())
+			.ok_or_else(|| ironworks::Error::NotFound(ironworks::ErrorValue::Path(path.into())))?;
+		Ok(Box::new(Cursor::new(bytes.clone())))
+	}
+}
+
+/// Schema serving a fixed set of offset-ordered sheet definitions.
+pub struct TestSchema(HashMap<String, schema::Node>);
+
+impl schema::Schema for TestSchema {
+	fn sheet(&self, name: &str) -> schema::Result<schema::Sheet> {
+		let node = self
+			.0
+			.get(name)
+			.ok_or_else(|| schema::Error::NotFound(schema::ErrorValue::Sheet(name.into())))?;
+
+		Ok(schema::Sheet {
+			name: name.into(),
+			order: schema::Order::Offset,
+			node: node.clone(),
+		})
+	}
+}
+
+/// Excel data and schemas for a set of test sheets.
+pub struct Fixture {
+	pub ironworks: Arc<Ironworks>,
+	pub excel: excel::Excel,
+	pub schema: TestSchema,
+}
+
+impl Fixture {
+	pub fn new(sheets: Vec<(TestSheet, schema::Node)>) -> Self {
+		let (sheets, nodes): (Vec<_>, Vec<_>) = sheets.into_iter().unzip();
+
+		let schema = TestSchema(
+			sheets
+				.iter()
+				.map(|sheet| sheet.name.clone())
+				.zip(nodes)
+				.collect(),
+		);
+
+		let ironworks = Arc::new(Ironworks::new().with_resource(TestResource::new(&sheets)));
+
+		Self {
+			excel: excel::Excel::new(ironworks.clone()),
+			ironworks,
+			schema,
+		}
+	}
+}
+
+/// Schema node for a struct of fields, as pairs of name and node, laid out
+/// one column after another.
+pub fn struct_node(fields: impl IntoIterator<Item = (&'static str, schema::Node)>) -> schema::Node {
+	let mut offset = 0;
+	schema::Node::Struct(
+		fields
+			.into_iter()
+			.map(|(name, node)| {
+				let field = schema::StructField {
+					name: name.into(),
+					offset,
+					node,
+				};
+				offset += field.node.size();
+				field
+			})
+			.collect(),
+	)
+}
+
+pub fn scalar() -> schema::Node {
+	schema::Node::Scalar(schema::Scalar::Default)
+}
+
+/// Schema node referencing each of the given sheets, in order.
+pub fn reference(sheets: &[&str]) -> schema::Node {
+	schema::Node::Scalar(schema::Scalar::Reference(
+		sheets
+			.iter()
+			.map(|sheet| schema::ReferenceTarget {
+				sheet: sheet.to_string(),
+				selector: None,
+				condition: None,
+			})
+			.collect(),
+	))
+}
@@ -0,0 +1,18 @@
+use ironworks::{file::exh, Ironworks};
+
+/// Path of the header file of the named sheet.
+pub fn sheet_header_path(sheet: &str) -> String {
+	format!("exd/{sheet}.exh")
+}
+
+/// Read the header of the named sheet, for metadata not exposed by the excel
+/// module.
+pub fn sheet_header(ironworks: &Ironworks, sheet: &str) -> ironworks::Result<exh::ExcelHeader> {
+	ironworks.file::<exh::ExcelHeader>(&sheet_header_path(sheet))
+}
+
+/// Number of rows in a sheet, as recorded by the row count of each of its pages.
+/// Sheets with subrows report their number of rows, rather than subrows.
+pub fn header_row_count(header: &exh::ExcelHeader) -> u32 {
+	header.pages().iter().map(|page| page.row_count()).sum()
+}
@@ -1,13 +1,21 @@
+mod diff;
 mod error;
 mod filter;
+#[cfg(any(test, feature = "fixture"))]
+pub mod fixture;
+mod header;
 mod language;
+mod name;
 mod read;
 mod value;
 
 pub use {
-	error::Error,
-	filter::{As, Filter, StructEntry},
+	diff::{diff, Change},
+	error::{ColumnDriftError, Error},
+	filter::{ArrayRange, As, Filter, StructEntry},
+	header::{header_row_count, sheet_header, sheet_header_path},
 	language::LanguageString,
-	read::{Config, Read},
+	name::resolve_name,
+	read::{packed_bool_bit, Config, Coverage, Read, ReadCache, ReadOptions},
 	value::{Reference, Value},
 };
@@ -0,0 +1,90 @@
+use super::error::{Error, Result};
+
+/// Resolve a name against a list of candidates. Exact matches are always
+/// preferred - if there is none, candidates are compared case-insensitively.
+/// A name matching more than one candidate case-insensitively is ambiguous,
+/// and will fail.
+pub fn resolve_name<T>(
+	name: &str,
+	candidates: impl IntoIterator<Item = T>,
+	key: impl Fn(&T) -> &str,
+) -> Result<Option<T>> {
+	let mut insensitive = vec![];
+
+	for candidate in candidates {
+		let candidate_name = key(&candidate);
+		if candidate_name == name {
+			return Ok(Some(candidate));
+		}
+
+		if candidate_name.eq_ignore_ascii_case(name) {
+			insensitive.push(candidate);
+		}
+	}
+
+	match insensitive.len() {
+		0 | 1 => Ok(insensitive.pop()),
+		_ => Err(Error::AmbiguousName(name.into())),
+	}
+}
+
+/// Check if a name starts with the given prefix. When case insensitive, ASCII
+/// case differences are ignored.
+pub fn matches_prefix(name: &str, prefix: &str, case_insensitive: bool) -> bool {
+	let Some(start) = name.get(..prefix.len()) else {
+		return false;
+	};
+
+	match case_insensitive {
+		true => start.eq_ignore_ascii_case(prefix),
+		false => start == prefix,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn test_resolve<'a>(name: &str, candidates: &[&'a str]) -> Result<Option<&'a str>> {
+		resolve_name(name, candidates.iter().copied(), |candidate| candidate)
+	}
+
+	#[test]
+	fn resolve_exact() {
+		let got = test_resolve("Item", &["Action", "Item"]).unwrap();
+		assert_eq!(got, Some("Item"));
+	}
+
+	#[test]
+	fn resolve_insensitive() {
+		let got = test_resolve("item", &["Action", "Item"]).unwrap();
+		assert_eq!(got, Some("Item"));
+	}
+
+	#[test]
+	fn resolve_prefers_exact() {
+		let got = test_resolve("item", &["Item", "item"]).unwrap();
+		assert_eq!(got, Some("item"));
+	}
+
+	#[test]
+	fn resolve_missing() {
+		let got = test_resolve("Status", &["Action", "Item"]).unwrap();
+		assert_eq!(got, None);
+	}
+
+	#[test]
+	fn prefix_matching() {
+		assert!(matches_prefix("Param0", "Param", false));
+		assert!(!matches_prefix("param0", "Param", false));
+		assert!(matches_prefix("param0", "Param", true));
+		assert!(!matches_prefix("Par", "Param", true));
+		assert!(matches_prefix("Param", "", false));
+	}
+
+	#[test]
+	fn resolve_ambiguous() {
+		let got = test_resolve("item", &["Item", "ITEM"]);
+		assert!(matches!(got, Err(Error::AmbiguousName(name)) if name == "item"));
+	}
+}
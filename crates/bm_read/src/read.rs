@@ -1,25 +1,52 @@
 use std::{
 	borrow::Cow,
+	cell::Cell,
 	collections::{hash_map, HashMap, HashSet},
 	iter,
 	ops::Range,
+	sync::{Arc, Mutex},
 };
 
 use anyhow::{anyhow, Context};
-use ironworks::{excel, file::exh};
+use ironworks::{excel, file::exh, sestring::SeString};
 use ironworks_schema as schema;
 use serde::Deserialize;
 
 use super::{
-	error::{Error, MismatchError, Result},
+	error::{ColumnDriftError, Error, MismatchError, Result},
 	filter::{As, Filter, StructEntry},
 	language::LanguageString,
+	name::{matches_prefix, resolve_name},
 	value::{Reference, Value},
 };
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
 	language: LanguageConfig,
+
+	#[serde(default)]
+	case_insensitive: bool,
+
+	#[serde(default)]
+	reference_sheets: bool,
+
+	#[serde(default)]
+	float_precision: Option<u8>,
+
+	#[serde(default)]
+	max_references: Option<usize>,
+
+	#[serde(default)]
+	lenient_filters: bool,
+
+	/// Field read as the label for rows of each sheet, keyed by sheet name.
+	#[serde(default)]
+	labels: HashMap<String, String>,
+
+	/// Group unknown packed boolean columns sharing a byte into a single field,
+	/// keyed by bit, rather than reading each bit as a separate field.
+	#[serde(default)]
+	group_packed_bools: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,9 +55,45 @@ struct LanguageConfig {
 	exclude: Vec<LanguageString>,
 }
 
+/// Per-request options controlling the shape of read output.
+#[derive(Debug, Default, Clone)]
+pub struct ReadOptions {
+	/// Emit a null placeholder for struct keys requested by the filter that
+	/// could not be read, rather than omitting them.
+	pub explicit_nulls: bool,
+
+	/// Read followed references with only the named field, rather than the full
+	/// target row.
+	pub compact_references: Option<String>,
+
+	/// Maximum number of references to expand within the read. Limited by the
+	/// configured maximum, if any.
+	pub max_references: Option<usize>,
+
+	/// Read references into sheets with subrows as an array of every subrow of
+	/// the target row, rather than only the first subrow.
+	pub all_subrows: bool,
+
+	/// Omit fields for columns not described by the schema, rather than reading
+	/// them as `unknownN` fields.
+	pub omit_unknowns: bool,
+
+	/// Maximum depth that struct entries may override the read depth to. Greater
+	/// overrides are clamped. Without a maximum, overrides may only reduce the
+	/// depth of the read.
+	pub max_depth: Option<u8>,
+}
+
 pub struct Read {
 	default_language: excel::Language,
 	excluded_languages: HashSet<excel::Language>,
+	case_insensitive: bool,
+	reference_sheets: bool,
+	float_precision: Option<u8>,
+	max_references: Option<usize>,
+	lenient_filters: bool,
+	labels: HashMap<String, String>,
+	group_packed_bools: bool,
 }
 
 impl Read {
@@ -43,6 +106,13 @@ impl Read {
 				.into_iter()
 				.map(|language| language.into())
 				.collect(),
+			case_insensitive: config.case_insensitive,
+			reference_sheets: config.reference_sheets,
+			float_precision: config.float_precision,
+			max_references: config.max_references,
+			lenient_filters: config.lenient_filters,
+			labels: config.labels,
+			group_packed_bools: config.group_packed_bools,
 		}
 	}
 
@@ -50,6 +120,53 @@ impl Read {
 		self.default_language
 	}
 
+	/// Whether the given language may be read. Excluded languages will fail.
+	pub fn language_enabled(&self, language: excel::Language) -> bool {
+		!self.excluded_languages.contains(&language)
+	}
+
+	/// Round a float field to the given number of decimal places, falling back
+	/// to the configured default precision, if any.
+	fn round(&self, field: excel::Field, places: Option<u8>) -> excel::Field {
+		match places.or(self.float_precision) {
+			Some(places) => round_field(field, places),
+			None => field,
+		}
+	}
+
+	/// Whether sheet and field names fall back to case-insensitive matching.
+	pub fn case_insensitive(&self) -> bool {
+		self.case_insensitive
+	}
+
+	/// Whether fields with filters that do not match the schema are skipped,
+	/// rather than failing the read.
+	pub fn lenient_filters(&self) -> bool {
+		self.lenient_filters
+	}
+
+	/// Resolve a requested sheet name to the name used by the game data. Names
+	/// are returned as-is unless case-insensitive matching is enabled and a
+	/// single case-insensitive match exists.
+	pub fn resolve_sheet<'a>(
+		&self,
+		excel: &excel::Excel,
+		sheet_name: &'a str,
+	) -> Result<Cow<'a, str>> {
+		if !self.case_insensitive {
+			return Ok(Cow::Borrowed(sheet_name));
+		}
+
+		let list = excel.list()?;
+		let resolved = resolve_name(sheet_name, list.iter(), |name| name.as_ref())?;
+
+		Ok(match resolved {
+			Some(name) => Cow::Owned(name.into_owned()),
+			// Leave unknown sheets for the read itself to report.
+			None => Cow::Borrowed(sheet_name),
+		})
+	}
+
 	pub fn read(
 		&self,
 		excel: &excel::Excel,
@@ -63,8 +180,15 @@ impl Read {
 
 		filter: &Filter,
 		depth: u8,
+		options: &ReadOptions,
+		cache: &ReadCache,
 	) -> Result<Value> {
-		let value = read_sheet(ReaderContext {
+		let max_references = match (options.max_references, self.max_references) {
+			(Some(requested), Some(max)) => Some(requested.min(max)),
+			(requested, max) => requested.or(max),
+		};
+
+		let result = read_sheet(ReaderContext {
 			read: self,
 
 			excel,
@@ -77,32 +201,78 @@ impl Read {
 
 			filter,
 			read_as: As::Default,
+			round: None,
 			rows: &mut HashMap::new(),
 			columns: &[],
 			depth,
+			options,
+			references: &ReferenceBudget::new(max_references),
+			selectors: &cache.selectors,
 
 			path: &[],
-		})?;
+		});
+
+		// Mismatches within the row are skipped per-field - one reaching here
+		// is at the root of the sheet, and leaves nothing to read.
+		let value = skip_filter_mismatch(result, self.lenient_filters)?
+			.unwrap_or_else(|| Value::Struct(HashMap::new()));
 
 		Ok(value)
 	}
+
+	/// Read every column of a row as its native field value, keyed as unknown
+	/// fields by column offset. No schema is applied, and references are not
+	/// followed.
+	pub fn read_raw(
+		&self,
+		excel: &excel::Excel,
+		sheet_name: &str,
+		row_id: u32,
+		subrow_id: u16,
+		language: excel::Language,
+	) -> Result<Value> {
+		if !self.language_enabled(language) {
+			return Err(Error::InvalidLanguage(
+				LanguageString::from(language).to_string(),
+			));
+		}
+
+		let sheet_data = excel.sheet(sheet_name)?;
+		let mut columns = sheet_data.columns()?;
+		columns.sort_by_key(|column| column.offset());
+
+		let row = sheet_data.subrow_with_options(row_id, subrow_id, language)?;
+		let fields = columns
+			.iter()
+			.map(|column| Ok((column.offset(), column.kind(), row.field(column)?)))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(raw_struct(fields))
+	}
+
+	/// Count the columns of a sheet that are described by the schema.
+	pub fn coverage(
+		&self,
+		excel: &excel::Excel,
+		schema: &dyn schema::Schema,
+		sheet_name: &str,
+	) -> Result<Coverage> {
+		let sheet_data = excel.sheet(sheet_name)?;
+		let sheet_schema = get_sheet_schema(schema, sheet_name)?;
+		let column_count = sheet_data.columns()?.len();
+		check_column_drift(&sheet_schema, column_count)?;
+
+		node_coverage(&sheet_schema.node, column_count)
+	}
 }
 
 fn read_sheet(context: ReaderContext) -> Result<Value> {
 	let sheet_name = context.sheet;
 	let sheet_data = context.excel.sheet(sheet_name)?;
 
-	// Fabricate an empty schema for missing sheet schemas so we're able to read _something_.
-	let sheet_schema = match context.schema.sheet(sheet_name) {
-		Err(schema::Error::NotFound(schema::ErrorValue::Sheet(sheet_name))) => Ok(schema::Sheet {
-			name: sheet_name,
-			order: schema::Order::Offset,
-			node: schema::Node::Struct(vec![]),
-		}),
-		other => other,
-	}?;
-
+	let sheet_schema = get_sheet_schema(context.schema, sheet_name)?;
 	let columns = get_sorted_columns(&sheet_schema, &sheet_data)?;
+	check_column_drift(&sheet_schema, columns.len())?;
 
 	let value = read_node(
 		&sheet_schema.node,
@@ -116,6 +286,35 @@ fn read_sheet(context: ReaderContext) -> Result<Value> {
 	Ok(value)
 }
 
+fn get_sheet_schema(schema: &dyn schema::Schema, sheet_name: &str) -> Result<schema::Sheet> {
+	// Fabricate an empty schema for missing sheet schemas so we're able to read _something_.
+	let sheet_schema = match schema.sheet(sheet_name) {
+		Err(schema::Error::NotFound(schema::ErrorValue::Sheet(sheet_name))) => Ok(schema::Sheet {
+			name: sheet_name,
+			order: schema::Order::Offset,
+			node: schema::Node::Struct(vec![]),
+		}),
+		other => other,
+	}?;
+
+	Ok(sheet_schema)
+}
+
+fn check_column_drift(schema: &schema::Sheet, column_count: usize) -> Result<()> {
+	// Schemas commonly lag behind game updates that add columns - surface that
+	// explicitly rather than failing somewhere in the node tree.
+	let expected = usize::try_from(schema.node.size()).context("schema node too large")?;
+	if expected > column_count {
+		return Err(Error::SchemaOutdated(ColumnDriftError {
+			sheet: schema.name.clone(),
+			expected,
+			actual: column_count,
+		}));
+	}
+
+	Ok(())
+}
+
 fn get_sorted_columns(
 	schema: &schema::Sheet,
 	data: &excel::Sheet<&str>,
@@ -142,20 +341,74 @@ fn read_node(node: &schema::Node, context: ReaderContext) -> Result<Value> {
 
 fn read_node_scalar(scalar: &schema::Scalar, mut context: ReaderContext) -> Result<Value> {
 	match context.read_as {
-		As::Raw => Ok(Value::Scalar(context.next_field()?)),
-		As::Html => read_scalar_html(context),
+		As::Raw => {
+			// Raw fields ignore the configured precision, but may still be
+			// explicitly rounded.
+			let field = context.next_field()?;
+			Ok(Value::Scalar(match context.round {
+				Some(places) => round_field(field, places),
+				None => field,
+			}))
+		}
+		As::Html => read_scalar_formatted(context, "html", Value::Html),
+		As::Markdown => read_scalar_formatted(context, "markdown", Value::Markdown),
+		As::Plain => read_scalar_formatted(context, "plain text", Value::Plain),
+		As::Hex => read_scalar_hex(context),
+		As::Label => read_scalar_label(scalar, context),
 		As::Default => read_scalar_default(scalar, context),
 	}
 }
 
-fn read_scalar_html(mut context: ReaderContext) -> Result<Value> {
+fn round_field(field: excel::Field, places: u8) -> excel::Field {
+	match field {
+		excel::Field::F32(value) => {
+			let factor = 10f64.powi(places.into());
+			excel::Field::F32(((f64::from(value) * factor).round() / factor) as f32)
+		}
+		other => other,
+	}
+}
+
+fn read_scalar_formatted(
+	mut context: ReaderContext,
+	format: &str,
+	value: impl FnOnce(SeString<'static>) -> Value,
+) -> Result<Value> {
 	let field = context.next_field()?;
 	let string = field.into_string().map_err(|field| {
 		Error::FilterSchemaMismatch(
-			context.mismatch_error(format!("cannot format {field:?} as html")),
+			context.mismatch_error(format!("cannot format {field:?} as {format}")),
+		)
+	})?;
+	Ok(value(string))
+}
+
+fn read_scalar_hex(mut context: ReaderContext) -> Result<Value> {
+	let field = context.next_field()?;
+	let bits = integer_bits(field).map_err(|field| {
+		Error::FilterSchemaMismatch(
+			context.mismatch_error(format!("cannot format {field:?} as hex")),
 		)
 	})?;
-	Ok(Value::Html(string))
+	Ok(Value::Hex(bits))
+}
+
+/// Bit pattern of an integer field, at the field's native width - signed values
+/// are not sign-extended, such that `-1i8` is `0xff`.
+fn integer_bits(field: excel::Field) -> Result<u64, excel::Field> {
+	use excel::Field as F;
+	let bits = match field {
+		F::I8(value) => (value as u8).into(),
+		F::I16(value) => (value as u16).into(),
+		F::I32(value) => (value as u32).into(),
+		F::I64(value) => value as u64,
+		F::U8(value) => value.into(),
+		F::U16(value) => value.into(),
+		F::U32(value) => value.into(),
+		F::U64(value) => value,
+		other => return Err(other),
+	};
+	Ok(bits)
 }
 
 fn read_scalar_default(scalar: &schema::Scalar, mut context: ReaderContext) -> Result<Value> {
@@ -163,7 +416,7 @@ fn read_scalar_default(scalar: &schema::Scalar, mut context: ReaderContext) -> R
 
 	use schema::Scalar as S;
 	let out = match scalar {
-		S::Default => Value::Scalar(field),
+		S::Default => Value::Scalar(context.read.round(field, context.round)),
 		S::Reference(targets) => read_scalar_reference(field, targets, context)?,
 		S::Icon => read_scalar_icon(field)?,
 
@@ -179,7 +432,7 @@ fn read_scalar_default(scalar: &schema::Scalar, mut context: ReaderContext) -> R
 fn read_scalar_reference(
 	field: excel::Field,
 	targets: &[schema::ReferenceTarget],
-	context: ReaderContext,
+	mut context: ReaderContext,
 ) -> Result<Value> {
 	// TODO: are references _always_ i32? like, always always?
 	let target_value = read_scalar_i32(field)
@@ -188,100 +441,396 @@ fn read_scalar_reference(
 	let mut reference = Reference::Scalar(target_value);
 
 	// A target less than 0 (typically -1) is usually used to signify that a link
-	// is not present on this row. Also ensure that we've not run out of recursion
-	// depth. We avoid early return if following an active reference chain.
-	// TODO: would be neat to halt recursion later, but target checking does have a cost that needs to be considered.
-	if target_value < 0 || (context.depth == 0 && context.filter == &Filter::All) {
+	// is not present on this row.
+	if target_value < 0 {
 		return Ok(Value::Reference(reference));
 	}
 	let target_value = u32::try_from(target_value)
 		.expect("target value should always be >= 0 due to prior condition");
 
+	// Ensure that we've not run out of recursion depth. We avoid early return if
+	// following an active reference chain.
+	// TODO: would be neat to halt recursion later, but target checking does have a cost that needs to be considered.
+	if context.depth == 0 && context.filter == &Filter::All {
+		if let Some((target, row_id)) = resolve_target(targets, target_value, &mut context)? {
+			reference = Reference::Unpopulated {
+				value: target_value,
+				sheet: target.sheet.to_string(),
+				row_id,
+			};
+		} else if context.read.reference_sheets {
+			reference = Reference::Unresolved {
+				value: target_value,
+				sheets: target_sheets(targets.iter().map(|target| target.sheet.as_str())),
+			};
+		}
+		return Ok(Value::Reference(reference));
+	}
+
+	// Leave the reference unexpanded if the read has exhausted its budget.
+	if !context.references.available() {
+		tracing::debug!(
+			sheet = context.sheet,
+			row_id = context.row_id,
+			"reference budget exhausted, leaving reference unexpanded"
+		);
+		return Ok(Value::Reference(reference));
+	}
+
 	// NOTE: a lot of the TODOs here are immediately break;ing - this is to avoid a potentially correct target that is simply unhandled being ignored and a later, incorrect target being picked as a result.
 	for target in targets {
-		if let Some(condition) = &target.condition {
-			let key = "__bm_target_condition";
-
-			// TODO: This is effectively spinning an entirely new read tree just to check the condition, which is dumb. It'll technically hit cache all the way down, but this is incredibly dumb.
-			let data = read_sheet(ReaderContext {
-				filter: &Filter::Struct(HashMap::from([(
-					key.to_string(),
-					StructEntry {
-						field: condition.selector.clone(),
-						language: context.language,
-						read_as: As::Raw,
-						filter: Filter::All,
-					},
-				)])),
-				rows: &mut *context.rows,
+		if !target_condition_met(target, &mut context)? {
+			continue;
+		}
+
+		let Some((row_id, subrow_id)) = target_row_ids(target, target_value, &context)? else {
+			continue;
+		};
+
+		let sheet_data = context.excel.sheet(&target.sheet)?;
+		let subrows = sheet_data.kind()? == exh::SheetKind::Subrows;
+
+		// Try to fetch the row data - if no matching row exists, continue to the next target.
+		// References into subrow sheets target the row, and are read from its first subrow.
+		let validated_language = context.validated_language()?;
+		let row_data = match sheet_data.subrow_with_options(row_id, subrow_id, validated_language) {
+			Err(ironworks::Error::NotFound(ironworks::ErrorValue::Row { .. })) => continue,
+			other => other,
+		}?;
+
+		// Compact references only apply where the request has not already narrowed
+		// the fields to read from the target.
+		let compact = match context.filter {
+			Filter::All => context
+				.options
+				.compact_references
+				.as_deref()
+				.map(|field| compact_filter(field, context.language)),
+			_ => None,
+		};
+
+		// The budget is consumed before reading the target, such that references
+		// within it are limited by what remains.
+		context.references.consume();
+
+		let read_child = |subrow_id: u16, row_data: excel::Row| {
+			read_sheet(ReaderContext {
+				sheet: &target.sheet,
+				row_id,
+				subrow_id,
+
+				filter: compact.as_ref().unwrap_or(context.filter),
+				rows: &mut HashMap::from([(context.language, row_data)]),
+				depth: match compact.is_some() {
+					true => 0,
+					false => context.depth.max(1) - 1,
+				},
+
 				..context
-			})?;
-
-			let struct_value = match data {
-				Value::Struct(mut map) => map
-					.remove(key)
-					.ok_or_else(|| Error::Failure(anyhow!("Schema target condition mismatch.")))?,
-				_ => Err(anyhow!(
-					"Did not recieve a struct from target condition lookup."
-				))?,
-			};
-			let scalar_value = match struct_value {
-				Value::Scalar(field) => read_scalar_u32(field)?,
-				_ => Err(anyhow!(
-					"Did not recieve a scalar from struct in target condition lookup."
-				))?,
-			};
+			})
+		};
 
-			if scalar_value != condition.value {
-				continue;
+		let child_data = match subrows && context.options.all_subrows {
+			false => read_child(row_data.subrow_id(), row_data)?,
+
+			// Subrows are contiguous from 0 - read until we run out.
+			true => {
+				let mut values = vec![];
+				for subrow_id in 0..=u16::MAX {
+					let row_data =
+						match sheet_data.subrow_with_options(row_id, subrow_id, validated_language)
+						{
+							// Only the row running out ends the subrows.
+							Err(ironworks::Error::NotFound(ironworks::ErrorValue::Row {
+								..
+							})) => break,
+							other => other,
+						}?;
+					values.push(read_child(subrow_id, row_data)?);
+				}
+				Value::Array(values)
 			}
+		};
+
+		reference = Reference::Populated {
+			value: target_value,
+			sheet: target.sheet.to_string(),
+			row_id,
+			fields: child_data.into(),
 		}
+	}
 
-		// TODO: handle retargeted refs
-		if target.selector.is_some() {
-			tracing::warn!("unhandled target selector: {target:?}");
-			break;
+	Ok(Value::Reference(reference))
+}
+
+/// Resolve the first target a reference would be followed to, and the ID of the
+/// row within it, without reading the target row's fields. Targets are checked
+/// in the same manner as when expanding the reference.
+fn resolve_target<'t>(
+	targets: &'t [schema::ReferenceTarget],
+	target_value: u32,
+	context: &mut ReaderContext,
+) -> Result<Option<(&'t schema::ReferenceTarget, u32)>> {
+	for target in targets {
+		if !target_condition_met(target, context)? {
+			continue;
 		}
 
+		let Some((row_id, subrow_id)) = target_row_ids(target, target_value, context)? else {
+			continue;
+		};
+
 		let sheet_data = context.excel.sheet(&target.sheet)?;
+		let validated_language = context.validated_language()?;
+		match sheet_data.subrow_with_options(row_id, subrow_id, validated_language) {
+			Err(ironworks::Error::NotFound(ironworks::ErrorValue::Row { .. })) => continue,
+			other => return Ok(Some((target, other?.row_id()))),
+		}
+	}
+
+	Ok(None)
+}
+
+/// Resolve the IDs of the row within a target sheet that a reference value points
+/// to. Values are row IDs, unless the target has a selector, in which case the
+/// value is matched against the selected field of the target sheet's rows.
+/// Returns `None` if the selector cannot be resolved.
+fn target_row_ids(
+	target: &schema::ReferenceTarget,
+	target_value: u32,
+	context: &ReaderContext,
+) -> Result<Option<(u32, u16)>> {
+	let Some(selector) = &target.selector else {
+		return Ok(Some((target_value, 0)));
+	};
+
+	let index = context
+		.selectors
+		.get_or_build(&target.sheet, selector, || {
+			build_selector_index(target, selector, context)
+		})?;
 
-		// TODO: handle references targeting subrows (how?)
-		if sheet_data.kind()? == exh::SheetKind::Subrows {
-			tracing::warn!("unhandled subrow sheet target: {target:?}");
-			break;
+	Ok(index.and_then(|index| index.get(&target_value).copied()))
+}
+
+/// Build an index of the rows of a target sheet, keyed by the value of the
+/// selected field. Where multiple rows share a value, the first is indexed.
+/// Returns `None` if the selector cannot be resolved.
+fn build_selector_index(
+	target: &schema::ReferenceTarget,
+	selector: &str,
+	context: &ReaderContext,
+) -> Result<Option<SelectorIndex>> {
+	let sheet_data = context.excel.sheet(&target.sheet)?;
+
+	// A schema that can't be read leaves the selector unresolvable, rather than
+	// failing the read of the referencing row.
+	let sheet_schema = match get_sheet_schema(context.schema, &target.sheet) {
+		Ok(sheet_schema) => sheet_schema,
+		Err(error) => {
+			tracing::warn!(?target, ?error, "could not read target selector schema");
+			return Ok(None);
 		}
+	};
+	let columns = get_sorted_columns(&sheet_schema, &sheet_data)?;
 
-		// Try to fetch the row data - if no matching row exists, continue to the next target.
-		// TODO: handle target selectors
+	// Selectors must name a single-column field on the target sheet.
+	let schema::Node::Struct(fields) = &sheet_schema.node else {
+		return Ok(None);
+	};
+	let column = iterate_struct_fields(fields, &columns, Unknowns::Omit)?.find_map(
+		|(name, node, columns)| match (&*node, &*columns) {
+			(schema::Node::Scalar(_), [column]) if name == selector => Some(column.clone()),
+			_ => None,
+		},
+	);
+	let Some(column) = column else {
+		tracing::warn!(?target, "unresolvable target selector");
+		return Ok(None);
+	};
+
+	// NOTE: Selected fields are expected to be numeric, so rows are scanned in the default language.
+	let mut index = HashMap::new();
+	for row in sheet_data.into_iter() {
+		let value = row
+			.field(&column)
+			.ok()
+			.and_then(|field| read_scalar_u32(field).ok());
+		if let Some(value) = value {
+			index
+				.entry(value)
+				.or_insert((row.row_id(), row.subrow_id()));
+		}
+	}
+
+	Ok(Some(Arc::new(index)))
+}
+
+/// Check if the condition of a reference target, if any, is met by the row
+/// being read.
+fn target_condition_met(
+	target: &schema::ReferenceTarget,
+	context: &mut ReaderContext,
+) -> Result<bool> {
+	let Some(condition) = &target.condition else {
+		return Ok(true);
+	};
+
+	let key = "__bm_target_condition";
+
+	// TODO: This is effectively spinning an entirely new read tree just to check the condition, which is dumb. It'll technically hit cache all the way down, but this is incredibly dumb.
+	let data = read_sheet(ReaderContext {
+		filter: &Filter::Struct(HashMap::from([(
+			key.to_string(),
+			StructEntry {
+				field: condition.selector.clone(),
+				language: context.language,
+				read_as: As::Raw,
+				prefix: false,
+				depth: None,
+				round: None,
+				filter: Filter::All,
+			},
+		)])),
+		round: None,
+		rows: &mut *context.rows,
+		..*context
+	})?;
+
+	let struct_value = match data {
+		Value::Struct(mut map) => map
+			.remove(key)
+			.ok_or_else(|| Error::Failure(anyhow!("Schema target condition mismatch.")))?,
+		_ => Err(anyhow!(
+			"Did not recieve a struct from target condition lookup."
+		))?,
+	};
+	let scalar_value = match struct_value {
+		Value::Scalar(field) => read_scalar_u32(field)?,
+		_ => Err(anyhow!(
+			"Did not recieve a scalar from struct in target condition lookup."
+		))?,
+	};
+
+	Ok(scalar_value == condition.value)
+}
+
+/// Read the configured label of the row targeted by a reference field. If no
+/// label can be read, the field's value is returned as-is.
+fn read_scalar_label(scalar: &schema::Scalar, mut context: ReaderContext) -> Result<Value> {
+	let field = context.next_field()?;
+
+	// Labels are a presentational nicety - failing to read one should not fail
+	// the read of the row it's on.
+	match read_label(scalar, field.clone(), &mut context) {
+		Ok(Some(label)) => Ok(label),
+		Ok(None) => Ok(Value::Scalar(field)),
+		Err(error) => {
+			tracing::debug!(
+				sheet = context.sheet,
+				row_id = context.row_id,
+				?error,
+				"could not read reference label"
+			);
+			Ok(Value::Scalar(field))
+		}
+	}
+}
+
+fn read_label(
+	scalar: &schema::Scalar,
+	field: excel::Field,
+	context: &mut ReaderContext,
+) -> Result<Option<Value>> {
+	let schema::Scalar::Reference(targets) = scalar else {
+		return Ok(None);
+	};
+
+	// Reading a label reads the target row, and is limited in the same manner
+	// as expanding the reference would be.
+	if context.depth == 0 || !context.references.available() {
+		return Ok(None);
+	}
+
+	let Some(target_value) = read_scalar_i32(field)
+		.ok()
+		.and_then(|value| u32::try_from(value).ok())
+	else {
+		return Ok(None);
+	};
+
+	for target in targets {
+		let Some(label_field) = context.read.labels.get(&target.sheet) else {
+			continue;
+		};
+
+		if !target_condition_met(target, context)? {
+			continue;
+		}
+
+		let Some((row_id, subrow_id)) = target_row_ids(target, target_value, context)? else {
+			continue;
+		};
+
+		let sheet_data = context.excel.sheet(&target.sheet)?;
 		let validated_language = context.validated_language()?;
-		let row_data = match sheet_data.row_with_options(target_value, validated_language) {
+		let row_data = match sheet_data.subrow_with_options(row_id, subrow_id, validated_language) {
 			Err(ironworks::Error::NotFound(ironworks::ErrorValue::Row { .. })) => continue,
 			other => other,
 		}?;
 
-		let row_id = row_data.row_id();
-		let subrow_id = row_data.subrow_id();
+		let filter = compact_filter(label_field, context.language);
 
-		let child_data = read_sheet(ReaderContext {
+		context.references.consume();
+		let data = read_sheet(ReaderContext {
 			sheet: &target.sheet,
 			row_id,
 			subrow_id,
 
+			filter: &filter,
+			read_as: As::Default,
 			rows: &mut HashMap::from([(context.language, row_data)]),
-			depth: context.depth.max(1) - 1,
+			depth: 0,
 
-			..context
+			..*context
 		})?;
 
-		reference = Reference::Populated {
-			value: target_value,
-			sheet: target.sheet.to_string(),
-			row_id,
-			fields: child_data.into(),
+		if let Value::Struct(mut fields) = data {
+			if let Some(label @ Value::Scalar(excel::Field::String(_))) =
+				fields.remove(label_field.as_str())
+			{
+				return Ok(Some(label));
+			}
 		}
 	}
 
-	Ok(Value::Reference(reference))
+	Ok(None)
+}
+
+/// Build a filter selecting a single field from a reference target.
+fn compact_filter(field: &str, language: excel::Language) -> Filter {
+	Filter::Struct(HashMap::from([(
+		field.to_string(),
+		StructEntry {
+			field: field.to_string(),
+			language,
+			read_as: As::Default,
+			prefix: false,
+			depth: None,
+			round: None,
+			filter: Filter::All,
+		},
+	)]))
+}
+
+/// Collect the unique sheets referenced by a list of targets, in target order.
+fn target_sheets<'a>(sheets: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+	let mut seen = HashSet::new();
+	sheets
+		.into_iter()
+		.filter(|sheet| seen.insert(*sheet))
+		.map(str::to_string)
+		.collect()
 }
 
 fn read_scalar_icon(field: excel::Field) -> Result<Value> {
@@ -328,9 +877,18 @@ fn read_node_array(
 	count: u32,
 	mut context: ReaderContext,
 ) -> Result<Value> {
-	let filter = match context.filter {
-		Filter::All => &Filter::All,
-		Filter::Array(inner) => inner.as_ref(),
+	let selections = match context.filter {
+		Filter::All => vec![(&Filter::All, 0..count)],
+		Filter::Array(inner) => vec![(inner.as_ref(), 0..count)],
+		// Ranges are clamped to the array, such that out of range indices select
+		// nothing rather than failing.
+		Filter::ArrayRanges(ranges) => ranges
+			.iter()
+			.map(|range| {
+				let end = range.end.map_or(count, |end| end.min(count));
+				(&range.filter, range.start.min(end)..end)
+			})
+			.collect(),
 		other => {
 			return Err(Error::FilterSchemaMismatch(
 				context.mismatch_error(format!("expected array filter, got {other:?}")),
@@ -339,16 +897,18 @@ fn read_node_array(
 	};
 
 	let size = usize::try_from(element_node.size()).context("schema node too large")?;
-	let values = (0..count)
-		.scan(0usize, |index, _| {
-			let Some(columns) = context.columns.get(*index..*index + size) else {
-				return Some(Err(Error::SchemaGameMismatch(
+	let values = selections
+		.into_iter()
+		.flat_map(|(filter, indices)| indices.map(move |index| (filter, index)))
+		.map(|(filter, index)| {
+			let offset = usize::try_from(index).context("array index too large")? * size;
+			let Some(columns) = context.columns.get(offset..offset + size) else {
+				return Err(Error::SchemaGameMismatch(
 					context.mismatch_error(format!("insufficient columns to satisfy array")),
-				)));
+				));
 			};
-			*index += size;
 
-			let result = read_node(
+			read_node(
 				element_node,
 				ReaderContext {
 					filter,
@@ -357,9 +917,7 @@ fn read_node_array(
 
 					..context
 				},
-			);
-
-			Some(result)
+			)
 		})
 		.collect::<Result<Vec<_>>>()?;
 
@@ -370,13 +928,20 @@ fn read_node_struct(
 	schema_fields: &[schema::StructField],
 	mut context: ReaderContext,
 ) -> Result<Value> {
+	let mut prefix_filters = vec![];
 	let filter_fields = match context.filter {
-		Filter::All => None,
+		Filter::All | Filter::AllExcept(_) => None,
 		Filter::Struct(filter_fields) => {
 			let mut filters_by_field = HashMap::new();
 			for (key, entry) in filter_fields.iter() {
+				// Prefixes may match any number of fields, and are resolved per field.
+				if entry.prefix {
+					prefix_filters.push((key, entry));
+					continue;
+				}
+
 				filters_by_field
-					.entry(entry.field.clone())
+					.entry(context.resolve_field_name(&entry.field, schema_fields)?)
 					.or_insert_with(|| Vec::new())
 					.push((key, entry));
 			}
@@ -389,29 +954,70 @@ fn read_node_struct(
 		}
 	};
 
+	let excluded = match context.filter {
+		Filter::AllExcept(names) => names
+			.iter()
+			.map(|name| context.resolve_field_name(name, schema_fields))
+			.collect::<Result<HashSet<_>>>()?,
+		_ => HashSet::new(),
+	};
+
 	let mut value_fields = HashMap::new();
 
-	for (field_name, node, columns) in iterate_struct_fields(schema_fields, context.columns)? {
+	let unknowns = match (
+		context.options.omit_unknowns,
+		context.read.group_packed_bools,
+	) {
+		(true, _) => Unknowns::Omit,
+		(false, false) => Unknowns::Columns,
+		(false, true) => Unknowns::Grouped,
+	};
+
+	for (field_name, node, columns) in
+		iterate_struct_fields(schema_fields, context.columns, unknowns)?
+	{
+		if excluded.contains(field_name.as_ref()) {
+			continue;
+		}
+
+		let prefixed = prefix_filters
+			.iter()
+			.filter(|(_key, entry)| {
+				matches_prefix(&field_name, &entry.field, context.read.case_insensitive)
+			})
+			.map(|(key, entry)| {
+				(
+					Cow::Owned(prefixed_key(key, entry, &field_name)),
+					Cow::Borrowed(*entry),
+				)
+			})
+			.collect::<Vec<_>>();
+
 		let language_filters = match &filter_fields {
-			Some(fields) => either::Left(match fields.get(field_name.as_ref()) {
-				// Filter exists, but has no entry for this name - no languages to filter to.
-				None => either::Left(iter::empty()),
-
-				// Entry exists for the name, map the language pairs to the expected shape.
-				Some(entries) => either::Right(
-					entries
-						.iter()
-						.map(|(key, entry)| (key.as_str(), Cow::Borrowed(*entry))),
-				),
-			}),
+			Some(fields) => {
+				let exact =
+					match fields.get(field_name.as_ref()) {
+						// Filter exists, but has no entry for this name - no languages to filter to.
+						None => either::Left(iter::empty()),
+
+						// Entry exists for the name, map the language pairs to the expected shape.
+						Some(entries) => either::Right(entries.iter().map(|(key, entry)| {
+							(Cow::Borrowed(key.as_str()), Cow::Borrowed(*entry))
+						})),
+					};
+				either::Left(exact.chain(prefixed))
+			}
 
 			// ::All filter, walk with the current context language.
 			None => either::Right(std::iter::once((
-				field_name.as_ref(),
+				Cow::Borrowed(field_name.as_ref()),
 				Cow::Owned(StructEntry {
 					field: field_name.to_string(),
 					language: context.language,
 					read_as: As::Default,
+					prefix: false,
+					depth: None,
+					round: None,
 					filter: Filter::All,
 				}),
 			))),
@@ -425,50 +1031,115 @@ fn read_node_struct(
 			.collect::<Vec<_>>();
 
 		for (key, entry) in language_filters {
-			let value = read_node(
-				node,
+			let result = read_node(
+				&node,
 				ReaderContext {
 					filter: &entry.filter,
 					language: entry.language,
 					read_as: entry.read_as,
-					columns,
+					round: entry.round.or(context.round),
+					columns: &columns,
 					rows: &mut context.rows,
+					depth: entry_depth(&entry, context.depth, context.options.max_depth),
 					path: &path,
 					..context
 				},
-			)?;
+			);
 
-			match value_fields.entry(key.to_string()) {
-				hash_map::Entry::Vacant(entry) => {
-					entry.insert(value);
-				}
-				hash_map::Entry::Occupied(entry) => {
-					tracing::warn!(key = ?entry.key(), "struct key collision");
-				}
-			}
+			let Some(value) = skip_filter_mismatch(result, context.read.lenient_filters)? else {
+				continue;
+			};
+
+			// Exact and prefix entries may select the same field under the same
+			// key - merge their selections rather than picking one.
+			let value = match value_fields.remove(key.as_ref()) {
+				Some(existing) => existing.merge(value),
+				None => value,
+			};
+			value_fields.insert(key.into_owned(), value);
 		}
 	}
 
-	// TODO: i can catch filterschemamismatch at the struct level and skip the key - ideally raise a warning in future
-	// what about schemagamemismatch?
+	// TODO: what about schemagamemismatch?
+
+	// Keys requested by the filter that did not match a field are otherwise omitted.
+	if let Some(fields) = filter_fields.filter(|_| context.options.explicit_nulls) {
+		let keys = fields.values().flatten().map(|(key, _)| key.as_str());
+		insert_null_keys(&mut value_fields, keys);
+	}
 
 	Ok(Value::Struct(value_fields))
 }
 
-// TODO: this is fairly gnarly - look into a crate for generators, i.e. genawaiter?
-fn iterate_struct_fields<'s, 'c>(
-	fields: &'s [schema::StructField],
-	columns: &'c [exh::ColumnDefinition],
-) -> Result<impl Iterator<Item = (Cow<'s, str>, &'s schema::Node, &'c [exh::ColumnDefinition])>> {
-	// Eagerly ensure that we have enough columns available to satisfy the struct field definitions.
-	let fields_length = match fields.last() {
-		Some(field) => {
-			usize::try_from(field.offset + field.node.size()).expect("schema field size too large")
-		}
-		None => 0,
+/// Depth to read a struct entry at. Overrides on the entry replace the current
+/// depth, clamped to the maximum depth. If there is no maximum, overrides are
+/// clamped to the current depth.
+fn entry_depth(entry: &StructEntry, depth: u8, max_depth: Option<u8>) -> u8 {
+	match entry.depth {
+		Some(requested) => requested.min(max_depth.unwrap_or(depth)),
+		None => depth,
+	}
+}
+
+/// Output key for a field matched by a prefix filter entry. The prefix pattern
+/// within the filter key is replaced with the full field name, retaining any
+/// decorators.
+fn prefixed_key(key: &str, entry: &StructEntry, field_name: &str) -> String {
+	let decorators = key
+		.strip_prefix(entry.field.as_str())
+		.map(|rest| rest.strip_prefix('*').unwrap_or(rest))
+		.unwrap_or_default();
+	format!("{field_name}{decorators}")
+}
+
+/// Skip struct fields whose filter does not match the schema, if lenient
+/// filtering is enabled.
+fn skip_filter_mismatch(result: Result<Value>, lenient: bool) -> Result<Option<Value>> {
+	match result {
+		Err(Error::FilterSchemaMismatch(error)) if lenient => {
+			tracing::warn!(
+				field = %error.field,
+				reason = %error.reason,
+				"skipping field with mismatched filter"
+			);
+			Ok(None)
+		}
+		other => other.map(Some),
+	}
+}
+
+/// Insert a null placeholder for any of the provided keys missing from the fields.
+fn insert_null_keys<'k>(
+	value_fields: &mut HashMap<String, Value>,
+	keys: impl IntoIterator<Item = &'k str>,
+) {
+	for key in keys {
+		value_fields.entry(key.to_string()).or_insert(Value::Null);
+	}
+}
+
+/// A span of columns within a struct.
+enum StructSpan<'s> {
+	/// Columns described by a schema field.
+	Field(&'s schema::StructField, Range<usize>),
+	/// A single column not described by any schema field.
+	Unknown(usize),
+}
+
+// TODO: this is fairly gnarly - look into a crate for generators, i.e. genawaiter?
+fn struct_spans(
+	fields: &[schema::StructField],
+	column_count: usize,
+) -> Result<impl Iterator<Item = StructSpan<'_>>> {
+	// Eagerly ensure that we have enough columns available to satisfy the struct field definitions.
+	let fields_length = match fields.last() {
+		Some(field) => {
+			usize::try_from(field.offset + field.node.size()).expect("schema field size too large")
+		}
+		None => 0,
 	};
 
-	if fields_length > columns.len() {
+	if fields_length > column_count {
 		// TODO: use context for the mismatch error?
 		return Err(Error::SchemaGameMismatch(MismatchError {
 			field: "TODO".into(),
@@ -476,24 +1147,8 @@ fn iterate_struct_fields<'s, 'c>(
 		}));
 	}
 
-	// Utility to generate items for columns not covered by a field.
-	let generate_unknowns = |range: Range<usize>| {
-		range.map(|offset| {
-			let column = &columns[offset];
-			(
-				Cow::<str>::Owned(format!(
-					"unknown{}{}",
-					column.offset(),
-					unknown_suffix(column.kind())
-				)),
-				&schema::Node::Scalar(schema::Scalar::Default),
-				&columns[offset..offset + 1],
-			)
-		})
-	};
-
-	let items = fields
-		.into_iter()
+	let spans = fields
+		.iter()
 		.scan(0usize, move |last_offset, field| {
 			let field_offset =
 				usize::try_from(field.offset).expect("schema field offset too large");
@@ -501,37 +1156,228 @@ fn iterate_struct_fields<'s, 'c>(
 				usize::try_from(field.node.size()).expect("schema field size too large");
 
 			// Generate unknowns for any columns between the last field and this one.
-			let items = generate_unknowns(*last_offset..field_offset)
-				// Add an item for this field's schema structure.
-				.chain(iter::once((
-					Cow::<str>::Borrowed(&field.name),
-					&field.node,
-					&columns[field_offset..field_offset + field_size],
+			let spans = (*last_offset..field_offset)
+				.map(StructSpan::Unknown)
+				// Add a span for this field's schema structure.
+				.chain(iter::once(StructSpan::Field(
+					field,
+					field_offset..field_offset + field_size,
 				)));
 
 			*last_offset = field_offset + field_size;
 
-			Some(items)
+			Some(spans)
 		})
 		.flatten()
 		// Generate unkowns for any trailing columns after the last field.
-		.chain(generate_unknowns(fields_length..columns.len()));
+		.chain((fields_length..column_count).map(StructSpan::Unknown));
+
+	Ok(spans)
+}
+
+/// Handling of columns not described by the schema when iterating struct fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unknowns {
+	/// Skip unknown columns.
+	Omit,
+	/// Yield each unknown column as a separate field.
+	Columns,
+	/// Yield each unknown column as a separate field, other than packed boolean
+	/// columns, which are grouped by byte into a struct keyed by bit. Groups
+	/// include every unknown packed boolean of the byte within the struct, and
+	/// are yielded in place of the first.
+	Grouped,
+}
+
+fn iterate_struct_fields<'s, 'c>(
+	fields: &'s [schema::StructField],
+	columns: &'c [exh::ColumnDefinition],
+	unknowns: Unknowns,
+) -> Result<
+	impl Iterator<
+		Item = (
+			Cow<'s, str>,
+			Cow<'s, schema::Node>,
+			Cow<'c, [exh::ColumnDefinition]>,
+		),
+	>,
+> {
+	let spans = struct_spans(fields, columns.len())?.collect::<Vec<_>>();
+
+	// Packed booleans of a byte are not necessarily adjacent - collect the full
+	// group for each byte up front.
+	let mut groups = HashMap::<u16, Vec<exh::ColumnDefinition>>::new();
+	if unknowns == Unknowns::Grouped {
+		for span in &spans {
+			let StructSpan::Unknown(index) = span else {
+				continue;
+			};
+			let column = &columns[*index];
+			if packed_bool_bit(column.kind()).is_some() {
+				groups
+					.entry(column.offset())
+					.or_default()
+					.push(column.clone());
+			}
+		}
+	}
+
+	let mut items = vec![];
+	for span in spans {
+		let index = match span {
+			StructSpan::Field(field, range) => {
+				items.push((
+					Cow::Borrowed(field.name.as_str()),
+					Cow::Borrowed(&field.node),
+					Cow::Borrowed(&columns[range]),
+				));
+				continue;
+			}
+			StructSpan::Unknown(_) if unknowns == Unknowns::Omit => continue,
+			StructSpan::Unknown(index) => index,
+		};
+
+		let column = &columns[index];
+		let grouped = unknowns == Unknowns::Grouped && packed_bool_bit(column.kind()).is_some();
+		if !grouped {
+			items.push((
+				Cow::Owned(unknown_key(column.offset(), column.kind())),
+				Cow::Owned(schema::Node::Scalar(schema::Scalar::Default)),
+				Cow::Borrowed(&columns[index..index + 1]),
+			));
+			continue;
+		}
+
+		// Groups are taken by their first column, later columns of the byte have
+		// already been yielded.
+		let Some(group) = groups.remove(&column.offset()) else {
+			continue;
+		};
 
-	Ok(items)
+		let node = schema::Node::Struct(
+			group
+				.iter()
+				.enumerate()
+				.filter_map(|(position, column)| {
+					Some(schema::StructField {
+						name: packed_bool_bit(column.kind())?.to_string(),
+						offset: u32::try_from(position).ok()?,
+						node: schema::Node::Scalar(schema::Scalar::Default),
+					})
+				})
+				.collect(),
+		);
+
+		items.push((
+			Cow::Owned(format!("unknown{}", column.offset())),
+			Cow::Owned(node),
+			Cow::Owned(group),
+		));
+	}
+
+	Ok(items.into_iter())
 }
 
-fn unknown_suffix(kind: exh::ColumnKind) -> &'static str {
+/// Bit of the byte read by a packed boolean column kind.
+pub fn packed_bool_bit(kind: exh::ColumnKind) -> Option<u8> {
 	use exh::ColumnKind as CK;
-	match kind {
-		CK::PackedBool0 => "_0",
-		CK::PackedBool1 => "_1",
-		CK::PackedBool2 => "_2",
-		CK::PackedBool3 => "_3",
-		CK::PackedBool4 => "_4",
-		CK::PackedBool5 => "_5",
-		CK::PackedBool6 => "_6",
-		CK::PackedBool7 => "_7",
-		_ => "",
+	let bit = match kind {
+		CK::PackedBool0 => 0,
+		CK::PackedBool1 => 1,
+		CK::PackedBool2 => 2,
+		CK::PackedBool3 => 3,
+		CK::PackedBool4 => 4,
+		CK::PackedBool5 => 5,
+		CK::PackedBool6 => 6,
+		CK::PackedBool7 => 7,
+		_ => return None,
+	};
+	Some(bit)
+}
+
+/// Count of columns in a sheet, and how many of those are described by the schema.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Coverage {
+	/// Total number of columns.
+	pub columns: usize,
+	/// Number of columns covered by named schema fields.
+	pub covered: usize,
+}
+
+impl Coverage {
+	/// Number of columns not covered by the schema, i.e. read as `unknownN` fields.
+	pub fn unknown(&self) -> usize {
+		self.columns - self.covered
+	}
+
+	/// Percentage of columns covered by the schema. Sheets without columns are
+	/// considered fully covered.
+	pub fn percentage(&self) -> f64 {
+		match self.columns {
+			0 => 100.0,
+			columns => self.covered as f64 / columns as f64 * 100.0,
+		}
+	}
+}
+
+impl std::ops::Add for Coverage {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self {
+			columns: self.columns + other.columns,
+			covered: self.covered + other.covered,
+		}
+	}
+}
+
+fn node_coverage(node: &schema::Node, column_count: usize) -> Result<Coverage> {
+	let coverage = match node {
+		schema::Node::Struct(fields) => {
+			struct_spans(fields, column_count)?.try_fold(Coverage::default(), |acc, span| {
+				let span_coverage = match span {
+					StructSpan::Field(field, range) => node_coverage(&field.node, range.len())?,
+					StructSpan::Unknown(_) => Coverage {
+						columns: 1,
+						covered: 0,
+					},
+				};
+				Ok::<_, Error>(acc + span_coverage)
+			})?
+		}
+
+		schema::Node::Array { count, node } => {
+			let size = usize::try_from(node.size()).context("schema node too large")?;
+			let count = usize::try_from(*count).context("schema array too large")?;
+			let element = node_coverage(node, size)?;
+			Coverage {
+				columns: element.columns * count,
+				covered: element.covered * count,
+			}
+		}
+
+		schema::Node::Scalar(_) => Coverage {
+			columns: column_count,
+			covered: column_count,
+		},
+	};
+
+	Ok(coverage)
+}
+
+fn raw_struct(fields: impl IntoIterator<Item = (u16, exh::ColumnKind, excel::Field)>) -> Value {
+	Value::Struct(
+		fields
+			.into_iter()
+			.map(|(offset, kind, field)| (unknown_key(offset, kind), Value::Scalar(field)))
+			.collect(),
+	)
+}
+
+fn unknown_key(offset: u16, kind: exh::ColumnKind) -> String {
+	match packed_bool_bit(kind) {
+		Some(bit) => format!("unknown{offset}_{bit}"),
+		None => format!("unknown{offset}"),
 	}
 }
 
@@ -548,9 +1394,13 @@ struct ReaderContext<'a> {
 
 	filter: &'a Filter,
 	read_as: As,
+	round: Option<u8>,
 	columns: &'a [exh::ColumnDefinition],
 	rows: &'a mut HashMap<excel::Language, excel::Row>,
 	depth: u8,
+	options: &'a ReadOptions,
+	references: &'a ReferenceBudget,
+	selectors: &'a SelectorIndices,
 
 	path: &'a [&'a str],
 }
@@ -590,6 +1440,17 @@ impl ReaderContext<'_> {
 		Ok(self.language)
 	}
 
+	fn resolve_field_name(&self, name: &str, fields: &[schema::StructField]) -> Result<String> {
+		if !self.read.case_insensitive {
+			return Ok(name.to_string());
+		}
+
+		let field = resolve_name(name, fields, |field| &field.name)
+			.map_err(|error| Error::FilterSchemaMismatch(self.mismatch_error(error.to_string())))?;
+
+		Ok(field.map_or_else(|| name.to_string(), |field| field.name.clone()))
+	}
+
 	fn mismatch_error(&self, reason: impl ToString) -> MismatchError {
 		MismatchError {
 			field: self.path.join("."),
@@ -597,3 +1458,1203 @@ impl ReaderContext<'_> {
 		}
 	}
 }
+
+/// Number of references that may still be expanded within a single read.
+struct ReferenceBudget(Cell<Option<usize>>);
+
+impl ReferenceBudget {
+	fn new(limit: Option<usize>) -> Self {
+		Self(Cell::new(limit))
+	}
+
+	fn available(&self) -> bool {
+		self.0.get() != Some(0)
+	}
+
+	fn consume(&self) {
+		if let Some(remaining) = self.0.get() {
+			self.0.set(Some(remaining.saturating_sub(1)));
+		}
+	}
+}
+
+/// Data built over the course of reads that may be reused by later reads, such
+/// as selector indices. Cached data is specific to the game data and schema it
+/// was read with - a cache must not be shared between versions or schemas.
+#[derive(Default)]
+pub struct ReadCache {
+	selectors: SelectorIndices,
+}
+
+/// Row and subrow IDs of a sheet's rows, keyed by the value of a selected field.
+type SelectorIndex = Arc<HashMap<u32, (u32, u16)>>;
+
+/// Selector indices keyed by sheet and selector. Unresolvable selectors are
+/// cached as `None`.
+#[derive(Default)]
+struct SelectorIndices(Mutex<HashMap<(String, String), Option<SelectorIndex>>>);
+
+impl SelectorIndices {
+	fn get_or_build(
+		&self,
+		sheet: &str,
+		selector: &str,
+		build: impl FnOnce() -> Result<Option<SelectorIndex>>,
+	) -> Result<Option<SelectorIndex>> {
+		let key = (sheet.to_string(), selector.to_string());
+		if let Some(index) = self.0.lock().expect("poisoned").get(&key) {
+			return Ok(index.clone());
+		}
+
+		// Building scans the target sheet - avoid holding the lock while doing so,
+		// such that reads of other selectors are not blocked.
+		let index = build()?;
+		self.0.lock().expect("poisoned").insert(key, index.clone());
+		Ok(index)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::fixture::{reference, scalar, struct_node, Cell, Fixture, TestSheet};
+
+	use super::*;
+
+	/// Recipes referencing items, which in turn reference their category.
+	fn reference_fixture() -> Fixture {
+		use exh::ColumnKind as CK;
+		Fixture::new(vec![
+			(
+				TestSheet::new("ItemUICategory", [(CK::String, 0)])
+					.row(1, [Cell::String("Medicine".into())]),
+				struct_node([("Name", scalar())]),
+			),
+			(
+				TestSheet::new("Item", [(CK::String, 0), (CK::Int32, 4)])
+					.row(1, [Cell::String("Potion".into()), Cell::I32(1)])
+					.row(2, [Cell::String("Ether".into()), Cell::I32(1)]),
+				struct_node([
+					("Name", scalar()),
+					("ItemUICategory", reference(&["ItemUICategory"])),
+				]),
+			),
+			(
+				TestSheet::new("Recipe", [(CK::Int32, 0), (CK::Int32, 4)])
+					.row(1, [Cell::I32(1), Cell::I32(2)]),
+				struct_node([
+					("ItemResult", reference(&["Item"])),
+					("Ingredient", reference(&["Item"])),
+				]),
+			),
+		])
+	}
+
+	fn read_row(
+		read: &Read,
+		fixture: &Fixture,
+		sheet: &str,
+		row_id: u32,
+		filter: &Filter,
+		depth: u8,
+		options: &ReadOptions,
+	) -> Value {
+		read.read(
+			&fixture.excel,
+			&fixture.schema,
+			sheet,
+			row_id,
+			0,
+			excel::Language::English,
+			filter,
+			depth,
+			options,
+			&ReadCache::default(),
+		)
+		.expect("read should not fail")
+	}
+
+	fn field<'v>(value: &'v Value, name: &str) -> &'v Value {
+		let Value::Struct(fields) = value else {
+			panic!("expected struct, got {value:?}");
+		};
+		&fields[name]
+	}
+
+	#[test]
+	fn unfollowed_reference_resolved_target() {
+		let fixture = reference_fixture();
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Recipe",
+			1,
+			&Filter::All,
+			0,
+			&ReadOptions::default(),
+		);
+
+		assert!(matches!(
+			field(&value, "ItemResult"),
+			Value::Reference(Reference::Unpopulated { value: 1, sheet, row_id: 1 }) if sheet == "Item"
+		));
+		assert!(matches!(
+			field(&value, "Ingredient"),
+			Value::Reference(Reference::Unpopulated { value: 2, sheet, row_id: 2 }) if sheet == "Item"
+		));
+	}
+
+	#[test]
+	fn unresolved_reference_target_sheets() {
+		use exh::ColumnKind as CK;
+		let fixture = Fixture::new(vec![
+			(
+				TestSheet::new("Item", [(CK::String, 0)]).row(1, [Cell::String("Potion".into())]),
+				struct_node([("Name", scalar())]),
+			),
+			(
+				TestSheet::new("EventItem", [(CK::String, 0)])
+					.row(1, [Cell::String("Letter".into())]),
+				struct_node([("Name", scalar())]),
+			),
+			(
+				TestSheet::new("Quest", [(CK::Int32, 0)]).row(1, [Cell::I32(5)]),
+				struct_node([("Reward", reference(&["Item", "EventItem", "Item"]))]),
+			),
+		]);
+		let read_quest = |read: &Read| {
+			read_row(
+				read,
+				&fixture,
+				"Quest",
+				1,
+				&Filter::All,
+				0,
+				&ReadOptions::default(),
+			)
+		};
+
+		// Without the opt-in, a reference without a target is left as a bare value.
+		let value = read_quest(&test_read(None));
+		assert!(matches!(
+			field(&value, "Reward"),
+			Value::Reference(Reference::Scalar(5))
+		));
+
+		let read = Read::new(Config {
+			reference_sheets: true,
+			..test_config(None)
+		});
+		let value = read_quest(&read);
+		assert!(matches!(
+			field(&value, "Reward"),
+			Value::Reference(Reference::Unresolved { value: 5, sheets }) if *sheets == ["Item", "EventItem"]
+		));
+	}
+
+	#[test]
+	fn reference_budget_limits_nested_references() {
+		let fixture = reference_fixture();
+		let options = ReadOptions {
+			max_references: Some(1),
+			..Default::default()
+		};
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Recipe",
+			1,
+			&Filter::All,
+			2,
+			&options,
+		);
+
+		// The single reference available is spent on the first field, leaving
+		// references within its target and later fields unexpanded.
+		let Value::Reference(Reference::Populated { fields, .. }) = field(&value, "ItemResult")
+		else {
+			panic!("expected populated reference, got {value:?}");
+		};
+		assert!(matches!(
+			field(fields, "ItemUICategory"),
+			Value::Reference(Reference::Scalar(1))
+		));
+		assert!(matches!(
+			field(&value, "Ingredient"),
+			Value::Reference(Reference::Scalar(2))
+		));
+	}
+
+	#[test]
+	fn compact_references_read_single_field() {
+		let fixture = reference_fixture();
+		let options = ReadOptions {
+			compact_references: Some("Name".into()),
+			..Default::default()
+		};
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Recipe",
+			1,
+			&Filter::All,
+			2,
+			&options,
+		);
+
+		// References expand to their ID and the named field, without following
+		// references within the target.
+		for (name, row) in [("ItemResult", 1), ("Ingredient", 2)] {
+			let Value::Reference(Reference::Populated {
+				value: 1 | 2,
+				sheet,
+				row_id,
+				fields,
+			}) = field(&value, name)
+			else {
+				panic!("expected populated reference, got {value:?}");
+			};
+			assert_eq!((sheet.as_str(), *row_id), ("Item", row));
+
+			let Value::Struct(target) = &**fields else {
+				panic!("expected struct, got {fields:?}");
+			};
+			assert_eq!(target.keys().collect::<Vec<_>>(), vec!["Name"]);
+		}
+	}
+
+	fn subrow_fixture() -> Fixture {
+		use exh::ColumnKind as CK;
+		Fixture::new(vec![
+			(
+				TestSheet::new("RecipeStep", [(CK::String, 0)]).subrows(
+					1,
+					vec![
+						vec![Cell::String("Mix".into())],
+						vec![Cell::String("Heat".into())],
+					],
+				),
+				struct_node([("Name", scalar())]),
+			),
+			(
+				TestSheet::new("Recipe", [(CK::Int32, 0)]).row(1, [Cell::I32(1)]),
+				struct_node([("Step", reference(&["RecipeStep"]))]),
+			),
+		])
+	}
+
+	fn name_is(value: &Value, expected: &str) -> bool {
+		matches!(
+			field(value, "Name"),
+			Value::Scalar(excel::Field::String(name)) if name.to_string() == expected
+		)
+	}
+
+	#[test]
+	fn subrow_reference_reads_first_subrow() {
+		let fixture = subrow_fixture();
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Recipe",
+			1,
+			&Filter::All,
+			1,
+			&ReadOptions::default(),
+		);
+
+		let Value::Reference(Reference::Populated { row_id, fields, .. }) = field(&value, "Step")
+		else {
+			panic!("expected populated reference, got {value:?}");
+		};
+		assert_eq!(*row_id, 1);
+		assert!(name_is(fields, "Mix"));
+	}
+
+	#[test]
+	fn subrow_reference_reads_all_subrows() {
+		let fixture = subrow_fixture();
+		let options = ReadOptions {
+			all_subrows: true,
+			..Default::default()
+		};
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Recipe",
+			1,
+			&Filter::All,
+			1,
+			&options,
+		);
+
+		let Value::Reference(Reference::Populated { fields, .. }) = field(&value, "Step") else {
+			panic!("expected populated reference, got {value:?}");
+		};
+		let Value::Array(subrows) = fields.as_ref() else {
+			panic!("expected array of subrows, got {fields:?}");
+		};
+		assert_eq!(subrows.len(), 2);
+		assert!(name_is(&subrows[0], "Mix"));
+		assert!(name_is(&subrows[1], "Heat"));
+	}
+
+	fn selector_fixture(selector: &str) -> Fixture {
+		use exh::ColumnKind as CK;
+		let target = |sheet: &str| schema::ReferenceTarget {
+			sheet: sheet.into(),
+			selector: Some(selector.into()),
+			condition: None,
+		};
+		Fixture::new(vec![
+			(
+				TestSheet::new("Item", [(CK::String, 0), (CK::UInt32, 4)])
+					.row(1, [Cell::String("Potion".into()), Cell::U32(20)])
+					.row(2, [Cell::String("Ether".into()), Cell::U32(10)])
+					.row(3, [Cell::String("Elixir".into()), Cell::U32(10)]),
+				struct_node([("Name", scalar()), ("Key", scalar())]),
+			),
+			(
+				TestSheet::new("Shop", [(CK::Int32, 0), (CK::Int32, 4)])
+					.row(1, [Cell::I32(10), Cell::I32(20)]),
+				struct_node([
+					(
+						"First",
+						schema::Node::Scalar(schema::Scalar::Reference(vec![target("Item")])),
+					),
+					(
+						"Second",
+						schema::Node::Scalar(schema::Scalar::Reference(vec![target("Item")])),
+					),
+				]),
+			),
+		])
+	}
+
+	#[test]
+	fn selector_reference_indexed() {
+		let fixture = selector_fixture("Key");
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Shop",
+			1,
+			&Filter::All,
+			1,
+			&ReadOptions::default(),
+		);
+
+		// Rows sharing a selected value resolve to the first.
+		assert!(matches!(
+			field(&value, "First"),
+			Value::Reference(Reference::Populated {
+				value: 10,
+				row_id: 2,
+				..
+			})
+		));
+		assert!(matches!(
+			field(&value, "Second"),
+			Value::Reference(Reference::Populated {
+				value: 20,
+				row_id: 1,
+				..
+			})
+		));
+	}
+
+	#[test]
+	fn selector_index_reused_across_reads() {
+		let fixture = selector_fixture("Key");
+		let read = test_read(None);
+		let cache = ReadCache::default();
+		let read_shop = || {
+			read.read(
+				&fixture.excel,
+				&fixture.schema,
+				"Shop",
+				1,
+				0,
+				excel::Language::English,
+				&Filter::All,
+				1,
+				&ReadOptions::default(),
+				&cache,
+			)
+			.expect("read should not fail")
+		};
+		let cached_index = || {
+			let indices = cache.selectors.0.lock().expect("poisoned");
+			assert_eq!(indices.len(), 1);
+			indices
+				.values()
+				.next()
+				.cloned()
+				.flatten()
+				.expect("index should be built")
+		};
+
+		read_shop();
+		let first = cached_index();
+		read_shop();
+		assert!(Arc::ptr_eq(&first, &cached_index()));
+	}
+
+	#[test]
+	fn selector_reference_unresolvable() {
+		let fixture = selector_fixture("Missing");
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Shop",
+			1,
+			&Filter::All,
+			1,
+			&ReadOptions::default(),
+		);
+
+		assert!(matches!(
+			field(&value, "First"),
+			Value::Reference(Reference::Scalar(10))
+		));
+	}
+
+	#[test]
+	fn unpopulated_target_sheets() {
+		let got = target_sheets(["Item", "EventItem", "Item", "Action"]);
+		assert_eq!(got, vec!["Item", "EventItem", "Action"]);
+	}
+
+	#[test]
+	fn integer_bits_native_width() {
+		assert_eq!(integer_bits(excel::Field::I8(-1)).ok(), Some(0xff));
+		assert_eq!(integer_bits(excel::Field::I32(-2)).ok(), Some(0xffff_fffe));
+		assert_eq!(integer_bits(excel::Field::U16(0x1234)).ok(), Some(0x1234));
+		assert!(integer_bits(excel::Field::F32(1.5)).is_err());
+		assert!(integer_bits(excel::Field::Bool(true)).is_err());
+	}
+
+	#[test]
+	fn prefixed_keys_retain_decorators() {
+		let entry = StructEntry {
+			field: "Param".into(),
+			language: excel::Language::English,
+			read_as: As::Default,
+			prefix: true,
+			depth: None,
+			round: None,
+			filter: Filter::All,
+		};
+
+		assert_eq!(prefixed_key("Param*", &entry, "ParamValue"), "ParamValue");
+		assert_eq!(
+			prefixed_key("Param*@lang(ja)", &entry, "ParamValue"),
+			"ParamValue@lang(ja)"
+		);
+	}
+
+	#[test]
+	fn colliding_selections_merged() {
+		use exh::ColumnKind as CK;
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Craft", [(CK::UInt32, 0), (CK::UInt32, 4), (CK::UInt32, 8)])
+				.row(1, [Cell::U32(1), Cell::U32(2), Cell::U32(3)]),
+			struct_node([
+				(
+					"Result",
+					struct_node([("Amount", scalar()), ("Quality", scalar())]),
+				),
+				("Count", scalar()),
+			]),
+		)]);
+
+		let entry = |field: &str, prefix, subfield: &str| StructEntry {
+			field: field.into(),
+			language: excel::Language::English,
+			read_as: As::Default,
+			prefix,
+			depth: None,
+			round: None,
+			filter: Filter::Struct(HashMap::from([(
+				subfield.to_string(),
+				StructEntry {
+					field: subfield.into(),
+					language: excel::Language::English,
+					read_as: As::Default,
+					prefix: false,
+					depth: None,
+					round: None,
+					filter: Filter::All,
+				},
+			)])),
+		};
+
+		// Both the exact entry and the prefix entry output to the `Result` key.
+		let filter = Filter::Struct(HashMap::from([
+			("Result".to_string(), entry("Result", false, "Amount")),
+			("Res*".to_string(), entry("Res", true, "Quality")),
+		]));
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Craft",
+			1,
+			&filter,
+			0,
+			&ReadOptions::default(),
+		);
+
+		let Value::Struct(fields) = &value else {
+			panic!("expected struct, got {value:?}");
+		};
+		assert_eq!(fields.keys().collect::<Vec<_>>(), vec!["Result"]);
+
+		let result = field(&value, "Result");
+		assert!(matches!(
+			field(result, "Amount"),
+			Value::Scalar(excel::Field::U32(1))
+		));
+		assert!(matches!(
+			field(result, "Quality"),
+			Value::Scalar(excel::Field::U32(2))
+		));
+	}
+
+	#[test]
+	fn packed_bools_grouped_across_struct() {
+		use exh::ColumnKind as CK;
+		// The known field splits the byte's unknown bits into non-adjacent columns.
+		let fixture = Fixture::new(vec![(
+			TestSheet::new(
+				"Flags",
+				[
+					(CK::PackedBool0, 0),
+					(CK::PackedBool1, 0),
+					(CK::PackedBool2, 0),
+				],
+			)
+			.row(1, [Cell::Bool(true), Cell::Bool(false), Cell::Bool(true)]),
+			schema::Node::Struct(vec![schema::StructField {
+				name: "Known".into(),
+				offset: 1,
+				node: scalar(),
+			}]),
+		)]);
+		let read = Read::new(Config {
+			group_packed_bools: true,
+			..test_config(None)
+		});
+		let value = read_row(
+			&read,
+			&fixture,
+			"Flags",
+			1,
+			&Filter::All,
+			0,
+			&ReadOptions::default(),
+		);
+
+		let Value::Struct(fields) = &value else {
+			panic!("expected struct, got {value:?}");
+		};
+		let mut keys = fields.keys().collect::<Vec<_>>();
+		keys.sort();
+		assert_eq!(keys, vec!["Known", "unknown0"]);
+
+		let Value::Struct(bits) = field(&value, "unknown0") else {
+			panic!("expected grouped bits, got {value:?}");
+		};
+		let mut bits = bits
+			.iter()
+			.map(|(bit, value)| {
+				(
+					bit.as_str(),
+					matches!(value, Value::Scalar(excel::Field::Bool(true))),
+				)
+			})
+			.collect::<Vec<_>>();
+		bits.sort();
+		assert_eq!(bits, vec![("0", true), ("2", true)]);
+	}
+
+	#[test]
+	fn packed_bool_bits() {
+		use exh::ColumnKind as CK;
+		assert_eq!(packed_bool_bit(CK::PackedBool0), Some(0));
+		assert_eq!(packed_bool_bit(CK::PackedBool7), Some(7));
+		assert_eq!(packed_bool_bit(CK::Bool), None);
+	}
+
+	#[test]
+	fn entry_depth_overrides() {
+		let entry = |depth| StructEntry {
+			field: "Item".into(),
+			language: excel::Language::English,
+			read_as: As::Default,
+			prefix: false,
+			depth,
+			round: None,
+			filter: Filter::All,
+		};
+
+		// A zero depth leaves the entry's references unexpanded, while entries
+		// without an override retain the depth of the read.
+		assert_eq!(entry_depth(&entry(Some(0)), 2, Some(4)), 0);
+		assert_eq!(entry_depth(&entry(None), 2, Some(4)), 2);
+
+		// Overrides may raise the depth, clamped to the maximum.
+		assert_eq!(entry_depth(&entry(Some(3)), 2, Some(4)), 3);
+		assert_eq!(entry_depth(&entry(Some(9)), 2, Some(4)), 4);
+
+		// Without a maximum, overrides may only reduce the depth.
+		assert_eq!(entry_depth(&entry(Some(9)), 2, None), 2);
+	}
+
+	#[test]
+	fn entry_depth_stops_single_field() {
+		let fixture = reference_fixture();
+		let filter = Filter::Struct(HashMap::from([
+			(
+				"ItemResult".to_string(),
+				StructEntry {
+					field: "ItemResult".into(),
+					language: excel::Language::English,
+					read_as: As::Default,
+					prefix: false,
+					depth: Some(0),
+					round: None,
+					filter: Filter::All,
+				},
+			),
+			(
+				"Ingredient".to_string(),
+				StructEntry {
+					field: "Ingredient".into(),
+					language: excel::Language::English,
+					read_as: As::Default,
+					prefix: false,
+					depth: None,
+					round: None,
+					filter: Filter::All,
+				},
+			),
+		]));
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Recipe",
+			1,
+			&filter,
+			1,
+			&ReadOptions::default(),
+		);
+
+		assert!(matches!(
+			field(&value, "ItemResult"),
+			Value::Reference(Reference::Unpopulated { value: 1, .. })
+		));
+		assert!(matches!(
+			field(&value, "Ingredient"),
+			Value::Reference(Reference::Populated { value: 2, .. })
+		));
+	}
+
+	#[test]
+	fn entry_depth_raises_single_field() {
+		let fixture = reference_fixture();
+		let filter = Filter::Struct(HashMap::from([
+			(
+				"ItemResult".to_string(),
+				StructEntry {
+					field: "ItemResult".into(),
+					language: excel::Language::English,
+					read_as: As::Default,
+					prefix: false,
+					depth: Some(1),
+					round: None,
+					filter: Filter::All,
+				},
+			),
+			(
+				"Ingredient".to_string(),
+				StructEntry {
+					field: "Ingredient".into(),
+					language: excel::Language::English,
+					read_as: As::Default,
+					prefix: false,
+					depth: None,
+					round: None,
+					filter: Filter::All,
+				},
+			),
+		]));
+		let options = ReadOptions {
+			max_depth: Some(1),
+			..Default::default()
+		};
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Recipe",
+			1,
+			&filter,
+			0,
+			&options,
+		);
+
+		assert!(matches!(
+			field(&value, "ItemResult"),
+			Value::Reference(Reference::Populated { value: 1, .. })
+		));
+		assert!(matches!(
+			field(&value, "Ingredient"),
+			Value::Reference(Reference::Unpopulated { value: 2, .. })
+		));
+	}
+
+	#[test]
+	fn compact_reference_fields() {
+		let Filter::Struct(fields) = compact_filter("Name", excel::Language::English) else {
+			panic!("expected struct filter");
+		};
+
+		assert_eq!(fields.len(), 1);
+		assert_eq!(
+			fields["Name"],
+			StructEntry {
+				field: "Name".into(),
+				language: excel::Language::English,
+				read_as: As::Default,
+				prefix: false,
+				depth: None,
+				round: None,
+				filter: Filter::All,
+			}
+		);
+	}
+
+	#[test]
+	fn reference_budget_exhausted() {
+		let budget = ReferenceBudget::new(Some(2));
+
+		let expanded = (0..4)
+			.map(|_| {
+				let available = budget.available();
+				if available {
+					budget.consume();
+				}
+				available
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(expanded, vec![true, true, false, false]);
+	}
+
+	#[test]
+	fn reference_budget_unlimited() {
+		let budget = ReferenceBudget::new(None);
+		for _ in 0..100 {
+			assert!(budget.available());
+			budget.consume();
+		}
+	}
+
+	#[test]
+	fn round_float_field() {
+		let got = round_field(excel::Field::F32(1.2345678), 2);
+		assert!(matches!(got, excel::Field::F32(value) if value == 1.23));
+
+		let got = round_field(excel::Field::U32(12345), 2);
+		assert!(matches!(got, excel::Field::U32(12345)));
+	}
+
+	fn test_read(float_precision: Option<u8>) -> Read {
+		Read::new(test_config(float_precision))
+	}
+
+	fn test_config(float_precision: Option<u8>) -> Config {
+		Config {
+			language: LanguageConfig {
+				default: "en".parse().unwrap(),
+				exclude: vec![],
+			},
+			case_insensitive: false,
+			reference_sheets: false,
+			float_precision,
+			max_references: None,
+			lenient_filters: false,
+			labels: HashMap::new(),
+			group_packed_bools: false,
+		}
+	}
+
+	fn label_read(labels: &[(&str, &str)]) -> Read {
+		Read::new(Config {
+			labels: labels
+				.iter()
+				.map(|(sheet, field)| (sheet.to_string(), field.to_string()))
+				.collect(),
+			..test_config(None)
+		})
+	}
+
+	fn label_filter(field: &str) -> Filter {
+		Filter::Struct(HashMap::from([(
+			field.to_string(),
+			StructEntry {
+				field: field.into(),
+				language: excel::Language::English,
+				read_as: As::Label,
+				prefix: false,
+				depth: None,
+				round: None,
+				filter: Filter::All,
+			},
+		)]))
+	}
+
+	#[test]
+	fn label_reads_target_field() {
+		let fixture = reference_fixture();
+		let value = read_row(
+			&label_read(&[("Item", "Name")]),
+			&fixture,
+			"Recipe",
+			1,
+			&label_filter("ItemResult"),
+			1,
+			&ReadOptions::default(),
+		);
+
+		assert!(matches!(
+			field(&value, "ItemResult"),
+			Value::Scalar(excel::Field::String(label)) if label.to_string() == "Potion"
+		));
+	}
+
+	#[test]
+	fn label_failure_falls_back_to_value() {
+		use exh::ColumnKind as CK;
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Recipe", [(CK::Int32, 0)]).row(1, [Cell::I32(1)]),
+			struct_node([("ItemResult", reference(&["Missing"]))]),
+		)]);
+		let value = read_row(
+			&label_read(&[("Missing", "Name")]),
+			&fixture,
+			"Recipe",
+			1,
+			&label_filter("ItemResult"),
+			1,
+			&ReadOptions::default(),
+		);
+
+		assert!(matches!(
+			field(&value, "ItemResult"),
+			Value::Scalar(excel::Field::I32(1))
+		));
+	}
+
+	#[test]
+	fn round_float_configured() {
+		let got = test_read(Some(1)).round(excel::Field::F32(0.36), None);
+		assert!(matches!(got, excel::Field::F32(value) if value == 0.4));
+
+		let got = test_read(None).round(excel::Field::F32(0.36), None);
+		assert!(matches!(got, excel::Field::F32(value) if value == 0.36));
+
+		let got = test_read(Some(1)).round(excel::Field::F32(0.364), Some(2));
+		assert!(matches!(got, excel::Field::F32(value) if value == 0.36));
+	}
+
+	fn round_entry(read_as: As, round: Option<u8>) -> Filter {
+		Filter::Struct(HashMap::from([(
+			"Value".to_string(),
+			StructEntry {
+				field: "Value".into(),
+				language: excel::Language::English,
+				read_as,
+				prefix: false,
+				depth: None,
+				round,
+				filter: Filter::All,
+			},
+		)]))
+	}
+
+	#[test]
+	fn round_decorator_combines_with_as() {
+		use exh::ColumnKind as CK;
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Stat", [(CK::Float32, 0)]).row(1, [Cell::F32(1.2345)]),
+			struct_node([("Value", scalar())]),
+		)]);
+		let read = |read_as, round| {
+			let value = read_row(
+				&test_read(Some(3)),
+				&fixture,
+				"Stat",
+				1,
+				&round_entry(read_as, round),
+				0,
+				&ReadOptions::default(),
+			);
+			match field(&value, "Value") {
+				Value::Scalar(excel::Field::F32(value)) => *value,
+				other => panic!("expected float, got {other:?}"),
+			}
+		};
+
+		assert_eq!(read(As::Default, None), 1.235);
+		assert_eq!(read(As::Default, Some(1)), 1.2);
+		assert_eq!(read(As::Raw, None), 1.2345);
+		assert_eq!(read(As::Raw, Some(1)), 1.2);
+	}
+
+	fn fields() -> HashMap<String, Value> {
+		HashMap::from([("Name".to_string(), Value::Scalar(excel::Field::U32(1)))])
+	}
+
+	#[test]
+	fn partial_coverage() {
+		let scalar = || schema::Node::Scalar(schema::Scalar::Default);
+		let node = schema::Node::Struct(vec![
+			schema::StructField {
+				name: "Name".into(),
+				offset: 0,
+				node: scalar(),
+			},
+			schema::StructField {
+				name: "Params".into(),
+				offset: 2,
+				node: schema::Node::Array {
+					count: 2,
+					node: Box::new(scalar()),
+				},
+			},
+		]);
+
+		// Columns 1, 4, and 5 are unknown.
+		let got = node_coverage(&node, 6).expect("should not fail");
+		assert_eq!(
+			got,
+			Coverage {
+				columns: 6,
+				covered: 3
+			}
+		);
+		assert_eq!(got.unknown(), 3);
+		assert_eq!(got.percentage(), 50.0);
+	}
+
+	#[test]
+	fn empty_schema_coverage() {
+		let got = node_coverage(&schema::Node::Struct(vec![]), 4).expect("should not fail");
+		assert_eq!(got.covered, 0);
+		assert_eq!(got.unknown(), 4);
+	}
+
+	fn filter_mismatch() -> Result<Value> {
+		Err(Error::FilterSchemaMismatch(MismatchError {
+			field: "Params".into(),
+			reason: "expected array filter".into(),
+		}))
+	}
+
+	#[test]
+	fn strict_filter_mismatch() {
+		let got = skip_filter_mismatch(filter_mismatch(), false);
+		assert!(matches!(got, Err(Error::FilterSchemaMismatch(_))));
+	}
+
+	#[test]
+	fn lenient_filter_mismatch() {
+		let got = skip_filter_mismatch(filter_mismatch(), true).expect("should not fail");
+		assert!(got.is_none());
+
+		// Other errors are not affected by leniency.
+		let got = skip_filter_mismatch(Err(Error::NotFound("Item".into())), true);
+		assert!(matches!(got, Err(Error::NotFound(_))));
+
+		let got = skip_filter_mismatch(Ok(Value::Null), true).expect("should not fail");
+		assert!(matches!(got, Some(Value::Null)));
+	}
+
+	#[test]
+	fn root_filter_mismatch() {
+		let fixture = reference_fixture();
+		let filter = Filter::Array(Filter::All.into());
+		let read = |lenient_filters| {
+			Read::new(Config {
+				lenient_filters,
+				..test_config(None)
+			})
+			.read(
+				&fixture.excel,
+				&fixture.schema,
+				"Recipe",
+				1,
+				0,
+				excel::Language::English,
+				&filter,
+				0,
+				&ReadOptions::default(),
+				&ReadCache::default(),
+			)
+		};
+
+		assert!(matches!(read(false), Err(Error::FilterSchemaMismatch(_))));
+		assert!(matches!(
+			read(true),
+			Ok(Value::Struct(fields)) if fields.is_empty()
+		));
+	}
+
+	#[test]
+	fn skipped_keys_explicit_null() {
+		let omitted = fields();
+		let mut explicit = fields();
+		insert_null_keys(&mut explicit, ["Name", "Missing"]);
+
+		assert!(!omitted.contains_key("Missing"));
+		assert_eq!(explicit.len(), 2);
+		assert!(matches!(
+			explicit["Name"],
+			Value::Scalar(excel::Field::U32(1))
+		));
+		assert!(matches!(explicit["Missing"], Value::Null));
+	}
+
+	#[test]
+	fn raw_fields_keyed_by_column() {
+		use exh::ColumnKind as CK;
+		let columns = [
+			(0, CK::UInt32, excel::Field::U32(1)),
+			(4, CK::PackedBool0, excel::Field::Bool(true)),
+			(4, CK::PackedBool3, excel::Field::Bool(false)),
+			(8, CK::Int16, excel::Field::I16(-2)),
+		];
+
+		let Value::Struct(fields) = raw_struct(columns) else {
+			panic!("raw read should produce a struct");
+		};
+
+		let mut keys = fields.keys().map(String::as_str).collect::<Vec<_>>();
+		keys.sort_unstable();
+		assert_eq!(keys, ["unknown0", "unknown4_0", "unknown4_3", "unknown8"]);
+
+		assert!(matches!(
+			fields["unknown0"],
+			Value::Scalar(excel::Field::U32(1))
+		));
+		assert!(matches!(
+			fields["unknown4_0"],
+			Value::Scalar(excel::Field::Bool(true))
+		));
+		assert!(matches!(
+			fields["unknown4_3"],
+			Value::Scalar(excel::Field::Bool(false))
+		));
+		assert!(matches!(
+			fields["unknown8"],
+			Value::Scalar(excel::Field::I16(-2))
+		));
+	}
+
+	#[test]
+	fn raw_read_of_fixture_row() {
+		use exh::ColumnKind as CK;
+		// The schema names and references columns, none of which is applied.
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Item", [(CK::Int32, 8), (CK::String, 0), (CK::UInt32, 4)]).row(
+				1,
+				[Cell::I32(-3), Cell::String("Potion".into()), Cell::U32(5)],
+			),
+			struct_node([
+				("Name", scalar()),
+				("Item", reference(&["Item"])),
+				("Delta", scalar()),
+			]),
+		)]);
+
+		let value = test_read(None)
+			.read_raw(&fixture.excel, "Item", 1, 0, excel::Language::English)
+			.expect("read should not fail");
+
+		let Value::Struct(fields) = value else {
+			panic!("expected struct, got {value:?}");
+		};
+		let mut keys = fields.keys().map(String::as_str).collect::<Vec<_>>();
+		keys.sort_unstable();
+		assert_eq!(keys, ["unknown0", "unknown4", "unknown8"]);
+
+		assert!(matches!(
+			&fields["unknown0"],
+			Value::Scalar(excel::Field::String(name)) if name.to_string() == "Potion"
+		));
+		assert!(matches!(
+			fields["unknown4"],
+			Value::Scalar(excel::Field::U32(5))
+		));
+		assert!(matches!(
+			fields["unknown8"],
+			Value::Scalar(excel::Field::I32(-3))
+		));
+	}
+
+	/// Items with two columns, described by a schema of the given fields.
+	fn drift_fixture(fields: &[&'static str]) -> Fixture {
+		use exh::ColumnKind as CK;
+		Fixture::new(vec![(
+			TestSheet::new("Item", [(CK::String, 0), (CK::UInt32, 4)])
+				.row(1, [Cell::String("Potion".into()), Cell::U32(5)]),
+			struct_node(fields.iter().map(|&name| (name, scalar()))),
+		)])
+	}
+
+	#[test]
+	fn schema_expecting_more_columns_outdated() {
+		let fixture = drift_fixture(&["Name", "Count", "Added"]);
+		let got = test_read(None).read(
+			&fixture.excel,
+			&fixture.schema,
+			"Item",
+			1,
+			0,
+			excel::Language::English,
+			&Filter::All,
+			0,
+			&ReadOptions::default(),
+			&ReadCache::default(),
+		);
+
+		assert!(matches!(
+			got,
+			Err(Error::SchemaOutdated(ColumnDriftError { sheet, expected: 3, actual: 2 }))
+				if sheet == "Item"
+		));
+	}
+
+	#[test]
+	fn schema_expecting_fewer_columns_reads() {
+		// Columns the schema does not yet describe are read as unknown fields.
+		let fixture = drift_fixture(&["Name"]);
+		let value = read_row(
+			&test_read(None),
+			&fixture,
+			"Item",
+			1,
+			&Filter::All,
+			0,
+			&ReadOptions::default(),
+		);
+
+		assert!(matches!(
+			field(&value, "Name"),
+			Value::Scalar(excel::Field::String(..))
+		));
+		assert!(matches!(
+			field(&value, "unknown4"),
+			Value::Scalar(excel::Field::U32(5))
+		));
+	}
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{hash_map, HashMap};
 
 use ironworks::{excel, sestring::SeString};
 
@@ -6,8 +6,14 @@ use ironworks::{excel, sestring::SeString};
 pub enum Value {
 	Array(Vec<Value>),
 	// TODO: consider moving icon/html (maybe reference?) into a seperate scalar type/enum (if html is kept)
+	/// Bit pattern of an integer field, to be formatted as hexadecimal.
+	Hex(u64),
 	Html(SeString<'static>),
 	Icon(i32),
+	Markdown(SeString<'static>),
+	Plain(SeString<'static>),
+	/// Placeholder for a requested value that could not be read.
+	Null,
 	Reference(Reference),
 	Scalar(excel::Field),
 	Struct(HashMap<String, Value>),
@@ -16,6 +22,19 @@ pub enum Value {
 #[derive(Debug)]
 pub enum Reference {
 	Scalar(i32),
+	/// A reference that was not followed, along with the target it would have
+	/// been followed to.
+	Unpopulated {
+		value: u32,
+		sheet: String,
+		row_id: u32,
+	},
+	/// A reference that was not followed, and for which no target could be
+	/// resolved, along with the sheets it may target.
+	Unresolved {
+		value: u32,
+		sheets: Vec<String>,
+	},
 	Populated {
 		value: u32,
 		sheet: String,
@@ -23,3 +42,260 @@ pub enum Reference {
 		fields: Box<Value>,
 	},
 }
+
+impl Value {
+	/// Hoist the fields of populated references into the struct containing the
+	/// reference, keyed by their dotted path, i.e. `ClassJob.Name`. Arrays of
+	/// populated references are hoisted with indexed keys, i.e. `Item[0].Name`.
+	/// References themselves are retained as unpopulated references under their
+	/// original key.
+	///
+	/// If a hoisted key collides with a key already present on the struct, the
+	/// existing key takes precedence and the hoisted value is discarded.
+	pub fn flatten(self) -> Self {
+		match self {
+			Value::Struct(fields) => Value::Struct(flatten_struct(fields)),
+			Value::Array(values) => Value::Array(values.into_iter().map(Value::flatten).collect()),
+			Value::Reference(Reference::Populated {
+				value,
+				sheet,
+				row_id,
+				fields,
+			}) => Value::Reference(Reference::Populated {
+				value,
+				sheet,
+				row_id,
+				fields: fields.flatten().into(),
+			}),
+			other => other,
+		}
+	}
+
+	/// Merge another value read from the same field into this one. Multiple
+	/// filter entries selecting a field may each read a different subset of it -
+	/// structs are merged by key, arrays by index, and populated references by
+	/// their fields. Values that cannot be merged retain this value.
+	pub(crate) fn merge(self, other: Self) -> Self {
+		match (self, other) {
+			(Value::Struct(mut fields), Value::Struct(other)) => {
+				for (key, value) in other {
+					let value = match fields.remove(&key) {
+						Some(existing) => existing.merge(value),
+						None => value,
+					};
+					fields.insert(key, value);
+				}
+				Value::Struct(fields)
+			}
+
+			(Value::Array(values), Value::Array(other)) if values.len() == other.len() => {
+				Value::Array(
+					values
+						.into_iter()
+						.zip(other)
+						.map(|(value, other)| value.merge(other))
+						.collect(),
+				)
+			}
+
+			(
+				Value::Reference(Reference::Populated {
+					value,
+					sheet,
+					row_id,
+					fields,
+				}),
+				Value::Reference(Reference::Populated { fields: other, .. }),
+			) => Value::Reference(Reference::Populated {
+				value,
+				sheet,
+				row_id,
+				fields: fields.merge(*other).into(),
+			}),
+
+			// Prefer values that were read over those that were not.
+			(Value::Null, other) => other,
+			(value @ Value::Reference(Reference::Populated { .. }), _) => value,
+			(_, other @ Value::Reference(Reference::Populated { .. })) => other,
+
+			(value, _) => value,
+		}
+	}
+}
+
+fn flatten_struct(fields: HashMap<String, Value>) -> HashMap<String, Value> {
+	let mut output = HashMap::with_capacity(fields.len());
+	let mut hoisted = vec![];
+
+	for (key, value) in fields {
+		let value = match value.flatten() {
+			Value::Reference(reference) => hoist_reference(reference, &key, &mut hoisted),
+			Value::Array(values) => Value::Array(
+				values
+					.into_iter()
+					.enumerate()
+					.map(|(index, value)| match value {
+						Value::Reference(reference) => {
+							hoist_reference(reference, &format!("{key}[{index}]"), &mut hoisted)
+						}
+						other => other,
+					})
+					.collect(),
+			),
+			other => other,
+		};
+		output.insert(key, value);
+	}
+
+	// Hoisted keys are inserted last so existing keys are retained on collision.
+	for (key, value) in hoisted {
+		match output.entry(key) {
+			hash_map::Entry::Vacant(entry) => {
+				entry.insert(value);
+			}
+			hash_map::Entry::Occupied(entry) => {
+				tracing::debug!(key = ?entry.key(), "flattened key collision");
+			}
+		}
+	}
+
+	output
+}
+
+/// Collect the fields of a populated reference into the hoisted list, prefixed
+/// by the provided path, returning the reference in its unpopulated form.
+fn hoist_reference(reference: Reference, path: &str, hoisted: &mut Vec<(String, Value)>) -> Value {
+	let Reference::Populated {
+		value,
+		sheet,
+		row_id,
+		fields,
+	} = reference
+	else {
+		return Value::Reference(reference);
+	};
+
+	match *fields {
+		Value::Struct(fields) => hoisted.extend(
+			fields
+				.into_iter()
+				.map(|(key, value)| (format!("{path}.{key}"), value)),
+		),
+		other => {
+			return Value::Reference(Reference::Populated {
+				value,
+				sheet,
+				row_id,
+				fields: other.into(),
+			})
+		}
+	}
+
+	Value::Reference(Reference::Unpopulated {
+		value,
+		sheet,
+		row_id,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn scalar(value: u32) -> Value {
+		Value::Scalar(excel::Field::U32(value))
+	}
+
+	fn populated(value: u32, fields: impl IntoIterator<Item = (&'static str, Value)>) -> Value {
+		Value::Reference(Reference::Populated {
+			value,
+			sheet: "ClassJob".into(),
+			row_id: value,
+			fields: Value::Struct(
+				fields
+					.into_iter()
+					.map(|(key, value)| (key.to_string(), value))
+					.collect(),
+			)
+			.into(),
+		})
+	}
+
+	fn flatten(fields: impl IntoIterator<Item = (&'static str, Value)>) -> HashMap<String, Value> {
+		let value = Value::Struct(
+			fields
+				.into_iter()
+				.map(|(key, value)| (key.to_string(), value))
+				.collect(),
+		);
+
+		match value.flatten() {
+			Value::Struct(fields) => fields,
+			other => panic!("expected struct, got {other:?}"),
+		}
+	}
+
+	fn unpopulated(value: &Value) -> Option<u32> {
+		match value {
+			Value::Reference(Reference::Unpopulated { value, .. }) => Some(*value),
+			_ => None,
+		}
+	}
+
+	#[test]
+	fn flatten_nested_references() {
+		let got = flatten([(
+			"ClassJob",
+			populated(
+				1,
+				[
+					("Name", scalar(10)),
+					("Parent", populated(2, [("Name", scalar(20))])),
+				],
+			),
+		)]);
+
+		assert_eq!(unpopulated(&got["ClassJob"]), Some(1));
+		assert_eq!(unpopulated(&got["ClassJob.Parent"]), Some(2));
+		assert!(matches!(
+			got["ClassJob.Name"],
+			Value::Scalar(excel::Field::U32(10))
+		));
+		assert!(matches!(
+			got["ClassJob.Parent.Name"],
+			Value::Scalar(excel::Field::U32(20))
+		));
+		assert_eq!(got.len(), 4);
+	}
+
+	#[test]
+	fn flatten_reference_arrays() {
+		let got = flatten([(
+			"Item",
+			Value::Array(vec![populated(1, [("Name", scalar(10))]), scalar(0)]),
+		)]);
+
+		assert!(matches!(
+			got["Item[0].Name"],
+			Value::Scalar(excel::Field::U32(10))
+		));
+		let Value::Array(items) = &got["Item"] else {
+			panic!("expected array");
+		};
+		assert_eq!(unpopulated(&items[0]), Some(1));
+		assert!(matches!(items[1], Value::Scalar(excel::Field::U32(0))));
+	}
+
+	#[test]
+	fn flatten_collision_retains_existing() {
+		let got = flatten([
+			("ClassJob", populated(1, [("Name", scalar(10))])),
+			("ClassJob.Name", scalar(99)),
+		]);
+
+		assert!(matches!(
+			got["ClassJob.Name"],
+			Value::Scalar(excel::Field::U32(99))
+		));
+	}
+}
@@ -0,0 +1,27 @@
+use ironworks_schema::{Error as SchemaError, ErrorValue, Schema, Sheet};
+
+use super::specifier::CanonicalSpecifier;
+
+/// Source reported for reads performed without a schema. This is not a
+/// selectable source - the empty schema is only used for raw reads, and as a
+/// fallback when schema resolution fails.
+pub const EMPTY_SOURCE: &str = "none";
+const EMPTY_VERSION: &str = "none";
+
+/// Get the empty schema, and the specifier it is reported as. The schema has no
+/// sheet definitions, such that reads expose every column as an unknown field.
+pub fn empty_schema() -> (CanonicalSpecifier, Box<dyn Schema + Send>) {
+	let specifier = CanonicalSpecifier {
+		source: EMPTY_SOURCE.into(),
+		version: EMPTY_VERSION.into(),
+	};
+	(specifier, Box::new(EmptySchema))
+}
+
+struct EmptySchema;
+
+impl Schema for EmptySchema {
+	fn sheet(&self, name: &str) -> ironworks_schema::Result<Sheet> {
+		Err(SchemaError::NotFound(ErrorValue::Sheet(name.into())))
+	}
+}
@@ -1,10 +1,12 @@
+mod empty;
 mod error;
 mod exdschema;
 mod provider;
 mod specifier;
 
 pub use {
+	empty::{empty_schema, EMPTY_SOURCE},
 	error::Error,
-	provider::{Config, Provider},
+	provider::{Config, Provider, SourceMetadata},
 	specifier::{CanonicalSpecifier, Specifier},
 };
@@ -1,13 +1,16 @@
 use std::{collections::HashMap, sync::Arc};
 
+use anyhow::anyhow;
 use bm_version::VersionKey;
 use futures::future::join_all;
 use ironworks_schema::Schema;
+use mini_moka::sync as moka;
 use serde::Deserialize;
-use tokio::{select, time};
+use tokio::{select, sync::Mutex, time};
 use tokio_util::sync::CancellationToken;
 
 use super::{
+	empty::empty_schema,
 	error::{Error, Result},
 	exdschema,
 	specifier::CanonicalSpecifier,
@@ -30,15 +33,60 @@ pub struct Config {
 	default: Specifier,
 	interval: u64,
 
+	/// Delay before retrying a failed update, in seconds. Defaults to the
+	/// regular update interval.
+	#[serde(default)]
+	retry: Option<u64>,
+
+	/// Fall back to the empty schema if a schema cannot be resolved.
+	#[serde(default)]
+	fallback: bool,
+
+	/// Human-readable labels for sources, keyed by source name.
+	#[serde(default)]
+	labels: HashMap<String, SourceLabel>,
+
 	exdschema: exdschema::Config,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceLabel {
+	label: String,
+	#[serde(default)]
+	description: Option<String>,
+}
+
+/// Metadata about a schema source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMetadata {
+	/// Name of the source, as used in specifiers.
+	pub name: String,
+	/// Human-readable label for the source. Defaults to the source name.
+	pub label: String,
+	/// Description of the source, if configured.
+	pub description: Option<String>,
+	/// Whether this source is used when no specifier is provided.
+	pub default: bool,
+}
+
+// Canonical specifiers are small, an entry count bound is sufficient.
+const CANONICAL_CACHE_CAPACITY: u64 = 4096;
+
+fn canonical_cache() -> moka::Cache<(Specifier, VersionKey), CanonicalSpecifier> {
+	moka::Cache::new(CANONICAL_CACHE_CAPACITY)
+}
+
 // TODO: need a way to handle updating the repo
 // TODO: look into moving sources into a channel so i'm not leaning on send+sync for other shit
 pub struct Provider {
 	default: Specifier,
 	update_interval: u64,
+	retry_interval: Option<u64>,
+	fallback: bool,
+	labels: HashMap<String, SourceLabel>,
 	sources: HashMap<&'static str, Arc<dyn Source>>,
+	canonical: moka::Cache<(Specifier, VersionKey), CanonicalSpecifier>,
+	refreshing: Mutex<()>,
 }
 
 impl Provider {
@@ -47,10 +95,15 @@ impl Provider {
 		Ok(Self {
 			default: config.default,
 			update_interval: config.interval,
+			retry_interval: config.retry,
+			fallback: config.fallback,
+			labels: config.labels,
 			sources: HashMap::from([(
 				"exdschema",
 				boxed(exdschema::ExdSchema::new(config.exdschema, data)?),
 			)]),
+			canonical: canonical_cache(),
+			refreshing: Mutex::new(()),
 		})
 	}
 
@@ -67,17 +120,25 @@ impl Provider {
 	}
 
 	async fn start_inner(&self) {
-		let mut interval = time::interval(time::Duration::from_secs(self.update_interval));
-		interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
-
 		loop {
-			interval.tick().await;
+			// Failed updates are retried sooner than the regular interval, if configured.
+			let delay = match self.refresh().await {
+				Ok(()) => self.update_interval,
+				Err(_) => self.retry_interval.unwrap_or(self.update_interval),
+			};
 
-			self.update().await;
+			time::sleep(time::Duration::from_secs(delay)).await;
 		}
 	}
 
-	async fn update(&self) {
+	/// Fetch updates for all schema sources. Previously resolved specifiers are
+	/// invalidated, such that subsequent requests will observe any changes.
+	/// Concurrent refreshes are run one at a time.
+	pub async fn refresh(&self) -> Result<()> {
+		// Sources are not expected to handle concurrent updates, and an update
+		// finishing after another's invalidation would leave stale specifiers.
+		let _guard = self.refreshing.lock().await;
+
 		tracing::info!("checking for schema updates");
 
 		// TODO: Should this be spawn_blocking?
@@ -86,12 +147,44 @@ impl Provider {
 			tokio::spawn(async move { (name, source.update()) })
 		});
 
-		// Bubble panics, but log + ignore failures.
+		// Bubble panics, but log + collect failures.
+		let mut failed = vec![];
 		for result in join_all(pending_updates).await {
 			if let (name, Err(error)) = result.expect("schema update panic") {
-				tracing::error!(%name, ?error, "schema update failed")
+				tracing::error!(%name, ?error, "schema update failed");
+				failed.push(name);
 			}
 		}
+
+		self.canonical.invalidate_all();
+
+		match failed.is_empty() {
+			true => Ok(()),
+			false => Err(Error::Failure(anyhow!(
+				"schema update failed for {}",
+				failed.join(", ")
+			))),
+		}
+	}
+
+	/// Get metadata about the available schema sources, ordered by name.
+	pub fn sources(&self) -> Vec<SourceMetadata> {
+		let mut sources = self
+			.sources
+			.keys()
+			.map(|&name| {
+				let label = self.labels.get(name);
+				SourceMetadata {
+					name: name.to_string(),
+					label: label.map_or_else(|| name.to_string(), |label| label.label.clone()),
+					description: label.and_then(|label| label.description.clone()),
+					default: self.default.source == name,
+				}
+			})
+			.collect::<Vec<_>>();
+
+		sources.sort_by(|a, b| a.name.cmp(&b.name));
+		sources
 	}
 
 	/// Canonicalise an optional specifier.
@@ -102,15 +195,25 @@ impl Provider {
 	) -> Result<CanonicalSpecifier> {
 		let specifier = specifier.unwrap_or_else(|| self.default.clone());
 
+		let cache_key = (specifier, version);
+		if let Some(canonical) = self.canonical.get(&cache_key) {
+			return Ok(canonical);
+		}
+
+		let (specifier, _) = &cache_key;
 		let source = self
 			.sources
 			.get(specifier.source.as_str())
 			.ok_or_else(|| Error::UnknownSource(specifier.source.clone()))?;
 
-		Ok(CanonicalSpecifier {
-			source: specifier.source,
+		let canonical = CanonicalSpecifier {
+			source: specifier.source.clone(),
 			version: source.canonicalize(specifier.version.as_deref(), version)?,
-		})
+		};
+
+		self.canonical.insert(cache_key, canonical.clone());
+
+		Ok(canonical)
 	}
 
 	pub fn schema(&self, specifier: CanonicalSpecifier) -> Result<Box<dyn Schema + Send>> {
@@ -120,8 +223,256 @@ impl Provider {
 			.ok_or_else(|| Error::UnknownSource(specifier.source.clone()))?;
 		source.version(&specifier.version)
 	}
+
+	/// Canonicalise an optional specifier, and fetch the schema it refers to. If
+	/// configured, failures to resolve the schema will fall back to the empty
+	/// schema, rather than failing outright.
+	pub fn resolve(
+		&self,
+		specifier: Option<Specifier>,
+		version: VersionKey,
+	) -> Result<(CanonicalSpecifier, Box<dyn Schema + Send>)> {
+		let result = self.canonicalize(specifier, version).and_then(|canonical| {
+			let schema = self.schema(canonical.clone())?;
+			Ok((canonical, schema))
+		});
+
+		match result {
+			Err(Error::Failure(error)) if self.fallback => {
+				tracing::warn!(
+					?error,
+					"schema resolution failed, falling back to empty schema"
+				);
+				Ok(empty_schema())
+			}
+			other => other,
+		}
+	}
 }
 
 fn boxed(x: impl Source + 'static) -> Arc<dyn Source> {
 	Arc::new(x)
 }
+
+#[cfg(test)]
+mod test {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use bm_read::fixture::{scalar, struct_node, Cell, Fixture, TestSheet};
+	use ironworks::{excel, file::exh};
+
+	use crate::EMPTY_SOURCE;
+
+	use super::*;
+
+	struct FailingSource;
+
+	impl Source for FailingSource {
+		fn ready(&self) -> bool {
+			true
+		}
+
+		fn update(&self) -> Result<()> {
+			Ok(())
+		}
+
+		fn canonicalize(&self, _: Option<&str>, _: VersionKey) -> Result<String> {
+			Ok("version".into())
+		}
+
+		fn version(&self, _: &str) -> Result<Box<dyn Schema + Send>> {
+			Err(Error::Failure(anyhow!("source unavailable")))
+		}
+	}
+
+	/// Source that canonicalizes to an incrementing revision, mimicking a moving
+	/// branch head.
+	#[derive(Default)]
+	struct MovingSource(AtomicUsize);
+
+	impl Source for MovingSource {
+		fn ready(&self) -> bool {
+			true
+		}
+
+		fn update(&self) -> Result<()> {
+			Ok(())
+		}
+
+		fn canonicalize(&self, _: Option<&str>, _: VersionKey) -> Result<String> {
+			Ok(self.0.fetch_add(1, Ordering::SeqCst).to_string())
+		}
+
+		fn version(&self, _: &str) -> Result<Box<dyn Schema + Send>> {
+			Ok(empty_schema().1)
+		}
+	}
+
+	fn test_provider(fallback: bool) -> Provider {
+		Provider {
+			default: Specifier {
+				source: "failing".into(),
+				version: None,
+			},
+			update_interval: 0,
+			retry_interval: None,
+			fallback,
+			labels: HashMap::from([(
+				"failing".to_string(),
+				SourceLabel {
+					label: "Failing Source".into(),
+					description: Some("Always fails.".into()),
+				},
+			)]),
+			sources: HashMap::from([
+				("failing", boxed(FailingSource)),
+				("moving", boxed(MovingSource::default())),
+			]),
+			canonical: canonical_cache(),
+			refreshing: Mutex::new(()),
+		}
+	}
+
+	#[test]
+	fn source_labels() {
+		let got = test_provider(false).sources();
+		assert_eq!(
+			got,
+			vec![
+				SourceMetadata {
+					name: "failing".into(),
+					label: "Failing Source".into(),
+					description: Some("Always fails.".into()),
+					default: true,
+				},
+				SourceMetadata {
+					name: "moving".into(),
+					label: "moving".into(),
+					description: None,
+					default: false,
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn empty_source_not_selectable() {
+		let got = test_provider(true).canonicalize(
+			Some(Specifier {
+				source: EMPTY_SOURCE.into(),
+				version: None,
+			}),
+			"0000000000000000".parse().unwrap(),
+		);
+		assert!(matches!(got, Err(Error::UnknownSource(source)) if source == EMPTY_SOURCE));
+	}
+
+	#[tokio::test]
+	async fn refresh_invalidates_canonical() {
+		let provider = test_provider(false);
+		let version = "0000000000000000".parse().unwrap();
+		let canonicalize = || {
+			provider
+				.canonicalize(Some("moving".parse().unwrap()), version)
+				.expect("canonicalize should not fail")
+				.version
+		};
+
+		// Repeat requests are served from the cache.
+		assert_eq!(canonicalize(), "0");
+		assert_eq!(canonicalize(), "0");
+
+		provider.refresh().await.expect("refresh should not fail");
+		assert_eq!(canonicalize(), "1");
+	}
+
+	/// Source recording the greatest number of updates running at once.
+	#[derive(Default)]
+	struct SlowSource {
+		active: AtomicUsize,
+		peak: AtomicUsize,
+	}
+
+	impl Source for SlowSource {
+		fn ready(&self) -> bool {
+			true
+		}
+
+		fn update(&self) -> Result<()> {
+			let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+			self.peak.fetch_max(active, Ordering::SeqCst);
+			std::thread::sleep(std::time::Duration::from_millis(20));
+			self.active.fetch_sub(1, Ordering::SeqCst);
+			Ok(())
+		}
+
+		fn canonicalize(&self, _: Option<&str>, _: VersionKey) -> Result<String> {
+			Ok("version".into())
+		}
+
+		fn version(&self, _: &str) -> Result<Box<dyn Schema + Send>> {
+			Ok(empty_schema().1)
+		}
+	}
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+	async fn refresh_serialized() {
+		let source = Arc::new(SlowSource::default());
+		let mut provider = test_provider(false);
+		provider.sources.insert("slow", source.clone());
+
+		let (first, second) = tokio::join!(provider.refresh(), provider.refresh());
+		assert!(first.is_ok() && second.is_ok());
+		assert_eq!(source.peak.load(Ordering::SeqCst), 1);
+	}
+
+	#[test]
+	fn resolve_failure() {
+		let provider = test_provider(false);
+		let result = provider.resolve(None, "0000000000000000".parse().unwrap());
+		assert!(matches!(result, Err(Error::Failure(_))));
+	}
+
+	#[test]
+	fn resolve_fallback() {
+		let provider = test_provider(true);
+		let (specifier, schema) = provider
+			.resolve(None, "0000000000000000".parse().unwrap())
+			.expect("fallback should not fail");
+
+		assert_eq!(specifier.source, EMPTY_SOURCE);
+
+		// The configured schema would name the column, the fallback reads it raw.
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Item", [(exh::ColumnKind::UInt32, 0)]).row(1, [Cell::U32(5)]),
+			struct_node([("Count", scalar())]),
+		)]);
+		let config = serde_json::from_value::<bm_read::Config>(serde_json::json!({
+			"language": {"default": "en", "exclude": []},
+		}))
+		.expect("config should deserialize");
+		let value = bm_read::Read::new(config)
+			.read(
+				&fixture.excel,
+				schema.as_ref(),
+				"Item",
+				1,
+				0,
+				excel::Language::English,
+				&bm_read::Filter::All,
+				0,
+				&bm_read::ReadOptions::default(),
+				&bm_read::ReadCache::default(),
+			)
+			.expect("read should not fail");
+
+		let bm_read::Value::Struct(fields) = value else {
+			panic!("expected struct, got {value:?}");
+		};
+		assert_eq!(fields.keys().collect::<Vec<_>>(), vec!["unknown0"]);
+		assert!(matches!(
+			fields["unknown0"],
+			bm_read::Value::Scalar(excel::Field::U32(5))
+		));
+	}
+}
@@ -2,8 +2,7 @@ use std::{convert::Infallible, str::FromStr};
 
 use serde::{de, Deserialize, Serialize};
 
-// TODO: will probably need eq/hash so i can use these as cache keys?
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CanonicalSpecifier {
 	pub source: String,
 	pub version: String,
@@ -24,7 +23,7 @@ impl Serialize for CanonicalSpecifier {
 	}
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Specifier {
 	pub source: String,
 	pub version: Option<String>,
@@ -23,9 +23,17 @@ pub enum Error {
 	#[error("schema <-> game mismatch on {}: {}", .0.field, .0.reason)]
 	SchemaGameMismatch(MismatchError),
 
+	/// The sheet schema in use expects more columns than the game data contains.
+	#[error("schema for {} is outdated: expected {} columns, game data has {}", .0.sheet, .0.expected, .0.actual)]
+	SchemaOutdated(ColumnDriftError),
+
 	#[error("unknown cursor {0}")]
 	UnknownCursor(Uuid),
 
+	/// The cursor existed, but has outlived its lifetime and been evicted.
+	#[error("cursor {0} has expired")]
+	CursorExpired(Uuid),
+
 	#[error(transparent)]
 	Failure(anyhow::Error),
 }
@@ -43,6 +51,15 @@ pub struct MismatchError {
 	pub(super) reason: String,
 }
 
+/// Column counts for a sheet whose schema expects more columns than are present
+/// in the game data, typically as the result of a game update.
+#[derive(Debug)]
+pub struct ColumnDriftError {
+	pub sheet: String,
+	pub expected: usize,
+	pub actual: usize,
+}
+
 // Implement From traits for common search-related failures that can be marked as a full failure.
 macro_rules! impl_to_failure {
 	($source:ty) => {
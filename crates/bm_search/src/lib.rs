@@ -5,7 +5,10 @@ mod search;
 mod sqlite;
 
 pub use {
-	error::{Error, FieldTypeError, MismatchError},
+	error::{ColumnDriftError, Error, FieldTypeError, MismatchError},
 	internal_query::pre as query,
-	search::{Config, Search, SearchRequest, SearchRequestQuery},
+	search::{
+		Config, MatchHighlight, MatchedColumn, Search, SearchCursor, SearchDebug, SearchExplain,
+		SearchRequest, SearchRequestQuery, SearchResult,
+	},
 };
@@ -5,4 +5,4 @@ mod query;
 pub mod post;
 pub mod pre;
 
-pub use normalize::Normalizer;
+pub use normalize::{MatchLength, Normalizer};
@@ -1,7 +1,8 @@
 use ironworks::{excel, file::exh};
 use ironworks_schema as schema;
+use serde::Deserialize;
 
-use crate::error::{Error, MismatchError, Result};
+use crate::error::{ColumnDriftError, Error, MismatchError, Result};
 
 use super::{field, post, pre};
 
@@ -14,6 +15,8 @@ struct Context<'a> {
 	columns: &'a [exh::ColumnDefinition],
 	language: excel::Language,
 
+	sheet_columns: SheetColumns,
+
 	ambient_language: excel::Language,
 
 	path: &'a [&'a str],
@@ -26,16 +29,79 @@ impl Context<'_> {
 			reason: reason.to_string(),
 		}
 	}
+
+	fn column_drift(&self) -> Error {
+		Error::SchemaOutdated(ColumnDriftError {
+			sheet: self.current_sheet.to_string(),
+			expected: self.sheet_columns.expected,
+			actual: self.sheet_columns.actual,
+		})
+	}
+}
+
+/// Column counts of the current sheet, as expected by the schema and as present
+/// in the game data.
+#[derive(Clone, Copy)]
+struct SheetColumns {
+	expected: usize,
+	actual: usize,
+}
+
+/// Bounds on the length of strings used by match operations, and of regular
+/// expression patterns, in characters.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct MatchLength {
+	/// Minimum length of a match string.
+	#[serde(default)]
+	min: usize,
+
+	/// Maximum length of a match string. If unset, match strings are not limited.
+	max: Option<usize>,
+}
+
+impl MatchLength {
+	fn validate(&self, string: &str) -> Result<()> {
+		let length = string.chars().count();
+
+		if length < self.min {
+			return Err(Error::MalformedQuery(format!(
+				"match string must be at least {} characters (got {length})",
+				self.min
+			)));
+		}
+
+		if let Some(max) = self.max {
+			if length > max {
+				return Err(Error::MalformedQuery(format!(
+					"match string must be at most {max} characters (got {length})"
+				)));
+			}
+		}
+
+		Ok(())
+	}
 }
 
 pub struct Normalizer<'a> {
 	excel: &'a excel::Excel,
 	schema: &'a dyn schema::Schema,
+	case_insensitive: bool,
+	match_length: MatchLength,
 }
 
 impl<'a> Normalizer<'a> {
-	pub fn new(excel: &'a excel::Excel, schema: &'a dyn schema::Schema) -> Self {
-		Self { excel, schema }
+	pub fn new(
+		excel: &'a excel::Excel,
+		schema: &'a dyn schema::Schema,
+		case_insensitive: bool,
+		match_length: MatchLength,
+	) -> Self {
+		Self {
+			excel,
+			schema,
+			case_insensitive,
+			match_length,
+		}
 	}
 
 	#[inline]
@@ -48,6 +114,19 @@ impl<'a> Normalizer<'a> {
 		self.normalize_query(query, sheet_name, ambient_language, &[])
 	}
 
+	/// Resolve a field of a sheet to the single column it is stored in, and the
+	/// language it is read in.
+	pub fn normalize_field(
+		&self,
+		specifier: &pre::FieldSpecifier,
+		sheet_name: &str,
+		ambient_language: excel::Language,
+	) -> Result<post::LeafField> {
+		self.with_sheet(sheet_name, ambient_language, &[], |context| {
+			self.normalize_field_column(specifier, context)
+		})
+	}
+
 	fn normalize_query(
 		&self,
 		query: &pre::Node,
@@ -55,16 +134,21 @@ impl<'a> Normalizer<'a> {
 		ambient_language: excel::Language,
 		path: &[&str],
 	) -> Result<post::Node> {
+		self.with_sheet(sheet_name, ambient_language, path, |context| {
+			self.normalize_node(query, context)
+		})
+	}
+
+	/// Run a normalization step with the context of the root of a sheet.
+	fn with_sheet<T>(
+		&self,
+		sheet_name: &str,
+		ambient_language: excel::Language,
+		path: &[&str],
+		normalize: impl FnOnce(Context) -> Result<T>,
+	) -> Result<T> {
 		// Fetch the schema and columns for the requested sheet.
-		let sheet_schema = self.schema.sheet(sheet_name).map_err(|error| match error {
-			// A missing schema can be considered analogous to a missing field _in_ a
-			// schema, and is such a mismatch between the query and the schema.
-			schema::Error::NotFound(inner) => Error::QuerySchemaMismatch(MismatchError {
-				field: inner.to_string(),
-				reason: "not found".into(),
-			}),
-			other => Error::Failure(other.into()),
-		})?;
+		let sheet_schema = self.sheet_schema(sheet_name)?;
 
 		let sheet_data = self.excel.sheet(sheet_name).map_err(|error| match error {
 			ironworks::Error::NotFound(ironworks::ErrorValue::Sheet(sheet)) => {
@@ -99,18 +183,31 @@ impl<'a> Normalizer<'a> {
 			})?;
 
 		// Start walking the node tree
-		self.normalize_node(
-			query,
-			Context {
-				current_sheet: sheet_name,
-				languages: &languages,
-				schema: &sheet_schema.node,
-				columns: &columns,
-				language,
-				ambient_language,
-				path,
+		normalize(Context {
+			current_sheet: sheet_name,
+			languages: &languages,
+			schema: &sheet_schema.node,
+			columns: &columns,
+			language,
+			sheet_columns: SheetColumns {
+				expected: usize::try_from(sheet_schema.node.size()).unwrap(),
+				actual: columns.len(),
 			},
-		)
+			ambient_language,
+			path,
+		})
+	}
+
+	fn sheet_schema(&self, sheet_name: &str) -> Result<schema::Sheet> {
+		self.schema.sheet(sheet_name).map_err(|error| match error {
+			// A missing schema can be considered analogous to a missing field _in_ a
+			// schema, and is such a mismatch between the query and the schema.
+			schema::Error::NotFound(inner) => Error::QuerySchemaMismatch(MismatchError {
+				field: inner.to_string(),
+				reason: "not found".into(),
+			}),
+			other => Error::Failure(other.into()),
+		})
 	}
 
 	fn normalize_node(&self, node: &pre::Node, context: Context) -> Result<post::Node> {
@@ -161,12 +258,25 @@ impl<'a> Normalizer<'a> {
 				self.normalize_leaf_bound_array(operation, node, *count, *index, context)
 			}
 
+			// Raw columns bypass the schema entirely, and can be used anywhere.
+			(pre::FieldSpecifier::Column(offset, bit), _) => {
+				self.normalize_leaf_bound_column(operation, *offset, *bit, context)
+			}
+
+			// Reverse relations are relative to the current row, rather than any
+			// structure of its schema.
+			(pre::FieldSpecifier::ReverseRelation(sheet_name, field_name), _) => {
+				self.normalize_leaf_bound_reverse(operation, sheet_name, field_name, context)
+			}
+
 			// Anything other than a like-for-like match is, well, a mismatch.
 			(specifier, node) => Err(Error::QuerySchemaMismatch(context.mismatch(format!(
 				"cannot use {} query specifier for {} schema structures",
 				match specifier {
 					pre::FieldSpecifier::Struct(..) => "struct",
 					pre::FieldSpecifier::Array(..) => "array",
+					pre::FieldSpecifier::Column(..) => "column",
+					pre::FieldSpecifier::ReverseRelation(..) => "reverse relation",
 				},
 				match node {
 					schema::Node::Array { .. } => "array",
@@ -192,12 +302,38 @@ impl<'a> Normalizer<'a> {
 			..context
 		};
 
+		let (field, narrowed_columns, language) =
+			self.struct_field_columns(fields, field_name, requested_language, &context)?;
+
+		// TODO: by leaving ambient_language as-is here, a query of `A@ja.B(Relation)` will fall back to the default language of the query for B.
+		//       tempting to say that language overrides shouldn't spill outside their immediate field at all, honestly
+		self.normalize_operation(
+			operation,
+			Context {
+				schema: &field.node,
+				columns: narrowed_columns,
+				language,
+				..context
+			},
+		)
+	}
+
+	/// Find a field of a struct, and the columns and language it is read from.
+	fn struct_field_columns<'c>(
+		&self,
+		fields: &'c [schema::StructField],
+		field_name: &str,
+		requested_language: Option<excel::Language>,
+		context: &Context<'c>,
+	) -> Result<(
+		&'c schema::StructField,
+		&'c [exh::ColumnDefinition],
+		excel::Language,
+	)> {
 		// Get the requested field from the struct, mismatch if no such field
 		// exists. Mismatch here implies the query and schema do not match.
-		let field = fields
-			.iter()
-			// TODO: this is _really_ wasteful. see TODO in the utility file w/r/t sanitizing schema preemptively
-			.find(|field| &field::sanitize_name(&field.name) == field_name)
+		let field = find_struct_field(fields, field_name, self.case_insensitive)
+			.map_err(|error| Error::QuerySchemaMismatch(context.mismatch(error)))?
 			.ok_or_else(|| Error::QuerySchemaMismatch(context.mismatch("field does not exist")))?;
 
 		// Get the requested language, falling back to the contextual language. We
@@ -218,21 +354,12 @@ impl<'a> Normalizer<'a> {
 		// schema do not match.
 		let start = usize::try_from(field.offset).unwrap();
 		let end = start + usize::try_from(field.node.size()).unwrap();
-		let narrowed_columns = context.columns.get(start..end).ok_or_else(|| {
-			Error::SchemaGameMismatch(context.mismatch("game data does not contain enough columns"))
-		})?;
+		let narrowed_columns = context
+			.columns
+			.get(start..end)
+			.ok_or_else(|| context.column_drift())?;
 
-		// TODO: by leaving ambient_language as-is here, a query of `A@ja.B(Relation)` will fall back to the default language of the query for B.
-		//       tempting to say that language overrides shouldn't spill outside their immediate field at all, honestly
-		self.normalize_operation(
-			operation,
-			Context {
-				schema: &field.node,
-				columns: narrowed_columns,
-				language,
-				..context
-			},
-		)
+		Ok((field, narrowed_columns, language))
 	}
 
 	fn normalize_leaf_bound_array(
@@ -277,7 +404,7 @@ impl<'a> Normalizer<'a> {
 					context.clone(),
 				)?;
 
-				Ok((post::Occur::Should, query))
+				Ok((array_occur(operation), query))
 			})
 			.collect::<Result<Vec<_>>>()?;
 
@@ -296,9 +423,10 @@ impl<'a> Normalizer<'a> {
 		let end = start + size;
 
 		// TODO: This is duped, helper?
-		let narrowed_columns = context.columns.get(start..end).ok_or_else(|| {
-			Error::SchemaGameMismatch(context.mismatch("game data does not contain enough columns"))
-		})?;
+		let narrowed_columns = context
+			.columns
+			.get(start..end)
+			.ok_or_else(|| context.column_drift())?;
 
 		self.normalize_operation(
 			operation,
@@ -310,16 +438,193 @@ impl<'a> Normalizer<'a> {
 		)
 	}
 
+	fn normalize_leaf_bound_column(
+		&self,
+		operation: &pre::Operation,
+		offset: u16,
+		bit: Option<u8>,
+		context: Context,
+	) -> Result<post::Node> {
+		let path_entry = match bit {
+			None => format!("@col({offset})"),
+			Some(bit) => format!("@col({offset}_{bit})"),
+		};
+
+		let context = Context {
+			path: &([context.path, &[path_entry.as_str()]].concat()),
+			..context
+		};
+
+		let index = column_index(offset, bit, &context)?;
+
+		// Columns are read without a schema, treat them as plain scalars.
+		let node = schema::Node::Scalar(schema::Scalar::Default);
+		self.normalize_operation(
+			operation,
+			Context {
+				schema: &node,
+				columns: &context.columns[index..index + 1],
+				..context
+			},
+		)
+	}
+
+	fn normalize_leaf_bound_reverse(
+		&self,
+		operation: &pre::Operation,
+		sheet_name: &str,
+		field_name: &str,
+		context: Context,
+	) -> Result<post::Node> {
+		let path_entry = format!("@ref({sheet_name}.{field_name})");
+
+		let context = Context {
+			path: &([context.path, &[path_entry.as_str()]].concat()),
+			..context
+		};
+
+		// The query of a reverse relation is executed against the referencing sheet.
+		let pre::Operation::Relation(relation) = operation else {
+			return Err(Error::MalformedQuery(format!(
+				"{path_entry} must be followed by a relation query, i.e. {path_entry}.Name~\"value\""
+			)));
+		};
+
+		// The referencing field must be a reference to the current sheet.
+		let sheet_schema = self.sheet_schema(sheet_name)?;
+		let target = reverse_reference_target(
+			&sheet_schema.node,
+			field_name,
+			context.current_sheet,
+			self.case_insensitive,
+		)
+		.map_err(|reason| Error::QuerySchemaMismatch(context.mismatch(reason)))?;
+
+		if target.selector.is_some() {
+			return Err(Error::MalformedQuery(
+				"search system does not currently support relationships with target selectors"
+					.into(),
+			));
+		}
+
+		// Resolve the referencing column from the referencing field.
+		let field = self.with_sheet(
+			sheet_name,
+			context.ambient_language,
+			context.path,
+			|context| {
+				self.normalize_field_column(
+					&pre::FieldSpecifier::Struct(field_name.into(), None),
+					context,
+				)
+			},
+		)?;
+
+		let query = self.normalize_query(
+			&relation.query,
+			sheet_name,
+			context.ambient_language,
+			context.path,
+		)?;
+
+		// Reference conditions select on the referencing sheet, which is the target
+		// of a reverse relation.
+		let condition = match &target.condition {
+			None => None,
+			Some(condition) => Some(Box::new(self.normalize_query(
+				&reference_condition_query(condition),
+				sheet_name,
+				context.ambient_language,
+				context.path,
+			)?)),
+		};
+
+		let operation = post::Operation::Relation(post::Relation {
+			target: post::RelationTarget {
+				sheet: sheet_name.to_string(),
+				condition,
+				direction: post::RelationDirection::Reverse(context.language),
+			},
+			query: Box::new(query),
+		});
+
+		Ok(post::Node::Leaf(post::Leaf { field, operation }))
+	}
+
+	fn normalize_field_column(
+		&self,
+		specifier: &pre::FieldSpecifier,
+		context: Context,
+	) -> Result<post::LeafField> {
+		let (columns, language) = match (specifier, context.schema) {
+			(
+				pre::FieldSpecifier::Struct(field_name, requested_language),
+				schema::Node::Struct(fields),
+			) => {
+				let (_field, columns, language) =
+					self.struct_field_columns(fields, field_name, *requested_language, &context)?;
+				(columns, language)
+			}
+
+			(pre::FieldSpecifier::Column(offset, bit), _) => {
+				let index = column_index(*offset, *bit, &context)?;
+				(&context.columns[index..index + 1], context.language)
+			}
+
+			(specifier, _) => {
+				return Err(Error::QuerySchemaMismatch(context.mismatch(format!(
+					"{specifier:?} cannot be resolved to a single field"
+				))))
+			}
+		};
+
+		match columns {
+			[column] => Ok((column.clone(), language)),
+			_ => Err(Error::MalformedQuery(format!(
+				"{specifier:?} must target a single column"
+			))),
+		}
+	}
+
 	fn normalize_leaf_unbound(
 		&self,
-		_operation: &pre::Operation,
-		_context: Context,
+		operation: &pre::Operation,
+		context: Context,
 	) -> Result<post::Node> {
-		// TODO: notes; an unbound leaf only makes semantic sense on a structural schema node; were it pointing to a scalar node, it would be equivalent semantically to a bound leaf on that node. following from that; an unbound leaf should "fan out" to all of the current structural node's children as an or-group, in doing so effectively "consuming" the current node at the leaf point, which maintains consistency with bound leaf handling.
+		// An unbound leaf "fans out" to every string column covered by the current
+		// node, in doing so effectively "consuming" the current node at the leaf
+		// point, which maintains consistency with bound leaf handling. Columns are
+		// read directly rather than walking the schema, so string columns not
+		// covered by the schema are included at the sheet root.
+		validate_unbound_operation(operation)?;
+
+		let node = schema::Node::Scalar(schema::Scalar::Default);
+		let clauses = context
+			.columns
+			.iter()
+			.filter(|column| column.kind() == exh::ColumnKind::String)
+			.map(|column| -> Result<_> {
+				let query = self.normalize_operation(
+					operation,
+					Context {
+						schema: &node,
+						columns: std::slice::from_ref(column),
+						..context.clone()
+					},
+				)?;
+
+				Ok((unbound_occur(operation), query))
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		// Without any string columns, this node cannot be matched at all.
+		if clauses.is_empty() {
+			return Err(Error::QuerySchemaMismatch(
+				context.mismatch("no string fields exist for unbound clause"),
+			));
+		}
 
-		Err(Error::MalformedQuery(
-			"unbound query nodes are not currently supported".into(),
-		))
+		Ok(post::Node::Group(post::Group { clauses }))
 	}
 
 	fn normalize_operation(
@@ -332,14 +637,52 @@ impl<'a> Normalizer<'a> {
 				self.normalize_operation_relation(relation, context)
 			}
 
-			pre::Operation::Match(string) => scalar_operation(
-				|column| column.kind() == exh::ColumnKind::String,
-				|| post::Operation::Match(string.clone()),
+			pre::Operation::Match(string) => {
+				self.string_operation(string, post::Operation::Match, context)
+			}
+			pre::Operation::MatchCase(string) => {
+				self.string_operation(string, post::Operation::MatchCase, context)
+			}
+			pre::Operation::StartsWith(string) => {
+				self.string_operation(string, post::Operation::StartsWith, context)
+			}
+			pre::Operation::EndsWith(string) => {
+				self.string_operation(string, post::Operation::EndsWith, context)
+			}
+			pre::Operation::NotMatch(string) => {
+				self.string_operation(string, post::Operation::NotMatch, context)
+			}
+
+			pre::Operation::Regex(pattern) => {
+				validate_regex(pattern)?;
+				self.string_operation(pattern, post::Operation::Regex, context)
+			}
+
+			pre::Operation::Fuzzy(string) => {
+				validate_fuzzy(string)?;
+				self.string_operation(string, post::Operation::Fuzzy, context)
+			}
+
+			pre::Operation::Eq(value) => scalar_operation(
+				|column| is_value_comparable(value, column.kind()),
+				|| post::Operation::Eq(value.clone()),
 				context,
 			),
 
-			pre::Operation::Eq(value) => {
-				scalar_operation(|_| true, || post::Operation::Eq(value.clone()), context)
+			pre::Operation::Ne(value) => scalar_operation(
+				|column| is_value_comparable(value, column.kind()),
+				|| post::Operation::Ne(value.clone()),
+				context,
+			),
+
+			pre::Operation::In(values) => {
+				validate_value_set(values)?;
+				// Sets are validated to share a type, checking one is sufficient.
+				scalar_operation(
+					|column| is_value_comparable(&values[0], column.kind()),
+					|| post::Operation::In(values.clone()),
+					context,
+				)
 			}
 
 			pre::Operation::Gt(number) => scalar_operation(
@@ -365,6 +708,22 @@ impl<'a> Normalizer<'a> {
 		}
 	}
 
+	/// Normalise an operation matching the text of a string field, checking the
+	/// text against the configured match length.
+	fn string_operation(
+		&self,
+		string: &str,
+		operation: impl Fn(String) -> post::Operation,
+		context: Context,
+	) -> Result<post::Node> {
+		self.match_length.validate(string)?;
+		scalar_operation(
+			|column| column.kind() == exh::ColumnKind::String,
+			|| operation(string.to_string()),
+			context,
+		)
+	}
+
 	fn normalize_operation_relation(
 		&self,
 		relation: &pre::Relation,
@@ -441,19 +800,8 @@ impl<'a> Normalizer<'a> {
 		let condition = match &target.condition {
 			None => None,
 			Some(condition) => {
-				let condition_query = pre::Node::Leaf(pre::Leaf {
-					// NOTE: This is letting the language fall through to ambient - I think that's correct?
-					field: Some(pre::FieldSpecifier::Struct(
-						condition.selector.clone(),
-						None,
-					)),
-					operation: pre::Operation::Eq(pre::Value::Number(pre::Number::U64(
-						condition.value.into(),
-					))),
-				});
-
 				let node = self.normalize_query(
-					&condition_query,
+					&reference_condition_query(condition),
 					context.current_sheet,
 					context.ambient_language,
 					context.path,
@@ -467,6 +815,7 @@ impl<'a> Normalizer<'a> {
 			target: post::RelationTarget {
 				sheet: target.sheet.clone(),
 				condition,
+				direction: post::RelationDirection::Forward,
 			},
 			query: Box::new(query),
 		});
@@ -480,6 +829,79 @@ impl<'a> Normalizer<'a> {
 	}
 }
 
+/// Build a query matching rows that satisfy the condition of a reference.
+fn reference_condition_query(condition: &schema::ReferenceCondition) -> pre::Node {
+	pre::Node::Leaf(pre::Leaf {
+		// NOTE: This is letting the language fall through to ambient - I think that's correct?
+		field: Some(pre::FieldSpecifier::Struct(
+			condition.selector.clone(),
+			None,
+		)),
+		operation: pre::Operation::Eq(pre::Value::Number(pre::Number::U64(condition.value.into()))),
+	})
+}
+
+/// Find a field of a struct schema by its sanitized name.
+fn find_struct_field<'s>(
+	fields: &'s [schema::StructField],
+	field_name: &str,
+	case_insensitive: bool,
+) -> std::result::Result<Option<&'s schema::StructField>, String> {
+	let field = match case_insensitive {
+		false => fields
+			.iter()
+			// TODO: this is _really_ wasteful. see TODO in the utility file w/r/t sanitizing schema preemptively
+			.find(|field| field::sanitize_name(&field.name) == field_name),
+		true => bm_read::resolve_name(
+			field_name,
+			fields
+				.iter()
+				.map(|field| (field::sanitize_name(&field.name), field)),
+			|(name, _field)| name,
+		)
+		.map_err(|error| error.to_string())?
+		.map(|(_name, field)| field),
+	};
+
+	Ok(field)
+}
+
+/// Find the target of a referencing sheet's field that references the given
+/// sheet. Errors describe why the field cannot be used as a reverse relation.
+fn reverse_reference_target<'s>(
+	schema: &'s schema::Node,
+	field_name: &str,
+	referenced_sheet: &str,
+	case_insensitive: bool,
+) -> std::result::Result<&'s schema::ReferenceTarget, String> {
+	let schema::Node::Struct(fields) = schema else {
+		return Err("referencing sheet schema is not a struct".into());
+	};
+
+	let field =
+		find_struct_field(fields, field_name, case_insensitive)?.ok_or("field does not exist")?;
+
+	let schema::Node::Scalar(schema::Scalar::Reference(targets)) = &field.node else {
+		return Err("field is not a reference".into());
+	};
+
+	targets
+		.iter()
+		.find(|target| target.sheet == referenced_sheet)
+		.ok_or_else(|| format!("field does not reference {referenced_sheet}"))
+}
+
+/// Check if a column matches a raw column specifier. Packed boolean columns
+/// share an offset, and must be disambiguated by their bit position.
+fn column_matches(
+	column_offset: u16,
+	column_kind: exh::ColumnKind,
+	offset: u16,
+	bit: Option<u8>,
+) -> bool {
+	column_offset == offset && bm_read::packed_bool_bit(column_kind) == bit
+}
+
 fn is_column_numeric(column: &exh::ColumnDefinition) -> bool {
 	// NOTE: This is written to be comprehensive to ensure it does not drift if column kinds are updated.
 	use exh::ColumnKind as CK;
@@ -507,6 +929,145 @@ fn is_column_numeric(column: &exh::ColumnDefinition) -> bool {
 	}
 }
 
+/// Check if a value can be meaningfully compared with a column for equality.
+///
+/// Booleans are stored as integers, and many flags are stored in integer
+/// columns rather than boolean ones - boolean values are comparable with any
+/// column other than strings. Other values are left to the comparison
+/// semantics of the index.
+fn is_value_comparable(value: &pre::Value, kind: exh::ColumnKind) -> bool {
+	match value {
+		pre::Value::Boolean(..) => kind != exh::ColumnKind::String,
+		pre::Value::Number(..) | pre::Value::String(..) => true,
+	}
+}
+
+/// Ensure a regular expression pattern compiles, so that invalid patterns are
+/// rejected before the query is executed.
+fn validate_regex(pattern: &str) -> Result<()> {
+	regex::Regex::new(pattern)
+		.map(|_| ())
+		.map_err(|error| Error::MalformedQuery(format!("invalid regular expression: {error}")))
+}
+
+/// Maximum length of a fuzzy match string, in characters. Edit distance is
+/// computed against every row scanned, at a cost proportional to the length of
+/// the string, so this applies regardless of the configured match length.
+const MAX_FUZZY_LENGTH: usize = 64;
+
+fn validate_fuzzy(string: &str) -> Result<()> {
+	let length = string.chars().count();
+	if length > MAX_FUZZY_LENGTH {
+		return Err(Error::MalformedQuery(format!(
+			"fuzzy match string must be at most {MAX_FUZZY_LENGTH} characters (got {length})"
+		)));
+	}
+
+	Ok(())
+}
+
+/// Maximum number of values in a set. Each value is bound as a separate SQL
+/// parameter, for every column and array element the set is compared against.
+const MAX_VALUE_SET_LENGTH: usize = 100;
+
+/// Ensure a set of values is non-empty and bounded in size, and that every
+/// value shares a type. Numbers are considered the same type regardless of
+/// representation.
+fn validate_value_set(values: &[pre::Value]) -> Result<()> {
+	let Some((first, rest)) = values.split_first() else {
+		return Err(Error::MalformedQuery("value sets must not be empty".into()));
+	};
+
+	if values.len() > MAX_VALUE_SET_LENGTH {
+		return Err(Error::MalformedQuery(format!(
+			"value sets must contain at most {MAX_VALUE_SET_LENGTH} values (got {})",
+			values.len()
+		)));
+	}
+
+	let kind = std::mem::discriminant(first);
+	if rest
+		.iter()
+		.any(|value| std::mem::discriminant(value) != kind)
+	{
+		return Err(Error::MalformedQuery(
+			"values in a set must all be of the same type".into(),
+		));
+	}
+
+	Ok(())
+}
+
+/// Occurrence of the clauses an operation is fanned out into when targeting
+/// every element of an array.
+///
+/// Operations fan out as an OR group, matching if any element satisfies the
+/// operation. Negated operations are the exception - `Foo[]!=1` is intended to
+/// read as "no element of Foo is 1", rather than "some element of Foo is not 1",
+/// which would match nearly every row. As such, every element must satisfy them.
+fn array_occur(operation: &pre::Operation) -> post::Occur {
+	match operation {
+		pre::Operation::Ne(..) | pre::Operation::NotMatch(..) => post::Occur::Must,
+		_ => post::Occur::Should,
+	}
+}
+
+/// Ensure an operation can be used by an unbound leaf. Unbound leaves only
+/// target string columns, so only operations comparing strings are accepted.
+fn validate_unbound_operation(operation: &pre::Operation) -> Result<()> {
+	let is_string = |value: &pre::Value| matches!(value, pre::Value::String(..));
+
+	let valid = match operation {
+		pre::Operation::Match(..)
+		| pre::Operation::MatchCase(..)
+		| pre::Operation::StartsWith(..)
+		| pre::Operation::EndsWith(..)
+		| pre::Operation::NotMatch(..)
+		| pre::Operation::Regex(..)
+		| pre::Operation::Fuzzy(..) => true,
+
+		pre::Operation::Eq(value) | pre::Operation::Ne(value) => is_string(value),
+		pre::Operation::In(values) => values.iter().all(is_string),
+
+		pre::Operation::Relation(..)
+		| pre::Operation::Gt(..)
+		| pre::Operation::Gte(..)
+		| pre::Operation::Lt(..)
+		| pre::Operation::Lte(..) => false,
+	};
+
+	match valid {
+		true => Ok(()),
+		false => Err(Error::MalformedQuery(
+			"clauses without a field may only perform string operations".into(),
+		)),
+	}
+}
+
+/// Occurrence of the clauses an unbound operation is fanned out into.
+///
+/// As with arrays, operations match if any field satisfies them. Negated
+/// operations must instead be satisfied by every field - `!~"foo"` reads as
+/// "foo does not appear in the row", rather than "some field lacks foo".
+fn unbound_occur(operation: &pre::Operation) -> post::Occur {
+	match operation {
+		pre::Operation::Ne(..) | pre::Operation::NotMatch(..) => post::Occur::Must,
+		_ => post::Occur::Should,
+	}
+}
+
+/// Find the index of the column at the given offset, and bit for packed
+/// booleans, within the current columns.
+fn column_index(offset: u16, bit: Option<u8>, context: &Context) -> Result<usize> {
+	context
+		.columns
+		.iter()
+		.position(|column| column_matches(column.offset(), column.kind(), offset, bit))
+		.ok_or_else(|| {
+			Error::QueryGameMismatch(context.mismatch("no column exists at this offset"))
+		})
+}
+
 fn scalar_operation(
 	filter: impl Fn(&exh::ColumnDefinition) -> bool,
 	operation: impl Fn() -> post::Operation,
@@ -555,3 +1116,476 @@ fn create_or_group(mut nodes: impl Iterator<Item = post::Node>) -> Option<post::
 
 	Some(node)
 }
+
+#[cfg(test)]
+mod test {
+	use std::sync::Arc;
+
+	use exh::ColumnKind as CK;
+
+	use bm_read::fixture;
+
+	use super::*;
+
+	#[test]
+	fn column_offset() {
+		assert!(column_matches(37, CK::UInt32, 37, None));
+		assert!(!column_matches(36, CK::UInt32, 37, None));
+		assert!(!column_matches(37, CK::UInt32, 37, Some(0)));
+	}
+
+	#[test]
+	fn column_packed_bool() {
+		assert!(column_matches(12, CK::PackedBool2, 12, Some(2)));
+		assert!(!column_matches(12, CK::PackedBool2, 12, Some(3)));
+		assert!(!column_matches(12, CK::PackedBool2, 12, None));
+	}
+
+	#[test]
+	fn array_inequality_requires_every_element() {
+		let ne = pre::Operation::Ne(pre::Value::Number(pre::Number::U64(1)));
+		let eq = pre::Operation::Eq(pre::Value::Number(pre::Number::U64(1)));
+		assert_eq!(array_occur(&ne), post::Occur::Must);
+		assert_eq!(
+			array_occur(&pre::Operation::NotMatch("sword".into())),
+			post::Occur::Must
+		);
+		assert_eq!(array_occur(&eq), post::Occur::Should);
+	}
+
+	#[test]
+	fn unbound_operations() {
+		let string = || pre::Value::String("sprint".into());
+		for operation in [
+			pre::Operation::Match("sprint".into()),
+			pre::Operation::Regex("^Sprint$".into()),
+			pre::Operation::Eq(string()),
+			pre::Operation::In(vec![string(), string()]),
+		] {
+			assert!(validate_unbound_operation(&operation).is_ok());
+			assert_eq!(unbound_occur(&operation), post::Occur::Should);
+		}
+
+		let not_match = pre::Operation::NotMatch("sprint".into());
+		assert!(validate_unbound_operation(&not_match).is_ok());
+		assert_eq!(unbound_occur(&not_match), post::Occur::Must);
+
+		for operation in [
+			pre::Operation::Eq(pre::Value::Number(pre::Number::U64(1))),
+			pre::Operation::Gt(pre::Number::U64(1)),
+		] {
+			assert!(matches!(
+				validate_unbound_operation(&operation),
+				Err(Error::MalformedQuery(..))
+			));
+		}
+	}
+
+	#[test]
+	fn boolean_comparable_with_non_string_columns() {
+		let flag = pre::Value::Boolean(true);
+		assert!(is_value_comparable(&flag, CK::Bool));
+		assert!(is_value_comparable(&flag, CK::PackedBool0));
+		assert!(is_value_comparable(&flag, CK::PackedBool7));
+		assert!(is_value_comparable(&flag, CK::UInt8));
+		assert!(!is_value_comparable(&flag, CK::String));
+
+		let number = pre::Value::Number(pre::Number::U64(1));
+		assert!(is_value_comparable(&number, CK::Bool));
+		assert!(is_value_comparable(&number, CK::UInt8));
+	}
+
+	#[test]
+	fn regex_validated() {
+		assert!(validate_regex("^Iron (Sword|Shield)$").is_ok());
+		assert!(matches!(
+			validate_regex("(unclosed"),
+			Err(Error::MalformedQuery(message)) if message.contains("invalid regular expression")
+		));
+	}
+
+	#[test]
+	fn fuzzy_length_capped() {
+		assert!(validate_fuzzy(&"a".repeat(MAX_FUZZY_LENGTH)).is_ok());
+		assert!(matches!(
+			validate_fuzzy(&"a".repeat(MAX_FUZZY_LENGTH + 1)),
+			Err(Error::MalformedQuery(message)) if message.contains("fuzzy")
+		));
+	}
+
+	#[test]
+	fn value_set_types() {
+		let number = |value| pre::Value::Number(pre::Number::U64(value));
+		assert!(
+			validate_value_set(&[number(1), pre::Value::Number(pre::Number::F64(2.5))]).is_ok()
+		);
+		assert!(matches!(
+			validate_value_set(&[number(1), pre::Value::String("2".into())]),
+			Err(Error::MalformedQuery(message)) if message.contains("same type")
+		));
+		assert!(matches!(
+			validate_value_set(&[]),
+			Err(Error::MalformedQuery(..))
+		));
+	}
+
+	#[test]
+	fn value_set_length_capped() {
+		let values = |count| {
+			(0..count)
+				.map(|value| pre::Value::Number(pre::Number::U64(value)))
+				.collect::<Vec<_>>()
+		};
+		assert!(validate_value_set(&values(MAX_VALUE_SET_LENGTH as u64)).is_ok());
+		assert!(matches!(
+			validate_value_set(&values(MAX_VALUE_SET_LENGTH as u64 + 1)),
+			Err(Error::MalformedQuery(message)) if message.contains("at most")
+		));
+	}
+
+	#[test]
+	fn match_below_min_length() {
+		let length = MatchLength { min: 3, max: None };
+		assert!(matches!(
+			length.validate("ab"),
+			Err(Error::MalformedQuery(message)) if message.contains("at least 3")
+		));
+		assert!(length.validate("abc").is_ok());
+	}
+
+	#[test]
+	fn match_above_max_length() {
+		let length = MatchLength {
+			min: 0,
+			max: Some(4),
+		};
+		assert!(matches!(
+			length.validate("abcde"),
+			Err(Error::MalformedQuery(message)) if message.contains("at most 4")
+		));
+		// Length is measured in characters, not bytes.
+		assert!(length.validate("ｱｲｳｴ").is_ok());
+	}
+
+	struct EmptySchema;
+
+	impl schema::Schema for EmptySchema {
+		fn sheet(&self, name: &str) -> schema::Result<schema::Sheet> {
+			Err(schema::Error::NotFound(schema::ErrorValue::Sheet(
+				name.into(),
+			)))
+		}
+	}
+
+	/// Run a normalization against a schemaless sheet with the given columns.
+	fn normalize_columns(
+		match_length: MatchLength,
+		columns: &[exh::ColumnDefinition],
+		normalize: impl FnOnce(&Normalizer, Context) -> Result<post::Node>,
+	) -> Result<post::Node> {
+		let excel = excel::Excel::new(Arc::new(ironworks::Ironworks::new()));
+		let normalizer = Normalizer::new(&excel, &EmptySchema, false, match_length);
+		let schema = schema::Node::Struct(vec![]);
+
+		normalize(
+			&normalizer,
+			Context {
+				current_sheet: "Item",
+				languages: &[excel::Language::English],
+				schema: &schema,
+				columns,
+				language: excel::Language::English,
+				sheet_columns: SheetColumns {
+					expected: columns.len(),
+					actual: columns.len(),
+				},
+				ambient_language: excel::Language::English,
+				path: &[],
+			},
+		)
+	}
+
+	fn normalize_unbound(
+		match_length: MatchLength,
+		columns: &[exh::ColumnDefinition],
+		operation: pre::Operation,
+	) -> Result<post::Node> {
+		normalize_columns(match_length, columns, |normalizer, context| {
+			normalizer.normalize_leaf_unbound(&operation, context)
+		})
+	}
+
+	#[test]
+	fn bound_column_selects_packed_bit() {
+		let columns = [
+			fixture::column(CK::UInt32, 4),
+			fixture::column(CK::PackedBool0, 8),
+			fixture::column(CK::PackedBool2, 8),
+		];
+		let normalize = |offset, bit| {
+			normalize_columns(MatchLength::default(), &columns, |normalizer, context| {
+				normalizer.normalize_leaf_bound_column(
+					&pre::Operation::Eq(pre::Value::Boolean(true)),
+					offset,
+					bit,
+					context,
+				)
+			})
+		};
+
+		let node = normalize(8, Some(2)).expect("normalize should not fail");
+		let post::Node::Leaf(post::Leaf {
+			field: (column, _language),
+			..
+		}) = node
+		else {
+			panic!("expected leaf, got {node:?}");
+		};
+		assert_eq!((column.offset(), column.kind()), (8, CK::PackedBool2));
+
+		assert!(matches!(
+			normalize(8, Some(1)),
+			Err(Error::QueryGameMismatch(..))
+		));
+	}
+
+	#[test]
+	fn unbound_leaf_targets_string_columns() {
+		let columns = [
+			fixture::column(CK::String, 0),
+			fixture::column(CK::UInt32, 4),
+			fixture::column(CK::String, 8),
+		];
+		let node = normalize_unbound(
+			MatchLength::default(),
+			&columns,
+			pre::Operation::Match("sprint".into()),
+		)
+		.expect("normalize should not fail");
+
+		let post::Node::Group(group) = node else {
+			panic!("expected group, got {node:?}");
+		};
+		let clauses = group
+			.clauses
+			.into_iter()
+			.map(|(occur, node)| match node {
+				post::Node::Leaf(post::Leaf {
+					field: (column, _language),
+					operation: post::Operation::Match(string),
+				}) => (occur, column.offset(), string),
+				other => panic!("expected match leaf, got {other:?}"),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			clauses,
+			vec![
+				(post::Occur::Should, 0, "sprint".to_string()),
+				(post::Occur::Should, 8, "sprint".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn unbound_leaf_without_string_columns() {
+		// Sheets without string columns are a schema mismatch, such that they are
+		// pruned from searches across every sheet rather than failing them.
+		let columns = [fixture::column(CK::UInt32, 0)];
+		assert!(matches!(
+			normalize_unbound(
+				MatchLength::default(),
+				&columns,
+				pre::Operation::Match("sprint".into()),
+			),
+			Err(Error::QuerySchemaMismatch(..))
+		));
+	}
+
+	#[test]
+	fn regex_match_length() {
+		let columns = [fixture::column(CK::String, 0)];
+		let length = || MatchLength {
+			min: 0,
+			max: Some(8),
+		};
+
+		assert!(
+			normalize_unbound(length(), &columns, pre::Operation::Regex("^Sprint$".into())).is_ok()
+		);
+		assert!(matches!(
+			normalize_unbound(length(), &columns, pre::Operation::Regex("^(a|b)+Sprint$".into())),
+			Err(Error::MalformedQuery(message)) if message.contains("at most 8")
+		));
+	}
+
+	fn recipe_schema() -> schema::Node {
+		schema::Node::Struct(vec![
+			schema::StructField {
+				name: "ItemResult".into(),
+				offset: 0,
+				node: schema::Node::Scalar(schema::Scalar::Reference(vec![
+					schema::ReferenceTarget {
+						sheet: "Item".into(),
+						selector: None,
+						condition: None,
+					},
+				])),
+			},
+			schema::StructField {
+				name: "CraftType".into(),
+				offset: 4,
+				node: schema::Node::Scalar(schema::Scalar::Default),
+			},
+		])
+	}
+
+	#[test]
+	fn reverse_reference_found() {
+		let schema = recipe_schema();
+		let target = reverse_reference_target(&schema, "ItemResult", "Item", false)
+			.expect("should find reference");
+		assert_eq!(target.sheet, "Item");
+
+		let target = reverse_reference_target(&schema, "itemresult", "Item", true)
+			.expect("should find reference case insensitively");
+		assert_eq!(target.sheet, "Item");
+	}
+
+	#[test]
+	fn reverse_reference_mismatch() {
+		let schema = recipe_schema();
+		assert_eq!(
+			reverse_reference_target(&schema, "ItemResult", "Action", false).unwrap_err(),
+			"field does not reference Action"
+		);
+		assert_eq!(
+			reverse_reference_target(&schema, "CraftType", "Item", false).unwrap_err(),
+			"field is not a reference"
+		);
+		assert_eq!(
+			reverse_reference_target(&schema, "Missing", "Item", false).unwrap_err(),
+			"field does not exist"
+		);
+		assert!(reverse_reference_target(&schema, "itemresult", "Item", false).is_err());
+	}
+
+	#[test]
+	fn schema_expecting_more_columns_outdated() {
+		use bm_read::fixture::{scalar, struct_node, Cell, Fixture, TestSheet};
+
+		let fixture = Fixture::new(vec![(
+			TestSheet::new("Item", [(CK::String, 0), (CK::UInt32, 4)])
+				.row(1, [Cell::String("Potion".into()), Cell::U32(5)]),
+			struct_node([("Name", scalar()), ("Count", scalar()), ("Added", scalar())]),
+		)]);
+		let normalizer = Normalizer::new(
+			&fixture.excel,
+			&fixture.schema,
+			false,
+			MatchLength::default(),
+		);
+		let normalize = |field: &str| {
+			let query = pre::Node::Leaf(pre::Leaf {
+				field: Some(pre::FieldSpecifier::Struct(field.into(), None)),
+				operation: pre::Operation::Eq(pre::Value::Number(pre::Number::U64(1))),
+			});
+			normalizer.normalize(&query, "Item", excel::Language::English)
+		};
+
+		// Fields within the game data's columns remain queryable.
+		assert!(normalize("Count").is_ok());
+		assert!(matches!(
+			normalize("Added"),
+			Err(Error::SchemaOutdated(ColumnDriftError { sheet, expected: 3, actual: 2 }))
+				if sheet == "Item"
+		));
+	}
+
+	#[test]
+	fn reverse_relation_normalized() {
+		use bm_read::fixture::{reference, scalar, struct_node, Cell, Fixture, TestSheet};
+
+		let fixture = Fixture::new(vec![
+			(
+				TestSheet::new("Item", [(CK::String, 0)]).row(1, [Cell::String("Sword".into())]),
+				struct_node([("Name", scalar())]),
+			),
+			(
+				TestSheet::new("Recipe", [(CK::UInt32, 0), (CK::UInt8, 4)])
+					.row(1, [Cell::U32(1), Cell::U8(1)]),
+				struct_node([
+					("ItemResult", reference(&["Item"])),
+					("CraftType", scalar()),
+				]),
+			),
+		]);
+		let normalizer = Normalizer::new(
+			&fixture.excel,
+			&fixture.schema,
+			false,
+			MatchLength::default(),
+		);
+
+		let query = pre::Node::Leaf(pre::Leaf {
+			field: Some(pre::FieldSpecifier::ReverseRelation(
+				"Recipe".into(),
+				"ItemResult".into(),
+			)),
+			operation: pre::Operation::Relation(pre::Relation {
+				target: (),
+				query: Box::new(pre::Node::Leaf(pre::Leaf {
+					field: Some(pre::FieldSpecifier::Struct("CraftType".into(), None)),
+					operation: pre::Operation::Eq(pre::Value::Number(pre::Number::U64(1))),
+				})),
+			}),
+		});
+		let node = normalizer
+			.normalize(&query, "Item", excel::Language::English)
+			.expect("normalize should not fail");
+
+		// The leaf field is the referencing column of the referencing sheet, with
+		// the relation resolved relative to the current sheet's language.
+		let post::Node::Leaf(post::Leaf {
+			field: (column, _language),
+			operation: post::Operation::Relation(relation),
+		}) = node
+		else {
+			panic!("expected relation leaf, got {node:?}");
+		};
+		assert_eq!((column.offset(), column.kind()), (0, CK::UInt32));
+		assert_eq!(relation.target.sheet, "Recipe");
+		assert!(relation.target.condition.is_none());
+		assert_eq!(
+			relation.target.direction,
+			post::RelationDirection::Reverse(excel::Language::None)
+		);
+
+		let post::Node::Leaf(post::Leaf {
+			field: (column, _language),
+			operation: post::Operation::Eq(_),
+		}) = *relation.query
+		else {
+			panic!("expected equality leaf, got {:?}", relation.query);
+		};
+		assert_eq!((column.offset(), column.kind()), (4, CK::UInt8));
+
+		// Fields that do not reference the current sheet are a mismatch.
+		let query = pre::Node::Leaf(pre::Leaf {
+			field: Some(pre::FieldSpecifier::ReverseRelation(
+				"Recipe".into(),
+				"CraftType".into(),
+			)),
+			operation: pre::Operation::Relation(pre::Relation {
+				target: (),
+				query: Box::new(pre::Node::Leaf(pre::Leaf {
+					field: Some(pre::FieldSpecifier::Struct("CraftType".into(), None)),
+					operation: pre::Operation::Eq(pre::Value::Number(pre::Number::U64(1))),
+				})),
+			}),
+		});
+		assert!(matches!(
+			normalizer.normalize(&query, "Item", excel::Language::English),
+			Err(Error::QuerySchemaMismatch(..))
+		));
+	}
+}
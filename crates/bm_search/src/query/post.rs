@@ -1,4 +1,8 @@
+use std::collections::HashMap;
+
+use bm_read::LanguageString;
 use ironworks::{excel, file::exh};
+use serde::Serialize;
 
 use super::query;
 
@@ -9,7 +13,12 @@ pub type Leaf = query::Leaf<LeafField, RelationTarget>;
 pub type Operation = query::Operation<LeafField, RelationTarget>;
 pub type Relation = query::Relation<LeafField, RelationTarget>;
 
-pub use query::{Number, Occur, Value};
+/// Sort fields are normalized per-sheet, keyed by sheet name. Sheets that the
+/// field could not be normalized against are omitted.
+pub type Sort = query::Sort<HashMap<String, LeafField>>;
+pub type SortKey = query::SortKey<HashMap<String, LeafField>>;
+
+pub use query::{Number, Occur, SortDirection, Value};
 
 // Types specific to post-normalised queries
 pub type LeafField = (exh::ColumnDefinition, excel::Language);
@@ -18,4 +27,59 @@ pub type LeafField = (exh::ColumnDefinition, excel::Language);
 pub struct RelationTarget {
 	pub sheet: String,
 	pub condition: Option<Box<Node>>,
+	pub direction: RelationDirection,
+}
+
+/// Direction of the foreign key of a relationship. The field of a relation leaf
+/// is always the foreign key column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelationDirection {
+	/// The field is a column of the current sheet, referencing rows of the target
+	/// sheet.
+	Forward,
+	/// The field is a column of the target sheet, referencing rows of the current
+	/// sheet. The current sheet is joined in the specified language.
+	Reverse(excel::Language),
+}
+
+/// Serializable representation of a normalized query, for debugging.
+pub type DebugNode = query::Node<DebugField, DebugTarget>;
+
+#[derive(Debug, Serialize)]
+pub struct DebugField {
+	pub offset: u16,
+	pub kind: String,
+	pub language: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DebugTarget {
+	pub sheet: String,
+	pub condition: Option<Box<DebugNode>>,
+	pub reverse: bool,
+}
+
+impl Node {
+	pub fn to_debug(&self) -> DebugNode {
+		self.map(&debug_field, &debug_target)
+	}
+}
+
+fn debug_field((column, language): &LeafField) -> DebugField {
+	DebugField {
+		offset: column.offset(),
+		kind: format!("{:?}", column.kind()),
+		language: LanguageString::from(*language).to_string(),
+	}
+}
+
+fn debug_target(target: &RelationTarget) -> DebugTarget {
+	DebugTarget {
+		sheet: target.sheet.clone(),
+		condition: target
+			.condition
+			.as_ref()
+			.map(|condition| Box::new(condition.to_debug())),
+		reverse: matches!(target.direction, RelationDirection::Reverse(_)),
+	}
 }
@@ -1,4 +1,6 @@
+use bm_read::LanguageString;
 use ironworks::excel;
+use serde::{Serialize, Serializer};
 
 use super::query;
 
@@ -9,14 +11,39 @@ pub type Leaf = query::Leaf<LeafField, RelationTarget>;
 pub type Operation = query::Operation<LeafField, RelationTarget>;
 pub type Relation = query::Relation<LeafField, RelationTarget>;
 
-pub use query::{Number, Occur, Value};
+pub type Sort = query::Sort<FieldSpecifier>;
+pub type SortKey = query::SortKey<FieldSpecifier>;
+
+pub use query::{Number, Occur, SortDirection, Value};
 
 // Types specific to pre-normalised queries
 pub type LeafField = Option<FieldSpecifier>;
 pub type RelationTarget = ();
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FieldSpecifier {
-	Struct(String, Option<excel::Language>),
+	Struct(
+		String,
+		#[serde(serialize_with = "serialize_language")] Option<excel::Language>,
+	),
 	Array(Option<u32>),
+	/// A raw column, by byte offset, and bit position for packed booleans.
+	Column(u16, Option<u8>),
+	/// Rows of another sheet that reference the current row, by the name of the
+	/// referencing sheet and of its referencing field.
+	ReverseRelation(String, String),
+}
+
+fn serialize_language<S>(
+	language: &Option<excel::Language>,
+	serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	match language {
+		Some(language) => serializer.collect_str(&LanguageString::from(*language)),
+		None => serializer.serialize_none(),
+	}
 }
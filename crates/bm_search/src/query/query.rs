@@ -1,35 +1,54 @@
-#[derive(Debug, Clone, PartialEq)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Node<F, T> {
 	Group(Group<F, T>),
 	Leaf(Leaf<F, T>),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Group<F, T> {
 	pub clauses: Vec<(Occur, Node<F, T>)>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Occur {
 	Must,
 	Should,
 	MustNot,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Leaf<F, T> {
 	/// Column offset this leaf targets.
 	pub field: F,
 	pub operation: Operation<F, T>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Operation<F, T> {
 	Relation(Relation<F, T>),
 
 	Match(String),
+	/// Partial string match, respecting case.
+	MatchCase(String),
+	/// String match anchored to the start of the value.
+	StartsWith(String),
+	/// String match anchored to the end of the value.
+	EndsWith(String),
+	NotMatch(String),
+	/// Regular expression match.
+	Regex(String),
+	/// Approximate string match, tolerating a bounded edit distance.
+	Fuzzy(String),
 
 	Eq(Value),
+	Ne(Value),
+	/// Equality with any of a set of values. Values must share a type.
+	In(Vec<Value>),
 
 	Gt(Number),
 	Gte(Number),
@@ -37,21 +56,23 @@ pub enum Operation<F, T> {
 	Lte(Number),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Relation<F, T> {
 	pub target: T,
 	/// Query to be executed on the target sheet's index.
 	pub query: Box<Node<F, T>>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
 pub enum Value {
 	Boolean(bool),
 	Number(Number),
 	String(String),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
 pub enum Number {
 	/// A positive integer.
 	U64(u64),
@@ -60,3 +81,87 @@ pub enum Number {
 	/// A floating point number.
 	F64(f64),
 }
+
+/// Ordering applied to the results of a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sort<K> {
+	pub key: SortKey<K>,
+	pub direction: SortDirection,
+}
+
+impl<K> Default for Sort<K> {
+	/// Results are ordered by relevance by default, most relevant first.
+	fn default() -> Self {
+		Self {
+			key: SortKey::Score,
+			direction: SortDirection::Descending,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortKey<K> {
+	/// Computed relevance of the result to the query.
+	Score,
+	/// Value of a field of the result.
+	Field(K),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortDirection {
+	Ascending,
+	Descending,
+}
+
+impl<F, T> Node<F, T> {
+	/// Build a copy of this node with its fields and relation targets converted
+	/// by the provided functions.
+	pub fn map<F2, T2>(
+		&self,
+		field: &impl Fn(&F) -> F2,
+		target: &impl Fn(&T) -> T2,
+	) -> Node<F2, T2> {
+		match self {
+			Self::Group(group) => Node::Group(Group {
+				clauses: group
+					.clauses
+					.iter()
+					.map(|(occur, node)| (occur.clone(), node.map(field, target)))
+					.collect(),
+			}),
+			Self::Leaf(leaf) => Node::Leaf(Leaf {
+				field: field(&leaf.field),
+				operation: leaf.operation.map(field, target),
+			}),
+		}
+	}
+}
+
+impl<F, T> Operation<F, T> {
+	fn map<F2, T2>(
+		&self,
+		field: &impl Fn(&F) -> F2,
+		target: &impl Fn(&T) -> T2,
+	) -> Operation<F2, T2> {
+		match self {
+			Self::Relation(relation) => Operation::Relation(Relation {
+				target: target(&relation.target),
+				query: Box::new(relation.query.map(field, target)),
+			}),
+			Self::Match(string) => Operation::Match(string.clone()),
+			Self::MatchCase(string) => Operation::MatchCase(string.clone()),
+			Self::StartsWith(string) => Operation::StartsWith(string.clone()),
+			Self::EndsWith(string) => Operation::EndsWith(string.clone()),
+			Self::NotMatch(string) => Operation::NotMatch(string.clone()),
+			Self::Regex(pattern) => Operation::Regex(pattern.clone()),
+			Self::Fuzzy(string) => Operation::Fuzzy(string.clone()),
+			Self::Eq(value) => Operation::Eq(value.clone()),
+			Self::Ne(value) => Operation::Ne(value.clone()),
+			Self::In(values) => Operation::In(values.clone()),
+			Self::Gt(number) => Operation::Gt(number.clone()),
+			Self::Gte(number) => Operation::Gte(number.clone()),
+			Self::Lt(number) => Operation::Lt(number.clone()),
+			Self::Lte(number) => Operation::Lte(number.clone()),
+		}
+	}
+}
@@ -1,10 +1,11 @@
 use std::{
 	borrow::Cow,
-	collections::HashSet,
+	collections::{BTreeMap, HashMap, HashSet},
 	sync::{
 		atomic::{AtomicBool, Ordering},
 		Arc,
 	},
+	time::SystemTime,
 };
 
 use anyhow::Context;
@@ -13,20 +14,56 @@ use bm_version::VersionKey;
 use either::Either;
 use ironworks::excel;
 use itertools::Itertools;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tokio::select;
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use super::{
 	error::{Error, Result},
-	internal_query::{pre, Normalizer},
+	internal_query::{post, pre, MatchLength, Normalizer},
 	sqlite,
 };
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
 	sqlite: sqlite::Config,
+
+	/// Bounds on the length of strings in match operations.
+	#[serde(default)]
+	match_length: MatchLength,
+
+	/// Searches across every sheet, performed when a request does not specify
+	/// any sheets.
+	#[serde(default)]
+	all_sheets: AllSheetsConfig,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct AllSheetsConfig {
+	/// Whether requests may omit sheets to search every sheet.
+	#[serde(default)]
+	enabled: bool,
+
+	/// Maximum number of sheets that may be searched by a single request
+	/// without specified sheets, after sheets that the query cannot target have
+	/// been pruned. Clauses without a field can target any sheet with a string
+	/// column, and are rarely pruned.
+	#[serde(default = "default_all_sheets_max")]
+	max: usize,
+}
+
+impl Default for AllSheetsConfig {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			max: default_all_sheets_max(),
+		}
+	}
+}
+
+fn default_all_sheets_max() -> usize {
+	100
 }
 
 #[derive(Debug)]
@@ -40,8 +77,21 @@ pub struct SearchRequestQuery {
 	pub version: VersionKey,
 	pub query: pre::Node,
 	pub language: excel::Language,
+	/// Sheets to search. If omitted, every sheet the query can be normalized
+	/// against is searched, if enabled by configuration.
 	pub sheets: Option<HashSet<String>>,
 	pub schema: bm_schema::CanonicalSpecifier,
+	pub case_insensitive: bool,
+	/// Ordering applied to results.
+	pub sort: pre::Sort,
+	/// Row IDs to constrain results to. If omitted, any row may be returned.
+	pub rows: Option<Vec<u32>>,
+	/// Include details of the columns matched by each result, and the query
+	/// trees used to find them.
+	pub debug: bool,
+	/// Include snippets of the column values that satisfied string match
+	/// clauses for each result.
+	pub highlights: bool,
 }
 
 #[derive(Debug)]
@@ -51,6 +101,58 @@ pub struct SearchResult {
 	pub sheet: String,
 	pub row_id: u32,
 	pub subrow_id: u16,
+	/// Columns of the result's sheet that matched the query. Only populated for
+	/// debug requests.
+	pub matched_columns: Option<Vec<MatchedColumn>>,
+	/// Snippets of the columns of the result's sheet that satisfied string match
+	/// clauses. Only populated for highlighting requests.
+	pub highlights: Option<Vec<MatchHighlight>>,
+}
+
+/// Continuation token for retrieving further results of a search.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchCursor {
+	pub id: Uuid,
+	/// Time until which the cursor is guaranteed to remain available. Cursors
+	/// may be evicted at any point after this time.
+	pub expires_at: SystemTime,
+}
+
+/// Query trees used to execute a search, for debugging.
+#[derive(Debug, Serialize)]
+pub struct SearchDebug {
+	/// Query as parsed from the request.
+	pub query: pre::Node,
+	/// Query as normalized against each sheet searched, keyed by sheet name.
+	/// Sheets the query could not be normalized against are omitted.
+	pub normalized: BTreeMap<String, post::DebugNode>,
+}
+
+/// Query trees and SQL generated for a search, without executing it.
+#[derive(Debug, Serialize)]
+pub struct SearchExplain {
+	/// Query as normalized against each sheet searched, keyed by sheet name.
+	/// Sheets the query could not be normalized against are omitted.
+	pub normalized: BTreeMap<String, post::DebugNode>,
+	/// Statements that would be executed to retrieve the first page of results,
+	/// with values inlined. Multiple statements are separated by semicolons.
+	pub sql: String,
+}
+
+/// A physical column that matched a clause of a search query.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MatchedColumn {
+	pub offset: u16,
+	pub kind: String,
+}
+
+/// A snippet of a column value that satisfied a string match clause of a
+/// search query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchHighlight {
+	pub offset: u16,
+	pub language: excel::Language,
+	pub snippet: String,
 }
 
 pub struct Search {
@@ -60,6 +162,9 @@ pub struct Search {
 
 	data: Arc<Data>,
 	schema: Arc<bm_schema::Provider>,
+
+	match_length: MatchLength,
+	all_sheets: AllSheetsConfig,
 }
 
 impl Search {
@@ -69,6 +174,8 @@ impl Search {
 			provider: Arc::new(sqlite::Provider::new(config.sqlite, data.clone())?),
 			data,
 			schema,
+			match_length: config.match_length,
+			all_sheets: config.all_sheets,
 		})
 	}
 
@@ -76,6 +183,11 @@ impl Search {
 		self.ready.load(Ordering::Relaxed)
 	}
 
+	/// Check if search ingestion has completed for the specified version.
+	pub fn version_ready(&self, version: VersionKey) -> bool {
+		self.provider.ready(version)
+	}
+
 	pub async fn start(&self, cancel: CancellationToken) -> Result<()> {
 		let mut receiver = self.data.subscribe();
 		self.ingest(cancel.child_token(), receiver.borrow().clone())
@@ -151,51 +263,355 @@ impl Search {
 		&self,
 		request: SearchRequest,
 		limit: usize,
-	) -> Result<(Vec<SearchResult>, Option<Uuid>)> {
+	) -> Result<(Vec<SearchResult>, Option<SearchCursor>, Option<SearchDebug>)> {
 		// Translate the request into the format used by providers.
-		let provider_request = match request {
+		let (provider_request, debug) = match request {
 			SearchRequest::Query(query) => self.normalize_request_query(query)?,
-			SearchRequest::Cursor(uuid) => sqlite::SearchRequest::Cursor(uuid),
+			SearchRequest::Cursor(uuid) => (sqlite::SearchRequest::Cursor(uuid), None),
 		};
 
 		// Execute the search.
-		self.provider.search(provider_request, limit).await
+		let (results, cursor) = self.provider.search(provider_request, limit).await?;
+
+		Ok((results, cursor, debug))
 	}
 
-	fn normalize_request_query(&self, query: SearchRequestQuery) -> Result<sqlite::SearchRequest> {
+	/// Normalize and resolve a search query, returning the generated SQL rather
+	/// than executing it.
+	pub fn explain(&self, query: SearchRequestQuery, limit: usize) -> Result<SearchExplain> {
+		let (provider_request, _debug) = self.normalize_request_query(query)?;
+
+		let sqlite::SearchRequest::Query { queries, .. } = &provider_request else {
+			unreachable!("query requests are always normalized to provider queries")
+		};
+		let normalized = queries
+			.iter()
+			.map(|(sheet, node)| (sheet.clone(), node.to_debug()))
+			.collect();
+
+		let sql = self.provider.explain(provider_request, limit)?;
+
+		Ok(SearchExplain { normalized, sql })
+	}
+
+	fn normalize_request_query(
+		&self,
+		query: SearchRequestQuery,
+	) -> Result<(sqlite::SearchRequest, Option<SearchDebug>)> {
 		// Get references to the game data we'll need.
 		let excel = self
 			.data
 			.version(query.version)
 			.with_context(|| format!("data for version {} not ready", query.version))?
 			.excel();
-		let list = excel.list()?;
 
 		// Build the helpers for this search call.
 		let schema = self.schema.schema(query.schema)?;
-		let normalizer = Normalizer::new(&excel, schema.as_ref());
-
-		// Get an iterator over the provided sheet filter, falling back to the full list of sheets.
-		let sheet_names = query
-			.sheets
-			.map(|filter| Either::Left(filter.into_iter().map(Cow::from)))
-			.unwrap_or_else(|| Either::Right(list.iter()));
-
-		let normalized_queries = sheet_names
-			.map(|name| {
-				let normalized_query = normalizer.normalize(&query.query, &name, query.language)?;
-				Ok((name.to_string(), normalized_query))
-			})
-			// TODO: This is filtering out non-fatal errors. To raise as warnings, these will need to be split out at this point.
-			.filter(|query| match query {
-				Err(Error::Failure(_)) | Ok(_) => true,
-				Err(_) => false,
-			})
-			.collect::<Result<Vec<_>>>()?;
+		let normalizer = Normalizer::new(
+			&excel,
+			schema.as_ref(),
+			query.case_insensitive,
+			self.match_length,
+		);
+
+		let normalized_queries = normalize_sheets(
+			&normalizer,
+			&excel,
+			&self.all_sheets,
+			&query.query,
+			query.sheets,
+			query.language,
+		)?;
 
-		Ok(sqlite::SearchRequest::Query {
+		let sort = self.normalize_sort(
+			&normalizer,
+			query.sort,
+			normalized_queries
+				.iter()
+				.map(|(sheet, _node)| sheet.as_str()),
+			query.language,
+		)?;
+
+		let debug = query.debug.then(|| SearchDebug {
+			query: query.query,
+			normalized: normalized_queries
+				.iter()
+				.map(|(sheet, node)| (sheet.clone(), node.to_debug()))
+				.collect(),
+		});
+
+		let request = sqlite::SearchRequest::Query {
 			version: query.version,
 			queries: normalized_queries,
+			sort,
+			rows: query.rows,
+			debug: query.debug,
+			highlights: query.highlights,
+		};
+
+		Ok((request, debug))
+	}
+
+	/// Normalize a sort field against each of the searched sheets.
+	fn normalize_sort<'a>(
+		&self,
+		normalizer: &Normalizer,
+		sort: pre::Sort,
+		sheets: impl Iterator<Item = &'a str>,
+		language: excel::Language,
+	) -> Result<post::Sort> {
+		let specifier = match sort.key {
+			pre::SortKey::Score => {
+				return Ok(post::Sort {
+					key: post::SortKey::Score,
+					direction: sort.direction,
+				})
+			}
+			pre::SortKey::Field(specifier) => specifier,
+		};
+
+		// Sheets the field cannot be resolved against are omitted, and sort with
+		// a null value.
+		let mut searched = false;
+		let mut columns = HashMap::new();
+		for sheet in sheets {
+			searched = true;
+			match normalizer.normalize_field(&specifier, sheet, language) {
+				Ok(field) => {
+					columns.insert(sheet.to_string(), field);
+				}
+				Err(error @ (Error::Failure(_) | Error::MalformedQuery(_))) => return Err(error),
+				Err(_) => {}
+			}
+		}
+
+		// If there are no sheets at all, leave it to query resolution to report.
+		if searched && columns.is_empty() {
+			return Err(Error::MalformedQuery(format!(
+				"sort field {specifier:?} could not be resolved for any searched sheet"
+			)));
+		}
+
+		Ok(post::Sort {
+			key: post::SortKey::Field(columns),
+			direction: sort.direction,
 		})
 	}
 }
+
+/// Normalize a query against each of the given sheets, or every sheet if none
+/// are given. Sheets that the query cannot target are omitted.
+fn normalize_sheets(
+	normalizer: &Normalizer,
+	excel: &excel::Excel,
+	all_sheets_config: &AllSheetsConfig,
+	query: &pre::Node,
+	sheets: Option<HashSet<String>>,
+	language: excel::Language,
+) -> Result<Vec<(String, post::Node)>> {
+	// Searching every sheet unions a select for each of them, which gets very
+	// expensive - it must be explicitly enabled.
+	let all_sheets = sheets.is_none();
+	if all_sheets && !all_sheets_config.enabled {
+		return Err(Error::MalformedQuery(
+			"searches must specify a list of sheets to search".into(),
+		));
+	}
+
+	let list = excel.list()?;
+
+	// Get an iterator over the provided sheet filter, falling back to the full list of sheets.
+	let sheet_names = sheets
+		.map(|filter| Either::Left(filter.into_iter().map(Cow::from)))
+		.unwrap_or_else(|| Either::Right(list.iter()));
+
+	let normalized_queries = sheet_names
+		.map(|name| {
+			let normalized_query = normalizer.normalize(query, &name, language)?;
+			Ok((name.to_string(), normalized_query))
+		})
+		// TODO: This is filtering out non-fatal errors. To raise as warnings, these will need to be split out at this point.
+		.filter(|query| match query {
+			Err(Error::Failure(_)) | Ok(_) => true,
+			Err(_) => false,
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	// Sheets the query cannot target, including those not covered by the
+	// schema, have been pruned above - the cap applies to what remains. Clauses
+	// without a field fan out to every string column of every remaining sheet,
+	// making them the most expensive queries to run across all sheets, so are
+	// deliberately held to the same cap.
+	if all_sheets && normalized_queries.len() > all_sheets_config.max {
+		return Err(Error::MalformedQuery(format!(
+			"query can target {} sheets, but at most {} may be searched without specifying sheets - specify sheets, or target fields present on fewer sheets",
+			normalized_queries.len(),
+			all_sheets_config.max
+		)));
+	}
+
+	Ok(normalized_queries)
+}
+
+#[cfg(test)]
+mod test {
+	use bm_read::fixture::{reference, scalar, struct_node, Cell, Fixture, TestSheet};
+	use ironworks::file::exh::ColumnKind as CK;
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn all_sheets_disabled_by_default() {
+		let config = serde_json::from_value::<AllSheetsConfig>(json!({})).unwrap();
+		assert!(!config.enabled);
+		assert_eq!(config.max, default_all_sheets_max());
+
+		let config = serde_json::from_value::<AllSheetsConfig>(json!({"enabled": true})).unwrap();
+		assert!(config.enabled);
+		assert_eq!(config.max, default_all_sheets_max());
+	}
+
+	#[test]
+	fn all_sheets_gated_and_capped() {
+		let fixture = Fixture::new(vec![
+			(
+				TestSheet::new("Item", [(CK::String, 0)]).row(1, [Cell::String("Sword".into())]),
+				struct_node([("Name", scalar())]),
+			),
+			(
+				TestSheet::new("Action", [(CK::String, 0)]).row(1, [Cell::String("Sprint".into())]),
+				struct_node([("Label", scalar())]),
+			),
+			(
+				TestSheet::new("Recipe", [(CK::UInt32, 0)]).row(1, [Cell::U32(1)]),
+				struct_node([("ItemResult", scalar())]),
+			),
+		]);
+		let normalizer = Normalizer::new(
+			&fixture.excel,
+			&fixture.schema,
+			false,
+			MatchLength::default(),
+		);
+		let query = |field: Option<&str>| {
+			pre::Node::Leaf(pre::Leaf {
+				field: field.map(|name| pre::FieldSpecifier::Struct(name.into(), None)),
+				operation: pre::Operation::Match("s".into()),
+			})
+		};
+		let normalize = |enabled, query: &pre::Node, sheets: Option<&[&str]>| {
+			let config = AllSheetsConfig { enabled, max: 1 };
+			let sheets =
+				sheets.map(|sheets| sheets.iter().map(|sheet| sheet.to_string()).collect());
+			normalize_sheets(
+				&normalizer,
+				&fixture.excel,
+				&config,
+				query,
+				sheets,
+				excel::Language::English,
+			)
+			.map(|queries| {
+				queries
+					.into_iter()
+					.map(|(sheet, _node)| sheet)
+					.collect::<Vec<_>>()
+			})
+		};
+
+		// Omitting sheets must be enabled.
+		assert!(matches!(
+			normalize(false, &query(Some("Name")), None),
+			Err(Error::MalformedQuery(message)) if message.contains("must specify")
+		));
+
+		// Sheets the query cannot target are pruned before the cap is applied.
+		assert_eq!(
+			normalize(true, &query(Some("Name")), None).unwrap(),
+			vec!["Item"]
+		);
+
+		// Clauses without a field target every sheet with a string column.
+		assert!(matches!(
+			normalize(true, &query(None), None),
+			Err(Error::MalformedQuery(message)) if message.contains("at most 1")
+		));
+
+		// Specified sheets are neither gated nor capped.
+		let mut got = normalize(false, &query(None), Some(&["Item", "Action"])).unwrap();
+		got.sort();
+		assert_eq!(got, vec!["Action", "Item"]);
+	}
+
+	#[test]
+	fn debug_relation_query() {
+		let query = pre::Node::Leaf(pre::Leaf {
+			field: Some(pre::FieldSpecifier::Struct("ItemResult".into(), None)),
+			operation: pre::Operation::Relation(pre::Relation {
+				target: (),
+				query: Box::new(pre::Node::Leaf(pre::Leaf {
+					field: Some(pre::FieldSpecifier::Struct("Name".into(), None)),
+					operation: pre::Operation::Match("sword".into()),
+				})),
+			}),
+		});
+
+		let fixture = Fixture::new(vec![
+			(
+				TestSheet::new("Item", [(CK::String, 0)]).row(1, [Cell::String("Sword".into())]),
+				struct_node([("Name", scalar())]),
+			),
+			(
+				TestSheet::new("Recipe", [(CK::UInt8, 0), (CK::UInt32, 4)])
+					.row(1, [Cell::U8(1), Cell::U32(1)]),
+				struct_node([
+					("CraftType", scalar()),
+					("ItemResult", reference(&["Item"])),
+				]),
+			),
+		]);
+		let normalizer = Normalizer::new(
+			&fixture.excel,
+			&fixture.schema,
+			false,
+			MatchLength::default(),
+		);
+		let normalized = normalizer
+			.normalize(&query, "Recipe", excel::Language::English)
+			.expect("normalize should not fail");
+
+		let debug = SearchDebug {
+			query,
+			normalized: BTreeMap::from([("Recipe".to_string(), normalized.to_debug())]),
+		};
+
+		let got = serde_json::to_value(&debug).unwrap();
+		assert_eq!(
+			got["query"],
+			json!({"leaf": {
+				"field": {"struct": ["ItemResult", null]},
+				"operation": {"relation": {
+					"target": null,
+					"query": {"leaf": {
+						"field": {"struct": ["Name", null]},
+						"operation": {"match": "sword"},
+					}},
+				}},
+			}})
+		);
+
+		let leaf = &got["normalized"]["Recipe"]["leaf"];
+		assert_eq!(leaf["field"]["offset"], json!(4));
+		assert_eq!(leaf["field"]["kind"], json!("UInt32"));
+
+		let relation = &leaf["operation"]["relation"];
+		assert_eq!(
+			relation["target"],
+			json!({"sheet": "Item", "condition": null, "reverse": false})
+		);
+		let inner = &relation["query"]["leaf"]["field"];
+		assert_eq!(inner["offset"], json!(0));
+		assert_eq!(inner["kind"], json!("String"));
+	}
+}
@@ -1,18 +1,58 @@
 use std::{path::PathBuf, sync::Arc};
 
 use bb8::ManageConnection;
-use ironworks::excel::Excel;
+use ironworks::{excel::Excel, Ironworks};
+use regex::Regex;
+use rusqlite::{functions::FunctionFlags, types::ValueRef};
+use serde::Deserialize;
 
 use super::vtable;
 
+/// Pragmas applied to each connection as it is opened.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PragmaConfig {
+	/// Page cache size. Negative values are in KiB, positive in pages.
+	cache_size: i64,
+	/// Maximum number of bytes of the database to memory map.
+	mmap_size: i64,
+	/// Storage for temporary tables and indices, one of `default`, `file`, or `memory`.
+	temp_store: String,
+	/// Journaling mode for the database, i.e. `wal` or `delete`.
+	journal_mode: String,
+}
+
+impl Default for PragmaConfig {
+	fn default() -> Self {
+		Self {
+			cache_size: -64 * 1024,
+			mmap_size: 256 * 1024 * 1024,
+			temp_store: "memory".into(),
+			journal_mode: "wal".into(),
+		}
+	}
+}
+
 pub struct SqliteConnectionManager {
 	path: PathBuf,
 	excel: Arc<Excel>,
+	ironworks: Arc<Ironworks>,
+	pragmas: PragmaConfig,
 }
 
 impl SqliteConnectionManager {
-	pub fn new(path: PathBuf, excel: Arc<Excel>) -> Self {
-		Self { path, excel }
+	pub fn new(
+		path: PathBuf,
+		excel: Arc<Excel>,
+		ironworks: Arc<Ironworks>,
+		pragmas: PragmaConfig,
+	) -> Self {
+		Self {
+			path,
+			excel,
+			ironworks,
+			pragmas,
+		}
 	}
 }
 
@@ -23,9 +63,11 @@ impl ManageConnection for SqliteConnectionManager {
 	async fn connect(&self) -> Result<Self::Connection, Self::Error> {
 		let connection = rusqlite::Connection::open(&self.path)?;
 
-		connection.pragma_update(None, "synchronous", "OFF")?;
+		apply_pragmas(&connection, &self.pragmas)?;
+
+		register_functions(&connection)?;
 
-		vtable::load_module(&connection, self.excel.clone())?;
+		vtable::load_module(&connection, self.excel.clone(), self.ironworks.clone())?;
 
 		Ok(connection)
 	}
@@ -38,3 +80,196 @@ impl ManageConnection for SqliteConnectionManager {
 		false
 	}
 }
+
+fn apply_pragmas(
+	connection: &rusqlite::Connection,
+	pragmas: &PragmaConfig,
+) -> rusqlite::Result<()> {
+	connection.pragma_update(None, "synchronous", "OFF")?;
+	connection.pragma_update(None, "cache_size", pragmas.cache_size)?;
+	connection.pragma_update(None, "temp_store", &pragmas.temp_store)?;
+
+	// These pragmas report their resulting value, which must be consumed.
+	connection.pragma_update_and_check(None, "mmap_size", pragmas.mmap_size, |_| Ok(()))?;
+	connection.pragma_update_and_check(None, "journal_mode", &pragmas.journal_mode, |_| Ok(()))?;
+
+	Ok(())
+}
+
+/// Register scalar functions used by search queries.
+pub(super) fn register_functions(connection: &rusqlite::Connection) -> rusqlite::Result<()> {
+	// SQLite parses `value REGEXP pattern`, but provides no implementation of the
+	// function itself. Patterns are compiled once per statement, but matching is
+	// still performed against every row scanned.
+	connection.create_scalar_function(
+		"regexp",
+		2,
+		FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+		|context| {
+			let regex = context.get_or_create_aux(0, |pattern| -> Result<_, BoxError> {
+				Ok(Regex::new(pattern.as_str()?)?)
+			})?;
+
+			let matched = match context.get_raw(1) {
+				ValueRef::Text(text) => {
+					std::str::from_utf8(text).is_ok_and(|text| regex.is_match(text))
+				}
+				_ => false,
+			};
+
+			Ok(matched)
+		},
+	)?;
+
+	// Edit distance between two strings, in characters, up to a maximum. Any
+	// distance beyond the maximum is reported as the maximum plus one. Non-text
+	// values have no meaningful distance, and result in NULL.
+	// A fuzzy match references the distance in both its condition and score -
+	// the most recent result is kept, such that each row is only measured once.
+	let mut last: Option<(Vec<u8>, Vec<u8>, u32, u32)> = None;
+	connection.create_scalar_function(
+		"levenshtein",
+		3,
+		FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+		move |context| {
+			let (ValueRef::Text(a), ValueRef::Text(b)) = (context.get_raw(0), context.get_raw(1))
+			else {
+				return Ok(None);
+			};
+			let max = context.get::<u32>(2)?;
+
+			if let Some((last_a, last_b, last_max, distance)) = &last {
+				if last_a == a && last_b == b && *last_max == max {
+					return Ok(Some(*distance));
+				}
+			}
+
+			let (Ok(a_str), Ok(b_str)) = (std::str::from_utf8(a), std::str::from_utf8(b)) else {
+				return Ok(None);
+			};
+
+			let distance = bounded_levenshtein(a_str, b_str, max);
+			last = Some((a.to_vec(), b.to_vec(), max, distance));
+
+			Ok(Some(distance))
+		},
+	)
+}
+
+fn bounded_levenshtein(a: &str, b: &str, max: u32) -> u32 {
+	// The distance is at least the difference in length - skip the full
+	// calculation when that alone rules out a match.
+	let (a_length, b_length) = (a.chars().count(), b.chars().count());
+	if a_length.abs_diff(b_length) > usize::try_from(max).unwrap_or(usize::MAX) {
+		return max.saturating_add(1);
+	}
+
+	levenshtein(a, b).min(max.saturating_add(1))
+}
+
+fn levenshtein(a: &str, b: &str) -> u32 {
+	let b = b.chars().collect::<Vec<_>>();
+
+	// Single-row dynamic programming over the edit distance matrix.
+	let mut row = (0..=b.len()).collect::<Vec<_>>();
+	for (i, a_char) in a.chars().enumerate() {
+		let mut diagonal = row[0];
+		row[0] = i + 1;
+		for (j, b_char) in b.iter().enumerate() {
+			let substitution = diagonal + usize::from(a_char != *b_char);
+			diagonal = row[j + 1];
+			row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+		}
+	}
+
+	u32::try_from(row[b.len()]).unwrap_or(u32::MAX)
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+#[cfg(test)]
+mod test {
+	use std::fs;
+
+	use uuid::Uuid;
+
+	use super::*;
+
+	fn pragma<T: rusqlite::types::FromSql>(connection: &rusqlite::Connection, name: &str) -> T {
+		connection
+			.pragma_query_value(None, name, |row| row.get(0))
+			.expect("pragma query should not fail")
+	}
+
+	#[test]
+	fn regexp_function() {
+		let connection = rusqlite::Connection::open_in_memory().unwrap();
+		register_functions(&connection).expect("functions should register");
+
+		let matches = |value: &str, pattern: &str| -> bool {
+			connection
+				.query_row("SELECT ?1 REGEXP ?2", (value, pattern), |row| row.get(0))
+				.expect("query should not fail")
+		};
+
+		assert!(matches("Iron Sword", "^Iron (Sword|Shield)$"));
+		assert!(!matches("Iron Axe", "^Iron (Sword|Shield)$"));
+		assert!(!matches("iron sword", "Sword"));
+	}
+
+	#[test]
+	fn levenshtein_function() {
+		let connection = rusqlite::Connection::open_in_memory().unwrap();
+		register_functions(&connection).expect("functions should register");
+
+		let distance = |a: &str, b: &str| -> Option<u32> {
+			connection
+				.query_row("SELECT levenshtein(?1, ?2, 5)", (a, b), |row| row.get(0))
+				.expect("query should not fail")
+		};
+
+		assert_eq!(distance("sprint", "sprint"), Some(0));
+		assert_eq!(distance("sprint", "sprnt"), Some(1));
+		assert_eq!(distance("kitten", "sitting"), Some(3));
+		assert_eq!(distance("", "abc"), Some(3));
+		// Distance is measured in characters, not bytes.
+		assert_eq!(distance("ソード", "ソーダ"), Some(1));
+
+		// Distances beyond the maximum are capped, whether ruled out by length
+		// alone or measured in full.
+		assert_eq!(distance("a", "abcdefghij"), Some(6));
+		assert_eq!(distance("abcdefgh", "hgfedcba"), Some(6));
+		// Repeated arguments are served from the last result.
+		assert_eq!(distance("kitten", "sitting"), Some(3));
+		assert_eq!(distance("kitten", "sittin"), Some(2));
+
+		let null: Option<u32> = connection
+			.query_row("SELECT levenshtein(NULL, 'abc', 5)", [], |row| row.get(0))
+			.unwrap();
+		assert_eq!(null, None);
+	}
+
+	#[test]
+	fn pragmas_applied() {
+		let directory = std::env::temp_dir().join(format!("bm_search-{}", Uuid::new_v4()));
+		fs::create_dir_all(&directory).unwrap();
+
+		let connection = rusqlite::Connection::open(directory.join("test.db")).unwrap();
+		let pragmas = PragmaConfig {
+			cache_size: -1024,
+			mmap_size: 1024 * 1024,
+			temp_store: "memory".into(),
+			journal_mode: "wal".into(),
+		};
+		apply_pragmas(&connection, &pragmas).expect("pragmas should apply");
+
+		assert_eq!(pragma::<i64>(&connection, "cache_size"), -1024);
+		assert_eq!(pragma::<i64>(&connection, "mmap_size"), 1024 * 1024);
+		// 2 = MEMORY
+		assert_eq!(pragma::<i64>(&connection, "temp_store"), 2);
+		assert_eq!(pragma::<String>(&connection, "journal_mode"), "wal");
+
+		drop(connection);
+		fs::remove_dir_all(&directory).unwrap();
+	}
+}
@@ -1,11 +1,14 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use bm_version::VersionKey;
 use mini_moka::sync as moka;
-use sea_query::SelectStatement;
 use serde::Deserialize;
 use uuid::Uuid;
 
+use crate::error::{Error, Result};
+
+use super::query::ResolvedStatement;
+
 #[derive(Debug, Clone)]
 pub struct Cursor {
 	pub version: VersionKey,
@@ -14,42 +17,98 @@ pub struct Cursor {
 
 #[derive(Debug, Clone)]
 pub struct DatabaseCursor {
-	pub statement: SelectStatement,
+	pub statement: ResolvedStatement,
 	pub offset: usize,
+	pub debug: bool,
+	pub highlights: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
-	ttl: Option<u64>,
+	/// Maximum lifetime of a cursor, in seconds.
+	#[serde(default = "default_ttl")]
+	ttl: u64,
+
+	/// Lifetime of a cursor since it was last used, in seconds.
 	tti: Option<u64>,
 }
 
+fn default_ttl() -> u64 {
+	60 * 60
+}
+
 pub struct Cache {
 	cache: moka::Cache<Uuid, Cursor>,
+	/// Minimum time a cursor is guaranteed to remain available after insertion.
+	lifetime: Duration,
 }
 
 impl Cache {
 	pub fn new(config: Config) -> Self {
-		let mut builder = moka::Cache::builder();
-		if let Some(ttl) = config.ttl {
-			builder = builder.time_to_live(Duration::from_secs(ttl));
-		}
+		let ttl = Duration::from_secs(config.ttl);
+		let mut builder = moka::Cache::builder().time_to_live(ttl);
+		let mut lifetime = ttl;
 		if let Some(tti) = config.tti {
-			builder = builder.time_to_idle(Duration::from_secs(tti));
+			let tti = Duration::from_secs(tti);
+			builder = builder.time_to_idle(tti);
+			lifetime = lifetime.min(tti);
 		}
 
 		Self {
 			cache: builder.build(),
+			lifetime,
 		}
 	}
 
-	pub fn get(&self, key: Uuid) -> Option<Cursor> {
-		self.cache.get(&key)
+	pub fn get(&self, key: Uuid) -> Result<Cursor> {
+		if let Some(cursor) = self.cache.get(&key) {
+			return Ok(cursor);
+		}
+
+		// Keys are time-ordered, recording when they were issued - if a missing
+		// cursor was issued long enough ago, it can be assumed to have expired.
+		let expired = issued_at(key)
+			.is_some_and(|issued_at| expired(issued_at, self.lifetime, SystemTime::now()));
+
+		match expired {
+			true => Err(Error::CursorExpired(key)),
+			false => Err(Error::UnknownCursor(key)),
+		}
 	}
 
-	pub fn insert(&self, cursor: Cursor) -> Uuid {
-		let key = Uuid::new_v4();
+	/// Insert a cursor, returning its key and the time until which it is
+	/// guaranteed to remain available.
+	pub fn insert(&self, cursor: Cursor) -> (Uuid, SystemTime) {
+		let key = Uuid::now_v7();
 		self.cache.insert(key, cursor);
-		key
+		let expires_at = issued_at(key).unwrap_or_else(SystemTime::now) + self.lifetime;
+		(key, expires_at)
+	}
+}
+
+fn issued_at(key: Uuid) -> Option<SystemTime> {
+	let (seconds, nanos) = key.get_timestamp()?.to_unix();
+	Some(UNIX_EPOCH + Duration::new(seconds, nanos))
+}
+
+fn expired(issued_at: SystemTime, lifetime: Duration, now: SystemTime) -> bool {
+	issued_at + lifetime <= now
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn expiry_from_key() {
+		let key = Uuid::now_v7();
+		let issued_at = issued_at(key).expect("v7 keys should have a timestamp");
+		let lifetime = Duration::from_secs(60);
+
+		assert!(!expired(issued_at, lifetime, issued_at));
+		assert!(expired(issued_at, lifetime, issued_at + lifetime));
+
+		// Keys without a timestamp cannot be considered expired.
+		assert_eq!(super::issued_at(Uuid::new_v4()), None);
 	}
 }
@@ -1,19 +1,26 @@
 use std::{
+	num::NonZeroUsize,
 	path::PathBuf,
 	sync::{
 		atomic::{AtomicBool, Ordering},
 		Arc,
 	},
+	time::Instant,
 };
 
 use anyhow::{anyhow, Context};
 use bb8::{Pool, PooledConnection};
 use bm_read::LanguageString;
-use ironworks::excel::{Excel, Sheet};
+use futures::future::try_join_all;
+use ironworks::{
+	excel::{Excel, Sheet},
+	Ironworks,
+};
 use itertools::Itertools;
-use sea_query::{Iden, Quote, SqliteQueryBuilder};
+use sea_query::{Iden, Quote, SelectStatement, SqliteQueryBuilder};
 use sea_query_rusqlite::RusqliteBinder;
-use tokio::task;
+use serde::Deserialize;
+use tokio::{sync::Semaphore, task};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
@@ -23,29 +30,81 @@ use crate::{
 };
 
 use super::{
-	connection::SqliteConnectionManager, cursor::DatabaseCursor, query::resolve_queries,
+	connection::{PragmaConfig, SqliteConnectionManager},
+	cursor::DatabaseCursor,
+	query::{
+		merge_results, read_highlights, read_matched_columns, read_result_key, resolve_queries,
+		FuzzyConfig, HighlightPatterns, ResolvedStatement, ResultKey, ResultOrder,
+	},
 	schema::table_name,
 };
 
+/// Concurrent execution of searches across multiple sheets.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ParallelConfig {
+	/// Whether searches across multiple sheets execute a statement per sheet,
+	/// merging their results, rather than a single combined statement.
+	#[serde(default)]
+	enabled: bool,
+
+	/// Maximum number of per-sheet statements executing at once against a
+	/// single version. Must be at least 1.
+	#[serde(default = "default_concurrency")]
+	concurrency: NonZeroUsize,
+}
+
+impl Default for ParallelConfig {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			concurrency: default_concurrency(),
+		}
+	}
+}
+
+fn default_concurrency() -> NonZeroUsize {
+	NonZeroUsize::new(4).unwrap()
+}
+
 pub struct Database {
 	pool: Pool<SqliteConnectionManager>,
 
+	warm: bool,
+	fuzzy: FuzzyConfig,
+	parallel: bool,
+	semaphore: Semaphore,
 	ready: AtomicBool,
 }
 
 impl Database {
-	pub fn new(path: PathBuf, excel: Arc<Excel>) -> Self {
-		let manager = SqliteConnectionManager::new(path, excel);
+	pub fn new(
+		path: PathBuf,
+		excel: Arc<Excel>,
+		ironworks: Arc<Ironworks>,
+		pragmas: PragmaConfig,
+		warm: bool,
+		fuzzy: FuzzyConfig,
+		parallel: ParallelConfig,
+	) -> Self {
+		let manager = SqliteConnectionManager::new(path, excel, ironworks, pragmas);
 
 		// TODO: should probably configure this a bit. stuff like a min idle of 1, etc. likely should be in config file
 		let pool = Pool::builder().build_unchecked(manager);
 
 		Self {
 			pool,
+			warm,
+			fuzzy,
+			parallel: parallel.enabled,
+			semaphore: Semaphore::new(parallel.concurrency.get()),
 			ready: false.into(),
 		}
 	}
 
+	pub fn ready(&self) -> bool {
+		self.ready.load(Ordering::Relaxed)
+	}
+
 	pub async fn ingest(
 		&self,
 		cancel: CancellationToken,
@@ -57,7 +116,8 @@ impl Database {
 		}
 
 		let connection = self.pool.get_owned().await?;
-		let task = task::spawn_blocking(move || Self::prepare(cancel, connection, sheets));
+		let warm = self.warm;
+		let task = task::spawn_blocking(move || Self::prepare(cancel, connection, sheets, warm));
 		task.await??;
 
 		self.ready.store(true, Ordering::Relaxed);
@@ -69,6 +129,7 @@ impl Database {
 		cancel: CancellationToken,
 		connection: PooledConnection<SqliteConnectionManager>,
 		sheets: Vec<Sheet<String>>,
+		warm: bool,
 	) -> Result<()> {
 		tracing::debug!("preparing search database");
 
@@ -80,10 +141,14 @@ impl Database {
 
 			let name = sheet.name();
 			let languages = sheet.languages()?;
+			let table_names = languages
+				.iter()
+				.map(|language| table_name(&name, *language).quoted(Quote::new(b'"')))
+				.collect::<Vec<_>>();
 			let tables = languages
 				.into_iter()
-				.map(|language| {
-					let table_name = table_name(&name, language).quoted(Quote::new(b'"'));
+				.zip(&table_names)
+				.map(|(language, table_name)| {
 					let language_string = LanguageString::from(language);
 					format!(
 						r#"CREATE VIRTUAL TABLE IF NOT EXISTS "{table_name}" USING ironworks(sheet={name}, language={language_string});"#
@@ -91,6 +156,10 @@ impl Database {
 				})
 				.join("\n");
 			connection.execute_batch(&format!("BEGIN;\n{tables}\nCOMMIT;"))?;
+
+			if warm {
+				Self::warm(&connection, &table_names)?;
+			}
 		}
 
 		tracing::debug!("search database ready");
@@ -98,13 +167,56 @@ impl Database {
 		Ok(())
 	}
 
-	pub fn build_cursor(&self, queries: Vec<(String, post::Node)>) -> Result<DatabaseCursor> {
+	// Scanning a table pulls every page of the backing sheet through excel,
+	// leaving them cached for subsequent queries.
+	fn warm(connection: &rusqlite::Connection, table_names: &[String]) -> Result<()> {
+		for table_name in table_names {
+			let start = Instant::now();
+			let rows: i64 = connection.query_row(
+				&format!(r#"SELECT COUNT(*) FROM "{table_name}""#),
+				[],
+				|row| row.get(0),
+			)?;
+			tracing::trace!(%table_name, rows, elapsed = ?start.elapsed(), "warmed table");
+		}
+
+		Ok(())
+	}
+
+	pub fn build_cursor(
+		&self,
+		queries: Vec<(String, post::Node)>,
+		sort: &post::Sort,
+		rows: Option<&[u32]>,
+		debug: bool,
+		highlights: bool,
+	) -> Result<DatabaseCursor> {
 		Ok(DatabaseCursor {
-			statement: resolve_queries(queries)?,
+			statement: resolve_queries(
+				queries,
+				sort,
+				rows,
+				self.fuzzy,
+				debug,
+				highlights,
+				self.parallel,
+			)?,
 			offset: 0,
+			debug,
+			highlights,
 		})
 	}
 
+	/// Render the statements that would be executed for a cursor, without
+	/// executing them. Values are inlined into the resulting SQL.
+	pub fn explain(&self, cursor: DatabaseCursor, limit: usize) -> Result<String> {
+		let DatabaseCursor {
+			statement, offset, ..
+		} = cursor;
+
+		explain_statement(statement, offset, limit)
+	}
+
 	pub async fn search(
 		&self,
 		cursor: DatabaseCursor,
@@ -116,36 +228,36 @@ impl Database {
 		}
 
 		let DatabaseCursor {
-			mut statement,
+			statement,
 			offset,
+			debug,
+			highlights,
 		} = cursor;
 
-		// We're requesting one more item than we want to ensure we know if we've hit EOF.
-		statement.limit(u64::try_from(limit + 1).context("invalid limit")?);
-		if offset > 0 {
-			statement.offset(u64::try_from(offset).context("invalid offset")?);
-		}
+		let (mut search_results, statement) = match statement {
+			ResolvedStatement::Single(mut statement) => {
+				paginate(&mut statement, offset, limit)?;
 
-		if tracing::enabled!(tracing::Level::TRACE) {
-			let query_string = statement.to_string(SqliteQueryBuilder);
-			tracing::trace!(%query_string, "executing query");
-		}
+				let connection = self.pool.get().await?;
+				let mut patterns = HighlightPatterns::default();
+				let search_results = query_results(&connection, &statement, |row| {
+					read_result(row, debug, highlights, &mut patterns)
+				})?;
 
-		let (query, values) = statement.build_rusqlite(SqliteQueryBuilder);
-
-		let connection = self.pool.get().await?;
-		let mut prepared_statement = connection.prepare(&query)?;
-		// TODO: not a fan of this implicit structure shared between query and here
-		let mut search_results = prepared_statement
-			.query_map(&*values.as_params(), |row| {
-				Ok(SearchResult {
-					sheet: row.get(0)?,
-					row_id: row.get(1)?,
-					subrow_id: row.get(2)?,
-					score: row.get(3)?,
-				})
-			})?
-			.collect::<Result<Vec<_>, _>>()?;
+				(search_results, ResolvedStatement::Single(statement))
+			}
+
+			ResolvedStatement::Parallel { statements, order } => {
+				let search_results = self
+					.search_parallel(&statements, order, offset, limit, debug, highlights)
+					.await?;
+
+				(
+					search_results,
+					ResolvedStatement::Parallel { statements, order },
+				)
+			}
+		};
 
 		// If we did indeed get more than the expected limit due to the +1, truncate
 		// down to the limit and prepare a cursor for the next query.
@@ -155,9 +267,276 @@ impl Database {
 			next_cursor = Some(DatabaseCursor {
 				statement,
 				offset: offset + limit,
+				debug,
+				highlights,
 			})
 		}
 
 		Ok((search_results, next_cursor))
 	}
+
+	async fn search_parallel(
+		&self,
+		statements: &[SelectStatement],
+		order: ResultOrder,
+		offset: usize,
+		limit: usize,
+		debug: bool,
+		highlights: bool,
+	) -> Result<Vec<SearchResult>> {
+		// Any of the results up to the end of the requested page may originate from
+		// a single sheet, so every statement is executed from its start.
+		let page_end = page_end(offset, limit)?;
+		let sort_index = order.field().then(|| sort_index(debug, highlights));
+
+		let pending = statements.iter().map(|statement| {
+			let mut statement = statement.clone();
+			statement.limit(page_end);
+			self.search_sheet(statement, sort_index, debug, highlights)
+		});
+		let results = try_join_all(pending).await?;
+
+		let search_results = merge_results(results, order)
+			.into_iter()
+			.skip(offset)
+			.take(limit + 1)
+			.collect();
+
+		Ok(search_results)
+	}
+
+	async fn search_sheet(
+		&self,
+		statement: SelectStatement,
+		sort_index: Option<usize>,
+		debug: bool,
+		highlights: bool,
+	) -> Result<Vec<(ResultKey, SearchResult)>> {
+		let _permit = self
+			.semaphore
+			.acquire()
+			.await
+			.context("search semaphore closed")?;
+
+		let connection = self.pool.get_owned().await?;
+		let task = task::spawn_blocking(move || {
+			let mut patterns = HighlightPatterns::default();
+			query_results(&connection, &statement, |row| {
+				Ok((
+					read_result_key(row, sort_index)?,
+					read_result(row, debug, highlights, &mut patterns)?,
+				))
+			})
+		});
+
+		task.await?
+	}
+}
+
+fn explain_statement(statement: ResolvedStatement, offset: usize, limit: usize) -> Result<String> {
+	let sql = match statement {
+		ResolvedStatement::Single(mut statement) => {
+			paginate(&mut statement, offset, limit)?;
+			statement.to_string(SqliteQueryBuilder)
+		}
+
+		ResolvedStatement::Parallel { statements, .. } => {
+			let page_end = page_end(offset, limit)?;
+			statements
+				.into_iter()
+				.map(|mut statement| statement.limit(page_end).to_string(SqliteQueryBuilder))
+				.join(";\n")
+		}
+	};
+
+	Ok(sql)
+}
+
+fn paginate(statement: &mut SelectStatement, offset: usize, limit: usize) -> Result<()> {
+	// We're requesting one more item than we want to ensure we know if we've hit EOF.
+	statement.limit(u64::try_from(limit + 1).context("invalid limit")?);
+	if offset > 0 {
+		statement.offset(u64::try_from(offset).context("invalid offset")?);
+	}
+
+	Ok(())
+}
+
+// Number of results required to fill a page, and determine if there's any
+// further pages, when reading from the start of a statement.
+fn page_end(offset: usize, limit: usize) -> Result<u64> {
+	Ok(u64::try_from(offset + limit + 1).context("invalid limit")?)
+}
+
+fn query_results<T>(
+	connection: &rusqlite::Connection,
+	statement: &SelectStatement,
+	read: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+) -> Result<Vec<T>> {
+	if tracing::enabled!(tracing::Level::TRACE) {
+		let query_string = statement.to_string(SqliteQueryBuilder);
+		tracing::trace!(%query_string, "executing query");
+	}
+
+	let (query, values) = statement.build_rusqlite(SqliteQueryBuilder);
+
+	let mut prepared_statement = connection.prepare(&query)?;
+	let results = prepared_statement
+		.query_map(&*values.as_params(), read)?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	Ok(results)
+}
+
+// TODO: not a fan of this implicit structure shared between query and here
+fn read_result(
+	row: &rusqlite::Row,
+	debug: bool,
+	highlights: bool,
+	patterns: &mut HighlightPatterns,
+) -> rusqlite::Result<SearchResult> {
+	let highlights_index = 4 + usize::from(debug);
+	Ok(SearchResult {
+		sheet: row.get(0)?,
+		row_id: row.get(1)?,
+		subrow_id: row.get(2)?,
+		score: row.get(3)?,
+		matched_columns: match debug {
+			true => Some(read_matched_columns(row, 4)?),
+			false => None,
+		},
+		highlights: match highlights {
+			true => Some(read_highlights(row, highlights_index, patterns)?),
+			false => None,
+		},
+	})
+}
+
+// The sort column is selected after every other column.
+fn sort_index(debug: bool, highlights: bool) -> usize {
+	4 + usize::from(debug) + usize::from(highlights)
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use bm_read::fixture::{column, scalar, struct_node, Cell, Fixture, TestSheet};
+	use ironworks::{excel::Language, file::exh::ColumnKind as CK};
+
+	use crate::sqlite::{fixture, vtable};
+
+	use super::*;
+
+	#[test]
+	fn explain_paginated_statements() {
+		let query = |sheet: &str| {
+			let node = post::Node::Leaf(post::Leaf {
+				field: (column(CK::String, 0), Language::English),
+				operation: post::Operation::Match("sword".into()),
+			});
+			(sheet.to_string(), node)
+		};
+		let explain = |parallel| {
+			let statement = resolve_queries(
+				vec![query("Item"), query("Action")],
+				&post::Sort::default(),
+				None,
+				FuzzyConfig::default(),
+				false,
+				false,
+				parallel,
+			)
+			.expect("query should resolve");
+			explain_statement(statement, 20, 10).expect("explain should not fail")
+		};
+
+		// A single statement is paginated by offset, with values inlined.
+		let sql = explain(false);
+		assert_eq!(sql.matches(';').count(), 0);
+		assert!(sql.contains(r#""sheet-Item@en""#) && sql.contains(r#""sheet-Action@en""#));
+		assert!(sql.contains("'%sword%'"));
+		assert!(sql.ends_with("LIMIT 11 OFFSET 20"));
+
+		// Each statement of a parallel search reads up to the end of the page.
+		let sql = explain(true);
+		let statements = sql.split(";\n").collect::<Vec<_>>();
+		assert_eq!(statements.len(), 2);
+		assert!(statements
+			.iter()
+			.all(|statement| statement.ends_with("LIMIT 31")));
+	}
+
+	#[test]
+	fn zero_concurrency_rejected() {
+		let config = serde_json::from_value::<ParallelConfig>(serde_json::json!({
+			"enabled": true,
+			"concurrency": 0,
+		}));
+		assert!(config.is_err());
+
+		let config = serde_json::from_value::<ParallelConfig>(serde_json::json!({
+			"enabled": true,
+		}))
+		.expect("config should deserialize");
+		assert_eq!(config.concurrency.get(), 4);
+	}
+
+	/// Time taken by the first query against a freshly created table over a
+	/// large sheet, optionally warming the table beforehand.
+	fn first_query(warm: bool) -> Duration {
+		let fixture = Fixture::new(vec![(
+			(0..20_000).fold(
+				TestSheet::new("Item", [(CK::String, 0)]),
+				|sheet, row_id| sheet.row(row_id, [Cell::String(format!("Iron Sword {row_id}"))]),
+			),
+			struct_node([("Name", scalar())]),
+		)]);
+
+		let connection = fixture::connection("");
+		vtable::load_module(&connection, Arc::new(fixture.excel), fixture.ironworks).unwrap();
+
+		let table_name = table_name("Item", Language::None).quoted(Quote::new(b'"'));
+		connection
+			.execute_batch(&format!(
+				r#"CREATE VIRTUAL TABLE "{table_name}" USING ironworks(sheet=Item, language=none);"#
+			))
+			.unwrap();
+
+		if warm {
+			Database::warm(&connection, &[table_name.clone()]).unwrap();
+		}
+
+		let start = Instant::now();
+		let count: i64 = connection
+			.query_row(
+				&format!(r#"SELECT COUNT(*) FROM "{table_name}" WHERE "0" LIKE '%sword 1999%'"#),
+				[],
+				|row| row.get(0),
+			)
+			.unwrap();
+		let elapsed = start.elapsed();
+
+		assert_eq!(count, 11);
+		elapsed
+	}
+
+	/// Benchmark of first-query latency with and without warming. Run with
+	/// `cargo test -p bm_search --release -- --ignored --nocapture warm`.
+	#[test]
+	#[ignore = "benchmark"]
+	fn warm_first_query() {
+		const RUNS: usize = 5;
+		let median = |warm| {
+			let mut runs = (0..RUNS).map(|_| first_query(warm)).collect::<Vec<_>>();
+			runs.sort();
+			runs[RUNS / 2]
+		};
+
+		let cold = median(false);
+		let warm = median(true);
+		println!("first query latency: cold {cold:?}, warm {warm:?}");
+
+		assert!(warm < cold, "warming should reduce first query latency");
+	}
 }
@@ -0,0 +1,45 @@
+//! In-memory databases for search tests.
+
+use std::sync::Arc;
+
+use bm_read::fixture::Fixture;
+use ironworks::excel::{self, Language};
+use itertools::Itertools;
+use sea_query::{Iden, Quote};
+
+use super::{connection::register_functions, schema::table_name, vtable};
+
+/// Open an in-memory database with search functions registered, and run the
+/// given SQL against it.
+pub fn connection(sql: &str) -> rusqlite::Connection {
+	let connection = rusqlite::Connection::open_in_memory().unwrap();
+	register_functions(&connection).expect("functions should register");
+	connection.execute_batch(sql).unwrap();
+	connection
+}
+
+/// Open an in-memory database with search functions registered, and a table
+/// over each of the given sheets of a read fixture.
+pub fn fixture_connection(fixture: &Fixture, sheets: &[&str]) -> rusqlite::Connection {
+	let connection = connection("");
+	vtable::load_module(
+		&connection,
+		Arc::new(excel::Excel::new(fixture.ironworks.clone())),
+		fixture.ironworks.clone(),
+	)
+	.expect("module should load");
+
+	// Fixture sheets are only available in the `none` language.
+	let tables = sheets
+		.iter()
+		.map(|sheet| {
+			let table_name = table_name(sheet, Language::None).quoted(Quote::new(b'"'));
+			format!(
+				r#"CREATE VIRTUAL TABLE "{table_name}" USING ironworks(sheet={sheet}, language=none);"#
+			)
+		})
+		.join("\n");
+	connection.execute_batch(&tables).unwrap();
+
+	connection
+}
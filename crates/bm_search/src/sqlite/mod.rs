@@ -1,6 +1,8 @@
 mod connection;
 mod cursor;
 mod database;
+#[cfg(test)]
+pub(crate) mod fixture;
 mod provider;
 mod query;
 mod schema;
@@ -17,17 +17,38 @@ use tracing::Instrument;
 use uuid::Uuid;
 
 use crate::{
-	error::{Error, Result},
+	error::Result,
 	internal_query::post,
-	search::SearchResult,
+	search::{SearchCursor, SearchResult},
 };
 
-use super::{cursor, database::Database};
+use super::{
+	connection::PragmaConfig,
+	cursor::{self, DatabaseCursor},
+	database::{Database, ParallelConfig},
+	query::FuzzyConfig,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
 	directory: RelativePathBuf,
 	cursor: cursor::Config,
+
+	#[serde(default)]
+	pragma: PragmaConfig,
+
+	/// Whether to scan each sheet during ingestion, such that the first search
+	/// against a sheet does not pay the cost of reading it from disk.
+	#[serde(default)]
+	warm: bool,
+
+	/// Approximate string matching used by fuzzy match operations.
+	#[serde(default)]
+	fuzzy: FuzzyConfig,
+
+	/// Concurrent execution of searches across multiple sheets.
+	#[serde(default)]
+	parallel: ParallelConfig,
 }
 
 #[derive(Debug)]
@@ -35,6 +56,10 @@ pub enum SearchRequest {
 	Query {
 		version: VersionKey,
 		queries: Vec<(String, post::Node)>,
+		sort: post::Sort,
+		rows: Option<Vec<u32>>,
+		debug: bool,
+		highlights: bool,
 	},
 	Cursor(Uuid),
 }
@@ -43,6 +68,10 @@ pub struct Provider {
 	data: Arc<Data>,
 
 	directory: PathBuf,
+	pragmas: PragmaConfig,
+	warm: bool,
+	fuzzy: FuzzyConfig,
+	parallel: ParallelConfig,
 
 	databases: RwLock<HashMap<VersionKey, Arc<Database>>>,
 	cursors: cursor::Cache,
@@ -56,11 +85,23 @@ impl Provider {
 		Ok(Self {
 			data,
 			directory,
+			pragmas: config.pragma,
+			warm: config.warm,
+			fuzzy: config.fuzzy,
+			parallel: config.parallel,
 			databases: Default::default(),
 			cursors: cursor::Cache::new(config.cursor),
 		})
 	}
 
+	pub fn ready(&self, version: VersionKey) -> bool {
+		self.databases
+			.read()
+			.expect("poisoned")
+			.get(&version)
+			.map_or(false, |database| database.ready())
+	}
+
 	pub async fn ingest(
 		self: Arc<Self>,
 		cancel: CancellationToken,
@@ -101,32 +142,55 @@ impl Provider {
 		&self,
 		request: SearchRequest,
 		limit: usize,
-	) -> Result<(Vec<SearchResult>, Option<Uuid>)> {
-		let (version, database, cursor) = match request {
-			SearchRequest::Query { version, queries } => {
+	) -> Result<(Vec<SearchResult>, Option<SearchCursor>)> {
+		let (version, database, cursor) = self.resolve(request)?;
+
+		let (results, next_cursor) = database.search(cursor, limit).await?;
+
+		let cursor = next_cursor.map(|inner| {
+			let (id, expires_at) = self.cursors.insert(cursor::Cursor { version, inner });
+			SearchCursor { id, expires_at }
+		});
+
+		Ok((results, cursor))
+	}
+
+	/// Build the SQL that would be executed for a search request, without
+	/// executing it.
+	pub fn explain(&self, request: SearchRequest, limit: usize) -> Result<String> {
+		let (_version, database, cursor) = self.resolve(request)?;
+		database.explain(cursor, limit)
+	}
+
+	fn resolve(
+		&self,
+		request: SearchRequest,
+	) -> Result<(VersionKey, Arc<Database>, DatabaseCursor)> {
+		let resolved = match request {
+			SearchRequest::Query {
+				version,
+				queries,
+				sort,
+				rows,
+				debug,
+				highlights,
+			} => {
 				let database = self.database(version)?;
-				let cursor = database.build_cursor(queries)?;
+				let cursor =
+					database.build_cursor(queries, &sort, rows.as_deref(), debug, highlights)?;
 
 				(version, database, cursor)
 			}
 
 			SearchRequest::Cursor(uuid) => {
-				let cursor = self
-					.cursors
-					.get(uuid)
-					.ok_or_else(|| Error::UnknownCursor(uuid))?;
+				let cursor = self.cursors.get(uuid)?;
 
 				let database = self.database(cursor.version)?;
 				(cursor.version, database, cursor.inner)
 			}
 		};
 
-		let (results, next_cursor) = database.search(cursor, limit).await?;
-
-		let cursor_key =
-			next_cursor.map(|inner| self.cursors.insert(cursor::Cursor { version, inner }));
-
-		Ok((results, cursor_key))
+		Ok(resolved)
 	}
 
 	fn database(&self, version: VersionKey) -> Result<Arc<Database>> {
@@ -135,9 +199,16 @@ impl Provider {
 			Entry::Occupied(entry) => entry.into_mut(),
 			Entry::Vacant(entry) => {
 				// TODO: log?
-				let excel = self.data.version(version)?.excel();
-				let database =
-					Database::new(self.directory.join(format!("version-{version}")), excel);
+				let data_version = self.data.version(version)?;
+				let database = Database::new(
+					self.directory.join(format!("version-{version}")),
+					data_version.excel(),
+					data_version.ironworks(),
+					self.pragmas.clone(),
+					self.warm,
+					self.fuzzy,
+					self.parallel,
+				);
 				entry.insert(Arc::new(database))
 			}
 		};
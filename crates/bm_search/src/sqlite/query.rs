@@ -1,30 +1,236 @@
-use std::{collections::HashSet, sync::OnceLock};
+use std::{
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
+	sync::OnceLock,
+};
 
 use aho_corasick::AhoCorasick;
 use bm_read::LanguageString;
-use ironworks::excel::Language;
+use ironworks::{excel::Language, file::exh};
+use rusqlite::types::{Type, Value as SqlValue};
 use sea_query::{
-	Alias, ColumnRef, Condition, DynIden, Expr, Func, Iden, IntoColumnRef, IntoCondition, LikeExpr,
-	Order, Query, SelectStatement, SimpleExpr, TableRef, UnionType,
+	Alias, Asterisk, BinOper, ColumnRef, Condition, DynIden, Expr, Func, Iden, IntoColumnRef,
+	IntoCondition, LikeExpr, Order, Query, SelectStatement, SimpleExpr, TableRef, UnionType,
 };
+use serde::Deserialize;
 
 use crate::{
 	error::{Error, Result},
 	internal_query::post,
+	search::{MatchHighlight, MatchedColumn},
 };
 
 use super::schema::{column_name, table_name, KnownColumn};
 
 #[derive(Iden)]
 enum KnownResolveColumn {
+	Sheet,
 	Score,
+	Sort,
 }
 
-pub fn resolve_queries(queries: Vec<(String, post::Node)>) -> Result<SelectStatement> {
-	let mut selects = queries
-		.into_iter()
-		.map(|(sheet_name, node)| resolve_query(sheet_name, node));
+const BASE_ALIAS: &str = "alias-base";
+
+/// Configuration for approximate string matching.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct FuzzyConfig {
+	/// Maximum edit distance between a fuzzy match string and a column value for
+	/// the value to be considered a match.
+	#[serde(default = "default_max_distance")]
+	max_distance: u32,
+}
+
+impl Default for FuzzyConfig {
+	fn default() -> Self {
+		Self {
+			max_distance: default_max_distance(),
+		}
+	}
+}
+
+fn default_max_distance() -> u32 {
+	2
+}
+
+/// Statements resolved from a search query.
+#[derive(Debug, Clone)]
+pub enum ResolvedStatement {
+	/// A single statement retrieving ordered results for every searched sheet.
+	Single(SelectStatement),
+	/// A statement per searched sheet, each ordered independently. Results of
+	/// each statement must be merged in the specified order.
+	Parallel {
+		statements: Vec<SelectStatement>,
+		order: ResultOrder,
+	},
+}
+
+/// Ordering of resolved results, for merging results of separately executed
+/// statements. Mirrors the ordering applied to combined statements.
+#[derive(Debug, Clone, Copy)]
+pub struct ResultOrder {
+	field: bool,
+	direction: post::SortDirection,
+}
 
+impl ResultOrder {
+	fn new(sort: &post::Sort) -> Self {
+		Self {
+			field: matches!(sort.key, post::SortKey::Field(_)),
+			direction: sort.direction,
+		}
+	}
+
+	/// Whether results are ordered by a sort column.
+	pub fn field(&self) -> bool {
+		self.field
+	}
+
+	pub fn compare(&self, a: &ResultKey, b: &ResultKey) -> Ordering {
+		let directed = |ordering: Ordering| match self.direction {
+			post::SortDirection::Ascending => ordering,
+			post::SortDirection::Descending => ordering.reverse(),
+		};
+
+		let ordering = match self.field {
+			false => directed(a.score.total_cmp(&b.score)),
+			true => directed(compare_sql_values(&a.sort, &b.sort))
+				.then_with(|| b.score.total_cmp(&a.score)),
+		};
+
+		ordering
+			.then_with(|| a.row_id.cmp(&b.row_id))
+			.then_with(|| a.subrow_id.cmp(&b.subrow_id))
+			.then_with(|| a.sheet.cmp(&b.sheet))
+	}
+}
+
+/// Values of a result row that it is ordered by.
+#[derive(Debug)]
+pub struct ResultKey {
+	sort: SqlValue,
+	score: f64,
+	row_id: u32,
+	subrow_id: u16,
+	sheet: String,
+}
+
+/// Read the ordering key of a result from the given row. `sort_index` must be
+/// provided if results are ordered by a sort column.
+pub fn read_result_key(
+	row: &rusqlite::Row,
+	sort_index: Option<usize>,
+) -> rusqlite::Result<ResultKey> {
+	Ok(ResultKey {
+		sort: match sort_index {
+			Some(index) => row.get(index)?,
+			None => SqlValue::Null,
+		},
+		score: row.get(3)?,
+		row_id: row.get(1)?,
+		subrow_id: row.get(2)?,
+		sheet: row.get(0)?,
+	})
+}
+
+/// Merge the results of each statement of a parallel resolution into a single
+/// list, in the given order.
+pub fn merge_results<T>(results: Vec<Vec<(ResultKey, T)>>, order: ResultOrder) -> Vec<T> {
+	let mut merged = results.into_iter().flatten().collect::<Vec<_>>();
+	// Sorting is stable - complete ties retain the order of the statements.
+	merged.sort_by(|(a, _), (b, _)| order.compare(a, b));
+	merged.into_iter().map(|(_key, result)| result).collect()
+}
+
+// Compare values the way SQLite orders them - nulls, then numbers, then text,
+// then blobs. Text is compared bytewise, per the default collation.
+fn compare_sql_values(a: &SqlValue, b: &SqlValue) -> Ordering {
+	fn class(value: &SqlValue) -> u8 {
+		match value {
+			SqlValue::Null => 0,
+			SqlValue::Integer(_) | SqlValue::Real(_) => 1,
+			SqlValue::Text(_) => 2,
+			SqlValue::Blob(_) => 3,
+		}
+	}
+
+	match (a, b) {
+		(SqlValue::Integer(a), SqlValue::Integer(b)) => a.cmp(b),
+		(SqlValue::Integer(a), SqlValue::Real(b)) => (*a as f64).total_cmp(b),
+		(SqlValue::Real(a), SqlValue::Integer(b)) => a.total_cmp(&(*b as f64)),
+		(SqlValue::Real(a), SqlValue::Real(b)) => a.total_cmp(b),
+		(SqlValue::Text(a), SqlValue::Text(b)) => a.as_bytes().cmp(b.as_bytes()),
+		(SqlValue::Blob(a), SqlValue::Blob(b)) => a.cmp(b),
+		(a, b) => class(a).cmp(&class(b)),
+	}
+}
+
+/// Resolve normalized queries into statements. If `parallel` is set, and more
+/// than one sheet is searched, a statement is resolved for each sheet to be
+/// executed independently.
+pub fn resolve_queries(
+	queries: Vec<(String, post::Node)>,
+	sort: &post::Sort,
+	rows: Option<&[u32]>,
+	fuzzy: FuzzyConfig,
+	debug: bool,
+	highlights: bool,
+	parallel: bool,
+) -> Result<ResolvedStatement> {
+	// Single-field equality lookups against one sheet are the most common search
+	// by far - skip the general machinery for them where possible. The fast path
+	// scores every match equally, so is only valid for the default sort.
+	let default_sort = matches!(
+		sort,
+		post::Sort {
+			key: post::SortKey::Score,
+			direction: post::SortDirection::Descending,
+		}
+	);
+	if !debug && !highlights && default_sort && rows.is_none() {
+		if let Some(query) = resolve_equality_fast_path(&queries) {
+			return Ok(ResolvedStatement::Single(query));
+		}
+	}
+
+	let parallel = parallel && queries.len() > 1;
+
+	let selects = queries.into_iter().map(|(sheet_name, node)| {
+		let sort_field = match &sort.key {
+			post::SortKey::Score => None,
+			post::SortKey::Field(fields) => Some(fields.get(&sheet_name)),
+		};
+		resolve_query(sheet_name, node, sort_field, rows, fuzzy, debug, highlights)
+	});
+
+	if parallel {
+		let statements = selects
+			.map(|select| Ok(ordered_subquery(select?, sort)))
+			.collect::<Result<Vec<_>>>()?;
+		return Ok(ResolvedStatement::Parallel {
+			statements,
+			order: ResultOrder::new(sort),
+		});
+	}
+
+	union_ordered(selects, sort).map(ResolvedStatement::Single)
+}
+
+fn ordered_subquery(select: SelectStatement, sort: &post::Sort) -> SelectStatement {
+	// Result columns are ordered by name, which is ambiguous in a select joining
+	// multiple tables - wrap the select so only its results are in scope.
+	let mut query = Query::select();
+	query
+		.column(Asterisk)
+		.from_subquery(select, Alias::new("results"));
+	order_results(&mut query, sort);
+	query
+}
+
+fn union_ordered(
+	mut selects: impl Iterator<Item = Result<SelectStatement>>,
+	sort: &post::Sort,
+) -> Result<SelectStatement> {
 	let mut query = selects
 		.next()
 		.ok_or_else(|| Error::MalformedQuery("no queries could be resolved".to_string()))??;
@@ -32,38 +238,182 @@ pub fn resolve_queries(queries: Vec<(String, post::Node)>) -> Result<SelectState
 		query.union(UnionType::All, select?);
 	}
 
-	query.order_by(KnownResolveColumn::Score, Order::Desc);
+	order_results(&mut query, sort);
 
 	Ok(query.take())
 }
 
-fn resolve_query(sheet_name: String, node: post::Node) -> Result<SelectStatement> {
-	let alias = "alias-base";
+fn order_results(query: &mut SelectStatement, sort: &post::Sort) {
+	let order = match sort.direction {
+		post::SortDirection::Ascending => Order::Asc,
+		post::SortDirection::Descending => Order::Desc,
+	};
 
-	let ResolveResult {
-		condition,
-		score,
-		languages,
-		relations,
-	} = resolve_node(
+	// Ties are broken by relevance, and then by row, subrow, and sheet for a
+	// stable ordering across pages.
+	match sort.key {
+		post::SortKey::Score => {
+			query.order_by(KnownResolveColumn::Score, order);
+		}
+		post::SortKey::Field(_) => {
+			query.order_by(KnownResolveColumn::Sort, order);
+			query.order_by(KnownResolveColumn::Score, Order::Desc);
+		}
+	}
+	query.order_by(KnownColumn::RowId, Order::Asc);
+	query.order_by(KnownColumn::SubrowId, Order::Asc);
+	query.order_by(KnownResolveColumn::Sheet, Order::Asc);
+}
+
+fn resolve_equality_fast_path(queries: &[(String, post::Node)]) -> Option<SelectStatement> {
+	let [(
+		sheet_name,
+		post::Node::Leaf(post::Leaf {
+			field: (column, language),
+			operation: post::Operation::Eq(value),
+		}),
+	)] = queries
+	else {
+		return None;
+	};
+
+	Some(equality_select(
+		sheet_name,
+		*language,
+		column_name(column),
+		value.clone(),
+	))
+}
+
+/// Build a minimal select for rows of a sheet with a column equal to a value.
+/// Every match is scored equally, so results are ordered by row alone.
+fn equality_select(
+	sheet_name: &str,
+	language: Language,
+	column: Alias,
+	value: post::Value,
+) -> SelectStatement {
+	let alias = table_alias(BASE_ALIAS, language);
+
+	Query::select()
+		.from(TableRef::TableAlias(
+			DynIden::new(table_name(sheet_name, language)),
+			DynIden::new(alias.clone()),
+		))
+		.expr_as(Expr::val(sheet_name), KnownResolveColumn::Sheet)
+		.column((alias.clone(), KnownColumn::RowId))
+		.column((alias.clone(), KnownColumn::SubrowId))
+		.expr_as(
+			Expr::value(1).cast_as(Alias::new("REAL")),
+			KnownResolveColumn::Score,
+		)
+		.cond_where(Expr::col((alias.clone(), column)).eq(value))
+		.order_by((alias.clone(), KnownColumn::RowId), Order::Asc)
+		.order_by((alias, KnownColumn::SubrowId), Order::Asc)
+		.take()
+}
+
+fn resolve_query(
+	sheet_name: String,
+	node: post::Node,
+	sort_field: Option<Option<&post::LeafField>>,
+	rows: Option<&[u32]>,
+	fuzzy: FuzzyConfig,
+	debug: bool,
+	highlights: bool,
+) -> Result<SelectStatement> {
+	let result = resolve_node(
 		node,
 		&ResolveContext {
-			alias,
+			alias: BASE_ALIAS,
 			next_alias: "alias-0",
+			fuzzy,
 		},
 	)?;
 
+	build_select(&sheet_name, result, sort_field, rows, debug, highlights)
+}
+
+/// Build the select for a single sheet. If `sort_field` is provided, a sort
+/// column is selected from the given field, or as NULL if the field could not be
+/// resolved for this sheet. If `rows` is provided, results are constrained to
+/// those row IDs of the base sheet.
+fn build_select(
+	sheet_name: &str,
+	result: ResolveResult,
+	sort_field: Option<Option<&post::LeafField>>,
+	rows: Option<&[u32]>,
+	debug: bool,
+	highlights: bool,
+) -> Result<SelectStatement> {
+	let ResolveResult {
+		condition,
+		score,
+		mut languages,
+		relations,
+		matches,
+		highlights: highlight_matches,
+	} = result;
+
+	// The sort field may be in a language not otherwise referenced by the query.
+	if let Some(Some((_column, language))) = sort_field {
+		languages.insert(*language);
+	}
+
 	let mut query = Query::select();
+	let base_alias = join_tables(&mut query, sheet_name, BASE_ALIAS, languages, relations)?;
+
+	// Select fields.
+	query.expr_as(Expr::val(sheet_name), KnownResolveColumn::Sheet);
+	query.column((base_alias.clone(), KnownColumn::RowId));
+	query.column((base_alias.clone(), KnownColumn::SubrowId));
+	query.expr_as(score.cast_as(Alias::new("REAL")), KnownResolveColumn::Score);
+	if debug {
+		query.expr(matched_columns_expression(matches));
+	}
+	if highlights {
+		query.expr(highlights_expression(highlight_matches));
+	}
+	// NOTE: The sort column is selected last, as results are read by index.
+	if let Some(sort_field) = sort_field {
+		let expression = match sort_field {
+			Some((column, language)) => {
+				Expr::col((table_alias(BASE_ALIAS, *language), column_name(column))).into()
+			}
+			None => Expr::cust("NULL"),
+		};
+		query.expr_as(expression, KnownResolveColumn::Sort);
+	}
 
+	query.cond_where(condition);
+
+	// Row constraints only apply to the base sheet - relations are joined
+	// against it, and are free to target any row.
+	if let Some(rows) = rows {
+		query.and_where(Expr::col((base_alias, KnownColumn::RowId)).is_in(rows.iter().copied()));
+	}
+
+	Ok(query.take())
+}
+
+/// Add the tables of a sheet in each language, and those of its relations, to
+/// a select. Returns the alias of the table the others are joined to.
+fn join_tables(
+	query: &mut SelectStatement,
+	sheet_name: &str,
+	alias: &str,
+	languages: HashSet<Language>,
+	relations: Vec<ResolveRelation>,
+) -> Result<Alias> {
 	// Base sheet and language joins.
-	let mut table_references = iter_language_references(languages, alias, &sheet_name);
+	let mut table_references = iter_language_references(languages, alias, sheet_name);
 
 	let (base_alias, base_reference) = table_references
 		.next()
-		.ok_or_else(|| Error::MalformedQuery("target sheet not referenced".to_string()))?;
+		.ok_or_else(|| Error::MalformedQuery(format!("sheet {sheet_name} not referenced")))?;
 	query.from(base_reference);
 
-	inner_join_references(&mut query, table_references, &base_alias);
+	inner_join_references(query, table_references, &base_alias);
 
 	// Relations.
 	for relation in relations {
@@ -85,18 +435,10 @@ fn resolve_query(sheet_name: String, node: post::Node) -> Result<SelectStatement
 		query.left_join(base_reference, condition);
 
 		// Remaining languages can be joined on the row ID.
-		inner_join_references(&mut query, relation_references, &base_alias);
+		inner_join_references(query, relation_references, &base_alias);
 	}
 
-	// Select fields.
-	query.expr(Expr::val(&sheet_name));
-	query.column((base_alias.clone(), KnownColumn::RowId));
-	query.column((base_alias, KnownColumn::SubrowId));
-	query.expr_as(score.cast_as(Alias::new("REAL")), KnownResolveColumn::Score);
-
-	query.cond_where(condition);
-
-	Ok(query.take())
+	Ok(base_alias)
 }
 
 fn iter_language_references<'a>(
@@ -131,6 +473,7 @@ fn inner_join_references(
 struct ResolveContext<'a> {
 	alias: &'a str,
 	next_alias: &'a str,
+	fuzzy: FuzzyConfig,
 }
 
 #[derive(Debug)]
@@ -143,6 +486,12 @@ struct ResolveResult {
 	languages: HashSet<Language>,
 	/// Relationships required by this result tree.
 	relations: Vec<ResolveRelation>,
+	/// Conditions for each leaf targeting the current sheet, alongside a label
+	/// for the column it targets.
+	matches: Vec<(Condition, String)>,
+	/// Conditions for each string match leaf targeting the current sheet,
+	/// alongside an expression describing the match for highlighting.
+	highlights: Vec<(Condition, SimpleExpr)>,
 }
 
 #[derive(Debug)]
@@ -172,6 +521,8 @@ fn resolve_group(group: post::Group, context: &ResolveContext) -> Result<Resolve
 	let mut must_not = Condition::any().not();
 	let mut score_expressions = vec![];
 	let mut relations = vec![];
+	let mut matches = vec![];
+	let mut highlights = vec![];
 
 	let mut languages = HashSet::new();
 
@@ -181,11 +532,14 @@ fn resolve_group(group: post::Group, context: &ResolveContext) -> Result<Resolve
 			score: inner_score,
 			languages: inner_languages,
 			relations: inner_relations,
+			matches: inner_matches,
+			highlights: inner_highlights,
 		} = resolve_node(
 			node,
 			&ResolveContext {
 				alias: context.alias,
 				next_alias: &format!("{}-{}", context.next_alias, index),
+				fuzzy: context.fuzzy,
 			},
 		)?;
 
@@ -206,6 +560,8 @@ fn resolve_group(group: post::Group, context: &ResolveContext) -> Result<Resolve
 
 		languages.extend(inner_languages);
 		relations.extend(inner_relations);
+		matches.extend(inner_matches);
+		highlights.extend(inner_highlights);
 	}
 
 	// Add all the score expressions together.
@@ -233,10 +589,28 @@ fn resolve_group(group: post::Group, context: &ResolveContext) -> Result<Resolve
 		score,
 		languages,
 		relations,
+		matches,
+		highlights,
 	})
 }
 
 fn resolve_leaf(leaf: post::Leaf, context: &ResolveContext) -> Result<ResolveResult> {
+	// Reverse relations target a column of the related sheet, rather than the
+	// current one.
+	let leaf = match leaf {
+		post::Leaf {
+			field,
+			operation: post::Operation::Relation(relation),
+		} if matches!(
+			relation.target.direction,
+			post::RelationDirection::Reverse(_)
+		) =>
+		{
+			return resolve_reverse_relation(field, relation, context)
+		}
+		leaf => leaf,
+	};
+
 	let mut relations = vec![];
 
 	let (column_definition, language) = leaf.field;
@@ -248,6 +622,10 @@ fn resolve_leaf(leaf: post::Leaf, context: &ResolveContext) -> Result<ResolveRes
 	let expression = Expr::col(column_ref.clone());
 	let mut outer_languages = HashSet::from([language]);
 
+	let highlight = highlight_mode(&leaf.operation).map(|(mode, pattern)| {
+		highlight_expression(&column_definition, language, mode, pattern, &column_ref)
+	});
+
 	let (resolved_expression, score) = match leaf.operation {
 		// TODO: break this into seperate function?
 		post::Operation::Relation(post::Relation { target, query }) => {
@@ -258,11 +636,15 @@ fn resolve_leaf(leaf: post::Leaf, context: &ResolveContext) -> Result<ResolveRes
 				score,
 				languages: inner_languages,
 				relations: mut inner_relations,
+				// Matches within the relation target columns of another sheet.
+				matches: _,
+				highlights: _,
 			} = resolve_node(
 				*query,
 				&ResolveContext {
 					alias: &target_alias,
 					next_alias: &format!("{}-0", target_alias),
+					fuzzy: context.fuzzy,
 				},
 			)?;
 
@@ -275,6 +657,8 @@ fn resolve_leaf(leaf: post::Leaf, context: &ResolveContext) -> Result<ResolveRes
 						score: _,
 						languages: condition_languages,
 						relations: condition_relations,
+						matches: _,
+						highlights: _,
 					} = resolve_node(*condition, context)?;
 
 					// NOTE: We need to merge the languages in with the outer set -
@@ -298,19 +682,80 @@ fn resolve_leaf(leaf: post::Leaf, context: &ResolveContext) -> Result<ResolveRes
 			(inner_condition, score)
 		}
 
-		// TODO: this is case insensitive due to LIKE semantics - if opting into case sensitive (is this something we want), will need to use GLOB or something with pragmas/collates, idk
+		// NOTE: This is case insensitive due to LIKE semantics - see MatchCase for case sensitive matching.
 		post::Operation::Match(string) => (
-			expression.like(build_like(&string)).into_condition(),
-			Expr::value(u32::try_from(string.len()).map_err(|error| {
-				Error::MalformedQuery(format!("excessively large string expression: {error}"))
-			})?)
-			.div(
-				SimpleExpr::from(Func::char_length(Expr::col(column_ref)))
-					.cast_as(Alias::new("REAL")),
-			),
+			expression
+				.like(build_like(&string, Anchor::None))
+				.into_condition(),
+			match_score(&string, column_ref)?,
+		),
+
+		// Anchored matches are a stronger signal of relevance than a match anywhere
+		// within the value, and are weighted accordingly.
+		post::Operation::StartsWith(string) => (
+			expression
+				.like(build_like(&string, Anchor::Start))
+				.into_condition(),
+			anchored_match_score(&string, column_ref)?,
+		),
+		post::Operation::EndsWith(string) => (
+			expression
+				.like(build_like(&string, Anchor::End))
+				.into_condition(),
+			anchored_match_score(&string, column_ref)?,
+		),
+
+		// REGEXP is backed by a function registered on each connection - see
+		// `connection::register_functions`. It cannot be used as an index
+		// constraint, and requires a full scan of the table.
+		post::Operation::Regex(pattern) => (
+			expression
+				.binary(BinOper::Custom("REGEXP"), pattern)
+				.into_condition(),
+			Expr::value(1),
+		),
+
+		// Fuzzy matching is backed by a function registered on each connection,
+		// and as with REGEXP, requires a full scan of the table. Closer matches
+		// score higher, with an exact match scoring 1. The distance is capped at the
+		// configured maximum, allowing values of very different length to be
+		// rejected early. SQLite's LOWER only folds ASCII, so the match string is
+		// folded to match.
+		post::Operation::Fuzzy(string) => {
+			let distance = SimpleExpr::from(
+				Func::cust(Alias::new("levenshtein"))
+					.arg(Func::lower(Expr::col(column_ref)))
+					.arg(string.to_ascii_lowercase())
+					.arg(context.fuzzy.max_distance),
+			);
+			(
+				distance
+					.clone()
+					.lte(context.fuzzy.max_distance)
+					.into_condition(),
+				Expr::value(1.).div(distance.add(1)),
+			)
+		}
+
+		// GLOB is case sensitive, unlike LIKE.
+		post::Operation::MatchCase(string) => (
+			expression
+				.binary(BinOper::Custom("GLOB"), build_glob(&string))
+				.into_condition(),
+			match_score(&string, column_ref)?,
+		),
+
+		// Exclusions carry no meaningful relevance - score them as a flat match.
+		post::Operation::NotMatch(string) => (
+			expression
+				.not_like(build_like(&string, Anchor::None))
+				.into_condition(),
+			Expr::value(1),
 		),
 
 		post::Operation::Eq(value) => (expression.eq(value).into_condition(), Expr::value(1)),
+		post::Operation::Ne(value) => (expression.ne(value).into_condition(), Expr::value(1)),
+		post::Operation::In(values) => (expression.is_in(values).into_condition(), Expr::value(1)),
 
 		post::Operation::Gt(number) => (expression.gt(number).into_condition(), Expr::value(1)),
 		post::Operation::Gte(number) => (expression.gte(number).into_condition(), Expr::value(1)),
@@ -318,15 +763,411 @@ fn resolve_leaf(leaf: post::Leaf, context: &ResolveContext) -> Result<ResolveRes
 		post::Operation::Lte(number) => (expression.lte(number).into_condition(), Expr::value(1)),
 	};
 
+	let condition = resolved_expression.into_condition();
+
 	Ok(ResolveResult {
-		condition: resolved_expression.into_condition(),
+		matches: vec![(condition.clone(), match_label(&column_definition))],
+		highlights: highlight
+			.map(|highlight| (condition.clone(), highlight))
+			.into_iter()
+			.collect(),
+		condition,
 		score,
 		languages: HashSet::from([language]),
 		relations,
 	})
 }
 
-fn build_like(string: &str) -> LikeExpr {
+/// Resolve a relation from rows of another sheet that reference the current
+/// sheet. Referencing rows are tested for existence with a correlated subquery -
+/// joining them would duplicate the current row for each referencing row.
+/// Matches are scored flatly, regardless of the number of referencing rows.
+fn resolve_reverse_relation(
+	field: post::LeafField,
+	relation: post::Relation,
+	context: &ResolveContext,
+) -> Result<ResolveResult> {
+	let post::Relation { target, query } = relation;
+	let post::RelationDirection::Reverse(source_language) = target.direction else {
+		return Err(Error::MalformedQuery(format!(
+			"relation to {} is not a reverse relation",
+			target.sheet
+		)));
+	};
+
+	let target_alias = context.next_alias.to_string();
+
+	let ResolveResult {
+		condition: inner_condition,
+		languages: mut target_languages,
+		relations: mut target_relations,
+		..
+	} = resolve_node(
+		*query,
+		&ResolveContext {
+			alias: &target_alias,
+			next_alias: &format!("{target_alias}-0"),
+			fuzzy: context.fuzzy,
+		},
+	)?;
+
+	let mut condition = Condition::all().add(inner_condition);
+
+	// Conditions of the reference are on the referencing sheet, which is the
+	// target of a reverse relation.
+	if let Some(target_condition) = target.condition {
+		let ResolveResult {
+			condition: condition_condition,
+			languages: condition_languages,
+			relations: condition_relations,
+			..
+		} = resolve_node(
+			*target_condition,
+			&ResolveContext {
+				alias: &target_alias,
+				next_alias: &format!("{target_alias}-1"),
+				fuzzy: context.fuzzy,
+			},
+		)?;
+
+		condition = condition.add(condition_condition);
+		target_languages.extend(condition_languages);
+		target_relations.extend(condition_relations);
+	}
+
+	let (column, language) = field;
+	target_languages.insert(language);
+	let foreign_key = (table_alias(&target_alias, language), column_name(&column));
+	let source_row_id = (
+		table_alias(context.alias, source_language),
+		KnownColumn::RowId,
+	);
+
+	let mut select = Query::select();
+	join_tables(
+		&mut select,
+		&target.sheet,
+		&target_alias,
+		target_languages,
+		target_relations,
+	)?;
+	select
+		.expr(Expr::value(1))
+		.cond_where(condition)
+		.and_where(Expr::col(foreign_key).equals(source_row_id));
+
+	Ok(ResolveResult {
+		condition: Expr::exists(select.take()).into_condition(),
+		score: Expr::value(1),
+		languages: HashSet::from([source_language]),
+		relations: vec![],
+		matches: vec![],
+		highlights: vec![],
+	})
+}
+
+/// Multiplier applied to the score of string matches anchored to the start or
+/// end of a value.
+const ANCHORED_MATCH_WEIGHT: f64 = 2.;
+
+/// Upper bound of anchored match scores, keeping them below an exact match.
+const ANCHORED_MATCH_MAX_SCORE: f64 = 0.99;
+
+// Score string matches by the proportion of the column's value that was matched.
+fn match_score(string: &str, column_ref: ColumnRef) -> Result<SimpleExpr> {
+	let length = u32::try_from(string.len()).map_err(|error| {
+		Error::MalformedQuery(format!("excessively large string expression: {error}"))
+	})?;
+
+	Ok(Expr::value(length).div(
+		SimpleExpr::from(Func::char_length(Expr::col(column_ref))).cast_as(Alias::new("REAL")),
+	))
+}
+
+// Score anchored string matches above an equivalent unanchored match, without
+// reaching the score of an exact match.
+fn anchored_match_score(string: &str, column_ref: ColumnRef) -> Result<SimpleExpr> {
+	Ok(Func::cust(Alias::new("min"))
+		.arg(match_score(string, column_ref)?.mul(ANCHORED_MATCH_WEIGHT))
+		.arg(ANCHORED_MATCH_MAX_SCORE)
+		.into())
+}
+
+fn match_label(column: &exh::ColumnDefinition) -> String {
+	format!("{}:{:?}", column.offset(), column.kind())
+}
+
+// Builds a JSON array containing the label of each match whose condition holds
+// for the row, and null otherwise.
+fn matched_columns_expression(matches: Vec<(Condition, String)>) -> SimpleExpr {
+	Func::cust(Alias::new("json_array"))
+		.args(
+			matches
+				.into_iter()
+				.map(|(condition, label)| SimpleExpr::from(Expr::case(condition, label))),
+		)
+		.into()
+}
+
+/// Read the matched columns selected by a debug query from the given row.
+pub fn read_matched_columns(
+	row: &rusqlite::Row,
+	index: usize,
+) -> rusqlite::Result<Vec<MatchedColumn>> {
+	let json = row.get::<_, String>(index)?;
+	parse_matched_columns(&json)
+		.map_err(|error| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, error.into()))
+}
+
+fn parse_matched_columns(json: &str) -> anyhow::Result<Vec<MatchedColumn>> {
+	let labels = serde_json::from_str::<Vec<Option<String>>>(json)?;
+
+	let mut columns = labels
+		.into_iter()
+		.flatten()
+		.map(|label| {
+			let (offset, kind) = label
+				.split_once(':')
+				.ok_or_else(|| anyhow::anyhow!("malformed match label {label}"))?;
+			Ok(MatchedColumn {
+				offset: offset.parse()?,
+				kind: kind.to_string(),
+			})
+		})
+		.collect::<anyhow::Result<Vec<_>>>()?;
+
+	// The same column may be targeted by multiple clauses.
+	columns.sort();
+	columns.dedup();
+
+	Ok(columns)
+}
+
+/// Number of characters either side of a match included in highlight snippets.
+const SNIPPET_CONTEXT: usize = 32;
+
+/// Strategy used to locate a match within a column value when building
+/// highlight snippets, mirroring the semantics of the SQL operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HighlightMode {
+	/// ASCII case insensitive substring, as with LIKE.
+	Insensitive,
+	/// As `Insensitive`, but preferring the last occurrence.
+	InsensitiveEnd,
+	/// Case sensitive substring, as with GLOB.
+	Sensitive,
+	/// Regular expression.
+	Regex,
+}
+
+impl HighlightMode {
+	fn as_str(self) -> &'static str {
+		match self {
+			Self::Insensitive => "i",
+			Self::InsensitiveEnd => "i$",
+			Self::Sensitive => "c",
+			Self::Regex => "r",
+		}
+	}
+
+	fn parse(string: &str) -> Option<Self> {
+		let mode = match string {
+			"i" => Self::Insensitive,
+			"i$" => Self::InsensitiveEnd,
+			"c" => Self::Sensitive,
+			"r" => Self::Regex,
+			_ => return None,
+		};
+		Some(mode)
+	}
+}
+
+// Only positive string matches have a meaningful region of the value to
+// highlight.
+fn highlight_mode(operation: &post::Operation) -> Option<(HighlightMode, &str)> {
+	use post::Operation as O;
+	match operation {
+		O::Match(string) | O::StartsWith(string) => Some((HighlightMode::Insensitive, string)),
+		O::EndsWith(string) => Some((HighlightMode::InsensitiveEnd, string)),
+		O::MatchCase(string) => Some((HighlightMode::Sensitive, string)),
+		O::Regex(pattern) => Some((HighlightMode::Regex, pattern)),
+		_ => None,
+	}
+}
+
+// Builds a JSON array of the details required to produce a highlight snippet
+// for a match against the given column.
+fn highlight_expression(
+	column: &exh::ColumnDefinition,
+	language: Language,
+	mode: HighlightMode,
+	pattern: &str,
+	column_ref: &ColumnRef,
+) -> SimpleExpr {
+	Func::cust(Alias::new("json_array"))
+		.args([
+			Expr::val(column.offset()).into(),
+			Expr::val(LanguageString::from(language).to_string()).into(),
+			Expr::val(mode.as_str()).into(),
+			Expr::val(pattern).into(),
+			SimpleExpr::from(Expr::col(column_ref.clone())),
+		])
+		.into()
+}
+
+// Builds a JSON array containing the highlight details of each match whose
+// condition holds for the row, and null otherwise.
+fn highlights_expression(highlights: Vec<(Condition, SimpleExpr)>) -> SimpleExpr {
+	Func::cust(Alias::new("json_array"))
+		.args(
+			highlights
+				.into_iter()
+				.map(|(condition, highlight)| SimpleExpr::from(Expr::case(condition, highlight))),
+		)
+		.into()
+}
+
+/// Regex patterns compiled while reading the highlights of a query's results.
+/// Each pattern is compiled once, and reused for every row of the query.
+#[derive(Default)]
+pub struct HighlightPatterns(HashMap<String, Option<regex::Regex>>);
+
+impl HighlightPatterns {
+	fn regex(&mut self, pattern: &str) -> Option<&regex::Regex> {
+		if !self.0.contains_key(pattern) {
+			self.0
+				.insert(pattern.to_string(), regex::Regex::new(pattern).ok());
+		}
+
+		self.0.get(pattern)?.as_ref()
+	}
+}
+
+/// Read the match highlights selected by a highlighting query from the given row.
+pub fn read_highlights(
+	row: &rusqlite::Row,
+	index: usize,
+	patterns: &mut HighlightPatterns,
+) -> rusqlite::Result<Vec<MatchHighlight>> {
+	let json = row.get::<_, String>(index)?;
+	parse_highlights(&json, patterns)
+		.map_err(|error| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, error.into()))
+}
+
+fn parse_highlights(
+	json: &str,
+	patterns: &mut HighlightPatterns,
+) -> anyhow::Result<Vec<MatchHighlight>> {
+	let entries = serde_json::from_str::<
+		Vec<Option<(u16, LanguageString, String, String, Option<String>)>>,
+	>(json)?;
+
+	let mut highlights = Vec::<MatchHighlight>::new();
+	for (offset, language, mode, pattern, value) in entries.into_iter().flatten() {
+		let language = Language::from(language);
+
+		// The same column may be targeted by multiple clauses - the first takes
+		// precedence.
+		if highlights
+			.iter()
+			.any(|highlight| highlight.offset == offset && highlight.language == language)
+		{
+			continue;
+		}
+
+		let mode = HighlightMode::parse(&mode)
+			.ok_or_else(|| anyhow::anyhow!("unknown highlight mode {mode}"))?;
+
+		highlights.push(MatchHighlight {
+			offset,
+			language,
+			snippet: build_snippet(
+				value.as_deref().unwrap_or_default(),
+				mode,
+				&pattern,
+				patterns,
+			),
+		});
+	}
+
+	Ok(highlights)
+}
+
+// Extracts the region of a value surrounding a match, marking truncated ends
+// with an ellipsis. If the match cannot be located, the start of the value is
+// used instead.
+fn build_snippet(
+	value: &str,
+	mode: HighlightMode,
+	pattern: &str,
+	patterns: &mut HighlightPatterns,
+) -> String {
+	let (start, end) = find_match(value, mode, pattern, patterns).unwrap_or((0, 0));
+
+	let snippet_start = value[..start]
+		.char_indices()
+		.rev()
+		.nth(SNIPPET_CONTEXT - 1)
+		.map(|(index, _)| index)
+		.unwrap_or(0);
+	let snippet_end = value[end..]
+		.char_indices()
+		.nth(SNIPPET_CONTEXT)
+		.map(|(index, _)| end + index)
+		.unwrap_or(value.len());
+
+	let mut snippet = String::new();
+	if snippet_start > 0 {
+		snippet.push('…');
+	}
+	snippet.push_str(&value[snippet_start..snippet_end]);
+	if snippet_end < value.len() {
+		snippet.push('…');
+	}
+
+	snippet
+}
+
+fn find_match(
+	value: &str,
+	mode: HighlightMode,
+	pattern: &str,
+	patterns: &mut HighlightPatterns,
+) -> Option<(usize, usize)> {
+	let matches_at = |index: usize| {
+		value.as_bytes()[index..]
+			.get(..pattern.len())
+			.is_some_and(|bytes| bytes.eq_ignore_ascii_case(pattern.as_bytes()))
+	};
+
+	let start = match mode {
+		HighlightMode::Insensitive => value
+			.char_indices()
+			.map(|(index, _)| index)
+			.find(|index| matches_at(*index)),
+		HighlightMode::InsensitiveEnd => value
+			.char_indices()
+			.map(|(index, _)| index)
+			.rfind(|index| matches_at(*index)),
+		HighlightMode::Sensitive => value.find(pattern),
+		HighlightMode::Regex => {
+			let found = patterns.regex(pattern)?.find(value)?;
+			return Some((found.start(), found.end()));
+		}
+	}?;
+
+	Some((start, start + pattern.len()))
+}
+
+/// Position within a value that a LIKE pattern must match at.
+#[derive(Clone, Copy)]
+enum Anchor {
+	None,
+	Start,
+	End,
+}
+
+fn build_like(string: &str, anchor: Anchor) -> LikeExpr {
 	static PATTERN: OnceLock<AhoCorasick> = OnceLock::new();
 	let pattern = PATTERN.get_or_init(|| {
 		AhoCorasick::new(["%", "_", "\\"]).expect("pattern construction should not fail")
@@ -334,7 +1175,26 @@ fn build_like(string: &str) -> LikeExpr {
 
 	let escaped = pattern.replace_all(string, &["\\%", "\\_", "\\\\"]);
 
-	LikeExpr::new(format!("%{escaped}%")).escape('\\')
+	let pattern = match anchor {
+		Anchor::None => format!("%{escaped}%"),
+		Anchor::Start => format!("{escaped}%"),
+		Anchor::End => format!("%{escaped}"),
+	};
+
+	LikeExpr::new(pattern).escape('\\')
+}
+
+fn build_glob(string: &str) -> String {
+	static PATTERN: OnceLock<AhoCorasick> = OnceLock::new();
+	let pattern = PATTERN.get_or_init(|| {
+		AhoCorasick::new(["*", "?", "["]).expect("pattern construction should not fail")
+	});
+
+	// GLOB has no escape character - metacharacters are matched literally by
+	// wrapping them in a character class.
+	let escaped = pattern.replace_all(string, &["[*]", "[?]", "[[]"]);
+
+	format!("*{escaped}*")
 }
 
 fn table_alias(alias_base: &str, language: Language) -> Alias {
@@ -360,3 +1220,923 @@ impl From<post::Number> for sea_query::Value {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use bm_read::fixture::{column, reference, scalar, struct_node, Cell, Fixture, TestSheet};
+	use ironworks::file::exh::ColumnKind as CK;
+	use ironworks_schema as schema;
+	use sea_query::SqliteQueryBuilder;
+	use sea_query_rusqlite::RusqliteBinder;
+
+	use crate::{
+		internal_query::{pre, MatchLength, Normalizer},
+		sqlite::fixture,
+	};
+
+	use super::*;
+
+	#[test]
+	fn matched_columns_for_row() {
+		let connection = rusqlite::Connection::open_in_memory().unwrap();
+		connection
+			.execute_batch(
+				r#"CREATE TABLE "sheet" ("12" TEXT, "16" INTEGER);
+				INSERT INTO "sheet" VALUES ('Example', 5);"#,
+			)
+			.unwrap();
+
+		let matches = vec![
+			(
+				Expr::col(Alias::new("12"))
+					.like(build_like("amp", Anchor::None))
+					.into_condition(),
+				"12:String".to_string(),
+			),
+			(
+				Expr::col(Alias::new("16")).eq(6).into_condition(),
+				"16:UInt32".to_string(),
+			),
+		];
+
+		let (query, values) = Query::select()
+			.expr(matched_columns_expression(matches))
+			.from(Alias::new("sheet"))
+			.build_rusqlite(SqliteQueryBuilder);
+
+		let got = connection
+			.query_row(&query, &*values.as_params(), |row| {
+				read_matched_columns(row, 0)
+			})
+			.expect("query should not fail");
+
+		assert_eq!(
+			got,
+			vec![MatchedColumn {
+				offset: 12,
+				kind: "String".into()
+			}]
+		);
+	}
+
+	#[test]
+	fn matched_columns_deduplicated() {
+		let got = parse_matched_columns(r#"["4:UInt8", null, "4:UInt8", "0:String"]"#)
+			.expect("parse should not fail");
+		assert_eq!(
+			got,
+			vec![
+				MatchedColumn {
+					offset: 0,
+					kind: "String".into()
+				},
+				MatchedColumn {
+					offset: 4,
+					kind: "UInt8".into()
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn snippet_surrounds_match() {
+		let mut patterns = HighlightPatterns::default();
+		let value = format!("{}Iron Sword{}", "a".repeat(40), "b".repeat(40));
+		assert_eq!(
+			build_snippet(
+				&value,
+				HighlightMode::Insensitive,
+				"iron sword",
+				&mut patterns
+			),
+			format!("…{}Iron Sword{}…", "a".repeat(32), "b".repeat(32))
+		);
+
+		assert_eq!(
+			build_snippet(
+				"Sword of Sword",
+				HighlightMode::InsensitiveEnd,
+				"SWORD",
+				&mut patterns
+			),
+			"Sword of Sword"
+		);
+
+		let mut find_match = |value, mode, pattern| find_match(value, mode, pattern, &mut patterns);
+		assert_eq!(
+			find_match("Sword of Sword", HighlightMode::InsensitiveEnd, "SWORD"),
+			Some((9, 14))
+		);
+		assert_eq!(
+			find_match("sword Sword", HighlightMode::Sensitive, "Sword"),
+			Some((6, 11))
+		);
+		assert_eq!(
+			find_match("Item 1234", HighlightMode::Regex, r"\d+"),
+			Some((5, 9))
+		);
+		assert_eq!(
+			find_match("ソードソード", HighlightMode::Insensitive, "ド"),
+			Some((6, 9))
+		);
+	}
+
+	#[test]
+	fn regex_compiled_once() {
+		let mut patterns = HighlightPatterns::default();
+		for value in ["Item 12", "Item 345"] {
+			assert!(find_match(value, HighlightMode::Regex, r"\d+", &mut patterns).is_some());
+		}
+		assert_eq!(patterns.0.len(), 1);
+
+		// Invalid patterns are remembered as such, and match nothing.
+		assert_eq!(
+			find_match("Item", HighlightMode::Regex, "(", &mut patterns),
+			None
+		);
+		assert!(patterns.0.get("(").is_some_and(Option::is_none));
+	}
+
+	#[test]
+	fn highlights_deduplicated() {
+		let got = parse_highlights(
+			r#"[[0, "en", "i", "sword", "Iron Sword"], null, [0, "en", "c", "Iron", "Iron Sword"], [0, "ja", "i", "ソード", "ソード"]]"#,
+			&mut HighlightPatterns::default(),
+		)
+		.expect("parse should not fail");
+		assert_eq!(
+			got,
+			vec![
+				MatchHighlight {
+					offset: 0,
+					language: Language::English,
+					snippet: "Iron Sword".into(),
+				},
+				MatchHighlight {
+					offset: 0,
+					language: Language::Japanese,
+					snippet: "ソード".into(),
+				},
+			]
+		);
+	}
+
+	fn equality_fixture(rows: u32) -> rusqlite::Connection {
+		let connection = fixture::connection(
+			r#"CREATE TABLE "sheet-Item@en" ("row_id" INTEGER, "subrow_id" INTEGER, "12" INTEGER);"#,
+		);
+		let mut insert = connection
+			.prepare(r#"INSERT INTO "sheet-Item@en" VALUES (?1, 0, ?2)"#)
+			.unwrap();
+		for row_id in 0..rows {
+			insert.execute((row_id, row_id % 7)).unwrap();
+		}
+		drop(insert);
+		connection
+	}
+
+	fn execute(
+		connection: &rusqlite::Connection,
+		query: SelectStatement,
+	) -> Vec<(String, u32, u16, f32)> {
+		let (query, values) = query.build_rusqlite(SqliteQueryBuilder);
+		let mut statement = connection.prepare(&query).unwrap();
+		statement
+			.query_map(&*values.as_params(), |row| {
+				Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+			})
+			.unwrap()
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap()
+	}
+
+	fn leaf(column: exh::ColumnDefinition, operation: post::Operation) -> post::Node {
+		post::Node::Leaf(post::Leaf {
+			field: (column, Language::English),
+			operation,
+		})
+	}
+
+	/// Resolve and execute queries against their sheets through the general
+	/// resolution path, bypassing any fast paths.
+	fn search(
+		connection: &rusqlite::Connection,
+		queries: Vec<(&str, post::Node)>,
+		sort: &post::Sort,
+	) -> Vec<(String, u32, u16, f32)> {
+		let selects = queries.into_iter().map(|(sheet_name, node)| {
+			let sort_field = match &sort.key {
+				post::SortKey::Score => None,
+				post::SortKey::Field(fields) => Some(fields.get(sheet_name)),
+			};
+			resolve_query(
+				sheet_name.into(),
+				node,
+				sort_field,
+				None,
+				FuzzyConfig::default(),
+				false,
+				false,
+			)
+		});
+		let query = union_ordered(selects, sort).expect("query should resolve");
+		execute(connection, query)
+	}
+
+	/// Resolve and execute a query against a single sheet as a search request
+	/// would, including any applicable fast paths.
+	fn resolved_search(
+		connection: &rusqlite::Connection,
+		sheet_name: &str,
+		node: post::Node,
+	) -> Vec<(String, u32, u16, f32)> {
+		let resolved = resolve_queries(
+			vec![(sheet_name.into(), node)],
+			&post::Sort::default(),
+			None,
+			FuzzyConfig::default(),
+			false,
+			false,
+			false,
+		)
+		.expect("query should resolve");
+		match resolved {
+			ResolvedStatement::Single(query) => execute(connection, query),
+			other => panic!("expected a single statement, got {other:?}"),
+		}
+	}
+
+	#[test]
+	fn equality_fast_path_matches_general() {
+		let connection = equality_fixture(100);
+		let node = leaf(
+			column(CK::UInt32, 12),
+			post::Operation::Eq(post::Value::Number(post::Number::U64(3))),
+		);
+
+		let fast_results = resolved_search(&connection, "Item", node.clone());
+		let general_results = search(&connection, vec![("Item", node)], &post::Sort::default());
+		assert!(!fast_results.is_empty());
+		assert_eq!(fast_results, general_results);
+	}
+
+	fn field_query(name: &str, operation: pre::Operation) -> pre::Node {
+		pre::Node::Leaf(pre::Leaf {
+			field: Some(pre::FieldSpecifier::Struct(name.into(), None)),
+			operation,
+		})
+	}
+
+	/// Normalize a query against a sheet of a read fixture, then resolve and
+	/// execute it as a search request would, returning the IDs of matched rows.
+	fn fixture_search(
+		fixture: &Fixture,
+		connection: &rusqlite::Connection,
+		sheet_name: &str,
+		query: &pre::Node,
+	) -> Result<Vec<u32>> {
+		let normalizer = Normalizer::new(
+			&fixture.excel,
+			&fixture.schema,
+			false,
+			MatchLength::default(),
+		);
+		let node = normalizer.normalize(query, sheet_name, Language::English)?;
+		Ok(resolved_search(connection, sheet_name, node)
+			.into_iter()
+			.map(|(_sheet, row_id, _subrow_id, _score)| row_id)
+			.collect())
+	}
+
+	/// Items with a single string column at offset 0.
+	fn item_fixture() -> rusqlite::Connection {
+		fixture::connection(
+			r#"CREATE TABLE "sheet-Item@en" ("row_id" INTEGER, "subrow_id" INTEGER, "0" TEXT);
+			INSERT INTO "sheet-Item@en" VALUES
+				(1, 0, 'Iron Sword'),
+				(2, 0, 'Iron Swords'),
+				(3, 0, 'Iron Shield'),
+				(4, 0, 'Sword of Iron');"#,
+		)
+	}
+
+	/// Resolve and execute a query against a single string column of the item
+	/// fixture, ordered by score.
+	fn item_search(
+		connection: &rusqlite::Connection,
+		operation: post::Operation,
+	) -> Vec<(String, u32, u16, f32)> {
+		let node = leaf(column(CK::String, 0), operation);
+		search(connection, vec![("Item", node)], &post::Sort::default())
+	}
+
+	/// Row IDs of items matched by an operation, in ascending order.
+	fn item_rows(connection: &rusqlite::Connection, operation: post::Operation) -> Vec<u32> {
+		let mut rows = item_search(connection, operation)
+			.into_iter()
+			.map(|(_sheet, row_id, _subrow_id, _score)| row_id)
+			.collect::<Vec<_>>();
+		rows.sort();
+		rows
+	}
+
+	#[test]
+	fn match_case_respects_case() {
+		let connection = item_fixture();
+		let match_case =
+			|string: &str| item_rows(&connection, post::Operation::MatchCase(string.into()));
+
+		assert_eq!(match_case("Sword"), vec![1, 2, 4]);
+		assert_eq!(match_case("sword"), Vec::<u32>::new());
+		assert_eq!(match_case("Iron S"), vec![1, 2, 3]);
+
+		// Glob metacharacters in the match string are matched literally.
+		let connection = fixture::connection(
+			r#"CREATE TABLE "sheet-Item@en" ("row_id" INTEGER, "subrow_id" INTEGER, "0" TEXT);
+			INSERT INTO "sheet-Item@en" VALUES (1, 0, '50*Off'), (2, 0, '50% Off');"#,
+		);
+		assert_eq!(
+			item_rows(&connection, post::Operation::MatchCase("0*O".into())),
+			vec![1]
+		);
+	}
+
+	#[test]
+	fn fuzzy_scored_by_distance() {
+		let connection = item_fixture();
+
+		// Match strings are folded to lower case, and closer matches score higher.
+		let got = item_search(&connection, post::Operation::Fuzzy("IRON SWORD".into()));
+		assert_eq!(
+			got,
+			vec![
+				("Item".to_string(), 1, 0, 1.),
+				("Item".to_string(), 2, 0, 0.5),
+			]
+		);
+	}
+
+	#[test]
+	fn anchored_match_weighted() {
+		let connection = item_fixture();
+		let result = |row_id, score: f64| ("Item".to_string(), row_id, 0, score as f32);
+
+		// Unanchored matches are scored by the proportion of the value matched.
+		let got = item_search(&connection, post::Operation::Match("sword".into()));
+		assert_eq!(
+			got,
+			vec![
+				result(1, 5. / 10.),
+				result(2, 5. / 11.),
+				result(4, 5. / 13.),
+			]
+		);
+
+		// Anchored matches only select values with the string at the anchor, and
+		// are weighted above an equivalent unanchored match.
+		let got = item_search(&connection, post::Operation::StartsWith("SWORD".into()));
+		assert_eq!(got, vec![result(4, 5. / 13. * 2.)]);
+
+		// Weighted scores are capped below that of an exact match.
+		let got = item_search(&connection, post::Operation::EndsWith("sword".into()));
+		assert_eq!(got, vec![result(1, ANCHORED_MATCH_MAX_SCORE)]);
+
+		let got = item_search(
+			&connection,
+			post::Operation::Eq(post::Value::String("Iron Sword".into())),
+		);
+		assert_eq!(got, vec![result(1, 1.)]);
+	}
+
+	#[test]
+	fn boolean_equality() {
+		let fixture = Fixture::new(vec![(
+			TestSheet::new(
+				"Item",
+				[(CK::UInt8, 0), (CK::Bool, 1), (CK::PackedBool2, 2)],
+			)
+			.row(1, [Cell::U8(1), Cell::Bool(false), Cell::Bool(false)])
+			.row(2, [Cell::U8(0), Cell::Bool(true), Cell::Bool(true)])
+			.row(3, [Cell::U8(1), Cell::Bool(true), Cell::Bool(false)]),
+			struct_node([
+				("Flag", scalar()),
+				("Enabled", scalar()),
+				("Packed", scalar()),
+			]),
+		)]);
+		let connection = fixture::fixture_connection(&fixture, &["Item"]);
+		let search = |name: &str, value: bool| {
+			let query = field_query(name, pre::Operation::Eq(pre::Value::Boolean(value)));
+			fixture_search(&fixture, &connection, "Item", &query).expect("search should not fail")
+		};
+
+		// Flags stored in integer columns are compared as booleans.
+		assert_eq!(search("Flag", true), vec![1, 3]);
+		assert_eq!(search("Flag", false), vec![2]);
+		assert_eq!(search("Enabled", true), vec![2, 3]);
+		assert_eq!(search("Packed", true), vec![2]);
+	}
+
+	/// Items with an array of two names.
+	fn names_fixture() -> Fixture {
+		let names = |a: &str, b: &str| [Cell::String(a.into()), Cell::String(b.into())];
+		Fixture::new(vec![(
+			TestSheet::new("Item", [(CK::String, 0), (CK::String, 4)])
+				.row(1, names("Iron Sword", "Shield"))
+				.row(2, names("Shield", "Bow"))
+				.row(3, names("Bow", "Sword")),
+			struct_node([(
+				"Names",
+				schema::Node::Array {
+					count: 2,
+					node: Box::new(scalar()),
+				},
+			)]),
+		)])
+	}
+
+	/// Query of the elements of the names array, optionally at an index.
+	fn names_query(index: Option<u32>, operation: pre::Operation) -> pre::Node {
+		field_query(
+			"Names",
+			pre::Operation::Relation(pre::Relation {
+				target: (),
+				query: Box::new(pre::Node::Leaf(pre::Leaf {
+					field: Some(pre::FieldSpecifier::Array(index)),
+					operation,
+				})),
+			}),
+		)
+	}
+
+	fn sorted_rows(
+		fixture: &Fixture,
+		connection: &rusqlite::Connection,
+		query: &pre::Node,
+	) -> Vec<u32> {
+		let mut rows =
+			fixture_search(fixture, connection, "Item", query).expect("search should not fail");
+		rows.sort();
+		rows
+	}
+
+	#[test]
+	fn not_match_excludes_substring() {
+		let connection = item_fixture();
+		let not_match =
+			|string: &str| item_rows(&connection, post::Operation::NotMatch(string.into()));
+
+		// Matching is case insensitive, and excludes the string anywhere in a value.
+		assert_eq!(not_match("SWORD"), vec![3]);
+		assert_eq!(not_match("iron s"), vec![4]);
+
+		// Wildcards in the match string are matched literally.
+		let connection = fixture::connection(
+			r#"CREATE TABLE "sheet-Item@en" ("row_id" INTEGER, "subrow_id" INTEGER, "0" TEXT);
+			INSERT INTO "sheet-Item@en" VALUES (1, 0, 'Example'), (2, 0, '50% Off'), (3, 0, '500 Off');"#,
+		);
+		assert_eq!(
+			item_rows(&connection, post::Operation::NotMatch("0% O".into())),
+			vec![1, 3]
+		);
+	}
+
+	#[test]
+	fn not_match_across_elements() {
+		let fixture = names_fixture();
+		let connection = fixture::fixture_connection(&fixture, &["Item"]);
+		let not_match = || pre::Operation::NotMatch("sword".into());
+
+		// Names[0]!~"sword"
+		let query = names_query(Some(0), not_match());
+		assert_eq!(sorted_rows(&fixture, &connection, &query), vec![2, 3]);
+
+		// Names[]!~"sword"
+		let query = names_query(None, not_match());
+		assert_eq!(sorted_rows(&fixture, &connection, &query), vec![2]);
+
+		// !~"sword"
+		let query = pre::Node::Leaf(pre::Leaf {
+			field: None,
+			operation: not_match(),
+		});
+		assert_eq!(sorted_rows(&fixture, &connection, &query), vec![2]);
+	}
+
+	#[test]
+	fn array_not_match_excludes_every_element() {
+		let fixture = names_fixture();
+		let connection = fixture::fixture_connection(&fixture, &["Item"]);
+		let search = |operation| sorted_rows(&fixture, &connection, &names_query(None, operation));
+
+		assert_eq!(search(pre::Operation::Match("sword".into())), vec![1, 3]);
+		// No element may contain the string, rather than any one element lacking it.
+		assert_eq!(search(pre::Operation::NotMatch("sword".into())), vec![2]);
+	}
+
+	#[test]
+	fn reverse_relation_matches_referencing_rows() {
+		let item = |name: &str| [Cell::String(name.into())];
+		let recipe = |item: u32, craft_type: u8| [Cell::U32(item), Cell::U8(craft_type)];
+		let fixture = Fixture::new(vec![
+			(
+				TestSheet::new("Item", [(CK::String, 0)])
+					.row(1, item("Sword"))
+					.row(2, item("Shield"))
+					.row(3, item("Bow")),
+				struct_node([("Name", scalar())]),
+			),
+			(
+				TestSheet::new("Recipe", [(CK::UInt32, 0), (CK::UInt8, 4)])
+					.row(1, recipe(1, 1))
+					.row(2, recipe(2, 2))
+					.row(3, recipe(3, 1))
+					.row(4, recipe(1, 2)),
+				struct_node([
+					("ItemResult", reference(&["Item"])),
+					("CraftType", scalar()),
+				]),
+			),
+		]);
+		let connection = fixture::fixture_connection(&fixture, &["Item", "Recipe"]);
+
+		let search = |craft_type: u64| {
+			let query = pre::Node::Leaf(pre::Leaf {
+				field: Some(pre::FieldSpecifier::ReverseRelation(
+					"Recipe".into(),
+					"ItemResult".into(),
+				)),
+				operation: pre::Operation::Relation(pre::Relation {
+					target: (),
+					query: Box::new(field_query(
+						"CraftType",
+						pre::Operation::Eq(pre::Value::Number(pre::Number::U64(craft_type))),
+					)),
+				}),
+			});
+			let mut rows = fixture_search(&fixture, &connection, "Item", &query)
+				.expect("search should not fail");
+			rows.sort();
+			rows
+		};
+
+		// Items are matched once for any number of referencing recipes.
+		assert_eq!(search(1), vec![1, 3]);
+		assert_eq!(search(2), vec![1, 2]);
+		assert_eq!(search(3), Vec::<u32>::new());
+	}
+
+	#[test]
+	fn packed_bool_column_resolved() {
+		let connection = fixture::connection(
+			r#"CREATE TABLE "sheet-Item@en" ("row_id" INTEGER, "subrow_id" INTEGER, "8_0" INTEGER, "8_2" INTEGER);
+			INSERT INTO "sheet-Item@en" VALUES (1, 0, 1, 0), (2, 0, 0, 1), (3, 0, 1, 1);"#,
+		);
+
+		// Columns sharing an offset resolve to the vtable column of their bit.
+		let node = post::Node::Leaf(post::Leaf {
+			field: (column(CK::PackedBool2, 8), Language::English),
+			operation: post::Operation::Eq(post::Value::Boolean(true)),
+		});
+		let select = resolve_query(
+			"Item".into(),
+			node,
+			None,
+			None,
+			FuzzyConfig::default(),
+			false,
+			false,
+		);
+		let query = union_ordered(std::iter::once(select), &post::Sort::default())
+			.expect("query should resolve");
+
+		let mut rows = execute(&connection, query)
+			.into_iter()
+			.map(|(_sheet, row_id, _subrow_id, _score)| row_id)
+			.collect::<Vec<_>>();
+		rows.sort();
+		assert_eq!(rows, vec![2, 3]);
+	}
+
+	#[test]
+	fn in_matches_any_value() {
+		let connection = equality_fixture(14);
+		let node = leaf(
+			column(CK::UInt32, 12),
+			post::Operation::In(vec![
+				post::Value::Number(post::Number::U64(2)),
+				post::Value::Number(post::Number::U64(5)),
+			]),
+		);
+
+		let mut got = search(&connection, vec![("Item", node)], &post::Sort::default())
+			.into_iter()
+			.map(|(_sheet, row_id, _subrow_id, _score)| row_id)
+			.collect::<Vec<_>>();
+		got.sort();
+		assert_eq!(got, vec![2, 5, 9, 12]);
+	}
+
+	#[test]
+	fn rows_constrain_base_sheet() {
+		let connection = equality_fixture(14);
+		let alias = table_alias(BASE_ALIAS, Language::English);
+		let condition = Expr::col((alias, Alias::new("12")))
+			.eq(post::Value::Number(post::Number::U64(3)))
+			.into_condition();
+		let result = ResolveResult {
+			matches: vec![],
+			highlights: vec![],
+			condition,
+			score: Expr::value(1),
+			languages: HashSet::from([Language::English]),
+			relations: vec![],
+		};
+
+		let select = build_select("Item", result, None, Some(&[3, 4, 5]), false, false)
+			.expect("select should resolve");
+		assert!(select
+			.to_string(SqliteQueryBuilder)
+			.contains(r#""alias-base@en"."row_id" IN (3, 4, 5)"#));
+
+		let got = execute(&connection, select)
+			.into_iter()
+			.map(|result| result.1)
+			.collect::<Vec<_>>();
+		assert_eq!(got, vec![3]);
+	}
+
+	#[test]
+	fn sort_ties_fall_back_to_row_id() {
+		let connection = equality_fixture(14);
+		let column = column(CK::UInt32, 12);
+
+		let sort = post::Sort {
+			key: post::SortKey::Field(HashMap::from([(
+				"Item".to_string(),
+				(column.clone(), Language::English),
+			)])),
+			direction: post::SortDirection::Descending,
+		};
+		let node = leaf(column, post::Operation::Gte(post::Number::U64(0)));
+
+		let got = search(&connection, vec![("Item", node)], &sort)
+			.into_iter()
+			.map(|result| result.1)
+			.collect::<Vec<_>>();
+		assert_eq!(got, vec![6, 13, 5, 12, 4, 11, 3, 10, 2, 9, 1, 8, 0, 7]);
+	}
+
+	#[test]
+	fn sort_ties_fall_back_to_subrow_and_sheet() {
+		let fixture = Fixture::new(vec![
+			(
+				TestSheet::new("Action", [(CK::UInt32, 0)])
+					.row(1, [Cell::U32(5)])
+					.row(2, [Cell::U32(5)]),
+				struct_node([("Value", scalar())]),
+			),
+			(
+				TestSheet::new("Quest", [(CK::UInt32, 0)])
+					.subrows(1, vec![vec![Cell::U32(5)], vec![Cell::U32(5)]]),
+				struct_node([("Value", scalar())]),
+			),
+		]);
+		let connection = fixture::fixture_connection(&fixture, &["Action", "Quest"]);
+		let normalizer = Normalizer::new(
+			&fixture.excel,
+			&fixture.schema,
+			false,
+			MatchLength::default(),
+		);
+
+		let specifier = pre::FieldSpecifier::Struct("Value".into(), None);
+		let query = field_query(
+			"Value",
+			pre::Operation::Eq(pre::Value::Number(pre::Number::U64(5))),
+		);
+		let mut fields = HashMap::new();
+		let mut queries = vec![];
+		for sheet in ["Quest", "Action"] {
+			let field = normalizer
+				.normalize_field(&specifier, sheet, Language::English)
+				.expect("sort field should resolve");
+			fields.insert(sheet.to_string(), field);
+			let node = normalizer
+				.normalize(&query, sheet, Language::English)
+				.expect("query should normalize");
+			queries.push((sheet, node));
+		}
+		let sort = post::Sort {
+			key: post::SortKey::Field(fields),
+			direction: post::SortDirection::Ascending,
+		};
+
+		// Every result has the same sort value and score.
+		let got = search(&connection, queries, &sort)
+			.into_iter()
+			.map(|(sheet, row_id, subrow_id, _score)| (sheet, row_id, subrow_id))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			got,
+			vec![
+				("Action".to_string(), 1, 0),
+				("Quest".to_string(), 1, 0),
+				("Quest".to_string(), 1, 1),
+				("Action".to_string(), 2, 0),
+			]
+		);
+	}
+
+	const PARALLEL_SHEETS: [&str; 3] = ["Action", "Item", "Status"];
+
+	// Each sheet has a table per language, such that selects must join them.
+	// Scores are unique across every sheet, to ensure a total ordering. Sort
+	// values are integers, text, and null for each sheet respectively.
+	fn parallel_fixture(rows: u32) -> rusqlite::Connection {
+		let connection = rusqlite::Connection::open_in_memory().unwrap();
+		for (index, sheet) in PARALLEL_SHEETS.into_iter().enumerate() {
+			for language in [Language::English, Language::Japanese] {
+				let table = table_name(sheet, language).to_string();
+				connection
+					.execute_batch(&format!(
+						r#"CREATE TABLE "{table}" ("row_id" INTEGER, "subrow_id" INTEGER, "1" REAL, "2");"#
+					))
+					.unwrap();
+
+				let mut insert = connection
+					.prepare(&format!(r#"INSERT INTO "{table}" VALUES (?1, 0, ?2, ?3)"#))
+					.unwrap();
+				for row_id in 0..rows {
+					let score = f64::from(row_id * 37 % 1000) + index as f64 / 10.;
+					let sort = match index {
+						0 => rusqlite::types::Value::Integer((row_id % 10).into()),
+						1 => rusqlite::types::Value::Text(format!("{}", row_id % 10)),
+						_ => rusqlite::types::Value::Null,
+					};
+					insert.execute((row_id, score, sort)).unwrap();
+				}
+			}
+		}
+		connection
+	}
+
+	fn parallel_select(sheet: &str, sort: bool) -> SelectStatement {
+		let alias = table_alias(BASE_ALIAS, Language::English);
+		let result = ResolveResult {
+			matches: vec![],
+			highlights: vec![],
+			condition: Expr::col((alias.clone(), Alias::new("1")))
+				.is_not_null()
+				.into_condition(),
+			score: Expr::col((alias.clone(), Alias::new("1"))).into(),
+			languages: HashSet::from([Language::English, Language::Japanese]),
+			relations: vec![],
+		};
+
+		let mut select =
+			build_select(sheet, result, None, None, false, false).expect("select should resolve");
+		if sort {
+			select.expr_as(
+				Expr::col((alias, Alias::new("2"))),
+				KnownResolveColumn::Sort,
+			);
+		}
+		select
+	}
+
+	#[test]
+	fn parallel_matches_union() {
+		let connection = parallel_fixture(300);
+
+		let sorts = [
+			post::Sort::default(),
+			post::Sort {
+				key: post::SortKey::Score,
+				direction: post::SortDirection::Ascending,
+			},
+			post::Sort {
+				key: post::SortKey::Field(HashMap::new()),
+				direction: post::SortDirection::Ascending,
+			},
+			post::Sort {
+				key: post::SortKey::Field(HashMap::new()),
+				direction: post::SortDirection::Descending,
+			},
+		];
+
+		for sort in sorts {
+			let order = ResultOrder::new(&sort);
+			let selects = || {
+				PARALLEL_SHEETS
+					.into_iter()
+					.map(|sheet| Ok(parallel_select(sheet, order.field())))
+			};
+
+			let union = union_ordered(selects(), &sort).expect("union should resolve");
+			let statements = selects()
+				.map(|select| ordered_subquery(select.unwrap(), &sort))
+				.collect::<Vec<_>>();
+
+			for (offset, limit) in [(0, 25), (25, 25), (280, 40), (850, 100)] {
+				let expected = execute(
+					&connection,
+					union.clone().limit(limit).offset(offset).take(),
+				);
+
+				let results = statements
+					.iter()
+					.map(|statement| {
+						let statement = statement.clone().limit(offset + limit).take();
+						let (query, values) = statement.build_rusqlite(SqliteQueryBuilder);
+						let mut statement = connection.prepare(&query).unwrap();
+						statement
+							.query_map(&*values.as_params(), |row| {
+								Ok((
+									read_result_key(row, order.field().then_some(4))?,
+									(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?),
+								))
+							})
+							.unwrap()
+							.collect::<Result<Vec<_>, _>>()
+							.unwrap()
+					})
+					.collect();
+
+				let got = merge_results(results, order)
+					.into_iter()
+					.skip(usize::try_from(offset).unwrap())
+					.take(usize::try_from(limit).unwrap())
+					.collect::<Vec<(String, u32, u16, f32)>>();
+
+				assert_eq!(got.len(), expected.len(), "{sort:?} {offset}+{limit}");
+				assert_eq!(got, expected, "{sort:?} {offset}+{limit}");
+			}
+		}
+	}
+
+	#[test]
+	fn sql_value_ordering() {
+		use rusqlite::types::Value as V;
+
+		let mut values = vec![
+			V::Text("b".into()),
+			V::Integer(2),
+			V::Blob(vec![0]),
+			V::Real(1.5),
+			V::Null,
+			V::Text("B".into()),
+			V::Integer(-1),
+		];
+		values.sort_by(compare_sql_values);
+
+		assert_eq!(
+			values,
+			vec![
+				V::Null,
+				V::Integer(-1),
+				V::Real(1.5),
+				V::Integer(2),
+				V::Text("B".into()),
+				V::Text("b".into()),
+				V::Blob(vec![0]),
+			]
+		);
+	}
+
+	// Run with `cargo test -p bm_search --release -- --ignored --nocapture`.
+	#[test]
+	#[ignore = "benchmark"]
+	fn equality_fast_path_benchmark() {
+		const ITERATIONS: u32 = 1_000;
+
+		let connection = equality_fixture(100_000);
+		let node = leaf(
+			column(CK::UInt32, 12),
+			post::Operation::Eq(post::Value::Number(post::Number::U64(3))),
+		);
+
+		let time = |run: &dyn Fn()| {
+			let start = std::time::Instant::now();
+			for _ in 0..ITERATIONS {
+				run();
+			}
+			start.elapsed() / ITERATIONS
+		};
+
+		let general = time(&|| {
+			search(
+				&connection,
+				vec![("Item", node.clone())],
+				&post::Sort::default(),
+			);
+		});
+		let fast = time(&|| {
+			resolved_search(&connection, "Item", node.clone());
+		});
+
+		println!("general: {general:?}/iter, fast path: {fast:?}/iter");
+	}
+}
@@ -1,15 +1,26 @@
 use std::{marker::PhantomData, os::raw::c_int, sync::Arc};
 
 use bm_read::LanguageString;
-use ironworks::{excel, file::exh};
+use ironworks::{excel, file::exh, Ironworks};
 use rusqlite::{types::ToSqlOutput, vtab, Connection, ToSql};
 use sea_query::{Alias, ColumnDef, SqliteQueryBuilder, Table};
 
 use super::schema::{column_name, column_type, KnownColumn};
 
-pub fn load_module(connection: &Connection, excel: Arc<excel::Excel>) -> rusqlite::Result<()> {
+pub fn load_module(
+	connection: &Connection,
+	excel: Arc<excel::Excel>,
+	ironworks: Arc<Ironworks>,
+) -> rusqlite::Result<()> {
 	let module = vtab::read_only_module::<'_, IronworksTable>();
-	connection.create_module("ironworks", module, Some(excel))
+	connection.create_module("ironworks", module, Some(ModuleData { excel, ironworks }))
+}
+
+/// Game data shared by every table of the module. Raw file access is used for
+/// metadata not exposed by excel.
+struct ModuleData {
+	excel: Arc<excel::Excel>,
+	ironworks: Arc<Ironworks>,
 }
 
 #[derive(Debug)]
@@ -20,10 +31,14 @@ struct IronworksTable {
 	excel: Arc<excel::Excel>,
 	sheet: String,
 	language: excel::Language,
+
+	/// Number of rows in the sheet, as recorded by its header. Sheets with
+	/// subrows are estimated by their number of rows, rather than subrows.
+	estimated_rows: u32,
 }
 
 unsafe impl<'vtab> vtab::VTab<'vtab> for IronworksTable {
-	type Aux = Arc<excel::Excel>;
+	type Aux = ModuleData;
 
 	type Cursor = IronworksTableCursor<'vtab>;
 
@@ -33,9 +48,8 @@ unsafe impl<'vtab> vtab::VTab<'vtab> for IronworksTable {
 		args: &[&[u8]],
 	) -> rusqlite::Result<(String, Self)> {
 		// This should never occur, but sanity check.
-		let excel = aux
-			.ok_or_else(|| module_error("vtable connection missing aux excel instance"))?
-			.clone();
+		let ModuleData { excel, ironworks } =
+			aux.ok_or_else(|| module_error("vtable connection missing aux game data"))?;
 
 		// Parse arguments - first few are standard, rest come from table declaration.
 		if args.len() < 4 {
@@ -44,9 +58,10 @@ unsafe impl<'vtab> vtab::VTab<'vtab> for IronworksTable {
 
 		let mut vtable = Self {
 			base: Default::default(),
-			excel,
+			excel: excel.clone(),
 			sheet: "".into(),
 			language: excel::Language::None,
+			estimated_rows: 0,
 		};
 
 		for slice in &args[3..] {
@@ -96,6 +111,11 @@ unsafe impl<'vtab> vtab::VTab<'vtab> for IronworksTable {
 
 		let schema = table.build(SqliteQueryBuilder);
 
+		// The header lists the row count of every page, giving a cheap estimate of
+		// the cost of scanning the sheet.
+		let header = bm_read::sheet_header(&ironworks, &vtable.sheet).map_err(module_error)?;
+		vtable.estimated_rows = bm_read::header_row_count(&header);
+
 		db.config(vtab::VTabConfig::DirectOnly)?;
 
 		Ok((schema, vtable))
@@ -121,10 +141,11 @@ unsafe impl<'vtab> vtab::VTab<'vtab> for IronworksTable {
 				info.set_idx_num(Index::ROW_ID);
 				info.set_estimated_cost(1_f64);
 			}
+			// NOTE: Constraints that cannot be used as an index, such as REGEXP
+			// and fuzzy match operations, will always fall through to a full scan.
 			false => {
 				info.set_idx_num(Index::SCAN);
-				// TODO: This would probably benefit from some variability, such that the schema optimiser can try to prioritise scans on smaller tables. Row count is difficult due to subrow tables; but maybe page count? that's entirely in the header.
-				info.set_estimated_cost(1000000_f64);
+				info.set_estimated_cost(scan_cost(self.estimated_rows));
 			}
 		}
 
@@ -301,6 +322,12 @@ unsafe impl vtab::VTabCursor for IronworksTableCursor<'_> {
 	}
 }
 
+// Scans are costed by the number of rows read, such that the planner can
+// prioritise scanning smaller sheets. Scans always cost more than a row ID lookup.
+fn scan_cost(rows: u32) -> f64 {
+	1_f64 + f64::from(rows)
+}
+
 struct FieldToSql(excel::Field);
 impl ToSql for FieldToSql {
 	fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
@@ -330,3 +357,29 @@ impl ToSql for FieldToSql {
 fn module_error(error: impl ToString) -> rusqlite::Error {
 	rusqlite::Error::ModuleError(error.to_string())
 }
+
+#[cfg(test)]
+mod test {
+	use exh::ColumnKind as CK;
+
+	use bm_read::fixture;
+
+	use super::*;
+
+	#[test]
+	fn smaller_sheets_scan_cheaper() {
+		let small = fixture::header(&[(CK::UInt32, 0)], &[(0, 50)]);
+		let large = fixture::header(&[(CK::UInt32, 0)], &[(0, 20_000), (20_000, 30_000)]);
+		let (small, large) = (
+			bm_read::header_row_count(&small),
+			bm_read::header_row_count(&large),
+		);
+		assert_eq!(small, 50);
+		assert_eq!(large, 50_000);
+
+		assert!(scan_cost(small) < scan_cost(large));
+
+		// Row ID lookups are costed at 1.
+		assert!(scan_cost(0) > 1_f64);
+	}
+}
@@ -1,8 +1,10 @@
 mod key;
 mod manager;
 mod patcher;
+mod retention;
 mod thaliak;
 mod version;
+mod webhook;
 
 pub use {
 	key::VersionKey,
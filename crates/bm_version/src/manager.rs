@@ -1,7 +1,9 @@
 use std::{
-	collections::{hash_map::Entry, BTreeMap, HashMap},
+	collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
 	fs,
+	future::Future,
 	io::{self, Read},
+	num::NonZeroUsize,
 	path::{Path, PathBuf},
 	sync::RwLock,
 	time::SystemTime,
@@ -13,13 +15,18 @@ use fs4::fs_std::FileExt;
 use futures::future::{join_all, try_join_all};
 use nonempty::NonEmpty;
 use serde::{Deserialize, Serialize};
-use tokio::{select, sync::broadcast, time};
+use tokio::{
+	select,
+	sync::{broadcast, Semaphore},
+	time,
+};
 use tokio_util::sync::CancellationToken;
 
 use super::{
 	key::VersionKey,
-	patcher, thaliak,
+	patcher, retention, thaliak,
 	version::{Repository, Version},
+	webhook,
 };
 
 const TAG_LATEST: &str = "latest";
@@ -32,6 +39,18 @@ pub struct Config {
 	interval: u64,
 	directory: RelativePathBuf,
 	repositories: Vec<String>,
+
+	/// Maximum number of repositories to fetch concurrently during an update.
+	/// Unlimited if not specified, must otherwise be at least 1.
+	repository_concurrency: Option<NonZeroUsize>,
+
+	/// Policy for pruning old versions. Named versions are always retained.
+	#[serde(default)]
+	retention: retention::Config,
+
+	/// Outbound notification of new or updated versions.
+	#[serde(default)]
+	webhook: webhook::Config,
 }
 
 /// Messgages that may be broadcast by the version system.
@@ -46,6 +65,10 @@ pub enum VersionMessage {
 	/// about the associated version has changed, and any caches of data are
 	/// stale.
 	Changed(VersionKey),
+
+	/// Versions were pruned by the retention policy. Version keys associated
+	/// with this event no longer exist.
+	Removed(Vec<VersionKey>),
 }
 
 pub struct Manager {
@@ -55,6 +78,9 @@ pub struct Manager {
 	update_interval: u64,
 	directory: PathBuf,
 	repositories: Vec<String>,
+	repository_semaphore: Semaphore,
+	retention: retention::Config,
+	webhook: webhook::Webhook,
 
 	versions: RwLock<HashMap<VersionKey, Version>>,
 	names: RwLock<HashMap<String, VersionKey>>,
@@ -77,6 +103,13 @@ impl Manager {
 			update_interval: config.interval,
 			directory,
 			repositories: config.repositories,
+			repository_semaphore: Semaphore::new(
+				config
+					.repository_concurrency
+					.map_or(Semaphore::MAX_PERMITS, NonZeroUsize::get),
+			),
+			retention: config.retention,
+			webhook: webhook::Webhook::new(config.webhook),
 
 			versions: Default::default(),
 			names: Default::default(),
@@ -178,14 +211,46 @@ impl Manager {
 		Ok(())
 	}
 
+	/// Remove a name from the version it refers to. Returns the key of that
+	/// version, or `None` if the name was not known.
+	pub async fn remove_name(&self, name: &str) -> Result<Option<VersionKey>> {
+		let key = self.names.write().expect("poisoned").remove(name);
+		if key.is_some() {
+			self.persist_metadata().await?;
+		}
+		Ok(key)
+	}
+
 	/// Get the full version metadata for a given key, if it exists.
 	pub fn version(&self, key: VersionKey) -> Option<Version> {
 		self.versions.read().expect("poisoned").get(&key).cloned()
 	}
 
+	/// Get the keys of the versions between two versions, inclusive, ordered from
+	/// earliest to latest ingestion. The bounds may be provided in either order.
+	/// Banned versions are omitted unless they are one of the bounds, as are
+	/// versions without a known ingestion time. Returns `None` if either bound is
+	/// not a known version, or has no known ingestion time.
+	pub fn range(&self, from: VersionKey, to: VersionKey) -> Option<Vec<VersionKey>> {
+		let versions = self.versions.read().expect("poisoned");
+		version_range(&versions, from, to)
+	}
+
 	pub async fn start(&self, cancel: CancellationToken) -> Result<()> {
+		// Subscribe before updates start, so no change is missed by the webhook. A
+		// disabled webhook never reads from the channel, so isn't subscribed at all.
+		let receiver = self.webhook.enabled().then(|| self.subscribe());
+		let webhook_enabled = receiver.is_some();
+		let names = |key| self.names(key).unwrap_or_default();
+		let webhook = async move {
+			if let Some(receiver) = receiver {
+				self.webhook.run(receiver, names).await
+			}
+		};
+
 		select! {
 			result = self.start_inner() => result,
+			_ = webhook, if webhook_enabled => Ok(()),
 			_ = cancel.cancelled() => Ok(())
 		}
 	}
@@ -216,12 +281,14 @@ impl Manager {
 			.repositories
 			.iter()
 			.map(|repository| self.fetch_repository(repository));
-		let repositories = try_join_all(pending_repositories).await?;
+		let repositories =
+			try_join_limited(&self.repository_semaphore, pending_repositories).await?;
 
 		// Build a version struct and it's associated key and save it to the versions map.
 		let mut version = Version {
 			repositories,
 			ban_time: None,
+			ingest_time: Some(SystemTime::now()),
 		};
 		let key = VersionKey::from(&version);
 
@@ -238,6 +305,7 @@ impl Manager {
 			Entry::Occupied(mut entry) => {
 				let old = entry.get();
 				version.ban_time = old.ban_time;
+				version.ingest_time = old.ingest_time.or(version.ingest_time);
 
 				let changed = *old.repositories != version.repositories;
 				if changed {
@@ -276,6 +344,79 @@ impl Manager {
 		// We don't care if anyone is actually listening on the channel.
 		let _ = self.channel.send(VersionMessage::Changed(key));
 
+		self.enforce_retention().await?;
+
+		Ok(())
+	}
+
+	/// Prune versions that fall outside the configured retention policy,
+	/// alongside any patch files that are no longer referenced.
+	async fn enforce_retention(&self) -> Result<()> {
+		let pruned = {
+			let versions = self.versions.read().expect("poisoned");
+			let pinned = self
+				.names
+				.read()
+				.expect("poisoned")
+				.values()
+				.copied()
+				.collect::<HashSet<_>>();
+			self.retention.prune(&versions, &pinned, SystemTime::now())
+		};
+
+		if pruned.is_empty() {
+			return Ok(());
+		}
+
+		// Patch files are commonly shared between versions - only those that are
+		// not referenced by a retained version are orphaned.
+		let orphaned_patches = {
+			let mut versions = self.versions.write().expect("poisoned");
+			let removed = pruned
+				.iter()
+				.filter_map(|key| versions.remove(key))
+				.collect::<Vec<_>>();
+
+			let retained = versions
+				.values()
+				.flat_map(patch_paths)
+				.collect::<HashSet<_>>();
+
+			removed
+				.iter()
+				.flat_map(patch_paths)
+				.filter(|path| !retained.contains(path))
+				.collect::<HashSet<_>>()
+		};
+
+		self.persist_metadata().await?;
+
+		for key in &pruned {
+			tracing::info!(%key, "pruned version");
+		}
+
+		for path in &orphaned_patches {
+			self.patcher.forget(path);
+		}
+
+		let paths = pruned
+			.iter()
+			.map(|key| self.version_path(*key))
+			.chain(orphaned_patches)
+			.collect::<Vec<_>>();
+		let join_handle = tokio::task::spawn_blocking(move || {
+			for path in paths {
+				if let Err(error) = fs::remove_file(&path) {
+					if error.kind() != io::ErrorKind::NotFound {
+						tracing::warn!(?path, ?error, "could not remove pruned file");
+					}
+				}
+			}
+		});
+		join_handle.await?;
+
+		let _ = self.channel.send(VersionMessage::Removed(pruned));
+
 		Ok(())
 	}
 
@@ -354,6 +495,12 @@ impl Manager {
 		let keys = versions.keys().copied().collect::<Vec<_>>();
 		let _ = self.channel.send(VersionMessage::Hydrate(keys));
 
+		drop(names);
+		drop(versions);
+
+		// The retention policy may have changed since versions were persisted.
+		self.enforce_retention().await?;
+
 		Ok(())
 	}
 
@@ -439,6 +586,37 @@ struct PersistedMetadata {
 	names: BTreeMap<String, VersionKey>,
 }
 
+fn version_range(
+	versions: &HashMap<VersionKey, Version>,
+	from: VersionKey,
+	to: VersionKey,
+) -> Option<Vec<VersionKey>> {
+	// Versions without a known ingestion time predate tracking it, and cannot
+	// be ordered relative to other versions. Keys break ties between versions
+	// ingested at the same time.
+	let position = |key: VersionKey| Some((versions.get(&key)?.ingest_time?, key));
+
+	let (from, to) = (position(from)?, position(to)?);
+	let (start, end) = (from.min(to), from.max(to));
+
+	let mut positions = versions
+		.iter()
+		.filter(|(key, version)| version.ban_time.is_none() || **key == start.1 || **key == end.1)
+		.filter_map(|(key, _version)| position(*key))
+		.filter(|position| (start..=end).contains(position))
+		.collect::<Vec<_>>();
+	positions.sort();
+
+	Some(positions.into_iter().map(|(_time, key)| key).collect())
+}
+
+fn patch_paths(version: &Version) -> impl Iterator<Item = PathBuf> + '_ {
+	version
+		.repositories
+		.iter()
+		.flat_map(|repository| repository.patches.iter().map(|patch| patch.path.clone()))
+}
+
 fn open_config_read(path: impl AsRef<Path>) -> Result<Option<fs::File>> {
 	let file = match fs::File::open(path) {
 		Ok(file) => file,
@@ -461,3 +639,138 @@ fn open_config_write(path: impl AsRef<Path>) -> Result<fs::File> {
 	file.set_len(0)?;
 	Ok(file)
 }
+
+/// Run the provided futures concurrently, with no more than the semaphore's
+/// permit count running at once. Output order matches the input.
+async fn try_join_limited<T>(
+	semaphore: &Semaphore,
+	futures: impl IntoIterator<Item = impl Future<Output = Result<T>>>,
+) -> Result<Vec<T>> {
+	let limited = futures.into_iter().map(|future| async move {
+		let _permit = semaphore.acquire().await?;
+		future.await
+	});
+
+	try_join_all(limited).await
+}
+
+#[cfg(any(test, feature = "fixture"))]
+impl Manager {
+	/// Build a manager over the given versions and their names, persisting
+	/// metadata to the given directory. Versions are never updated.
+	pub fn fixture(
+		directory: &Path,
+		versions: impl IntoIterator<Item = (VersionKey, Version, Vec<String>)>,
+	) -> Self {
+		use figment::{providers::Serialized, Figment};
+
+		let config = Figment::from(Serialized::defaults(serde_json::json!({
+			"thaliak": { "endpoint": "http://localhost" },
+			"patch": {
+				"directory": directory.join("patches"),
+				"concurrency": 1,
+				"user_agent": "",
+			},
+			"interval": u64::MAX,
+			"directory": directory,
+			"repositories": [],
+		})))
+		.extract::<Config>()
+		.expect("fixture config should be valid");
+
+		let manager = Self::new(config).expect("fixture directory should be writable");
+		{
+			let mut all_versions = manager.versions.write().expect("poisoned");
+			let mut all_names = manager.names.write().expect("poisoned");
+			for (key, version, names) in versions {
+				all_versions.insert(key, version);
+				all_names.extend(names.into_iter().map(|name| (name, key)));
+			}
+		}
+
+		manager
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn repository_fetch_limit() {
+		let semaphore = Semaphore::new(2);
+		let active = AtomicUsize::new(0);
+		let peak = AtomicUsize::new(0);
+
+		let futures = (0..6).map(|index| {
+			let (active, peak) = (&active, &peak);
+			async move {
+				let current = active.fetch_add(1, Ordering::SeqCst) + 1;
+				peak.fetch_max(current, Ordering::SeqCst);
+				// Yield so that any unlimited futures would be polled concurrently.
+				tokio::task::yield_now().await;
+				active.fetch_sub(1, Ordering::SeqCst);
+				Ok(index)
+			}
+		});
+
+		let got = try_join_limited(&semaphore, futures).await.unwrap();
+		assert_eq!(got, vec![0, 1, 2, 3, 4, 5]);
+		assert_eq!(peak.load(Ordering::SeqCst), 2);
+	}
+
+	fn key(value: &str) -> VersionKey {
+		value.parse().unwrap()
+	}
+
+	fn range_versions() -> HashMap<VersionKey, Version> {
+		let version = |ingested: Option<u64>, banned: bool| Version {
+			repositories: vec![],
+			ban_time: banned.then_some(SystemTime::UNIX_EPOCH),
+			ingest_time: ingested
+				.map(|secs| SystemTime::UNIX_EPOCH + time::Duration::from_secs(secs)),
+		};
+
+		HashMap::from([
+			(key("0a"), version(Some(10), false)),
+			(key("0b"), version(Some(20), true)),
+			(key("0c"), version(Some(30), false)),
+			(key("0d"), version(Some(40), false)),
+			(key("0e"), version(None, false)),
+		])
+	}
+
+	#[test]
+	fn range_ordered_by_ingestion() {
+		let versions = range_versions();
+		let expected = vec![key("0a"), key("0c"), key("0d")];
+
+		assert_eq!(
+			version_range(&versions, key("0a"), key("0d")),
+			Some(expected.clone())
+		);
+		// Bounds may be provided in either order.
+		assert_eq!(
+			version_range(&versions, key("0d"), key("0a")),
+			Some(expected)
+		);
+	}
+
+	#[test]
+	fn range_includes_banned_bounds() {
+		let versions = range_versions();
+		assert_eq!(
+			version_range(&versions, key("0b"), key("0c")),
+			Some(vec![key("0b"), key("0c")])
+		);
+	}
+
+	#[test]
+	fn range_rejects_unknown_ingestion() {
+		let versions = range_versions();
+		assert_eq!(version_range(&versions, key("0a"), key("0e")), None);
+		assert_eq!(version_range(&versions, key("0a"), key("ff")), None);
+	}
+}
@@ -49,6 +49,12 @@ impl Patcher {
 		self.directory.join(repository).join(patch)
 	}
 
+	/// Forget any known state for the patch at the given path, such that it will
+	/// be validated again if requested.
+	pub fn forget(&self, path: &Path) {
+		self.patch_states.lock().expect("poisoned").remove(path);
+	}
+
 	pub async fn to_local_patch(
 		&self,
 		repository: &str,
@@ -0,0 +1,150 @@
+use std::{
+	collections::{HashMap, HashSet},
+	time::{Duration, SystemTime},
+};
+
+use serde::Deserialize;
+
+use super::{key::VersionKey, version::Version};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+	/// Maximum number of versions to retain, most recently ingested first.
+	count: Option<usize>,
+
+	/// Maximum age, in seconds since ingestion, of versions to retain. Versions
+	/// without a known ingestion time are not pruned by age.
+	max_age: Option<u64>,
+}
+
+impl Config {
+	/// Select the versions that fall outside the retention policy. Pinned
+	/// versions are always retained.
+	pub fn prune(
+		&self,
+		versions: &HashMap<VersionKey, Version>,
+		pinned: &HashSet<VersionKey>,
+		now: SystemTime,
+	) -> Vec<VersionKey> {
+		// Versions without a known ingestion time predate tracking it, and are
+		// considered the oldest for the purposes of count.
+		let mut ordered = versions
+			.iter()
+			.map(|(key, version)| (*key, version.ingest_time))
+			.collect::<Vec<_>>();
+		ordered.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+		let max_age = self.max_age.map(Duration::from_secs);
+
+		ordered
+			.into_iter()
+			.enumerate()
+			.filter(|(index, (key, ingest_time))| {
+				if pinned.contains(key) {
+					return false;
+				}
+
+				let over_count = self.count.is_some_and(|count| *index >= count);
+				// Their age is unknown, however - such versions are not pruned by age.
+				let over_age = max_age
+					.zip(*ingest_time)
+					.is_some_and(|(max_age, ingest_time)| {
+						now.duration_since(ingest_time)
+							.is_ok_and(|age| age > max_age)
+					});
+
+				over_count || over_age
+			})
+			.map(|(_index, (key, _ingest_time))| key)
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn key(value: &str) -> VersionKey {
+		value.parse().unwrap()
+	}
+
+	fn versions(ages: &[(&str, u64)], now: SystemTime) -> HashMap<VersionKey, Version> {
+		ages.iter()
+			.map(|&(name, age)| {
+				let version = Version {
+					repositories: vec![],
+					ban_time: None,
+					ingest_time: Some(now - Duration::from_secs(age)),
+				};
+				(key(name), version)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn count_prunes_oldest_unpinned() {
+		let now = SystemTime::now();
+		let versions = versions(&[("a", 10), ("b", 20), ("c", 30), ("d", 40)], now);
+		let config = Config {
+			count: Some(2),
+			max_age: None,
+		};
+
+		let got = config.prune(&versions, &HashSet::new(), now);
+		assert_eq!(got, vec![key("c"), key("d")]);
+
+		let got = config.prune(&versions, &HashSet::from([key("d")]), now);
+		assert_eq!(got, vec![key("c")]);
+	}
+
+	#[test]
+	fn max_age_prunes_old() {
+		let now = SystemTime::now();
+		let versions = versions(&[("a", 10), ("b", 20), ("c", 30)], now);
+		let config = Config {
+			count: None,
+			max_age: Some(15),
+		};
+
+		let got = config.prune(&versions, &HashSet::from([key("c")]), now);
+		assert_eq!(got, vec![key("b")]);
+	}
+
+	#[test]
+	fn max_age_retains_unknown_ingest_time() {
+		let now = SystemTime::now();
+		let mut versions = versions(&[("a", 10), ("b", 20)], now);
+		versions.insert(
+			key("c"),
+			Version {
+				repositories: vec![],
+				ban_time: None,
+				ingest_time: None,
+			},
+		);
+		let config = Config {
+			count: None,
+			max_age: Some(15),
+		};
+
+		let got = config.prune(&versions, &HashSet::new(), now);
+		assert_eq!(got, vec![key("b")]);
+
+		// Unknown ingestion times are still considered oldest when counting.
+		let config = Config {
+			count: Some(2),
+			max_age: Some(15),
+		};
+		let got = config.prune(&versions, &HashSet::new(), now);
+		assert_eq!(got, vec![key("b"), key("c")]);
+	}
+
+	#[test]
+	fn unconfigured_retains_all() {
+		let now = SystemTime::now();
+		let versions = versions(&[("a", 10), ("b", 1_000_000)], now);
+
+		let got = Config::default().prune(&versions, &HashSet::new(), now);
+		assert!(got.is_empty());
+	}
+}
@@ -8,6 +8,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 pub struct Version {
 	pub repositories: Vec<Repository>,
 	pub ban_time: Option<SystemTime>,
+	pub ingest_time: Option<SystemTime>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,6 +22,8 @@ enum PersistedVersion {
 struct PersistedVersionV2 {
 	repositories: Vec<PersistedRepository>,
 	ban_time: Option<SystemTime>,
+	#[serde(default)]
+	ingest_time: Option<SystemTime>,
 }
 
 // NOTE: This using using `impl Serialize` so it doesn't become public API surface.
@@ -37,6 +40,7 @@ impl Version {
 				.collect(),
 
 			ban_time: self.ban_time,
+			ingest_time: self.ingest_time,
 		};
 
 		PersistedVersion::V2(persisted_version)
@@ -53,9 +57,11 @@ impl Version {
 		let persisted_version = PersistedVersion::deserialize(deserializer)
 			.map_err(|err| anyhow::anyhow!(err.to_string()))?;
 
-		let (persisted_repositories, ban_time) = match persisted_version {
-			PersistedVersion::V1(repositories) => (repositories, None),
-			PersistedVersion::V2(version) => (version.repositories, version.ban_time),
+		let (persisted_repositories, ban_time, ingest_time) = match persisted_version {
+			PersistedVersion::V1(repositories) => (repositories, None, None),
+			PersistedVersion::V2(version) => {
+				(version.repositories, version.ban_time, version.ingest_time)
+			}
 		};
 
 		let repositories = persisted_repositories
@@ -73,6 +79,7 @@ impl Version {
 		Ok(Version {
 			repositories,
 			ban_time,
+			ingest_time,
 		})
 	}
 }
@@ -0,0 +1,313 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::broadcast, time};
+
+use super::{key::VersionKey, manager::VersionMessage};
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+	/// URL to POST notifications of new or updated versions to. Notifications
+	/// are not sent if unset.
+	url: Option<String>,
+
+	/// Maximum number of attempts made to deliver each notification.
+	#[serde(default = "default_attempts")]
+	attempts: u32,
+
+	/// Delay before retrying a failed delivery, in milliseconds. Doubled after
+	/// each subsequent failure.
+	#[serde(default = "default_backoff")]
+	backoff: u64,
+
+	/// Time allowed for each delivery attempt, in milliseconds, including
+	/// connecting to the webhook.
+	#[serde(default = "default_timeout")]
+	timeout: u64,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			url: None,
+			attempts: default_attempts(),
+			backoff: default_backoff(),
+			timeout: default_timeout(),
+		}
+	}
+}
+
+fn default_attempts() -> u32 {
+	5
+}
+
+fn default_backoff() -> u64 {
+	1000
+}
+
+fn default_timeout() -> u64 {
+	10000
+}
+
+/// Body of notifications sent to the webhook.
+#[derive(Debug, Serialize)]
+struct Notification {
+	version_key: VersionKey,
+	names: Vec<String>,
+}
+
+pub struct Webhook {
+	url: Option<String>,
+	attempts: u32,
+	backoff: Duration,
+	client: reqwest::Client,
+}
+
+impl Webhook {
+	pub fn new(config: Config) -> Self {
+		let timeout = Duration::from_millis(config.timeout);
+		Self {
+			url: config.url,
+			attempts: config.attempts.max(1),
+			backoff: Duration::from_millis(config.backoff),
+			client: reqwest::Client::builder()
+				.timeout(timeout)
+				.connect_timeout(timeout)
+				.build()
+				.expect("failed to build reqwest client"),
+		}
+	}
+
+	pub fn enabled(&self) -> bool {
+		self.url.is_some()
+	}
+
+	/// Notify the webhook of each changed version received, until the channel
+	/// closes. Delivery failures are logged, and do not stop further
+	/// notifications.
+	pub async fn run(
+		&self,
+		mut receiver: broadcast::Receiver<VersionMessage>,
+		names: impl Fn(VersionKey) -> Vec<String>,
+	) {
+		let Some(url) = &self.url else {
+			return;
+		};
+
+		loop {
+			let key = match receiver.recv().await {
+				Ok(VersionMessage::Changed(key)) => key,
+				Ok(VersionMessage::Hydrate(..) | VersionMessage::Removed(..)) => continue,
+				Err(broadcast::error::RecvError::Lagged(skipped)) => {
+					tracing::warn!(skipped, "webhook missed version messages");
+					continue;
+				}
+				Err(broadcast::error::RecvError::Closed) => return,
+			};
+
+			let notification = Notification {
+				version_key: key,
+				names: names(key),
+			};
+
+			if let Err(error) = self.deliver(url, &notification).await {
+				tracing::error!(%key, ?error, "webhook delivery failed");
+			}
+		}
+	}
+
+	async fn deliver(&self, url: &str, notification: &Notification) -> Result<()> {
+		let mut attempt = 0;
+		loop {
+			let result = self
+				.client
+				.post(url)
+				.json(notification)
+				.send()
+				.await
+				.and_then(|response| response.error_for_status());
+
+			let error = match result {
+				Ok(_response) => return Ok(()),
+				Err(error) => error,
+			};
+
+			attempt += 1;
+			if attempt >= self.attempts {
+				return Err(error.into());
+			}
+
+			let delay = retry_delay(self.backoff, attempt);
+			tracing::warn!(
+				key = %notification.version_key,
+				attempt,
+				?delay,
+				?error,
+				"webhook delivery attempt failed, retrying"
+			);
+			time::sleep(delay).await;
+		}
+	}
+}
+
+/// Delay before the next attempt, after the given number of failed attempts.
+fn retry_delay(backoff: Duration, failures: u32) -> Duration {
+	backoff.saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{
+		atomic::{AtomicU32, Ordering},
+		Arc,
+	};
+
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	use super::*;
+
+	/// Start a server responding to each request with the status returned for
+	/// its attempt number, or never responding if `None`. Returns the server's
+	/// URL and the number of requests received.
+	async fn serve(
+		status: impl Fn(u32) -> Option<u16> + Send + 'static,
+	) -> (String, Arc<AtomicU32>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}/", listener.local_addr().unwrap());
+		let requests = Arc::new(AtomicU32::new(0));
+
+		let counter = requests.clone();
+		tokio::spawn(async move {
+			loop {
+				let (mut stream, _) = listener.accept().await.unwrap();
+				let attempt = counter.fetch_add(1, Ordering::SeqCst) + 1;
+
+				// Read the full request before responding. Notifications are small
+				// enough to arrive in a few reads.
+				let mut request = vec![];
+				let mut buffer = [0; 1024];
+				while !request_complete(&request) {
+					let read = stream.read(&mut buffer).await.unwrap();
+					if read == 0 {
+						break;
+					}
+					request.extend(&buffer[..read]);
+				}
+
+				let Some(status) = status(attempt) else {
+					// Hold the connection open without responding.
+					tokio::spawn(async move {
+						time::sleep(Duration::from_secs(60)).await;
+						drop(stream);
+					});
+					continue;
+				};
+
+				let response = format!(
+					"HTTP/1.1 {status} Test\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+				);
+				stream.write_all(response.as_bytes()).await.unwrap();
+			}
+		});
+
+		(url, requests)
+	}
+
+	fn request_complete(request: &[u8]) -> bool {
+		let request = String::from_utf8_lossy(request);
+		let Some((head, body)) = request.split_once("\r\n\r\n") else {
+			return false;
+		};
+		let length = head
+			.lines()
+			.find_map(|line| {
+				let (name, value) = line.split_once(':')?;
+				name.eq_ignore_ascii_case("content-length")
+					.then(|| value.trim().parse::<usize>().ok())
+					.flatten()
+			})
+			.unwrap_or(0);
+		body.len() >= length
+	}
+
+	fn webhook(url: &str, attempts: u32, timeout: u64) -> Webhook {
+		Webhook::new(Config {
+			url: Some(url.into()),
+			attempts,
+			backoff: 1,
+			timeout,
+		})
+	}
+
+	fn notification() -> Notification {
+		Notification {
+			version_key: "00000000000000ff".parse().unwrap(),
+			names: vec!["latest".into()],
+		}
+	}
+
+	#[tokio::test]
+	async fn deliver_retries_failures() {
+		let (url, requests) = serve(|attempt| Some(if attempt < 3 { 500 } else { 204 })).await;
+
+		let result = webhook(&url, 5, 1000).deliver(&url, &notification()).await;
+
+		assert!(result.is_ok());
+		assert_eq!(requests.load(Ordering::SeqCst), 3);
+	}
+
+	#[tokio::test]
+	async fn deliver_stops_after_attempts() {
+		let (url, requests) = serve(|_| Some(500)).await;
+
+		let result = webhook(&url, 2, 1000).deliver(&url, &notification()).await;
+
+		assert!(result.is_err());
+		assert_eq!(requests.load(Ordering::SeqCst), 2);
+	}
+
+	#[tokio::test]
+	async fn deliver_times_out() {
+		let (url, requests) = serve(|_| None).await;
+
+		let result = webhook(&url, 2, 50).deliver(&url, &notification()).await;
+
+		assert!(result.is_err());
+		assert_eq!(requests.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn delay_doubles() {
+		let backoff = Duration::from_millis(500);
+		assert_eq!(retry_delay(backoff, 1), Duration::from_millis(500));
+		assert_eq!(retry_delay(backoff, 2), Duration::from_millis(1000));
+		assert_eq!(retry_delay(backoff, 4), Duration::from_millis(4000));
+	}
+
+	#[test]
+	fn delay_saturates() {
+		assert_eq!(
+			retry_delay(Duration::from_secs(1), 64),
+			Duration::from_secs(u32::MAX.into())
+		);
+		assert_eq!(retry_delay(Duration::MAX, 2), Duration::MAX);
+	}
+
+	#[test]
+	fn serialize_notification() {
+		let notification = Notification {
+			version_key: "00000000000000ff".parse().unwrap(),
+			names: vec!["latest".into()],
+		};
+
+		let got = serde_json::to_value(notification).unwrap();
+		assert_eq!(
+			got,
+			serde_json::json!({"version_key": "00000000000000ff", "names": ["latest"]})
+		);
+	}
+}
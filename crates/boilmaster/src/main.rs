@@ -16,6 +16,7 @@ mod tracing;
 struct Config {
 	// tracing: tracing::Config, - read individually.
 	http: bm_http::Config,
+	asset: bm_asset::Config,
 	read: bm_read::Config,
 	version: bm_version::Config,
 	schema: bm_schema::Config,
@@ -48,7 +49,7 @@ async fn main() -> anyhow::Result<()> {
 		bm_version::Manager::new(config.version).context("failed to create version manager")?,
 	);
 	let data = Arc::new(bm_data::Data::new());
-	let asset = Arc::new(bm_asset::Service::new(data.clone()));
+	let asset = Arc::new(bm_asset::Service::new(config.asset, data.clone()));
 	let read = Arc::new(bm_read::Read::new(config.read));
 	let schema = Arc::new(
 		bm_schema::Provider::new(config.schema, data.clone())